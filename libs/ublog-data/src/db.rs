@@ -1,18 +1,69 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
 use uuid::Uuid;
 
-use crate::models::{Post, Resource};
-use crate::storage::{PaginatedList, Pagination, Storage};
+use crate::models::{
+    normalize_slug, ArchiveBucket, DailyViewCount, Delta, Post, PostEvent, PostEventKind, PostView,
+    Resource, ResourceMeta, Taxonomy,
+};
+use crate::storage::{PaginatedList, Pagination, PostSort, Storage};
+
+/// Page size used when walking through all posts of a database.
+const EXPORT_PAGE_SIZE: usize = 100;
+
+/// A listener that gets notified of posts written to a [`Database`].
+type PostListener = Box<dyn Fn(&PostEvent) + Send + Sync>;
 
 /// A database instance that loads data from an underlying storage.
-#[derive(Debug)]
 pub struct Database<S> {
     storage: S,
+    post_listeners: Vec<PostListener>,
 }
 
 impl<S> Database<S> {
     /// Create a new database instance from the given storage.
     pub fn new(storage: S) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            post_listeners: Vec::new(),
+        }
+    }
+
+    /// Register a listener that gets called after each post is successfully inserted or updated through this
+    /// database.
+    ///
+    /// Listeners are called synchronously on the writing task, so they should return quickly.
+    pub fn add_post_listener<F>(&mut self, listener: F)
+    where
+        F: Fn(&PostEvent) + Send + Sync + 'static,
+    {
+        self.post_listeners.push(Box::new(listener));
+    }
+
+    fn notify_post_listeners(&self, post: &Post, kind: PostEventKind) {
+        if self.post_listeners.is_empty() {
+            return;
+        }
+
+        let event = PostEvent::new(post, kind);
+        for listener in &self.post_listeners {
+            listener(&event);
+        }
+    }
+}
+
+impl<S> Debug for Database<S>
+where
+    S: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("storage", &self.storage)
+            .field("post_listeners", &self.post_listeners.len())
+            .finish()
     }
 }
 
@@ -21,61 +72,721 @@ where
     S: Storage,
 {
     /// Get the post object with the given slug.
-    pub async fn get_post<T>(&self, slug: T) -> Result<Option<Post>, S::Error>
+    ///
+    /// The slug is normalized in the same way as the slugs of inserted posts before looking up the post. Posts stored
+    /// before slugs were normalized are still found by their original slugs.
+    pub async fn get_post<T>(&self, slug: T) -> Result<Option<Post>, DatabaseError<S::Error>>
     where
         T: AsRef<str>,
     {
         let slug = slug.as_ref();
-        self.storage.get_post(slug).await
+        let normalized = lookup_slug(slug);
+        let post = self.storage.get_post(&normalized).await?;
+        if post.is_none() && normalized != slug {
+            return Ok(self.storage.get_post(slug).await?);
+        }
+        Ok(post)
     }
 
-    /// Get a view of post within the specified page.
+    /// Get a view of post within the specified page, ordered as specified by `sort`.
     ///
     /// The `special` parameter indicates whether the target posts is special posts.
     pub async fn get_posts(
         &self,
         special: bool,
+        sort: PostSort,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_posts(special, sort, pagination).await?)
+    }
+
+    /// Get a view of special posts within the specified page.
+    pub async fn get_special_posts(
+        &self,
         pagination: &Pagination,
-    ) -> Result<PaginatedList<Post>, S::Error> {
-        self.storage.get_posts(special, pagination).await
+    ) -> Result<PaginatedList<Post>, DatabaseError<S::Error>> {
+        self.get_posts(true, PostSort::CreatedDesc, pagination)
+            .await
+    }
+
+    /// Get a view of non-special posts that have the given tag within the specified page.
+    pub async fn get_posts_by_tag(
+        &self,
+        tag: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_posts_by_tag(tag, pagination).await?)
+    }
+
+    /// Get a view of non-special posts under the given category within the specified page.
+    pub async fn get_posts_by_category(
+        &self,
+        category: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, DatabaseError<S::Error>> {
+        Ok(self
+            .storage
+            .get_posts_by_category(category, pagination)
+            .await?)
+    }
+
+    /// Get non-special posts updated after the given timestamp, ordered by their update time from the latest to the
+    /// earliest.
+    pub async fn get_posts_updated_since(
+        &self,
+        since: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, DatabaseError<S::Error>> {
+        Ok(self
+            .storage
+            .get_posts_updated_since(since, pagination)
+            .await?)
+    }
+
+    /// Get the non-special posts immediately older and newer than the post with the given slug, without their
+    /// contents.
+    pub async fn get_adjacent_posts<T>(
+        &self,
+        slug: T,
+    ) -> Result<(Option<Post>, Option<Post>), DatabaseError<S::Error>>
+    where
+        T: AsRef<str>,
+    {
+        let slug = self.resolve_slug(slug.as_ref()).await?;
+        Ok(self.storage.get_adjacent_posts(&slug).await?)
+    }
+
+    /// Get at most `limit` non-special posts that share the most tags with the post with the given slug, without their
+    /// contents.
+    pub async fn get_related_posts<T>(
+        &self,
+        slug: T,
+        limit: usize,
+    ) -> Result<Vec<Post>, DatabaseError<S::Error>>
+    where
+        T: AsRef<str>,
+    {
+        let slug = self.resolve_slug(slug.as_ref()).await?;
+        Ok(self.storage.get_related_posts(&slug, limit).await?)
+    }
+
+    /// Search for non-special posts that match the given query, ordered by relevance.
+    pub async fn search_posts(
+        &self,
+        query: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, DatabaseError<S::Error>> {
+        Ok(self.storage.search_posts(query, pagination).await?)
+    }
+
+    /// Get all categories and tags used by non-special posts, together with the number of posts under each of them.
+    pub async fn get_taxonomy(&self) -> Result<Taxonomy, DatabaseError<S::Error>> {
+        Ok(self.storage.get_taxonomy().await?)
+    }
+
+    /// Get the number of non-special posts created within each month, ordered from the latest month to the earliest.
+    pub async fn get_post_archive(&self) -> Result<Vec<ArchiveBucket>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_post_archive().await?)
+    }
+
+    /// Get all distinct tags of non-special posts together with the number of non-special posts that have each tag,
+    /// ordered by the number of posts from the most to the least.
+    pub async fn get_tag_counts(&self) -> Result<Vec<(String, usize)>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_tag_counts().await?)
     }
 
     /// Insert the given post into the database.
-    pub async fn insert_post(&self, post: &Post, resources: &[Resource]) -> Result<(), S::Error> {
-        self.storage.insert_post(post, resources).await
+    ///
+    /// The slug of the post is normalized through [`normalize_slug`] before the post is inserted. Fails with
+    /// [`DatabaseError::Duplicate`] if a post with the same normalized slug already exists, and with
+    /// [`DatabaseError::InvalidInput`] if the slug is empty after normalization or the content of the post is not a
+    /// valid document tree.
+    pub async fn insert_post(
+        &self,
+        post: &Post,
+        resources: &[Resource],
+    ) -> Result<(), DatabaseError<S::Error>> {
+        let post = normalize_post(post)?;
+        self.check_new_post(&post).await?;
+        self.storage.insert_post(&post, resources).await?;
+        self.notify_post_listeners(&post, PostEventKind::Created);
+        Ok(())
+    }
+
+    /// Insert the given posts into the database within a single batch.
+    ///
+    /// If the underlying storage supports transactions, either all or none of the posts are inserted. The posts are
+    /// checked in the same way as [`insert_post`](Self::insert_post) before any of them is inserted.
+    pub async fn insert_posts(
+        &self,
+        posts: &[(Post, Vec<Resource>)],
+    ) -> Result<(), DatabaseError<S::Error>> {
+        let posts = if posts
+            .iter()
+            .all(|(post, _)| post.normalized_slug().as_ref() == Some(&post.slug))
+        {
+            Cow::Borrowed(posts)
+        } else {
+            let normalized = posts
+                .iter()
+                .map(|(post, resources)| {
+                    Ok((normalize_post(post)?.into_owned(), resources.clone()))
+                })
+                .collect::<Result<Vec<_>, DatabaseError<S::Error>>>()?;
+            Cow::Owned(normalized)
+        };
+
+        for (post, _) in posts.iter() {
+            self.check_new_post(post).await?;
+        }
+        self.storage.insert_posts(&posts).await?;
+        for (post, _) in posts.iter() {
+            self.notify_post_listeners(post, PostEventKind::Created);
+        }
+        Ok(())
     }
 
     /// Update the given post into the database.
-    pub async fn update_post(&self, post: &Post, resources: &[Resource]) -> Result<(), S::Error> {
-        self.storage.update_post(post, resources).await
+    ///
+    /// The post to be updated is identified by its normalized slug, or by its original slug if it is stored before slugs
+    /// were normalized. Fails with [`DatabaseError::NotFound`] if the post does not exist, and with
+    /// [`DatabaseError::InvalidInput`] if the new content of the post is not a valid document tree.
+    pub async fn update_post(
+        &self,
+        post: &Post,
+        resources: &[Resource],
+    ) -> Result<(), DatabaseError<S::Error>> {
+        validate_post_content(post)?;
+        let post = match post.normalized_slug() {
+            Some(slug) if slug == post.slug => Cow::Borrowed(post),
+            _ if self.resolve_slug(&post.slug).await? == post.slug => Cow::Borrowed(post),
+            _ => normalize_post(post)?,
+        };
+        if self.storage.get_post(&post.slug).await?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+        self.storage.update_post(&post, resources).await?;
+        self.notify_post_listeners(&post, PostEventKind::Updated);
+        Ok(())
+    }
+
+    async fn check_new_post(&self, post: &Post) -> Result<(), DatabaseError<S::Error>> {
+        validate_post_content(post)?;
+        if self.storage.get_post(&post.slug).await?.is_some() {
+            return Err(DatabaseError::Duplicate {
+                slug: post.slug.clone(),
+            });
+        }
+        Ok(())
     }
 
     /// Delete the post object with the given slug.
-    pub async fn delete_post<T>(&self, slug: T) -> Result<(), S::Error>
+    ///
+    /// Returns whether the post existed and has been deleted.
+    pub async fn delete_post<T>(&self, slug: T) -> Result<bool, DatabaseError<S::Error>>
     where
         T: AsRef<str>,
     {
-        let slug = slug.as_ref();
-        self.storage.delete_post(slug).await
+        let slug = self.resolve_slug(slug.as_ref()).await?;
+        let deleted_posts = self
+            .get_posts_to_notify(std::slice::from_ref(&slug))
+            .await?;
+        let deleted = self.storage.delete_post(&slug).await?;
+        if deleted {
+            for post in &deleted_posts {
+                self.notify_post_listeners(post, PostEventKind::Deleted);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Increment the view count of the post with the given slug.
+    ///
+    /// Returns the new view count, or `None` if the post does not exist.
+    pub async fn increment_post_views(
+        &self,
+        slug: &str,
+    ) -> Result<Option<u64>, DatabaseError<S::Error>> {
+        let slug = self.resolve_slug(slug).await?;
+        Ok(self.storage.increment_post_views(&slug).await?)
     }
 
     /// Get the static resource object with the given ID.
-    pub async fn get_resource(&self, id: &Uuid) -> Result<Option<Resource>, S::Error> {
-        self.storage.get_resource(id).await
+    pub async fn get_resource(
+        &self,
+        id: &Uuid,
+    ) -> Result<Option<Resource>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_resource(id).await?)
+    }
+
+    /// Get the metadata of the static resource with the given ID.
+    pub async fn get_resource_meta(
+        &self,
+        id: &Uuid,
+    ) -> Result<Option<ResourceMeta>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_resource_meta(id).await?)
+    }
+
+    /// Read a chunk of the data of the static resource with the given ID.
+    ///
+    /// At most `len` bytes starting at the given byte offset are read. Returns `None` if the resource does not exist.
+    pub async fn read_resource_chunk(
+        &self,
+        id: &Uuid,
+        offset: u64,
+        len: usize,
+    ) -> Result<Option<Vec<u8>>, DatabaseError<S::Error>> {
+        Ok(self.storage.read_resource_chunk(id, offset, len).await?)
     }
 
     /// Get a list of resources within the specified page.
-    pub async fn get_resources(&self) -> Result<Vec<Resource>, S::Error> {
-        self.storage.get_resources().await
+    pub async fn get_resources(&self) -> Result<Vec<Resource>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_resources().await?)
+    }
+
+    /// Get a list of resources within the specified page, without their data.
+    pub async fn get_resources_paged(
+        &self,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Resource>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_resources_paged(pagination).await?)
+    }
+
+    /// Get the metadata of all resources, including the sizes of their data.
+    pub async fn get_resource_metas(&self) -> Result<Vec<ResourceMeta>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_resource_metas().await?)
     }
 
     /// Insert the given resource object into the database.
-    pub async fn insert_resource(&self, res: &Resource) -> Result<(), S::Error> {
-        self.storage.insert_resource(res).await
+    pub async fn insert_resource(&self, res: &Resource) -> Result<(), DatabaseError<S::Error>> {
+        Ok(self.storage.insert_resource(res).await?)
+    }
+
+    /// Update the given resource object in the database.
+    ///
+    /// The resource to be updated is identified by its ID. Fails with [`DatabaseError::NotFound`] if the resource does
+    /// not exist.
+    pub async fn update_resource(&self, res: &Resource) -> Result<(), DatabaseError<S::Error>> {
+        if !self.storage.update_resource(res).await? {
+            return Err(DatabaseError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Find a resource whose data has the given content hash. See [`Resource::content_hash`].
+    pub async fn find_resource_by_hash(
+        &self,
+        hash: &str,
+    ) -> Result<Option<ResourceMeta>, DatabaseError<S::Error>> {
+        Ok(self.storage.find_resource_by_hash(hash).await?)
+    }
+
+    /// Delete all resources that are not referenced by any post. Returns the number of deleted resources.
+    pub async fn gc_resources(&self) -> Result<usize, DatabaseError<S::Error>> {
+        Ok(self.storage.gc_resources().await?)
+    }
+
+    /// Delete the resource object with the given ID.
+    ///
+    /// Returns whether the resource existed and has been deleted.
+    pub async fn delete_resource(&self, id: &Uuid) -> Result<bool, DatabaseError<S::Error>> {
+        Ok(self.storage.delete_resource(id).await?)
+    }
+
+    /// Get the metadata value with the given key.
+    pub async fn get_metadata(&self, key: &str) -> Result<Option<String>, DatabaseError<S::Error>> {
+        Ok(self.storage.get_metadata(key).await?)
     }
 
-    /// Delete the resource object with the given name.
-    pub async fn delete_resource(&self, id: &Uuid) -> Result<(), S::Error> {
-        self.storage.delete_resource(id).await
+    /// Set the metadata value with the given key, replacing the existing value if any.
+    pub async fn set_metadata(
+        &self,
+        key: &str,
+        value: &str,
+    ) -> Result<(), DatabaseError<S::Error>> {
+        Ok(self.storage.set_metadata(key, value).await?)
+    }
+
+    /// Record the given post views for analytics, within a single batch.
+    pub async fn record_views(&self, views: &[PostView]) -> Result<(), DatabaseError<S::Error>> {
+        Ok(self.storage.record_views(views).await?)
+    }
+
+    /// Get the daily view counts of the post with the given slug, counting only views recorded at or after the given
+    /// Unix timestamp.
+    pub async fn get_view_stats<T>(
+        &self,
+        slug: T,
+        since: i64,
+    ) -> Result<Vec<DailyViewCount>, DatabaseError<S::Error>>
+    where
+        T: AsRef<str>,
+    {
+        let slug = self.resolve_slug(slug.as_ref()).await?;
+        Ok(self.storage.get_view_stats(&slug, since).await?)
+    }
+
+    /// Export all posts, resources and commits in the database into a [`Delta`].
+    ///
+    /// Applying the delta to an empty database through [`apply_delta`](Self::apply_delta) reconstructs the database.
+    pub async fn export_delta(&self) -> Result<Delta, DatabaseError<S::Error>> {
+        let mut delta = Delta::new();
+
+        let mut post_resource_ids = HashSet::new();
+        for special in [false, true] {
+            for page in 1.. {
+                let pagination = Pagination::from_page_and_size(page, EXPORT_PAGE_SIZE);
+                let posts_page = self
+                    .storage
+                    .get_posts(special, PostSort::CreatedDesc, &pagination)
+                    .await?;
+                let is_last_page = posts_page.objects.len() < EXPORT_PAGE_SIZE;
+
+                for post in posts_page.objects {
+                    if let Some((post, resources)) =
+                        self.storage.get_post_with_resources(&post.slug).await?
+                    {
+                        post_resource_ids.extend(resources.iter().map(|res| res.id));
+                        delta.added_posts.push((post, resources));
+                    }
+                }
+
+                if is_last_page {
+                    break;
+                }
+            }
+        }
+
+        // Resources that are not referenced by any post are exported on their own.
+        for meta in self.storage.get_resource_metas().await? {
+            if post_resource_ids.contains(&meta.id) {
+                continue;
+            }
+            if let Some(resource) = self.storage.get_resource(&meta.id).await? {
+                delta.added_resources.push(resource);
+            }
+        }
+
+        delta.commits = self.storage.get_commits_since(i64::MIN).await?;
+
+        Ok(delta)
+    }
+
+    /// Apply the given delta to the database.
+    ///
+    /// The commits in the delta must follow the latest commit in the database.
+    pub async fn apply_delta(&self, delta: &Delta) -> Result<(), DatabaseError<S::Error>> {
+        let deleted_posts = self.get_posts_to_notify(&delta.deleted_post_slugs).await?;
+        self.storage.apply_delta(delta).await?;
+
+        for (post, _) in &delta.added_posts {
+            self.notify_post_listeners(post, PostEventKind::Created);
+        }
+        for post in &deleted_posts {
+            self.notify_post_listeners(post, PostEventKind::Deleted);
+        }
+        Ok(())
+    }
+
+    /// Get the posts with the given slugs that are about to be deleted, so that post listeners can be told about them
+    /// after they are gone. Does nothing if there are no post listeners.
+    async fn get_posts_to_notify<T>(
+        &self,
+        slugs: &[T],
+    ) -> Result<Vec<Post>, DatabaseError<S::Error>>
+    where
+        T: AsRef<str>,
+    {
+        let mut posts = Vec::new();
+        if self.post_listeners.is_empty() {
+            return Ok(posts);
+        }
+
+        for slug in slugs {
+            if let Some(post) = self.storage.get_post(slug.as_ref()).await? {
+                posts.push(post);
+            }
+        }
+        Ok(posts)
+    }
+
+    /// Get the slug under which the post with the given slug is stored.
+    ///
+    /// This is the normalized slug, unless only a post stored under the given slug as-is exists. Such posts are stored
+    /// before slugs were normalized.
+    async fn resolve_slug<'a>(
+        &self,
+        slug: &'a str,
+    ) -> Result<Cow<'a, str>, DatabaseError<S::Error>> {
+        let normalized = lookup_slug(slug);
+        if normalized == slug || self.storage.get_post(&normalized).await?.is_some() {
+            return Ok(normalized);
+        }
+        if self.storage.get_post(slug).await?.is_some() {
+            return Ok(Cow::Borrowed(slug));
+        }
+        Ok(normalized)
+    }
+}
+
+/// Get the normalized form of the given slug, under which posts with the given slug are stored.
+///
+/// Slugs that are empty after normalization are looked up as-is, which finds nothing as no such slugs are stored.
+fn lookup_slug(slug: &str) -> Cow<'_, str> {
+    match normalize_slug(slug) {
+        Some(normalized) if normalized != slug => Cow::Owned(normalized),
+        _ => Cow::Borrowed(slug),
+    }
+}
+
+/// Get the given post with its slug normalized, or fail if its slug is empty after normalization.
+fn normalize_post<E>(post: &Post) -> Result<Cow<'_, Post>, DatabaseError<E>> {
+    match post.normalized_slug() {
+        Some(slug) if slug == post.slug => Ok(Cow::Borrowed(post)),
+        Some(slug) => {
+            let mut post = post.clone();
+            post.slug = slug;
+            Ok(Cow::Owned(post))
+        }
+        None => Err(DatabaseError::InvalidInput(format!(
+            "slug of post {:?} is empty after normalization",
+            post.slug
+        ))),
+    }
+}
+
+fn validate_post_content<E>(post: &Post) -> Result<(), DatabaseError<E>> {
+    post.content.validate().map_err(|errors| {
+        DatabaseError::InvalidInput(format!(
+            "content of post {} is invalid: {} errors",
+            post.slug,
+            errors.len()
+        ))
+    })
+}
+
+/// Errors of database operations.
+#[derive(Debug)]
+pub enum DatabaseError<E> {
+    /// The underlying storage failed.
+    Storage(E),
+
+    /// The object to be operated on does not exist.
+    NotFound,
+
+    /// A post with the same slug already exists.
+    Duplicate { slug: String },
+
+    /// The input to the operation is invalid.
+    InvalidInput(String),
+}
+
+impl<E> Display for DatabaseError<E>
+where
+    E: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Storage(err) => write!(f, "storage error: {}", err),
+            Self::NotFound => write!(f, "not found"),
+            Self::Duplicate { slug } => write!(f, "duplicate post slug: {}", slug),
+            Self::InvalidInput(msg) => write!(f, "invalid input: {}", msg),
+        }
+    }
+}
+
+impl<E> Error for DatabaseError<E> where E: Error {}
+
+impl<E> From<E> for DatabaseError<E> {
+    fn from(err: E) -> Self {
+        Self::Storage(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ublog_doc::{DocumentNode, DocumentNodeTag};
+
+    use crate::storage::sqlite::SqliteStorage;
+
+    fn create_test_db() -> Database<SqliteStorage> {
+        Database::new(SqliteStorage::new_memory().unwrap())
+    }
+
+    fn create_test_post(slug: &str) -> Post {
+        Post {
+            title: String::from("title"),
+            slug: String::from(slug),
+            author: String::from("msr"),
+            create_timestamp: 0,
+            update_timestamp: 0,
+            category: String::from("category"),
+            tags: Vec::new(),
+            is_special: false,
+            views: 0,
+            excerpt: String::new(),
+            content: DocumentNode::new_empty(),
+        }
+    }
+
+    fn create_invalid_post(slug: &str) -> Post {
+        let mut post = create_test_post(slug);
+        post.content
+            .children
+            .push(DocumentNode::new(DocumentNodeTag::ListItem));
+        post
+    }
+
+    #[tokio::test]
+    async fn test_insert_post_duplicate() {
+        let db = create_test_db();
+        db.insert_post(&create_test_post("post1"), &[])
+            .await
+            .unwrap();
+
+        let err = db
+            .insert_post(&create_test_post("post1"), &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DatabaseError::Duplicate { slug } if slug == "post1"));
+
+        let posts = vec![
+            (create_test_post("post2"), Vec::new()),
+            (create_test_post("post1"), Vec::new()),
+        ];
+        let err = db.insert_posts(&posts).await.unwrap_err();
+        assert!(matches!(err, DatabaseError::Duplicate { slug } if slug == "post1"));
+        assert!(db.get_post("post2").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_insert_post_invalid_content() {
+        let db = create_test_db();
+
+        let err = db
+            .insert_post(&create_invalid_post("post1"), &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DatabaseError::InvalidInput(_)));
+        assert!(db.get_post("post1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_insert_post_normalize_slug() {
+        let db = create_test_db();
+        db.insert_post(&create_test_post("2022/10/Hello World"), &[])
+            .await
+            .unwrap();
+        db.insert_post(&create_test_post("Grüße 你好"), &[])
+            .await
+            .unwrap();
+
+        let post = db.get_post("2022-10-hello-world").await.unwrap().unwrap();
+        assert_eq!(post.slug, "2022-10-hello-world");
+        assert!(db.get_post("2022/10/Hello World").await.unwrap().is_some());
+        assert!(db.get_post("grüße-你好").await.unwrap().is_some());
+
+        let err = db
+            .insert_post(&create_test_post("2022/10/hello-world"), &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DatabaseError::Duplicate { slug } if slug == "2022-10-hello-world"));
+
+        let err = db
+            .insert_post(&create_test_post("/?/"), &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DatabaseError::InvalidInput(_)));
+
+        let mut post = create_test_post("Grüße 你好");
+        post.title = String::from("updated");
+        db.update_post(&post, &[]).await.unwrap();
+        assert_eq!(
+            db.get_post("grüße-你好").await.unwrap().unwrap().title,
+            "updated"
+        );
+
+        assert!(db.delete_post("Grüße/你好").await.unwrap());
+        assert!(db.get_post("grüße-你好").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_unnormalized_slug() {
+        let db = create_test_db();
+        // Posts stored before slugs were normalized keep their original slugs.
+        db.storage
+            .insert_post(&create_test_post("Hello World"), &[])
+            .await
+            .unwrap();
+
+        assert!(db.get_post("Hello World").await.unwrap().is_some());
+        assert!(db.get_post("hello-world").await.unwrap().is_none());
+        assert_eq!(
+            db.increment_post_views("Hello World").await.unwrap(),
+            Some(1)
+        );
+
+        let mut post = create_test_post("Hello World");
+        post.title = String::from("updated");
+        db.update_post(&post, &[]).await.unwrap();
+        assert_eq!(
+            db.get_post("Hello World").await.unwrap().unwrap().title,
+            "updated"
+        );
+
+        assert!(db.delete_post("Hello World").await.unwrap());
+        assert!(db.get_post("Hello World").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_post_errors() {
+        let db = create_test_db();
+
+        let err = db
+            .update_post(&create_test_post("post1"), &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DatabaseError::NotFound));
+
+        db.insert_post(&create_test_post("post1"), &[])
+            .await
+            .unwrap();
+        let err = db
+            .update_post(&create_invalid_post("post1"), &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DatabaseError::InvalidInput(_)));
+    }
+
+    #[tokio::test]
+    async fn test_post_listeners() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut db = create_test_db();
+        let listener_events = events.clone();
+        db.add_post_listener(move |event| listener_events.lock().unwrap().push(event.clone()));
+
+        db.insert_post(&create_test_post("Post 1"), &[])
+            .await
+            .unwrap();
+        db.insert_post(&create_test_post("post-1"), &[])
+            .await
+            .unwrap_err();
+        db.update_post(&create_test_post("POST-1"), &[])
+            .await
+            .unwrap();
+
+        assert!(db.delete_post("post-1").await.unwrap());
+        assert!(!db.delete_post("post-1").await.unwrap());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].slug, "post-1");
+        assert_eq!(events[0].kind, PostEventKind::Created);
+        assert_eq!(events[1].slug, "post-1");
+        assert_eq!(events[1].kind, PostEventKind::Updated);
+        assert_eq!(events[2].title, "title");
+        assert_eq!(events[2].kind, PostEventKind::Deleted);
     }
 }
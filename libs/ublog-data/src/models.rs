@@ -1,3 +1,6 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use time::OffsetDateTime;
@@ -33,6 +36,19 @@ pub struct Post {
     #[serde(rename = "isSpecial")]
     pub is_special: bool,
 
+    /// The number of times the post has been viewed.
+    ///
+    /// The view count is maintained by the storage and is not changed when the post is updated.
+    #[serde(default)]
+    pub views: u64,
+
+    /// A plain-text excerpt of the post's content, for list views. See [`DocumentNode::excerpt`].
+    ///
+    /// The excerpt is generated by the storage from the post's content whenever the post is inserted or updated, so
+    /// that it is available even when the content of the post is not loaded. The given excerpt is ignored.
+    #[serde(default)]
+    pub excerpt: String,
+
     /// Content of the post.
     pub content: DocumentNode,
 }
@@ -47,6 +63,153 @@ impl Post {
     pub fn update_time(&self) -> OffsetDateTime {
         OffsetDateTime::from_unix_timestamp(self.update_timestamp).unwrap()
     }
+
+    /// Get the estimated time to read the post, in whole minutes rounded up, for a reader that reads `wpm` words per
+    /// minute.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `wpm` is zero.
+    pub fn reading_time_minutes(&self, wpm: usize) -> usize {
+        assert!(wpm > 0, "reading speed must be positive");
+        self.content.word_count().div_ceil(wpm)
+    }
+
+    /// Get the normalized form of the post's slug. See [`normalize_slug`].
+    pub fn normalized_slug(&self) -> Option<String> {
+        normalize_slug(&self.slug)
+    }
+}
+
+/// Normalize the given slug so that it is safe to appear in URLs.
+///
+/// Letters are lowercased, and every run of whitespaces, slashes and hyphens is replaced with a single `-`. Alphanumeric
+/// characters, including non-ASCII ones, and `_` are kept, while other characters are dropped. Leading and trailing
+/// hyphens are trimmed. Returns `None` if nothing is left after normalization.
+///
+/// Normalization is idempotent, so normalized slugs are normalized to themselves.
+pub fn normalize_slug(slug: &str) -> Option<String> {
+    let mut normalized = String::with_capacity(slug.len());
+    for ch in slug.chars() {
+        if ch.is_alphanumeric() || ch == '_' {
+            normalized.extend(ch.to_lowercase());
+        } else if (ch.is_whitespace() || ch == '/' || ch == '-')
+            && !normalized.is_empty()
+            && !normalized.ends_with('-')
+        {
+            normalized.push('-');
+        }
+    }
+
+    if normalized.ends_with('-') {
+        normalized.pop();
+    }
+    if normalized.is_empty() {
+        None
+    } else {
+        Some(normalized)
+    }
+}
+
+/// The taxonomy of the blog, i.e. all categories and tags used by non-special posts.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Taxonomy {
+    /// All categories, ordered by name.
+    pub categories: Vec<TaxonomyTerm>,
+
+    /// All tags, ordered by name.
+    pub tags: Vec<TaxonomyTerm>,
+}
+
+/// A category or a tag within the blog's taxonomy.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxonomyTerm {
+    /// Name of the category or the tag.
+    pub name: String,
+
+    /// The number of non-special posts under the category or the tag.
+    pub post_count: usize,
+}
+
+/// The number of non-special posts created within a month.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ArchiveBucket {
+    /// The year, e.g. 2022.
+    pub year: i32,
+
+    /// The month within the year, starting from 1.
+    pub month: u32,
+
+    /// The number of non-special posts created within the month.
+    pub count: usize,
+}
+
+/// A view of a post recorded for analytics.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PostView {
+    /// Slug of the viewed post.
+    pub slug: String,
+
+    /// Unix timestamp of the view, in seconds.
+    pub timestamp: i64,
+
+    /// The page that referred the viewer to the post, if known.
+    pub referrer: Option<String>,
+}
+
+/// The number of views of a post within a day.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DailyViewCount {
+    /// The year, e.g. 2022.
+    pub year: i32,
+
+    /// The month within the year, starting from 1.
+    pub month: u32,
+
+    /// The day within the month, starting from 1.
+    pub day: u32,
+
+    /// The number of views within the day.
+    pub count: usize,
+}
+
+/// A notification that a post has been written to the database.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PostEvent {
+    /// Slug of the post.
+    pub slug: String,
+
+    /// Title of the post.
+    pub title: String,
+
+    /// How the post has been written.
+    pub kind: PostEventKind,
+}
+
+impl PostEvent {
+    /// Create a new `PostEvent` of the given kind for the given post.
+    pub fn new(post: &Post, kind: PostEventKind) -> Self {
+        Self {
+            slug: post.slug.clone(),
+            title: post.title.clone(),
+            kind,
+        }
+    }
+}
+
+/// Kinds of [`PostEvent`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PostEventKind {
+    /// The post has been inserted.
+    Created,
+
+    /// The post has been updated.
+    Updated,
+
+    /// The post has been deleted.
+    Deleted,
 }
 
 /// A static resource.
@@ -65,6 +228,87 @@ pub struct Resource {
     pub data: Vec<u8>,
 }
 
+impl Resource {
+    /// Get the metadata of the resource.
+    pub fn meta(&self) -> ResourceMeta {
+        ResourceMeta {
+            id: self.id,
+            name: self.name.clone(),
+            ty: self.ty.clone(),
+            size: self.data.len() as u64,
+            content_hash: self.content_hash(),
+        }
+    }
+
+    /// Get a file name for the resource with the given ID and MIME type, in the form of `<uuid>.<ext>`.
+    ///
+    /// The extension is inferred from the MIME type. See [`mime_type_extension`].
+    pub fn file_name(id: &Uuid, ty: &str) -> String {
+        format!("{}.{}", id.as_hyphenated(), mime_type_extension(ty))
+    }
+
+    /// Compute the content hash of the resource, which is the hex-encoded SHA-256 digest of the resource's data.
+    ///
+    /// Resources with identical data have identical content hashes, regardless of their IDs, names and types.
+    pub fn content_hash(&self) -> String {
+        compute_content_hash(&self.data)
+    }
+}
+
+/// Get the file extension, without the leading dot, commonly used for files of the given MIME type.
+///
+/// Parameters of the MIME type such as `charset` are ignored. `bin` is returned for unknown MIME types.
+pub fn mime_type_extension(ty: &str) -> &'static str {
+    let essence = ty.split(';').next().unwrap_or_default().trim();
+    match essence.to_ascii_lowercase().as_str() {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        "image/bmp" => "bmp",
+        "image/tiff" => "tiff",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        "audio/mpeg" => "mp3",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/json" => "json",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" => "js",
+        _ => "bin",
+    }
+}
+
+/// Compute the hex-encoded SHA-256 digest of the given resource data.
+pub(crate) fn compute_content_hash(data: &[u8]) -> String {
+    let digest = Sha256::digest(data);
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Metadata of a static resource, without the resource's data.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ResourceMeta {
+    /// UUID of the resource.
+    pub id: Uuid,
+
+    /// Name of the resource.
+    pub name: String,
+
+    /// The MIME type of the resource.
+    pub ty: String,
+
+    /// Size of the resource's data, in bytes.
+    pub size: u64,
+
+    /// Content hash of the resource's data. See [`Resource::content_hash`].
+    pub content_hash: String,
+}
+
 /// A commit object.
 ///
 /// A commit object represents a unit of change to the blog content.
@@ -101,6 +345,19 @@ impl Commit {
             prev_commit_id,
             payload,
         };
+        commit.id = commit.compute_id();
+
+        commit
+    }
+
+    /// Compute the ID of this commit from the commit's metadata and data, ignoring the commit's current ID.
+    pub fn compute_id(&self) -> Vec<u8> {
+        let commit = Self {
+            id: Vec::new(),
+            timestamp: self.timestamp,
+            prev_commit_id: self.prev_commit_id.clone(),
+            payload: self.payload.clone(),
+        };
         let commit_digest_data = bson::to_vec(&commit).unwrap();
         let commit_digest = {
             let mut hasher = Sha256::new();
@@ -108,9 +365,7 @@ impl Commit {
             hasher.finalize()
         };
 
-        commit.id = Vec::from(commit_digest.as_slice());
-
-        commit
+        Vec::from(commit_digest.as_slice())
     }
 }
 
@@ -119,6 +374,7 @@ pub enum CommitPayload {
     CreatePost(CreatePostCommitPayload),
     DeletePost(DeletePostCommitPayload),
     CreateResource(CreateResourceCommitPayload),
+    UpdateResource(UpdateResourceCommitPayload),
     DeleteResource(DeleteResourceCommitPayload),
 }
 
@@ -144,6 +400,11 @@ impl CommitPayload {
         Self::CreateResource(CreateResourceCommitPayload { id })
     }
 
+    /// Create a new `UpdateResource` commit payload.
+    pub fn update_resource(id: Uuid) -> Self {
+        Self::UpdateResource(UpdateResourceCommitPayload { id })
+    }
+
     /// Create a new `DeleteResource` commit payload.
     pub fn delete_resource(id: Uuid) -> Self {
         Self::DeleteResource(DeleteResourceCommitPayload { id })
@@ -165,6 +426,11 @@ pub struct CreateResourceCommitPayload {
     pub id: Uuid,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UpdateResourceCommitPayload {
+    pub id: Uuid,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeleteResourceCommitPayload {
     pub id: Uuid,
@@ -184,4 +450,144 @@ impl Delta {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Verify that the commits in this delta form an intact commit chain.
+    ///
+    /// The ID of each commit must match the digest of the commit, and each commit must point to the previous commit
+    /// in the delta as its parent. The first commit must point to `expected_prev`, which is the ID of the latest commit
+    /// in the storage that the delta is applied to, or `None` if that storage does not contain any commits.
+    pub fn verify_chain(&self, expected_prev: Option<&[u8]>) -> Result<(), DeltaError> {
+        let mut prev_commit_id = expected_prev.unwrap_or_default();
+        for (index, commit) in self.commits.iter().enumerate() {
+            if commit.prev_commit_id != prev_commit_id {
+                return Err(DeltaError::BrokenChain { index });
+            }
+
+            if commit.id != commit.compute_id() {
+                return Err(DeltaError::InvalidCommitId { index });
+            }
+
+            prev_commit_id = &commit.id;
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors found when verifying the commits in a [`Delta`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeltaError {
+    /// The commit at the given index does not point to the previous commit as its parent.
+    BrokenChain { index: usize },
+
+    /// The ID of the commit at the given index does not match the digest of the commit.
+    InvalidCommitId { index: usize },
+}
+
+impl Display for DeltaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BrokenChain { index } => {
+                write!(f, "commit #{} does not follow the previous commit", index)
+            }
+            Self::InvalidCommitId { index } => {
+                write!(f, "commit #{} has an invalid commit ID", index)
+            }
+        }
+    }
+}
+
+impl Error for DeltaError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mime_type_extension() {
+        assert_eq!(mime_type_extension("image/jpeg"), "jpg");
+        assert_eq!(mime_type_extension("image/PNG"), "png");
+        assert_eq!(mime_type_extension("text/plain; charset=utf-8"), "txt");
+        assert_eq!(mime_type_extension("application/x-unknown"), "bin");
+        assert_eq!(mime_type_extension(""), "bin");
+    }
+
+    fn create_test_post(content: DocumentNode) -> Post {
+        Post {
+            title: String::from("title"),
+            slug: String::from("slug"),
+            author: String::from("msr"),
+            create_timestamp: 0,
+            update_timestamp: 0,
+            category: String::from("category"),
+            tags: Vec::new(),
+            is_special: false,
+            views: 0,
+            excerpt: String::new(),
+            content,
+        }
+    }
+
+    #[test]
+    fn test_reading_time_minutes() {
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.text("a short post");
+        });
+        let short = create_test_post(builder.finish());
+        assert_eq!(short.reading_time_minutes(200), 1);
+
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        for _ in 0..100 {
+            builder.paragraph(|b| {
+                b.text("one two three four five");
+            });
+        }
+        let long = create_test_post(builder.finish());
+        assert_eq!(long.reading_time_minutes(200), 3);
+        assert_eq!(long.reading_time_minutes(250), 2);
+
+        let empty = create_test_post(DocumentNode::new_empty());
+        assert_eq!(empty.reading_time_minutes(200), 0);
+    }
+
+    #[test]
+    fn test_normalize_slug() {
+        assert_eq!(
+            normalize_slug("hello-world").as_deref(),
+            Some("hello-world")
+        );
+        assert_eq!(
+            normalize_slug("  Hello   World!  ").as_deref(),
+            Some("hello-world")
+        );
+        assert_eq!(
+            normalize_slug("2022/10/my_post").as_deref(),
+            Some("2022-10-my_post")
+        );
+        assert_eq!(
+            normalize_slug("/posts//a - b/").as_deref(),
+            Some("posts-a-b")
+        );
+        assert_eq!(
+            normalize_slug("Ünïcode 你好 Wörld").as_deref(),
+            Some("ünïcode-你好-wörld")
+        );
+        assert_eq!(normalize_slug("?#%/ "), None);
+        assert_eq!(normalize_slug(""), None);
+
+        for slug in ["Hello World", "2022/10/my_post", "Ünïcode 你好"] {
+            let normalized = normalize_slug(slug).unwrap();
+            assert_eq!(normalize_slug(&normalized), Some(normalized));
+        }
+    }
+
+    #[test]
+    fn test_resource_file_name() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            Resource::file_name(&id, "image/jpeg"),
+            format!("{}.jpg", id.as_hyphenated())
+        );
+    }
 }
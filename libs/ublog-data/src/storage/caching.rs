@@ -0,0 +1,620 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::models::{
+    ArchiveBucket, Commit, DailyViewCount, Delta, Post, PostView, Resource, ResourceMeta, Taxonomy,
+};
+use crate::storage::{PaginatedList, Pagination, PostSort, Storage};
+
+/// A storage decorator that caches posts queried from the inner storage in memory.
+///
+/// Results of [`get_post`](Storage::get_post) and [`get_posts`](Storage::get_posts) are cached for a fixed amount of
+/// time. Cached entries affected by writes through this storage are invalidated immediately, while writes that bypass
+/// this storage only become visible after the cached entries expire.
+///
+/// Posts that do not exist are not cached, and at most [`MAX_CACHED_POSTS`] posts and [`MAX_CACHED_POST_LISTS`] post
+/// lists are cached at a time, so that clients querying arbitrary slugs or pages cannot grow the cache without bound.
+#[derive(Debug)]
+pub struct CachingStorage<S> {
+    inner: S,
+    posts: TtlMap<String, Post>,
+    post_lists: TtlMap<(bool, PostSort, Pagination), PaginatedList<Post>>,
+}
+
+/// Maximum number of posts cached by a [`CachingStorage`].
+pub const MAX_CACHED_POSTS: usize = 1024;
+
+/// Maximum number of post lists cached by a [`CachingStorage`].
+pub const MAX_CACHED_POST_LISTS: usize = 256;
+
+impl<S> CachingStorage<S> {
+    /// Create a new `CachingStorage` that wraps the given storage and caches query results for `expire_secs` seconds.
+    pub fn new(inner: S, expire_secs: u64) -> Self {
+        let expire = Duration::from_secs(expire_secs);
+        Self {
+            inner,
+            posts: TtlMap::new(expire, MAX_CACHED_POSTS),
+            post_lists: TtlMap::new(expire, MAX_CACHED_POST_LISTS),
+        }
+    }
+
+    /// Get the inner storage.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn invalidate_post(&self, post_slug: &str) {
+        self.posts.remove(post_slug);
+        self.post_lists.clear();
+    }
+
+    fn invalidate_all(&self) {
+        self.posts.clear();
+        self.post_lists.clear();
+    }
+}
+
+#[async_trait]
+impl<S> Storage for CachingStorage<S>
+where
+    S: Storage,
+{
+    type Error = S::Error;
+
+    async fn insert_post(
+        &self,
+        post: &Post,
+        post_resources: &[Resource],
+    ) -> Result<(), Self::Error> {
+        let ret = self.inner.insert_post(post, post_resources).await;
+        self.invalidate_post(&post.slug);
+        ret
+    }
+
+    async fn insert_posts(&self, posts: &[(Post, Vec<Resource>)]) -> Result<(), Self::Error> {
+        let ret = self.inner.insert_posts(posts).await;
+        for (post, _) in posts {
+            self.invalidate_post(&post.slug);
+        }
+        ret
+    }
+
+    async fn update_post(
+        &self,
+        post: &Post,
+        post_resources: &[Resource],
+    ) -> Result<(), Self::Error> {
+        let ret = self.inner.update_post(post, post_resources).await;
+        self.invalidate_post(&post.slug);
+        ret
+    }
+
+    async fn delete_post(&self, post_slug: &str) -> Result<bool, Self::Error> {
+        let ret = self.inner.delete_post(post_slug).await;
+        self.invalidate_post(post_slug);
+        ret
+    }
+
+    async fn increment_post_views(&self, post_slug: &str) -> Result<Option<u64>, Self::Error> {
+        // Cached posts are not invalidated here, otherwise every view would evict the viewed post from the cache. The
+        // view counts of cached posts are thus stale until the cache entries expire.
+        self.inner.increment_post_views(post_slug).await
+    }
+
+    async fn get_post(&self, post_slug: &str) -> Result<Option<Post>, Self::Error> {
+        if let Some(post) = self.posts.get(post_slug) {
+            return Ok(Some(post));
+        }
+
+        let post = self.inner.get_post(post_slug).await?;
+        if let Some(post) = &post {
+            self.posts.insert(String::from(post_slug), post.clone());
+        }
+        Ok(post)
+    }
+
+    async fn get_post_with_resources(
+        &self,
+        post_slug: &str,
+    ) -> Result<Option<(Post, Vec<Resource>)>, Self::Error> {
+        self.inner.get_post_with_resources(post_slug).await
+    }
+
+    async fn get_posts(
+        &self,
+        special: bool,
+        sort: PostSort,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        let key = (special, sort, *pagination);
+        if let Some(posts) = self.post_lists.get(&key) {
+            return Ok(posts);
+        }
+
+        let posts = self.inner.get_posts(special, sort, pagination).await?;
+        self.post_lists.insert(key, posts.clone());
+        Ok(posts)
+    }
+
+    async fn get_posts_by_tag(
+        &self,
+        tag: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        self.inner.get_posts_by_tag(tag, pagination).await
+    }
+
+    async fn get_posts_by_category(
+        &self,
+        category: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        self.inner.get_posts_by_category(category, pagination).await
+    }
+
+    async fn get_posts_updated_since(
+        &self,
+        since: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        self.inner.get_posts_updated_since(since, pagination).await
+    }
+
+    async fn get_adjacent_posts(
+        &self,
+        post_slug: &str,
+    ) -> Result<(Option<Post>, Option<Post>), Self::Error> {
+        self.inner.get_adjacent_posts(post_slug).await
+    }
+
+    async fn get_related_posts(
+        &self,
+        post_slug: &str,
+        limit: usize,
+    ) -> Result<Vec<Post>, Self::Error> {
+        self.inner.get_related_posts(post_slug, limit).await
+    }
+
+    async fn get_taxonomy(&self) -> Result<Taxonomy, Self::Error> {
+        self.inner.get_taxonomy().await
+    }
+
+    async fn get_post_archive(&self) -> Result<Vec<ArchiveBucket>, Self::Error> {
+        self.inner.get_post_archive().await
+    }
+
+    async fn get_tag_counts(&self) -> Result<Vec<(String, usize)>, Self::Error> {
+        self.inner.get_tag_counts().await
+    }
+
+    async fn search_posts(
+        &self,
+        query: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        self.inner.search_posts(query, pagination).await
+    }
+
+    async fn insert_resource(&self, resource: &Resource) -> Result<(), Self::Error> {
+        self.inner.insert_resource(resource).await
+    }
+
+    async fn update_resource(&self, resource: &Resource) -> Result<bool, Self::Error> {
+        self.inner.update_resource(resource).await
+    }
+
+    async fn delete_resource(&self, resource_id: &Uuid) -> Result<bool, Self::Error> {
+        self.inner.delete_resource(resource_id).await
+    }
+
+    async fn get_resource(&self, resource_id: &Uuid) -> Result<Option<Resource>, Self::Error> {
+        self.inner.get_resource(resource_id).await
+    }
+
+    async fn get_resource_meta(
+        &self,
+        resource_id: &Uuid,
+    ) -> Result<Option<ResourceMeta>, Self::Error> {
+        self.inner.get_resource_meta(resource_id).await
+    }
+
+    async fn read_resource_chunk(
+        &self,
+        resource_id: &Uuid,
+        offset: u64,
+        len: usize,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.inner
+            .read_resource_chunk(resource_id, offset, len)
+            .await
+    }
+
+    async fn find_resource_by_hash(&self, hash: &str) -> Result<Option<ResourceMeta>, Self::Error> {
+        self.inner.find_resource_by_hash(hash).await
+    }
+
+    async fn gc_resources(&self) -> Result<usize, Self::Error> {
+        self.inner.gc_resources().await
+    }
+
+    async fn get_resources_paged(
+        &self,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Resource>, Self::Error> {
+        self.inner.get_resources_paged(pagination).await
+    }
+
+    async fn get_resources(&self) -> Result<Vec<Resource>, Self::Error> {
+        self.inner.get_resources().await
+    }
+
+    async fn get_resource_metas(&self) -> Result<Vec<ResourceMeta>, Self::Error> {
+        self.inner.get_resource_metas().await
+    }
+
+    async fn get_commits_paged(
+        &self,
+        since_timestamp: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Commit>, Self::Error> {
+        self.inner
+            .get_commits_paged(since_timestamp, pagination)
+            .await
+    }
+
+    async fn get_commits_since(&self, since_timestamp: i64) -> Result<Vec<Commit>, Self::Error> {
+        self.inner.get_commits_since(since_timestamp).await
+    }
+
+    async fn get_latest_commit(&self) -> Result<Option<Commit>, Self::Error> {
+        self.inner.get_latest_commit().await
+    }
+
+    async fn apply_delta(&self, delta: &Delta) -> Result<(), Self::Error> {
+        let ret = self.inner.apply_delta(delta).await;
+        self.invalidate_all();
+        ret
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<String>, Self::Error> {
+        self.inner.get_metadata(key).await
+    }
+
+    async fn set_metadata(&self, key: &str, value: &str) -> Result<(), Self::Error> {
+        self.inner.set_metadata(key, value).await
+    }
+
+    async fn record_views(&self, views: &[PostView]) -> Result<(), Self::Error> {
+        self.inner.record_views(views).await
+    }
+
+    async fn get_view_stats(
+        &self,
+        post_slug: &str,
+        since: i64,
+    ) -> Result<Vec<DailyViewCount>, Self::Error> {
+        self.inner.get_view_stats(post_slug, since).await
+    }
+}
+
+/// A map whose entries expire after some time, holding at most a fixed number of entries.
+///
+/// Expired entries are swept whenever an entry is inserted. If the map is still full after that, the oldest entry is
+/// evicted.
+///
+/// The lock is never held across await points, so a synchronous mutex is sufficient.
+#[derive(Debug)]
+struct TtlMap<K, V> {
+    expire: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K, V> TtlMap<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    fn new(expire: Duration, capacity: usize) -> Self {
+        Self {
+            expire,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((t, value)) if t.elapsed() < self.expire => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (t, _)| t.elapsed() < self.expire);
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (t, _))| *t)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    fn remove<Q>(&self, key: &Q)
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use ublog_doc::DocumentNode;
+
+    use crate::storage::UnsupportedOperationError;
+
+    /// A storage that serves a single post and counts the queries of it.
+    struct CountingStorage {
+        post: Post,
+        get_post_calls: AtomicUsize,
+        get_posts_calls: AtomicUsize,
+    }
+
+    impl CountingStorage {
+        fn new() -> Self {
+            Self {
+                post: Post {
+                    title: String::from("title"),
+                    slug: String::from("slug"),
+                    author: String::from("author"),
+                    create_timestamp: 0,
+                    update_timestamp: 0,
+                    category: String::from("category"),
+                    tags: Vec::new(),
+                    content: DocumentNode::new_empty(),
+                    is_special: false,
+                    views: 0,
+                    excerpt: String::new(),
+                },
+                get_post_calls: AtomicUsize::new(0),
+                get_posts_calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Storage for CountingStorage {
+        type Error = UnsupportedOperationError;
+
+        async fn insert_post(&self, _post: &Post, _: &[Resource]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn update_post(&self, _post: &Post, _: &[Resource]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn delete_post(&self, _post_slug: &str) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+
+        async fn increment_post_views(&self, _post_slug: &str) -> Result<Option<u64>, Self::Error> {
+            Err(UnsupportedOperationError::new("increment_post_views"))
+        }
+
+        async fn get_post(&self, post_slug: &str) -> Result<Option<Post>, Self::Error> {
+            self.get_post_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(self.post.clone()).filter(|post| post.slug == post_slug))
+        }
+
+        async fn get_post_with_resources(
+            &self,
+            _post_slug: &str,
+        ) -> Result<Option<(Post, Vec<Resource>)>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_post_with_resources"))
+        }
+
+        async fn get_posts(
+            &self,
+            _special: bool,
+            _sort: PostSort,
+            _pagination: &Pagination,
+        ) -> Result<PaginatedList<Post>, Self::Error> {
+            self.get_posts_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(PaginatedList {
+                objects: vec![self.post.clone()],
+                total_count: 1,
+            })
+        }
+
+        async fn get_posts_by_tag(
+            &self,
+            _tag: &str,
+            _pagination: &Pagination,
+        ) -> Result<PaginatedList<Post>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_posts_by_tag"))
+        }
+
+        async fn get_posts_by_category(
+            &self,
+            _category: &str,
+            _pagination: &Pagination,
+        ) -> Result<PaginatedList<Post>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_posts_by_category"))
+        }
+
+        async fn get_taxonomy(&self) -> Result<Taxonomy, Self::Error> {
+            Err(UnsupportedOperationError::new("get_taxonomy"))
+        }
+
+        async fn get_tag_counts(&self) -> Result<Vec<(String, usize)>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_tag_counts"))
+        }
+
+        async fn insert_resource(&self, _resource: &Resource) -> Result<(), Self::Error> {
+            Err(UnsupportedOperationError::new("insert_resource"))
+        }
+
+        async fn update_resource(&self, _resource: &Resource) -> Result<bool, Self::Error> {
+            Err(UnsupportedOperationError::new("update_resource"))
+        }
+
+        async fn delete_resource(&self, _resource_id: &Uuid) -> Result<bool, Self::Error> {
+            Err(UnsupportedOperationError::new("delete_resource"))
+        }
+
+        async fn get_resource(&self, _resource_id: &Uuid) -> Result<Option<Resource>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_resource"))
+        }
+
+        async fn get_resources_paged(
+            &self,
+            _pagination: &Pagination,
+        ) -> Result<PaginatedList<Resource>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_resources_paged"))
+        }
+
+        async fn get_resource_metas(&self) -> Result<Vec<ResourceMeta>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_resource_metas"))
+        }
+
+        async fn get_commits_paged(
+            &self,
+            _since_timestamp: i64,
+            _pagination: &Pagination,
+        ) -> Result<PaginatedList<Commit>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_commits_paged"))
+        }
+
+        async fn get_latest_commit(&self) -> Result<Option<Commit>, Self::Error> {
+            Err(UnsupportedOperationError::new("get_latest_commit"))
+        }
+
+        async fn apply_delta(&self, _delta: &Delta) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_post_cached() {
+        let storage = CachingStorage::new(CountingStorage::new(), 60);
+        let calls = || storage.inner().get_post_calls.load(Ordering::SeqCst);
+
+        let post = storage.get_post("slug").await.unwrap().unwrap();
+        assert_eq!(post.slug, "slug");
+        assert_eq!(calls(), 1);
+
+        let post = storage.get_post("slug").await.unwrap().unwrap();
+        assert_eq!(post.slug, "slug");
+        assert_eq!(calls(), 1);
+
+        // Posts that do not exist are not cached.
+        assert!(storage.get_post("other").await.unwrap().is_none());
+        assert!(storage.get_post("other").await.unwrap().is_none());
+        assert_eq!(calls(), 3);
+        assert_eq!(storage.posts.len(), 1);
+    }
+
+    #[test]
+    fn test_ttl_map_evicts_oldest() {
+        let map = TtlMap::new(Duration::from_secs(60), 2);
+        map.insert(1, "a");
+        map.insert(2, "b");
+        map.insert(1, "c");
+        assert_eq!(map.len(), 2);
+
+        // Replacing an entry makes it the newest one.
+        map.insert(3, "d");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&1), Some("c"));
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get(&3), Some("d"));
+    }
+
+    #[test]
+    fn test_ttl_map_sweeps_expired() {
+        let map = TtlMap::new(Duration::ZERO, 16);
+        for key in 0..8 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_post_expired() {
+        let storage = CachingStorage::new(CountingStorage::new(), 0);
+
+        storage.get_post("slug").await.unwrap();
+        storage.get_post("slug").await.unwrap();
+        assert_eq!(storage.inner().get_post_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_on_write() {
+        let storage = CachingStorage::new(CountingStorage::new(), 60);
+        let post = storage.inner().post.clone();
+        let pagination = Pagination::from_page_and_size(1, 10);
+
+        storage.get_post("slug").await.unwrap();
+        storage
+            .get_posts(false, PostSort::CreatedDesc, &pagination)
+            .await
+            .unwrap();
+        storage
+            .get_posts(false, PostSort::CreatedDesc, &pagination)
+            .await
+            .unwrap();
+        assert_eq!(storage.inner().get_posts_calls.load(Ordering::SeqCst), 1);
+
+        storage.update_post(&post, &[]).await.unwrap();
+        storage.get_post("slug").await.unwrap();
+        storage
+            .get_posts(false, PostSort::CreatedDesc, &pagination)
+            .await
+            .unwrap();
+        assert_eq!(storage.inner().get_post_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(storage.inner().get_posts_calls.load(Ordering::SeqCst), 2);
+
+        storage.apply_delta(&Delta::new()).await.unwrap();
+        storage.get_post("slug").await.unwrap();
+        assert_eq!(storage.inner().get_post_calls.load(Ordering::SeqCst), 3);
+    }
+}
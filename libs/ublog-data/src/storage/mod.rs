@@ -1,55 +1,296 @@
+pub mod caching;
 #[cfg(feature = "remote-storage")]
 pub mod remote;
 pub mod sqlite;
 pub mod sync;
 
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::models::{Commit, Delta, Post, Resource};
+use crate::models::{
+    ArchiveBucket, Commit, DailyViewCount, Delta, Post, PostView, Resource, ResourceMeta, Taxonomy,
+};
 
 /// Provide storage for databases.
 #[async_trait]
 pub trait Storage: Send + Sync {
     /// Error type used by this storage type.
-    type Error: std::error::Error;
+    type Error: std::error::Error + From<UnsupportedOperationError>;
 
     async fn insert_post(
         &self,
         post: &Post,
         post_resources: &[Resource],
     ) -> Result<(), Self::Error>;
+
+    /// Insert the given posts together with their resources.
+    ///
+    /// The default implementation inserts the posts one by one. Storages that support transactions should override
+    /// this to insert all the posts atomically.
+    async fn insert_posts(&self, posts: &[(Post, Vec<Resource>)]) -> Result<(), Self::Error> {
+        for (post, post_resources) in posts {
+            self.insert_post(post, post_resources).await?;
+        }
+        Ok(())
+    }
+
     async fn update_post(
         &self,
         post: &Post,
         post_resources: &[Resource],
     ) -> Result<(), Self::Error>;
-    async fn delete_post(&self, post_slug: &str) -> Result<(), Self::Error>;
+    /// Delete the post with the given slug. Returns whether the post existed and has been deleted.
+    async fn delete_post(&self, post_slug: &str) -> Result<bool, Self::Error>;
+    /// Atomically increment the view count of the post with the given slug by one. Returns the new view count, or
+    /// `None` if the post does not exist.
+    async fn increment_post_views(&self, post_slug: &str) -> Result<Option<u64>, Self::Error>;
     async fn get_post(&self, post_slug: &str) -> Result<Option<Post>, Self::Error>;
     async fn get_post_with_resources(
         &self,
         post_slug: &str,
     ) -> Result<Option<(Post, Vec<Resource>)>, Self::Error>;
+    /// Get posts within the specified page, ordered as specified by `sort`.
     async fn get_posts(
         &self,
         special: bool,
+        sort: PostSort,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error>;
+
+    /// Get non-special posts that have the given tag, ordered by their creation time from the latest to the earliest.
+    async fn get_posts_by_tag(
+        &self,
+        tag: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error>;
+
+    /// Get non-special posts under the given category, ordered by their creation time from the latest to the earliest.
+    async fn get_posts_by_category(
+        &self,
+        category: &str,
         pagination: &Pagination,
     ) -> Result<PaginatedList<Post>, Self::Error>;
 
+    /// Get non-special posts updated after the given timestamp, ordered by their update time from the latest to the
+    /// earliest.
+    ///
+    /// Storages that cannot filter posts by their update time fail with an [`UnsupportedOperationError`].
+    async fn get_posts_updated_since(
+        &self,
+        _since: i64,
+        _pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        Err(UnsupportedOperationError::new("get_posts_updated_since").into())
+    }
+
+    /// Get the non-special posts immediately older and newer than the post with the given slug by their creation
+    /// time, without their contents.
+    ///
+    /// Both of the returned posts are `None` if the given post does not exist. Storages that cannot navigate between
+    /// posts fail with an [`UnsupportedOperationError`].
+    async fn get_adjacent_posts(
+        &self,
+        _post_slug: &str,
+    ) -> Result<(Option<Post>, Option<Post>), Self::Error> {
+        Err(UnsupportedOperationError::new("get_adjacent_posts").into())
+    }
+
+    /// Get at most `limit` non-special posts that share tags with the post with the given slug, without their
+    /// contents.
+    ///
+    /// The posts are ordered by the number of shared tags from the most to the least, and then by their creation time
+    /// from the latest to the earliest. The given post itself is excluded. Storages that cannot find related posts fail
+    /// with an [`UnsupportedOperationError`].
+    async fn get_related_posts(
+        &self,
+        _post_slug: &str,
+        _limit: usize,
+    ) -> Result<Vec<Post>, Self::Error> {
+        Err(UnsupportedOperationError::new("get_related_posts").into())
+    }
+
+    async fn get_taxonomy(&self) -> Result<Taxonomy, Self::Error>;
+
+    /// Get the number of non-special posts created within each month, in UTC, ordered from the latest month to the
+    /// earliest. Months without any posts are omitted.
+    ///
+    /// Storages that cannot group posts by their creation time fail with an [`UnsupportedOperationError`].
+    async fn get_post_archive(&self) -> Result<Vec<ArchiveBucket>, Self::Error> {
+        Err(UnsupportedOperationError::new("get_post_archive").into())
+    }
+
+    /// Get all distinct tags of non-special posts together with the number of non-special posts that have each tag,
+    /// ordered by the number of posts from the most to the least.
+    async fn get_tag_counts(&self) -> Result<Vec<(String, usize)>, Self::Error>;
+
+    /// Search for non-special posts that match the given query, ordered by relevance.
+    ///
+    /// The query is a list of terms separated by whitespaces; posts matching all the terms are returned. Storages that
+    /// cannot perform full-text search fail with an [`UnsupportedOperationError`].
+    async fn search_posts(
+        &self,
+        _query: &str,
+        _pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        Err(UnsupportedOperationError::new("search_posts").into())
+    }
+
     async fn insert_resource(&self, resource: &Resource) -> Result<(), Self::Error>;
-    async fn delete_resource(&self, resource_id: &Uuid) -> Result<(), Self::Error>;
+    /// Update the resource with the same ID as the given resource. Returns whether the resource existed and has been
+    /// updated; no commit is produced if it did not exist.
+    async fn update_resource(&self, resource: &Resource) -> Result<bool, Self::Error>;
+    /// Delete the resource with the given ID. Returns whether the resource existed and has been deleted.
+    async fn delete_resource(&self, resource_id: &Uuid) -> Result<bool, Self::Error>;
     async fn get_resource(&self, resource_id: &Uuid) -> Result<Option<Resource>, Self::Error>;
-    async fn get_resources(&self) -> Result<Vec<Resource>, Self::Error>;
 
-    async fn get_commits_since(&self, since_timestamp: i64) -> Result<Vec<Commit>, Self::Error>;
+    /// Get the metadata of the resource with the given ID.
+    ///
+    /// The default implementation loads the whole resource through [`get_resource`](Self::get_resource).
+    async fn get_resource_meta(
+        &self,
+        resource_id: &Uuid,
+    ) -> Result<Option<ResourceMeta>, Self::Error> {
+        let resource = self.get_resource(resource_id).await?;
+        Ok(resource.map(|res| res.meta()))
+    }
+
+    /// Read at most `len` bytes of the data of the resource with the given ID, starting at the given byte offset.
+    ///
+    /// Returns `None` if the resource does not exist, and an empty chunk if the offset is beyond the end of the data.
+    /// The default implementation loads the whole resource through [`get_resource`](Self::get_resource); storages
+    /// should override it to avoid loading large resources into memory.
+    async fn read_resource_chunk(
+        &self,
+        resource_id: &Uuid,
+        offset: u64,
+        len: usize,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        let resource = self.get_resource(resource_id).await?;
+        Ok(resource.map(|res| {
+            let start = usize::try_from(offset)
+                .unwrap_or(usize::MAX)
+                .min(res.data.len());
+            let end = start.saturating_add(len).min(res.data.len());
+            res.data[start..end].to_vec()
+        }))
+    }
+
+    /// Find a resource whose data has the given content hash, as computed by [`Resource::content_hash`].
+    ///
+    /// Storages that cannot look up resources by their content hashes fail with an [`UnsupportedOperationError`].
+    async fn find_resource_by_hash(
+        &self,
+        _hash: &str,
+    ) -> Result<Option<ResourceMeta>, Self::Error> {
+        Err(UnsupportedOperationError::new("find_resource_by_hash").into())
+    }
+
+    /// Delete all resources that are not referenced by any post. Returns the number of deleted resources.
+    ///
+    /// Resources inserted on their own through [`insert_resource`](Self::insert_resource) are not referenced by any
+    /// post and are thus deleted as well. Storages that cannot find unreferenced resources fail with an
+    /// [`UnsupportedOperationError`].
+    async fn gc_resources(&self) -> Result<usize, Self::Error> {
+        Err(UnsupportedOperationError::new("gc_resources").into())
+    }
+
+    async fn get_resources_paged(
+        &self,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Resource>, Self::Error>;
+
+    /// Get all resources, without their data.
+    ///
+    /// The default implementation walks through all pages returned by [`get_resources_paged`](Self::get_resources_paged).
+    async fn get_resources(&self) -> Result<Vec<Resource>, Self::Error> {
+        let mut resources = Vec::new();
+        for page in 1.. {
+            let pagination = Pagination::from_page_and_size(page, DEFAULT_PAGE_SIZE);
+            let resources_page = self.get_resources_paged(&pagination).await?;
+            let is_last_page = resources_page.objects.len() < DEFAULT_PAGE_SIZE;
+            resources.extend(resources_page.objects);
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(resources)
+    }
+
+    /// Get the metadata of all resources, ordered by their IDs.
+    async fn get_resource_metas(&self) -> Result<Vec<ResourceMeta>, Self::Error>;
+
+    async fn get_commits_paged(
+        &self,
+        since_timestamp: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Commit>, Self::Error>;
+
+    /// Get all commits created at or after the given timestamp, ordered by their timestamps.
+    ///
+    /// The default implementation walks through all pages returned by [`get_commits_paged`](Self::get_commits_paged).
+    async fn get_commits_since(&self, since_timestamp: i64) -> Result<Vec<Commit>, Self::Error> {
+        let mut commits = Vec::new();
+        for page in 1.. {
+            let pagination = Pagination::from_page_and_size(page, DEFAULT_PAGE_SIZE);
+            let commits_page = self.get_commits_paged(since_timestamp, &pagination).await?;
+            let is_last_page = commits_page.objects.len() < DEFAULT_PAGE_SIZE;
+            commits.extend(commits_page.objects);
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(commits)
+    }
+
     async fn get_latest_commit(&self) -> Result<Option<Commit>, Self::Error>;
 
     async fn apply_delta(&self, delta: &Delta) -> Result<(), Self::Error>;
+
+    /// Get the metadata value with the given key.
+    ///
+    /// Metadata are free-form key-value pairs that record the state of tools working on the storage, e.g. when the
+    /// storage was last synchronized with an external source. They are not part of the blog content and do not produce
+    /// commits. Storages that cannot store metadata fail with an [`UnsupportedOperationError`].
+    async fn get_metadata(&self, _key: &str) -> Result<Option<String>, Self::Error> {
+        Err(UnsupportedOperationError::new("get_metadata").into())
+    }
+
+    /// Set the metadata value with the given key, replacing the existing value if any. See
+    /// [`get_metadata`](Self::get_metadata).
+    async fn set_metadata(&self, _key: &str, _value: &str) -> Result<(), Self::Error> {
+        Err(UnsupportedOperationError::new("set_metadata").into())
+    }
+
+    /// Record the given post views for analytics, within a single batch.
+    ///
+    /// Views are not part of the blog content and do not produce commits. Storages may delete views older than a
+    /// retention period when recording new views; the SQLite storage keeps views for a year. Storages that cannot
+    /// record views fail with an [`UnsupportedOperationError`].
+    async fn record_views(&self, _views: &[PostView]) -> Result<(), Self::Error> {
+        Err(UnsupportedOperationError::new("record_views").into())
+    }
+
+    /// Get the number of views of the post with the given slug within each day, in UTC, counting only views recorded
+    /// at or after the given Unix timestamp. The days are ordered from the earliest to the latest, and days without
+    /// any views are omitted. See [`record_views`](Self::record_views).
+    async fn get_view_stats(
+        &self,
+        _post_slug: &str,
+        _since: i64,
+    ) -> Result<Vec<DailyViewCount>, Self::Error> {
+        Err(UnsupportedOperationError::new("get_view_stats").into())
+    }
 }
 
+/// Page size used when walking through all pages of a paginated storage query.
+const DEFAULT_PAGE_SIZE: usize = 100;
+
 /// Pagination parameters.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Pagination {
     page: usize,
     page_size: usize,
@@ -59,12 +300,34 @@ impl Pagination {
     /// Create a new [`Pagination`] object.
     ///
     /// `page` gives the page number starting from 1. `page_size` gives the number of items displayed on each page.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameters are invalid. Use [`try_from_page_and_size`](Self::try_from_page_and_size) for
+    /// parameters that come from user input.
     pub fn from_page_and_size(page: usize, page_size: usize) -> Self {
-        assert!(page > 0);
-        assert!(page_size > 0);
-        assert!((page - 1).checked_mul(page_size).is_some());
+        match Self::try_from_page_and_size(page, page_size) {
+            Ok(pagination) => pagination,
+            Err(err) => panic!("{}", err),
+        }
+    }
 
-        Self { page, page_size }
+    /// Create a new [`Pagination`] object, or fail if the parameters are invalid.
+    ///
+    /// `page` and `page_size` have the same meaning as in [`from_page_and_size`](Self::from_page_and_size). Both of
+    /// them must be positive, and the number of items before the requested page must fit in a `usize`.
+    pub fn try_from_page_and_size(page: usize, page_size: usize) -> Result<Self, PaginationError> {
+        if page == 0 {
+            return Err(PaginationError::ZeroPage);
+        }
+        if page_size == 0 {
+            return Err(PaginationError::ZeroPageSize);
+        }
+        if (page - 1).checked_mul(page_size).is_none() {
+            return Err(PaginationError::Overflow);
+        }
+
+        Ok(Self { page, page_size })
     }
 
     /// Get the page number. Page numbers start from 1.
@@ -83,6 +346,49 @@ impl Pagination {
     }
 }
 
+/// Order of posts in a post list.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PostSort {
+    /// By creation time, from the latest to the earliest.
+    #[default]
+    CreatedDesc,
+
+    /// By creation time, from the earliest to the latest.
+    CreatedAsc,
+
+    /// By update time, from the latest to the earliest.
+    UpdatedDesc,
+
+    /// By title, alphabetically.
+    TitleAsc,
+}
+
+/// Error indicating that pagination parameters are invalid.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PaginationError {
+    /// The page number is zero.
+    ZeroPage,
+
+    /// The page size is zero.
+    ZeroPageSize,
+
+    /// The number of items before the requested page overflows.
+    Overflow,
+}
+
+impl Display for PaginationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ZeroPage => write!(f, "page numbers start from 1"),
+            Self::ZeroPageSize => write!(f, "page size must be positive"),
+            Self::Overflow => write!(f, "page number or page size is too large"),
+        }
+    }
+}
+
+impl Error for PaginationError {}
+
 /// A paginated list.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PaginatedList<T> {
@@ -93,3 +399,29 @@ pub struct PaginatedList<T> {
     #[serde(rename = "totalCount")]
     pub total_count: usize,
 }
+
+/// Error indicating that a storage does not support the requested operation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnsupportedOperationError {
+    operation: &'static str,
+}
+
+impl UnsupportedOperationError {
+    /// Create a new `UnsupportedOperationError` for the given operation.
+    pub fn new(operation: &'static str) -> Self {
+        Self { operation }
+    }
+
+    /// Get the name of the unsupported operation.
+    pub fn operation(&self) -> &'static str {
+        self.operation
+    }
+}
+
+impl Display for UnsupportedOperationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unsupported operation: {}", self.operation)
+    }
+}
+
+impl Error for UnsupportedOperationError {}
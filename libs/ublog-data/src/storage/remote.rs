@@ -1,16 +1,79 @@
 use std::borrow::Cow;
 use std::error::Error;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use std::future::Future;
+use std::io::{Read, Write};
+use std::pin::Pin;
 
 use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
-use crate::models::{Commit, Delta, Post, Resource};
-use crate::storage::{PaginatedList, Pagination, Storage};
+use crate::models::{
+    ArchiveBucket, Commit, DailyViewCount, Delta, Post, PostView, Resource, ResourceMeta, Taxonomy,
+};
+use crate::storage::{PaginatedList, Pagination, PostSort, Storage, UnsupportedOperationError};
+
+/// The default maximum size of a single message exchanged between remote storage servers and clients, in bytes.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// Options of the communication between remote storage servers and clients.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RemoteStorageOptions {
+    /// The maximum size of a single message, in bytes.
+    ///
+    /// The limit applies to both the encoded and the decoded size of a message. Receiving a larger message fails with
+    /// [`RemoteStorageError::Protocol`].
+    pub max_frame_size: usize,
+
+    /// The codec used to encode the messages sent.
+    ///
+    /// Received messages are always decoded with the codec specified by the sender, so peers using different codecs
+    /// can communicate with each other.
+    pub codec: RemoteStorageCodec,
+}
+
+impl Default for RemoteStorageOptions {
+    fn default() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            codec: RemoteStorageCodec::None,
+        }
+    }
+}
+
+/// Codecs that encode the messages exchanged between remote storage servers and clients.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RemoteStorageCodec {
+    /// Messages are not compressed.
+    None,
+
+    /// Messages are compressed with gzip.
+    Gzip,
+}
+
+impl RemoteStorageCodec {
+    fn tag(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Gzip => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Self::None),
+            1 => Some(Self::Gzip),
+            _ => None,
+        }
+    }
+}
 
 /// A server that exposes an inner storage object through an underlying channel to a remote storage client.
 #[derive(Debug)]
@@ -31,9 +94,15 @@ where
     /// The created `RemoteStorageServer` serves storage data from the given inner storage. The communication to remote
     /// storage client is performed on the given communication channel.
     pub fn new(inner: &'s S, channel: T) -> Self {
+        Self::with_options(inner, channel, RemoteStorageOptions::default())
+    }
+
+    /// Create a new `RemoteStorageServer` object that communicates with the remote storage client with the given
+    /// options.
+    pub fn with_options(inner: &'s S, channel: T, options: RemoteStorageOptions) -> Self {
         Self {
             inner,
-            channel: RemoteStorageChannel::new(channel),
+            channel: RemoteStorageChannel::new(channel, options),
         }
     }
 }
@@ -51,44 +120,86 @@ where
     T: AsyncRead + AsyncWrite + Unpin,
 {
     /// Start the server and serve the underlying storage data.
-    pub async fn serve(&mut self) -> std::io::Result<()> {
+    ///
+    /// The server stops when the channel fails or a malformed message is received.
+    pub async fn serve(&mut self) -> Result<(), RemoteStorageError> {
         loop {
             let request: Request<'static> = self.channel.receive().await?;
             self.handle_request(request).await?;
         }
     }
 
-    async fn handle_request(&mut self, request: Request<'_>) -> std::io::Result<()> {
+    async fn handle_request(&mut self, request: Request<'_>) -> Result<(), RemoteStorageError> {
         match request {
             Request::InsertPost {
                 post,
                 post_resources,
             } => {
-                process_request!(self, self.inner.insert_post(&*post, &*post_resources));
+                process_request!(self, self.inner.insert_post(&post, &post_resources));
             }
             Request::UpdatePost {
                 post,
                 post_resources,
             } => {
-                process_request!(self, self.inner.update_post(&*post, &*post_resources));
+                process_request!(self, self.inner.update_post(&post, &post_resources));
             }
             Request::DeletePost { post_slug } => {
-                process_request!(self, self.inner.delete_post(&*post_slug));
+                process_request!(self, self.inner.delete_post(&post_slug));
+            }
+            Request::IncrementPostViews { post_slug } => {
+                process_request!(self, self.inner.increment_post_views(&post_slug));
             }
             Request::GetPost { post_slug } => {
-                process_request!(self, self.inner.get_post(&*post_slug));
+                process_request!(self, self.inner.get_post(&post_slug));
             }
             Request::GetPostWithResources { post_slug } => {
-                process_request!(self, self.inner.get_post_with_resources(&*post_slug));
+                process_request!(self, self.inner.get_post_with_resources(&post_slug));
             }
             Request::GetPosts {
                 special,
+                sort,
                 pagination,
             } => {
-                process_request!(self, self.inner.get_posts(special, &*pagination));
+                process_request!(self, self.inner.get_posts(special, sort, &pagination));
+            }
+            Request::GetPostsByTag { tag, pagination } => {
+                process_request!(self, self.inner.get_posts_by_tag(&tag, &pagination));
+            }
+            Request::GetPostsUpdatedSince { since, pagination } => {
+                process_request!(self, self.inner.get_posts_updated_since(since, &pagination));
+            }
+            Request::GetAdjacentPosts { post_slug } => {
+                process_request!(self, self.inner.get_adjacent_posts(&post_slug));
+            }
+            Request::GetRelatedPosts { post_slug, limit } => {
+                process_request!(self, self.inner.get_related_posts(&post_slug, limit));
+            }
+            Request::GetPostsByCategory {
+                category,
+                pagination,
+            } => {
+                process_request!(
+                    self,
+                    self.inner.get_posts_by_category(&category, &pagination)
+                );
+            }
+            Request::GetTaxonomy => {
+                process_request!(self, self.inner.get_taxonomy());
+            }
+            Request::GetPostArchive => {
+                process_request!(self, self.inner.get_post_archive());
+            }
+            Request::GetTagCounts => {
+                process_request!(self, self.inner.get_tag_counts());
+            }
+            Request::SearchPosts { query, pagination } => {
+                process_request!(self, self.inner.search_posts(&query, &pagination));
             }
             Request::InsertResource { resource } => {
-                process_request!(self, self.inner.insert_resource(&*resource));
+                process_request!(self, self.inner.insert_resource(&resource));
+            }
+            Request::UpdateResource { resource } => {
+                process_request!(self, self.inner.update_resource(&resource));
             }
             Request::DeleteResource { resource_id } => {
                 process_request!(self, self.inner.delete_resource(&resource_id));
@@ -96,24 +207,73 @@ where
             Request::GetResource { resource_id } => {
                 process_request!(self, self.inner.get_resource(&resource_id));
             }
+            Request::GetResourceMeta { resource_id } => {
+                process_request!(self, self.inner.get_resource_meta(&resource_id));
+            }
+            Request::ReadResourceChunk {
+                resource_id,
+                offset,
+                len,
+            } => {
+                process_request!(
+                    self,
+                    self.inner.read_resource_chunk(&resource_id, offset, len)
+                );
+            }
+            Request::FindResourceByHash { hash } => {
+                process_request!(self, self.inner.find_resource_by_hash(&hash));
+            }
+            Request::GcResources => {
+                process_request!(self, self.inner.gc_resources());
+            }
             Request::GetResources => {
                 process_request!(self, self.inner.get_resources());
             }
+            Request::GetResourceMetas => {
+                process_request!(self, self.inner.get_resource_metas());
+            }
+            Request::GetResourcesPaged { pagination } => {
+                process_request!(self, self.inner.get_resources_paged(&pagination));
+            }
             Request::GetCommitsSince { since_timestamp } => {
                 process_request!(self, self.inner.get_commits_since(since_timestamp));
             }
+            Request::GetCommitsPaged {
+                since_timestamp,
+                pagination,
+            } => {
+                process_request!(
+                    self,
+                    self.inner.get_commits_paged(since_timestamp, &pagination)
+                );
+            }
             Request::GetLatestCommit => {
                 process_request!(self, self.inner.get_latest_commit());
             }
             Request::ApplyDelta { delta } => {
-                process_request!(self, self.inner.apply_delta(&*delta));
+                process_request!(self, self.inner.apply_delta(&delta));
+            }
+            Request::GetMetadata { key } => {
+                process_request!(self, self.inner.get_metadata(&key));
+            }
+            Request::SetMetadata { key, value } => {
+                process_request!(self, self.inner.set_metadata(&key, &value));
+            }
+            Request::RecordViews { views } => {
+                process_request!(self, self.inner.record_views(&views));
+            }
+            Request::GetViewStats { post_slug, since } => {
+                process_request!(self, self.inner.get_view_stats(&post_slug, since));
             }
         }
 
         Ok(())
     }
 
-    async fn finish_request<R>(&mut self, inner_res: Result<R, S::Error>) -> std::io::Result<()>
+    async fn finish_request<R>(
+        &mut self,
+        inner_res: Result<R, S::Error>,
+    ) -> Result<(), RemoteStorageError>
     where
         R: Serialize,
     {
@@ -124,22 +284,66 @@ where
 }
 
 /// A [`Storage`] implementation that connects to a remote storage object through a [`RemoteStorageServer`].
-#[derive(Debug)]
 pub struct RemoteStorageClient<T> {
-    channel: Mutex<RemoteStorageChannel<T>>,
+    channel: Mutex<Option<RemoteStorageChannel<T>>>,
+    connector: Option<RemoteStorageConnector<T>>,
+    options: RemoteStorageOptions,
 }
 
+/// A function that opens a new communication channel to a [`RemoteStorageServer`].
+type RemoteStorageConnector<T> =
+    Box<dyn Fn() -> Pin<Box<dyn Future<Output = std::io::Result<T>> + Send>> + Send + Sync>;
+
 impl<T> RemoteStorageClient<T> {
     /// Create a new `RemoteStorageClient` object.
     ///
     /// The communication to the [`RemoteStorageServer`] object is performed through the given communication channel.
     pub fn new(channel: T) -> Self {
+        Self::with_options(channel, RemoteStorageOptions::default())
+    }
+
+    /// Create a new `RemoteStorageClient` object that communicates with the [`RemoteStorageServer`] object with the
+    /// given options.
+    pub fn with_options(channel: T, options: RemoteStorageOptions) -> Self {
+        Self {
+            channel: Mutex::new(Some(RemoteStorageChannel::new(channel, options))),
+            connector: None,
+            options,
+        }
+    }
+
+    /// Create a new `RemoteStorageClient` object that opens its communication channels to the [`RemoteStorageServer`]
+    /// object with the given function.
+    ///
+    /// The first channel is opened when the first request is made. Whenever a request fails because the channel is
+    /// broken, the channel is discarded and the next request opens a new one, so the client recovers from transient
+    /// disconnects. The failed request itself is not retried since it may have taken effect on the server.
+    pub fn with_connector<F, Fut>(connect: F, options: RemoteStorageOptions) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<T>> + Send + 'static,
+    {
         Self {
-            channel: Mutex::new(RemoteStorageChannel::new(channel)),
+            channel: Mutex::new(None),
+            connector: Some(Box::new(move || Box::pin(connect()))),
+            options,
         }
     }
 }
 
+impl<T> Debug for RemoteStorageClient<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteStorageClient")
+            .field("channel", &self.channel)
+            .field("reconnects", &self.connector.is_some())
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
 impl<T> RemoteStorageClient<T>
 where
     T: AsyncRead + AsyncWrite + Unpin,
@@ -148,12 +352,38 @@ where
     where
         R: DeserializeOwned,
     {
-        let mut channel = self.channel.lock().await;
+        let mut channel_guard = self.channel.lock().await;
+        let channel = match (&mut *channel_guard, &self.connector) {
+            (Some(channel), _) => channel,
+            (None, Some(connect)) => {
+                let channel = RemoteStorageChannel::new(connect().await?, self.options);
+                channel_guard.insert(channel)
+            }
+            (None, None) => unreachable!("clients without a connector always hold a channel"),
+        };
 
-        channel.send(request).await?;
+        let response = Self::exchange(channel, request).await;
+        if matches!(
+            response,
+            Err(RemoteStorageError::Io(_) | RemoteStorageError::Protocol(_))
+        ) && self.connector.is_some()
+        {
+            // The channel may be closed or left in the middle of a message; open a new one for the next request.
+            *channel_guard = None;
+        }
 
-        let response: Result<R, String> = channel.receive().await?;
-        response.map_err(RemoteStorageError::Remote)
+        response?.map_err(RemoteStorageError::Remote)
+    }
+
+    async fn exchange<'a, R>(
+        channel: &mut RemoteStorageChannel<T>,
+        request: &Request<'a>,
+    ) -> Result<Result<R, String>, RemoteStorageError>
+    where
+        R: DeserializeOwned,
+    {
+        channel.send(request).await?;
+        channel.receive().await
     }
 }
 
@@ -188,13 +418,20 @@ where
         .await
     }
 
-    async fn delete_post(&self, post_slug: &str) -> Result<(), Self::Error> {
+    async fn delete_post(&self, post_slug: &str) -> Result<bool, Self::Error> {
         self.execute_request(&Request::DeletePost {
             post_slug: Cow::Borrowed(post_slug),
         })
         .await
     }
 
+    async fn increment_post_views(&self, post_slug: &str) -> Result<Option<u64>, Self::Error> {
+        self.execute_request(&Request::IncrementPostViews {
+            post_slug: Cow::Borrowed(post_slug),
+        })
+        .await
+    }
+
     async fn get_post(&self, post_slug: &str) -> Result<Option<Post>, Self::Error> {
         self.execute_request(&Request::GetPost {
             post_slug: Cow::Borrowed(post_slug),
@@ -215,10 +452,94 @@ where
     async fn get_posts(
         &self,
         special: bool,
+        sort: PostSort,
         pagination: &Pagination,
     ) -> Result<PaginatedList<Post>, Self::Error> {
         self.execute_request(&Request::GetPosts {
             special,
+            sort,
+            pagination: Cow::Borrowed(pagination),
+        })
+        .await
+    }
+
+    async fn get_posts_by_tag(
+        &self,
+        tag: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        self.execute_request(&Request::GetPostsByTag {
+            tag: Cow::Borrowed(tag),
+            pagination: Cow::Borrowed(pagination),
+        })
+        .await
+    }
+
+    async fn get_posts_by_category(
+        &self,
+        category: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        self.execute_request(&Request::GetPostsByCategory {
+            category: Cow::Borrowed(category),
+            pagination: Cow::Borrowed(pagination),
+        })
+        .await
+    }
+
+    async fn get_posts_updated_since(
+        &self,
+        since: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        self.execute_request(&Request::GetPostsUpdatedSince {
+            since,
+            pagination: Cow::Borrowed(pagination),
+        })
+        .await
+    }
+
+    async fn get_adjacent_posts(
+        &self,
+        post_slug: &str,
+    ) -> Result<(Option<Post>, Option<Post>), Self::Error> {
+        self.execute_request(&Request::GetAdjacentPosts {
+            post_slug: Cow::Borrowed(post_slug),
+        })
+        .await
+    }
+
+    async fn get_related_posts(
+        &self,
+        post_slug: &str,
+        limit: usize,
+    ) -> Result<Vec<Post>, Self::Error> {
+        self.execute_request(&Request::GetRelatedPosts {
+            post_slug: Cow::Borrowed(post_slug),
+            limit,
+        })
+        .await
+    }
+
+    async fn get_taxonomy(&self) -> Result<Taxonomy, Self::Error> {
+        self.execute_request(&Request::GetTaxonomy).await
+    }
+
+    async fn get_post_archive(&self) -> Result<Vec<ArchiveBucket>, Self::Error> {
+        self.execute_request(&Request::GetPostArchive).await
+    }
+
+    async fn get_tag_counts(&self) -> Result<Vec<(String, usize)>, Self::Error> {
+        self.execute_request(&Request::GetTagCounts).await
+    }
+
+    async fn search_posts(
+        &self,
+        query: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        self.execute_request(&Request::SearchPosts {
+            query: Cow::Borrowed(query),
             pagination: Cow::Borrowed(pagination),
         })
         .await
@@ -231,7 +552,14 @@ where
         .await
     }
 
-    async fn delete_resource(&self, resource_id: &Uuid) -> Result<(), Self::Error> {
+    async fn update_resource(&self, resource: &Resource) -> Result<bool, Self::Error> {
+        self.execute_request(&Request::UpdateResource {
+            resource: Cow::Borrowed(resource),
+        })
+        .await
+    }
+
+    async fn delete_resource(&self, resource_id: &Uuid) -> Result<bool, Self::Error> {
         self.execute_request(&Request::DeleteResource {
             resource_id: *resource_id,
         })
@@ -245,10 +573,71 @@ where
         .await
     }
 
+    async fn get_resource_meta(
+        &self,
+        resource_id: &Uuid,
+    ) -> Result<Option<ResourceMeta>, Self::Error> {
+        self.execute_request(&Request::GetResourceMeta {
+            resource_id: *resource_id,
+        })
+        .await
+    }
+
+    async fn read_resource_chunk(
+        &self,
+        resource_id: &Uuid,
+        offset: u64,
+        len: usize,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.execute_request(&Request::ReadResourceChunk {
+            resource_id: *resource_id,
+            offset,
+            len,
+        })
+        .await
+    }
+
+    async fn find_resource_by_hash(&self, hash: &str) -> Result<Option<ResourceMeta>, Self::Error> {
+        self.execute_request(&Request::FindResourceByHash {
+            hash: Cow::Borrowed(hash),
+        })
+        .await
+    }
+
+    async fn gc_resources(&self) -> Result<usize, Self::Error> {
+        self.execute_request(&Request::GcResources).await
+    }
+
     async fn get_resources(&self) -> Result<Vec<Resource>, Self::Error> {
         self.execute_request(&Request::GetResources).await
     }
 
+    async fn get_resource_metas(&self) -> Result<Vec<ResourceMeta>, Self::Error> {
+        self.execute_request(&Request::GetResourceMetas).await
+    }
+
+    async fn get_resources_paged(
+        &self,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Resource>, Self::Error> {
+        self.execute_request(&Request::GetResourcesPaged {
+            pagination: Cow::Borrowed(pagination),
+        })
+        .await
+    }
+
+    async fn get_commits_paged(
+        &self,
+        since_timestamp: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Commit>, Self::Error> {
+        self.execute_request(&Request::GetCommitsPaged {
+            since_timestamp,
+            pagination: Cow::Borrowed(pagination),
+        })
+        .await
+    }
+
     async fn get_commits_since(&self, since_timestamp: i64) -> Result<Vec<Commit>, Self::Error> {
         self.execute_request(&Request::GetCommitsSince { since_timestamp })
             .await
@@ -264,6 +653,40 @@ where
         })
         .await
     }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<String>, Self::Error> {
+        self.execute_request(&Request::GetMetadata {
+            key: Cow::Borrowed(key),
+        })
+        .await
+    }
+
+    async fn set_metadata(&self, key: &str, value: &str) -> Result<(), Self::Error> {
+        self.execute_request(&Request::SetMetadata {
+            key: Cow::Borrowed(key),
+            value: Cow::Borrowed(value),
+        })
+        .await
+    }
+
+    async fn record_views(&self, views: &[PostView]) -> Result<(), Self::Error> {
+        self.execute_request(&Request::RecordViews {
+            views: Cow::Borrowed(views),
+        })
+        .await
+    }
+
+    async fn get_view_stats(
+        &self,
+        post_slug: &str,
+        since: i64,
+    ) -> Result<Vec<DailyViewCount>, Self::Error> {
+        self.execute_request(&Request::GetViewStats {
+            post_slug: Cow::Borrowed(post_slug),
+            since,
+        })
+        .await
+    }
 }
 
 /// Error type of the remote storage.
@@ -271,6 +694,10 @@ where
 pub enum RemoteStorageError {
     Io(std::io::Error),
     Remote(String),
+    Unsupported(UnsupportedOperationError),
+
+    /// A message that violates the remote storage protocol is sent or received.
+    Protocol(String),
 }
 
 impl Display for RemoteStorageError {
@@ -278,6 +705,8 @@ impl Display for RemoteStorageError {
         match self {
             Self::Io(err) => write!(f, "IO error: {}", err),
             Self::Remote(msg) => write!(f, "remote error: {}", msg),
+            Self::Unsupported(err) => write!(f, "{}", err),
+            Self::Protocol(msg) => write!(f, "protocol error: {}", msg),
         }
     }
 }
@@ -290,6 +719,12 @@ impl From<std::io::Error> for RemoteStorageError {
     }
 }
 
+impl From<UnsupportedOperationError> for RemoteStorageError {
+    fn from(err: UnsupportedOperationError) -> Self {
+        Self::Unsupported(err)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 enum Request<'a> {
     InsertPost {
@@ -303,6 +738,9 @@ enum Request<'a> {
     DeletePost {
         post_slug: Cow<'a, str>,
     },
+    IncrementPostViews {
+        post_slug: Cow<'a, str>,
+    },
     GetPost {
         post_slug: Cow<'a, str>,
     },
@@ -311,35 +749,159 @@ enum Request<'a> {
     },
     GetPosts {
         special: bool,
+        #[serde(default)]
+        sort: PostSort,
+        pagination: Cow<'a, Pagination>,
+    },
+    GetPostsByTag {
+        tag: Cow<'a, str>,
+        pagination: Cow<'a, Pagination>,
+    },
+    GetPostsUpdatedSince {
+        since: i64,
+        pagination: Cow<'a, Pagination>,
+    },
+    GetAdjacentPosts {
+        post_slug: Cow<'a, str>,
+    },
+    GetRelatedPosts {
+        post_slug: Cow<'a, str>,
+        limit: usize,
+    },
+    GetPostsByCategory {
+        category: Cow<'a, str>,
+        pagination: Cow<'a, Pagination>,
+    },
+    GetTaxonomy,
+    GetPostArchive,
+    GetTagCounts,
+    SearchPosts {
+        query: Cow<'a, str>,
         pagination: Cow<'a, Pagination>,
     },
     InsertResource {
         resource: Cow<'a, Resource>,
     },
+    UpdateResource {
+        resource: Cow<'a, Resource>,
+    },
     DeleteResource {
         resource_id: Uuid,
     },
     GetResource {
         resource_id: Uuid,
     },
+    GetResourceMeta {
+        resource_id: Uuid,
+    },
+    ReadResourceChunk {
+        resource_id: Uuid,
+        offset: u64,
+        len: usize,
+    },
+    FindResourceByHash {
+        hash: Cow<'a, str>,
+    },
+    GcResources,
     GetResources,
+    GetResourceMetas,
+    GetResourcesPaged {
+        pagination: Cow<'a, Pagination>,
+    },
     GetCommitsSince {
         since_timestamp: i64,
     },
+    GetCommitsPaged {
+        since_timestamp: i64,
+        pagination: Cow<'a, Pagination>,
+    },
     GetLatestCommit,
     ApplyDelta {
         delta: Cow<'a, Delta>,
     },
+    GetMetadata {
+        key: Cow<'a, str>,
+    },
+    SetMetadata {
+        key: Cow<'a, str>,
+        value: Cow<'a, str>,
+    },
+    RecordViews {
+        views: Cow<'a, [PostView]>,
+    },
+    GetViewStats {
+        post_slug: Cow<'a, str>,
+        since: i64,
+    },
+}
+
+/// Wraps messages sent through [`RemoteStorageChannel`].
+///
+/// BSON only allows documents at the top level, so messages that serialize to other BSON types (e.g. unit enum
+/// variants, which serialize to strings) are wrapped into a document.
+#[derive(Debug, Deserialize, Serialize)]
+struct Envelope<T> {
+    value: T,
 }
 
+/// A channel that exchanges BSON messages.
+///
+/// Each message is sent as a frame, which consists of the payload size as a little-endian `u64`, a one-byte tag of the
+/// codec that encodes the payload, and the encoded payload.
 #[derive(Debug)]
 struct RemoteStorageChannel<T> {
     inner: T,
+    options: RemoteStorageOptions,
 }
 
 impl<T> RemoteStorageChannel<T> {
-    fn new(inner: T) -> Self {
-        Self { inner }
+    fn new(inner: T, options: RemoteStorageOptions) -> Self {
+        Self { inner, options }
+    }
+
+    fn check_frame_size(&self, frame_size: u64) -> Result<usize, RemoteStorageError> {
+        let max_frame_size = self.options.max_frame_size;
+        match usize::try_from(frame_size) {
+            Ok(frame_size) if frame_size <= max_frame_size => Ok(frame_size),
+            _ => Err(RemoteStorageError::Protocol(format!(
+                "frame size {} exceeds the maximum frame size {}",
+                frame_size, max_frame_size
+            ))),
+        }
+    }
+
+    fn encode_payload(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self.options.codec {
+            RemoteStorageCodec::None => Ok(data),
+            RemoteStorageCodec::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    fn decode_payload(
+        &self,
+        codec: RemoteStorageCodec,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, RemoteStorageError> {
+        match codec {
+            RemoteStorageCodec::None => Ok(data),
+            RemoteStorageCodec::Gzip => {
+                // Limit the decoded size so that a small frame cannot expand into an absurd amount of memory.
+                let limit = self.options.max_frame_size as u64 + 1;
+                let mut decoded = Vec::new();
+                GzDecoder::new(data.as_slice())
+                    .take(limit)
+                    .read_to_end(&mut decoded)
+                    .map_err(|err| {
+                        RemoteStorageError::Protocol(format!("cannot decompress message: {}", err))
+                    })?;
+                self.check_frame_size(decoded.len() as u64)?;
+                Ok(decoded)
+            }
+        }
     }
 }
 
@@ -347,34 +909,284 @@ impl<T> RemoteStorageChannel<T>
 where
     T: AsyncRead + AsyncWrite + Unpin,
 {
-    async fn send<U>(&mut self, value: &U) -> std::io::Result<()>
+    async fn send<U>(&mut self, value: &U) -> Result<(), RemoteStorageError>
     where
         U: Serialize,
     {
-        let mut value_bson_data = bson::to_vec(value).unwrap();
+        let value_bson_data = bson::to_vec(&Envelope { value }).map_err(|err| {
+            RemoteStorageError::Protocol(format!("cannot encode message: {}", err))
+        })?;
+        self.check_frame_size(value_bson_data.len() as u64)?;
 
-        let mut packet_data =
-            Vec::with_capacity(value_bson_data.len() + std::mem::size_of::<u64>());
-        packet_data.extend_from_slice(&value_bson_data.len().to_le_bytes());
-        packet_data.append(&mut value_bson_data);
+        let mut payload = self.encode_payload(value_bson_data)?;
+        self.check_frame_size(payload.len() as u64)?;
+
+        let mut packet_data = Vec::with_capacity(payload.len() + std::mem::size_of::<u64>() + 1);
+        packet_data.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        packet_data.push(self.options.codec.tag());
+        packet_data.append(&mut payload);
 
         self.inner.write_all(&packet_data).await?;
 
         Ok(())
     }
 
-    async fn receive<U>(&mut self) -> std::io::Result<U>
+    async fn receive<U>(&mut self) -> Result<U, RemoteStorageError>
     where
         U: DeserializeOwned,
     {
         let mut packet_size_data = [0u8; std::mem::size_of::<u64>()];
         self.inner.read_exact(&mut packet_size_data).await?;
 
-        let packet_size = u64::from_le_bytes(packet_size_data) as usize;
-        let mut value_bson_data = vec![0u8; packet_size];
-        self.inner.read_exact(&mut value_bson_data).await?;
+        // Reject absurd frame sizes before allocating the buffer for them.
+        let packet_size = self.check_frame_size(u64::from_le_bytes(packet_size_data))?;
+
+        let codec_tag = self.inner.read_u8().await?;
+        let codec = RemoteStorageCodec::from_tag(codec_tag).ok_or_else(|| {
+            RemoteStorageError::Protocol(format!("unknown codec tag {}", codec_tag))
+        })?;
+
+        let mut payload = vec![0u8; packet_size];
+        self.inner.read_exact(&mut payload).await?;
+        let value_bson_data = self.decode_payload(codec, payload)?;
+
+        let envelope: Envelope<U> = bson::from_slice(&value_bson_data).map_err(|err| {
+            RemoteStorageError::Protocol(format!("cannot decode message: {}", err))
+        })?;
+        Ok(envelope.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tokio::io::DuplexStream;
+
+    use crate::storage::sqlite::SqliteStorage;
+
+    fn options(max_frame_size: usize, codec: RemoteStorageCodec) -> RemoteStorageOptions {
+        RemoteStorageOptions {
+            max_frame_size,
+            codec,
+        }
+    }
+
+    async fn receive_raw(
+        data: &[u8],
+        max_frame_size: usize,
+    ) -> Result<Request<'static>, RemoteStorageError> {
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        writer.write_all(data).await.unwrap();
+        drop(writer);
+
+        let options = options(max_frame_size, RemoteStorageCodec::None);
+        let mut channel = RemoteStorageChannel::<DuplexStream>::new(reader, options);
+        channel.receive().await
+    }
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut data = (payload.len() as u64).to_le_bytes().to_vec();
+        data.push(RemoteStorageCodec::None.tag());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[tokio::test]
+    async fn test_receive_truncated_frame() {
+        let payload = bson::to_vec(&Envelope {
+            value: Request::GetTaxonomy,
+        })
+        .unwrap();
+        let data = frame(&payload);
+
+        let err = receive_raw(&data[..data.len() - 1], DEFAULT_MAX_FRAME_SIZE)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, RemoteStorageError::Io(err) if err.kind() == std::io::ErrorKind::UnexpectedEof)
+        );
+
+        let err = receive_raw(&data[..4], DEFAULT_MAX_FRAME_SIZE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RemoteStorageError::Io(_)));
+    }
+
+    #[tokio::test]
+    async fn test_receive_malformed_frame() {
+        let err = receive_raw(&frame(&[1, 2, 3, 4, 5]), DEFAULT_MAX_FRAME_SIZE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RemoteStorageError::Protocol(_)));
+    }
+
+    #[tokio::test]
+    async fn test_receive_oversized_frame() {
+        let err = receive_raw(&u64::MAX.to_le_bytes(), DEFAULT_MAX_FRAME_SIZE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RemoteStorageError::Protocol(_)));
+
+        let payload = bson::to_vec(&Envelope {
+            value: Request::GetTaxonomy,
+        })
+        .unwrap();
+        let err = receive_raw(&frame(&payload), payload.len() - 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RemoteStorageError::Protocol(_)));
+    }
+
+    #[tokio::test]
+    async fn test_receive_unknown_codec() {
+        let mut data = frame(&[]);
+        data[std::mem::size_of::<u64>()] = 0xff;
+
+        let err = receive_raw(&data, DEFAULT_MAX_FRAME_SIZE)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RemoteStorageError::Protocol(_)));
+    }
+
+    #[tokio::test]
+    async fn test_send_receive() {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut client = RemoteStorageChannel::new(client, RemoteStorageOptions::default());
+        let mut server = RemoteStorageChannel::new(server, RemoteStorageOptions::default());
+
+        client.send(&Request::GetLatestCommit).await.unwrap();
+        let request: Request = server.receive().await.unwrap();
+        assert!(matches!(request, Request::GetLatestCommit));
+    }
+
+    #[tokio::test]
+    async fn test_client_reconnect() {
+        let storage = Arc::new(SqliteStorage::new_memory().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let connect = {
+            let connections = connections.clone();
+            move || {
+                let storage = storage.clone();
+                let attempt = connections.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let (client, server) = tokio::io::duplex(1024);
+                    // The server behind the first connection goes away immediately.
+                    if attempt > 0 {
+                        tokio::spawn(async move {
+                            let _ = RemoteStorageServer::new(&*storage, server).serve().await;
+                        });
+                    }
+                    Ok(client)
+                }
+            }
+        };
+        let client = RemoteStorageClient::with_connector(connect, RemoteStorageOptions::default());
+        assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+        let err = client.get_latest_commit().await.unwrap_err();
+        assert!(matches!(err, RemoteStorageError::Io(_)));
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        for _ in 0..2 {
+            assert!(client.get_latest_commit().await.unwrap().is_none());
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    fn create_large_delta() -> Delta {
+        let mut delta = Delta::new();
+        for idx in 0..20 {
+            let mut content = ublog_doc::builder::DocumentTreeBuilder::new();
+            for para in 0..20 {
+                content.paragraph(|b| {
+                    b.text(format!("Paragraph {} of post {} has some text.", para, idx));
+                });
+            }
+
+            let post = Post {
+                title: format!("Post {}", idx),
+                slug: format!("post-{}", idx),
+                author: String::from("author"),
+                create_timestamp: idx,
+                update_timestamp: idx,
+                category: String::from("category"),
+                tags: vec![String::from("tag")],
+                is_special: false,
+                views: 0,
+                excerpt: String::new(),
+                content: content.finish(),
+            };
+            let resource = Resource {
+                id: Uuid::new_v4(),
+                name: format!("resource-{}", idx),
+                ty: String::from("text/plain"),
+                data: vec![idx as u8; 4096],
+            };
+            delta.added_posts.push((post, vec![resource]));
+        }
+        delta
+    }
+
+    /// Send the given value through a channel with the given codec. Returns the received value and the size of the
+    /// frame on the wire.
+    async fn round_trip<U>(value: &U, codec: RemoteStorageCodec) -> (U, usize)
+    where
+        U: DeserializeOwned + Serialize,
+    {
+        let options = options(DEFAULT_MAX_FRAME_SIZE, codec);
+
+        let mut sender = RemoteStorageChannel::new(std::io::Cursor::new(Vec::new()), options);
+        sender.send(value).await.unwrap();
+        let frame = sender.inner.into_inner();
+        let frame_size = frame.len();
+
+        // The receiver decodes the frame with the codec specified by the sender, regardless of its own codec.
+        let receiver_options = RemoteStorageOptions::default();
+        let mut receiver = RemoteStorageChannel::new(std::io::Cursor::new(frame), receiver_options);
+        (receiver.receive().await.unwrap(), frame_size)
+    }
+
+    #[tokio::test]
+    async fn test_compressed_round_trip() {
+        let delta = create_large_delta();
+        let delta_data = bson::to_vec(&Envelope { value: &delta }).unwrap();
+
+        let (plain, plain_size) = round_trip(&delta, RemoteStorageCodec::None).await;
+        let (compressed, compressed_size) = round_trip(&delta, RemoteStorageCodec::Gzip).await;
+
+        assert_eq!(
+            bson::to_vec(&Envelope { value: &plain }).unwrap(),
+            delta_data
+        );
+        assert_eq!(
+            bson::to_vec(&Envelope { value: &compressed }).unwrap(),
+            delta_data
+        );
+        assert!(compressed_size < plain_size / 4);
+    }
+
+    #[tokio::test]
+    async fn test_receive_compressed_oversized() {
+        let delta = create_large_delta();
+
+        let mut sender = RemoteStorageChannel::new(
+            std::io::Cursor::new(Vec::new()),
+            options(DEFAULT_MAX_FRAME_SIZE, RemoteStorageCodec::Gzip),
+        );
+        sender.send(&delta).await.unwrap();
+        let frame = sender.inner.into_inner();
 
-        let value = bson::from_slice(&value_bson_data).unwrap();
-        Ok(value)
+        // The compressed frame fits in the limit, but the decompressed message does not.
+        let max_frame_size = frame.len() * 2;
+        let mut receiver = RemoteStorageChannel::new(
+            std::io::Cursor::new(frame),
+            options(max_frame_size, RemoteStorageCodec::None),
+        );
+        let err = receiver.receive::<Delta>().await.unwrap_err();
+        assert!(matches!(err, RemoteStorageError::Protocol(_)));
     }
 }
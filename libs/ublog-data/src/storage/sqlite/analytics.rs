@@ -0,0 +1,158 @@
+use rusqlite::Connection;
+
+use crate::models::{DailyViewCount, PostView};
+use crate::storage::sqlite::{SqliteExt, SqliteStorageError};
+
+pub(crate) fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const INIT_SQL: &str = r#"
+        CREATE TABLE analytics (
+            post_slug TEXT    NOT NULL,
+            timestamp INTEGER NOT NULL,
+            referrer  TEXT
+        );
+        CREATE INDEX analytics_post_slug_timestamp ON analytics (post_slug, timestamp);
+    "#;
+    conn.execute_batch(INIT_SQL)?;
+
+    Ok(())
+}
+
+/// Number of seconds for which recorded views are kept. Views older than this, relative to the latest view recorded,
+/// are deleted when new views are recorded.
+pub(crate) const VIEW_RETENTION_SECONDS: i64 = 365 * 24 * 60 * 60;
+
+pub(crate) fn record_views(
+    conn: &Connection,
+    views: &[PostView],
+) -> Result<(), SqliteStorageError> {
+    const INSERT_SQL: &str = r#"
+        INSERT INTO analytics (post_slug, timestamp, referrer)
+        VALUES (?, ?, ?);
+    "#;
+    const DELETE_SQL: &str = r#"
+        DELETE FROM analytics
+        WHERE timestamp < ?;
+    "#;
+
+    let trans = conn.unchecked_transaction()?;
+    {
+        let mut stmt = trans.prepare_cached(INSERT_SQL)?;
+        for view in views {
+            stmt.execute((&view.slug, view.timestamp, &view.referrer))?;
+        }
+    }
+    if let Some(latest) = views.iter().map(|view| view.timestamp).max() {
+        trans.execute(DELETE_SQL, (latest - VIEW_RETENTION_SECONDS,))?;
+    }
+    trans.commit()?;
+
+    Ok(())
+}
+
+pub(crate) fn get_view_stats(
+    conn: &Connection,
+    post_slug: &str,
+    since: i64,
+) -> Result<Vec<DailyViewCount>, SqliteStorageError> {
+    // View timestamps are Unix timestamps, so views are grouped by the days in UTC.
+    const SELECT_SQL: &str = r#"
+        SELECT CAST(strftime('%Y', datetime(timestamp, 'unixepoch')) AS INTEGER) AS year,
+               CAST(strftime('%m', datetime(timestamp, 'unixepoch')) AS INTEGER) AS month,
+               CAST(strftime('%d', datetime(timestamp, 'unixepoch')) AS INTEGER) AS day,
+               count(*) AS count
+        FROM analytics
+        WHERE post_slug == ? AND timestamp >= ?
+        GROUP BY year, month, day
+        ORDER BY year, month, day;
+    "#;
+
+    conn.query_many(SELECT_SQL, (post_slug, since), |row| {
+        Ok(DailyViewCount {
+            year: row.get("year")?,
+            month: row.get("month")?,
+            day: row.get("day")?,
+            count: row.get("count")?,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+    fn init_db_connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db_schema(&conn).unwrap();
+        conn
+    }
+
+    fn create_view(slug: &str, timestamp: i64) -> PostView {
+        PostView {
+            slug: String::from(slug),
+            timestamp,
+            referrer: None,
+        }
+    }
+
+    #[test]
+    fn test_get_view_stats() {
+        let conn = init_db_connection();
+
+        // 2022-10-01T00:00:00Z.
+        let day = 1664582400;
+        let mut views = vec![
+            create_view("post1", day - 1),
+            create_view("post1", day),
+            create_view("post1", day + DAY_SECONDS - 1),
+            create_view("post1", day + 2 * DAY_SECONDS),
+            create_view("post2", day),
+        ];
+        views[1].referrer = Some(String::from("https://example.com/"));
+        record_views(&conn, &views).unwrap();
+
+        assert_eq!(
+            get_view_stats(&conn, "post1", day).unwrap(),
+            [
+                DailyViewCount {
+                    year: 2022,
+                    month: 10,
+                    day: 1,
+                    count: 2
+                },
+                DailyViewCount {
+                    year: 2022,
+                    month: 10,
+                    day: 3,
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(get_view_stats(&conn, "post1", 0).unwrap()[0].day, 30);
+        assert!(get_view_stats(&conn, "post3", 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_views_deletes_expired_views() {
+        let conn = init_db_connection();
+
+        let day = 1664582400;
+        record_views(&conn, &[create_view("post1", day)]).unwrap();
+        record_views(
+            &conn,
+            &[create_view("post1", day + VIEW_RETENTION_SECONDS - 1)],
+        )
+        .unwrap();
+        assert_eq!(get_view_stats(&conn, "post1", 0).unwrap().len(), 2);
+
+        record_views(
+            &conn,
+            &[create_view("post1", day + VIEW_RETENTION_SECONDS + 1)],
+        )
+        .unwrap();
+        let stats = get_view_stats(&conn, "post1", 0).unwrap();
+        assert_eq!(stats.iter().map(|stats| stats.count).sum::<usize>(), 2);
+        assert!(stats.iter().all(|stats| stats.year > 2022));
+    }
+}
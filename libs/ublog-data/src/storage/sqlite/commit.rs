@@ -2,6 +2,7 @@ use rusqlite::{Connection, Row, ToSql};
 
 use crate::models::{Commit, CommitPayload};
 use crate::storage::sqlite::{SqliteExt, SqliteStorageError};
+use crate::storage::{PaginatedList, Pagination};
 
 pub(crate) fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
     const INIT_SQL: &str = r#"
@@ -23,7 +24,7 @@ pub(crate) fn get_latest_commit(conn: &Connection) -> Result<Option<Commit>, Sql
     const SELECT_SQL: &str = r#"
         SELECT id, timestamp, prev_commit_id, payload
         FROM commits
-        ORDER BY timestamp DESC
+        ORDER BY timestamp DESC, rowid DESC
         LIMIT 1;
     "#;
 
@@ -38,12 +39,52 @@ pub(crate) fn get_commits(
         SELECT id, timestamp, prev_commit_id, payload
         FROM commits
         WHERE timestamp >= ?
-        ORDER BY timestamp ASC;
+        ORDER BY timestamp ASC, rowid ASC;
     "#;
 
     conn.query_many(SELECT_SQL, (since_timestamp,), create_commit_from_row)
 }
 
+pub(crate) fn get_commits_paged(
+    conn: &Connection,
+    since_timestamp: i64,
+    pagination: &Pagination,
+) -> Result<PaginatedList<Commit>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT id, timestamp, prev_commit_id, payload
+        FROM commits
+        WHERE timestamp >= ?
+        ORDER BY timestamp ASC, rowid ASC
+        LIMIT ? OFFSET ?;
+    "#;
+
+    const SELECT_COUNT_SQL: &str = r#"
+        SELECT count(*) AS cnt
+        FROM commits
+        WHERE timestamp >= ?;
+    "#;
+
+    let limit = pagination.page_size();
+    let offset = pagination.skip_count();
+
+    let total_count: usize = conn
+        .query_one(SELECT_COUNT_SQL, (since_timestamp,), |row| {
+            row.get(0).map_err(From::from)
+        })?
+        .unwrap();
+
+    let commits = conn.query_many(
+        SELECT_SQL,
+        (since_timestamp, limit, offset),
+        create_commit_from_row,
+    )?;
+
+    Ok(PaginatedList {
+        objects: commits,
+        total_count,
+    })
+}
+
 pub(crate) fn insert_commit(conn: &Connection, commit: &Commit) -> Result<(), SqliteStorageError> {
     insert_commits(conn, std::slice::from_ref(commit))
 }
@@ -52,9 +93,13 @@ pub(crate) fn insert_commits(
     conn: &Connection,
     commits: &[Commit],
 ) -> Result<(), SqliteStorageError> {
+    if commits.is_empty() {
+        return Ok(());
+    }
+
     let mut serialized_payload_data = Vec::with_capacity(commits.len());
     for c in commits {
-        let payload = serialize_commit_payload(&c.payload);
+        let payload = serialize_commit_payload(&c.payload)?;
         serialized_payload_data.push(payload);
     }
 
@@ -80,7 +125,7 @@ pub(crate) fn insert_commits(
 
 fn create_commit_from_row(row: &Row) -> Result<Commit, SqliteStorageError> {
     let payload_data: Vec<u8> = row.get("payload")?;
-    let payload = deserialize_commit_payload(&payload_data);
+    let payload = deserialize_commit_payload(&payload_data)?;
 
     let commit = Commit {
         id: row.get("id")?,
@@ -91,12 +136,12 @@ fn create_commit_from_row(row: &Row) -> Result<Commit, SqliteStorageError> {
     Ok(commit)
 }
 
-fn serialize_commit_payload(payload: &CommitPayload) -> Vec<u8> {
-    bson::to_vec(payload).unwrap()
+fn serialize_commit_payload(payload: &CommitPayload) -> Result<Vec<u8>, SqliteStorageError> {
+    Ok(bson::to_vec(payload)?)
 }
 
-fn deserialize_commit_payload(data: &[u8]) -> CommitPayload {
-    bson::from_slice(data).unwrap()
+fn deserialize_commit_payload(data: &[u8]) -> Result<CommitPayload, SqliteStorageError> {
+    Ok(bson::from_slice(data)?)
 }
 
 #[cfg(test)]
@@ -169,4 +214,41 @@ mod tests {
         assert_eq!(commits[1].timestamp, 300);
         assert_eq!(commits[1].prev_commit_id, vec![3, 4]);
     }
+
+    #[test]
+    fn test_select_commit_corrupt_payload() {
+        let conn = init_db_connection();
+        conn.execute(
+            "INSERT INTO commits (id, timestamp, prev_commit_id, payload) VALUES (X'01', 100, X'', X'DEADBEEF');",
+            (),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            get_latest_commit(&conn),
+            Err(SqliteStorageError::Bson(_))
+        ));
+    }
+
+    #[test]
+    fn test_select_commits_paged() {
+        let conn = init_db_connection();
+
+        for idx in 0..5u8 {
+            let commit = Commit {
+                id: vec![idx],
+                timestamp: 100 * i64::from(idx),
+                prev_commit_id: Vec::new(),
+                payload: CommitPayload::CreatePost(CreatePostCommitPayload {
+                    slug: String::from("slug"),
+                }),
+            };
+            insert_commit(&conn, &commit).unwrap();
+        }
+
+        let commits = get_commits_paged(&conn, 100, &Pagination::from_page_and_size(2, 3)).unwrap();
+        assert_eq!(commits.total_count, 4);
+        assert_eq!(commits.objects.len(), 1);
+        assert_eq!(commits.objects[0].id, vec![4]);
+    }
 }
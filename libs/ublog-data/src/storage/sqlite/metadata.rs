@@ -0,0 +1,77 @@
+use rusqlite::Connection;
+
+use crate::storage::sqlite::{SqliteExt, SqliteStorageError};
+
+pub(crate) fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const INIT_SQL: &str = r#"
+        CREATE TABLE metadata (
+            key   TEXT NOT NULL PRIMARY KEY,
+            value TEXT NOT NULL
+        ) WITHOUT ROWID;
+    "#;
+    conn.execute_batch(INIT_SQL)?;
+
+    Ok(())
+}
+
+pub(crate) fn get_metadata(
+    conn: &Connection,
+    key: &str,
+) -> Result<Option<String>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT value
+        FROM metadata
+        WHERE key == ?;
+    "#;
+
+    conn.query_one(SELECT_SQL, (key,), |row| row.get(0).map_err(From::from))
+}
+
+pub(crate) fn set_metadata(
+    conn: &Connection,
+    key: &str,
+    value: &str,
+) -> Result<(), SqliteStorageError> {
+    const UPSERT_SQL: &str = r#"
+        INSERT INTO metadata (key, value)
+        VALUES (?, ?)
+        ON CONFLICT (key) DO UPDATE SET value = excluded.value;
+    "#;
+
+    conn.execute(UPSERT_SQL, (key, value))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_db_connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db_schema(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_get_metadata_not_exist() {
+        let conn = init_db_connection();
+        assert!(get_metadata(&conn, "key").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_set_metadata() {
+        let conn = init_db_connection();
+
+        set_metadata(&conn, "key", "value1").unwrap();
+        assert_eq!(
+            get_metadata(&conn, "key").unwrap().as_deref(),
+            Some("value1")
+        );
+
+        set_metadata(&conn, "key", "value2").unwrap();
+        assert_eq!(
+            get_metadata(&conn, "key").unwrap().as_deref(),
+            Some("value2")
+        );
+    }
+}
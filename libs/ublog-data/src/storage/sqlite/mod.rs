@@ -1,4 +1,6 @@
+mod analytics;
 mod commit;
+mod metadata;
 mod post;
 mod resource;
 
@@ -11,8 +13,11 @@ use async_trait::async_trait;
 use rusqlite::{Connection, Params, Row};
 use uuid::Uuid;
 
-use crate::models::{Commit, CommitPayload, Delta, Post, Resource};
-use crate::storage::{PaginatedList, Pagination, Storage};
+use crate::models::{
+    ArchiveBucket, Commit, CommitPayload, DailyViewCount, Delta, DeltaError, Post, PostView,
+    Resource, ResourceMeta, Taxonomy,
+};
+use crate::storage::{PaginatedList, Pagination, PostSort, Storage, UnsupportedOperationError};
 
 /// Provide sqlite-based storage for databases.
 #[derive(Debug)]
@@ -45,7 +50,7 @@ impl SqliteStorage {
         Self::new(conn)
     }
 
-    fn lock(&self) -> MutexGuard<Connection> {
+    fn lock(&self) -> MutexGuard<'_, Connection> {
         self.conn.lock().unwrap()
     }
 
@@ -57,25 +62,40 @@ impl SqliteStorage {
     where
         T: IntoIterator<Item = CommitPayload>,
         F: FnOnce(&Connection) -> Result<(), SqliteStorageError>,
+    {
+        self.transact_and_commit_with(|conn| {
+            transact(conn)?;
+            Ok(((), commit_payloads))
+        })
+    }
+
+    /// Execute the given transaction and record the commits it produces.
+    ///
+    /// Unlike [`transact_and_commit`](Self::transact_and_commit), the commit payloads are decided by the transaction
+    /// itself, which allows transactions that turn out to be no-ops to produce no commits.
+    fn transact_and_commit_with<R, T, F>(&self, transact: F) -> Result<R, SqliteStorageError>
+    where
+        T: IntoIterator<Item = CommitPayload>,
+        F: FnOnce(&Connection) -> Result<(R, T), SqliteStorageError>,
     {
         let mut conn = self.lock();
         let trans = conn.transaction()?;
 
-        let last_commit = crate::storage::sqlite::commit::get_latest_commit(&*trans)?;
+        let last_commit = crate::storage::sqlite::commit::get_latest_commit(&trans)?;
         let mut last_commit_id = last_commit.map(|commit| commit.id).unwrap_or_default();
 
-        transact(&*trans)?;
+        let (ret, commit_payloads) = transact(&trans)?;
 
         for payload in commit_payloads {
             let commit = Commit::new(last_commit_id, payload);
             last_commit_id = commit.id.clone();
 
-            crate::storage::sqlite::commit::insert_commit(&*trans, &commit)?;
+            crate::storage::sqlite::commit::insert_commit(&trans, &commit)?;
         }
 
         trans.commit()?;
 
-        Ok(())
+        Ok(ret)
     }
 }
 
@@ -94,6 +114,18 @@ impl Storage for SqliteStorage {
         })
     }
 
+    async fn insert_posts(&self, posts: &[(Post, Vec<Resource>)]) -> Result<(), Self::Error> {
+        let commit_payloads = posts
+            .iter()
+            .map(|(post, _)| CommitPayload::create_post(post.slug.clone()));
+        self.transact_and_commit(commit_payloads, |conn| {
+            for (post, post_resources) in posts {
+                crate::storage::sqlite::post::insert_post(conn, post, post_resources)?;
+            }
+            Ok(())
+        })
+    }
+
     async fn update_post(
         &self,
         post: &Post,
@@ -104,22 +136,33 @@ impl Storage for SqliteStorage {
             CommitPayload::create_post(post.slug.clone()),
         ];
         self.transact_and_commit(commit_payloads, |conn| {
+            // The view count is kept across updates.
+            let views = crate::storage::sqlite::post::get_post_views(conn, &post.slug)?;
             crate::storage::sqlite::post::delete_post(conn, &post.slug)?;
             crate::storage::sqlite::post::insert_post(conn, post, post_resources)?;
+            if let Some(views) = views {
+                crate::storage::sqlite::post::set_post_views(conn, &post.slug, views)?;
+            }
             Ok(())
         })
     }
 
-    async fn delete_post(&self, post_slug: &str) -> Result<(), Self::Error> {
-        let commit_payload = CommitPayload::delete_post(post_slug);
-        self.transact_and_commit([commit_payload], |conn| {
-            crate::storage::sqlite::post::delete_post(conn, post_slug)
+    async fn delete_post(&self, post_slug: &str) -> Result<bool, Self::Error> {
+        self.transact_and_commit_with(|conn| {
+            let deleted = crate::storage::sqlite::post::delete_post(conn, post_slug)?;
+            let commit_payload = deleted.then(|| CommitPayload::delete_post(post_slug));
+            Ok((deleted, commit_payload))
         })
     }
 
+    async fn increment_post_views(&self, post_slug: &str) -> Result<Option<u64>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::increment_post_views(&conn, post_slug)
+    }
+
     async fn get_post(&self, post_slug: &str) -> Result<Option<Post>, Self::Error> {
         let conn = self.lock();
-        crate::storage::sqlite::post::get_post(&*conn, post_slug)
+        crate::storage::sqlite::post::get_post(&conn, post_slug)
     }
 
     async fn get_post_with_resources(
@@ -127,16 +170,85 @@ impl Storage for SqliteStorage {
         post_slug: &str,
     ) -> Result<Option<(Post, Vec<Resource>)>, Self::Error> {
         let conn = self.lock();
-        crate::storage::sqlite::post::get_post_with_resources(&*conn, post_slug)
+        crate::storage::sqlite::post::get_post_with_resources(&conn, post_slug)
     }
 
     async fn get_posts(
         &self,
         special: bool,
+        sort: PostSort,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::get_posts(&conn, special, sort, pagination)
+    }
+
+    async fn get_posts_by_tag(
+        &self,
+        tag: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::get_posts_by_tag(&conn, tag, pagination)
+    }
+
+    async fn get_posts_by_category(
+        &self,
+        category: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::get_posts_by_category(&conn, category, pagination)
+    }
+
+    async fn get_posts_updated_since(
+        &self,
+        since: i64,
         pagination: &Pagination,
     ) -> Result<PaginatedList<Post>, Self::Error> {
         let conn = self.lock();
-        crate::storage::sqlite::post::get_posts(&*conn, special, pagination)
+        crate::storage::sqlite::post::get_posts_updated_since(&conn, since, pagination)
+    }
+
+    async fn get_adjacent_posts(
+        &self,
+        post_slug: &str,
+    ) -> Result<(Option<Post>, Option<Post>), Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::get_adjacent_posts(&conn, post_slug)
+    }
+
+    async fn get_related_posts(
+        &self,
+        post_slug: &str,
+        limit: usize,
+    ) -> Result<Vec<Post>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::get_related_posts(&conn, post_slug, limit)
+    }
+
+    async fn get_tag_counts(&self) -> Result<Vec<(String, usize)>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::get_tag_counts(&conn)
+    }
+
+    async fn get_taxonomy(&self) -> Result<Taxonomy, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::get_taxonomy(&conn)
+    }
+
+    async fn get_post_archive(&self) -> Result<Vec<ArchiveBucket>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::get_post_archive(&conn)
+    }
+
+    async fn search_posts(
+        &self,
+        query: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::post::search_posts(&conn, query, pagination)
     }
 
     async fn insert_resource(&self, resource: &Resource) -> Result<(), Self::Error> {
@@ -146,37 +258,111 @@ impl Storage for SqliteStorage {
         })
     }
 
-    async fn delete_resource(&self, resource_id: &Uuid) -> Result<(), Self::Error> {
-        let commit_payload = CommitPayload::delete_resource(*resource_id);
-        self.transact_and_commit([commit_payload], |conn| {
-            crate::storage::sqlite::resource::delete_resource(conn, resource_id)
+    async fn update_resource(&self, resource: &Resource) -> Result<bool, Self::Error> {
+        self.transact_and_commit_with(|conn| {
+            let updated = crate::storage::sqlite::resource::update_resource(conn, resource)?;
+            let commit_payload = updated.then(|| CommitPayload::update_resource(resource.id));
+            Ok((updated, commit_payload))
+        })
+    }
+
+    async fn delete_resource(&self, resource_id: &Uuid) -> Result<bool, Self::Error> {
+        self.transact_and_commit_with(|conn| {
+            let deleted = crate::storage::sqlite::resource::delete_resource(conn, resource_id)?;
+            let commit_payload = deleted.then(|| CommitPayload::delete_resource(*resource_id));
+            Ok((deleted, commit_payload))
         })
     }
 
     async fn get_resource(&self, resource_id: &Uuid) -> Result<Option<Resource>, Self::Error> {
         let conn = self.lock();
-        crate::storage::sqlite::resource::get_resource(&*conn, resource_id)
+        crate::storage::sqlite::resource::get_resource(&conn, resource_id)
+    }
+
+    async fn get_resource_meta(
+        &self,
+        resource_id: &Uuid,
+    ) -> Result<Option<ResourceMeta>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::resource::get_resource_meta(&conn, resource_id)
+    }
+
+    async fn read_resource_chunk(
+        &self,
+        resource_id: &Uuid,
+        offset: u64,
+        len: usize,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::resource::read_resource_chunk(&conn, resource_id, offset, len)
+    }
+
+    async fn find_resource_by_hash(&self, hash: &str) -> Result<Option<ResourceMeta>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::resource::find_resource_by_hash(&conn, hash)
+    }
+
+    async fn gc_resources(&self) -> Result<usize, Self::Error> {
+        // Finding and deleting the orphan resources within one transaction ensures that resources referenced by posts
+        // inserted concurrently are never deleted.
+        self.transact_and_commit_with(|conn| {
+            let orphan_ids = crate::storage::sqlite::resource::get_orphan_resource_ids(conn)?;
+            for id in &orphan_ids {
+                crate::storage::sqlite::resource::delete_resource(conn, id)?;
+            }
+
+            let commit_payloads: Vec<_> = orphan_ids
+                .iter()
+                .map(|id| CommitPayload::delete_resource(*id))
+                .collect();
+            Ok((orphan_ids.len(), commit_payloads))
+        })
+    }
+
+    async fn get_resources_paged(
+        &self,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Resource>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::resource::get_resources_paged(&conn, pagination)
     }
 
     async fn get_resources(&self) -> Result<Vec<Resource>, Self::Error> {
         let conn = self.lock();
-        crate::storage::sqlite::resource::get_resources(&*conn)
+        crate::storage::sqlite::resource::get_resources(&conn)
+    }
+
+    async fn get_resource_metas(&self) -> Result<Vec<ResourceMeta>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::resource::get_resource_metas(&conn)
+    }
+
+    async fn get_commits_paged(
+        &self,
+        since_timestamp: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Commit>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::commit::get_commits_paged(&conn, since_timestamp, pagination)
     }
 
     async fn get_commits_since(&self, since_timestamp: i64) -> Result<Vec<Commit>, Self::Error> {
         let conn = self.lock();
-        crate::storage::sqlite::commit::get_commits(&*conn, since_timestamp)
+        crate::storage::sqlite::commit::get_commits(&conn, since_timestamp)
     }
 
     async fn get_latest_commit(&self) -> Result<Option<Commit>, Self::Error> {
         let conn = self.lock();
-        crate::storage::sqlite::commit::get_latest_commit(&*conn)
+        crate::storage::sqlite::commit::get_latest_commit(&conn)
     }
 
     async fn apply_delta(&self, delta: &Delta) -> Result<(), Self::Error> {
         let mut conn = self.lock();
         let trans = conn.transaction()?;
 
+        let latest_commit = crate::storage::sqlite::commit::get_latest_commit(&trans)?;
+        delta.verify_chain(latest_commit.as_ref().map(|commit| commit.id.as_slice()))?;
+
         for slug in &delta.deleted_post_slugs {
             crate::storage::sqlite::post::delete_post(&trans, slug)?;
         }
@@ -199,6 +385,30 @@ impl Storage for SqliteStorage {
 
         Ok(())
     }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<String>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::metadata::get_metadata(&conn, key)
+    }
+
+    async fn set_metadata(&self, key: &str, value: &str) -> Result<(), Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::metadata::set_metadata(&conn, key, value)
+    }
+
+    async fn record_views(&self, views: &[PostView]) -> Result<(), Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::analytics::record_views(&conn, views)
+    }
+
+    async fn get_view_stats(
+        &self,
+        post_slug: &str,
+        since: i64,
+    ) -> Result<Vec<DailyViewCount>, Self::Error> {
+        let conn = self.lock();
+        crate::storage::sqlite::analytics::get_view_stats(&conn, post_slug, since)
+    }
 }
 
 /// SQlite storage errors.
@@ -206,7 +416,20 @@ impl Storage for SqliteStorage {
 pub enum SqliteStorageError {
     Sqlite(rusqlite::Error),
     Bson(bson::de::Error),
+    BsonSerialize(bson::ser::Error),
     Uuid(uuid::Error),
+    Unsupported(UnsupportedOperationError),
+    SchemaVersion(u32),
+    Delta(DeltaError),
+
+    /// The content of a post is stored in an unknown format. Holds the format version of the content, which is absent
+    /// if the stored content is empty.
+    ContentFormat(Option<u8>),
+
+    /// A post with the same slug already exists.
+    Duplicate {
+        slug: String,
+    },
 }
 
 impl Display for SqliteStorageError {
@@ -214,7 +437,18 @@ impl Display for SqliteStorageError {
         match self {
             Self::Sqlite(err) => write!(f, "sqlite error: {}", err),
             Self::Bson(err) => write!(f, "bson deserialize error: {}", err),
+            Self::BsonSerialize(err) => write!(f, "bson serialize error: {}", err),
             Self::Uuid(err) => write!(f, "uuid error: {}", err),
+            Self::Unsupported(err) => write!(f, "{}", err),
+            Self::SchemaVersion(version) => {
+                write!(f, "unsupported database schema version: {}", version)
+            }
+            Self::Delta(err) => write!(f, "invalid delta: {}", err),
+            Self::ContentFormat(Some(version)) => {
+                write!(f, "unknown post content format version: {}", version)
+            }
+            Self::ContentFormat(None) => write!(f, "post content is empty"),
+            Self::Duplicate { slug } => write!(f, "duplicate post slug: {}", slug),
         }
     }
 }
@@ -233,13 +467,57 @@ impl From<bson::de::Error> for SqliteStorageError {
     }
 }
 
+impl From<bson::ser::Error> for SqliteStorageError {
+    fn from(err: bson::ser::Error) -> Self {
+        Self::BsonSerialize(err)
+    }
+}
+
 impl From<uuid::Error> for SqliteStorageError {
     fn from(err: uuid::Error) -> Self {
         Self::Uuid(err)
     }
 }
 
+impl From<DeltaError> for SqliteStorageError {
+    fn from(err: DeltaError) -> Self {
+        Self::Delta(err)
+    }
+}
+
+impl From<UnsupportedOperationError> for SqliteStorageError {
+    fn from(err: UnsupportedOperationError) -> Self {
+        Self::Unsupported(err)
+    }
+}
+
 fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
+    run_migrations(conn, MIGRATIONS)
+}
+
+type Migration = fn(&Connection) -> Result<(), SqliteStorageError>;
+
+/// Migrations of the database schema, in order.
+///
+/// The `i`-th migration (starting from 1) upgrades the database schema from version `i - 1` to version `i`. The
+/// schema version of a database is kept in its `user_version` pragma. New migrations must be appended to the end of
+/// this list, and existing migrations must never be changed.
+const MIGRATIONS: &[Migration] = &[
+    migrate_baseline_schema,
+    migrate_posts_fts,
+    migrate_post_views,
+    migrate_resources_rowid,
+    migrate_resources_hash,
+    migrate_metadata,
+    migrate_posts_update_timestamp_index,
+    migrate_posts_excerpt,
+    migrate_analytics,
+    migrate_posts_content_format,
+];
+
+fn migrate_baseline_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
+    // Databases created before schema versioning is introduced are at version 0 but already have the baseline schema,
+    // so the baseline schema is created with `IF NOT EXISTS` clauses.
     crate::storage::sqlite::commit::init_db_schema(conn)?;
     crate::storage::sqlite::post::init_db_schema(conn)?;
     crate::storage::sqlite::resource::init_db_schema(conn)?;
@@ -247,6 +525,64 @@ fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
     Ok(())
 }
 
+fn migrate_posts_fts(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::post::init_fts_schema(conn)
+}
+
+fn migrate_post_views(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::post::init_views_schema(conn)
+}
+
+fn migrate_resources_rowid(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::resource::migrate_rowid_table(conn)
+}
+
+fn migrate_resources_hash(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::resource::migrate_content_hash(conn)
+}
+
+fn migrate_metadata(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::metadata::init_db_schema(conn)
+}
+
+fn migrate_posts_update_timestamp_index(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::post::init_update_timestamp_index(conn)
+}
+
+fn migrate_posts_excerpt(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::post::init_excerpt_schema(conn)
+}
+
+fn migrate_analytics(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::analytics::init_db_schema(conn)
+}
+
+fn migrate_posts_content_format(conn: &Connection) -> Result<(), SqliteStorageError> {
+    crate::storage::sqlite::post::init_content_format(conn)
+}
+
+/// Apply the migrations that have not been applied to the database yet.
+///
+/// Each pending migration is applied within its own transaction, together with the update to the schema version.
+fn run_migrations<M>(conn: &Connection, migrations: &[M]) -> Result<(), SqliteStorageError>
+where
+    M: Fn(&Connection) -> Result<(), SqliteStorageError>,
+{
+    let version: u32 = conn.query_row("PRAGMA user_version;", (), |row| row.get(0))?;
+    if version as usize > migrations.len() {
+        return Err(SqliteStorageError::SchemaVersion(version));
+    }
+
+    for (idx, migration) in migrations.iter().enumerate().skip(version as usize) {
+        let trans = conn.unchecked_transaction()?;
+        migration(&trans)?;
+        trans.pragma_update(None, "user_version", idx + 1)?;
+        trans.commit()?;
+    }
+
+    Ok(())
+}
+
 trait SqliteExt {
     fn query_one<S, P, F, T>(
         &self,
@@ -311,3 +647,319 @@ impl SqliteExt for Connection {
         Ok(results)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ublog_doc::DocumentNode;
+
+    fn create_test_post(slug: &str) -> Post {
+        Post {
+            title: String::from("title"),
+            slug: String::from(slug),
+            author: String::from("msr"),
+            create_timestamp: 0,
+            update_timestamp: 0,
+            category: String::from("category"),
+            tags: Vec::new(),
+            is_special: false,
+            views: 0,
+            excerpt: String::new(),
+            content: DocumentNode::new_empty(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_posts() {
+        let storage = SqliteStorage::new_memory().unwrap();
+
+        let posts = vec![
+            (create_test_post("post1"), Vec::new()),
+            (create_test_post("post2"), Vec::new()),
+        ];
+        storage.insert_posts(&posts).await.unwrap();
+
+        assert!(storage.get_post("post1").await.unwrap().is_some());
+        assert!(storage.get_post("post2").await.unwrap().is_some());
+        assert_eq!(storage.get_commits_since(0).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_insert_posts_rollback() {
+        let storage = SqliteStorage::new_memory().unwrap();
+
+        let posts = vec![
+            (create_test_post("post1"), Vec::new()),
+            (create_test_post("post2"), Vec::new()),
+            (create_test_post("post1"), Vec::new()),
+        ];
+        assert!(storage.insert_posts(&posts).await.is_err());
+
+        assert!(storage.get_post("post1").await.unwrap().is_none());
+        assert!(storage.get_post("post2").await.unwrap().is_none());
+        assert!(storage.get_commits_since(0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_post() {
+        let storage = SqliteStorage::new_memory().unwrap();
+        storage
+            .insert_post(&create_test_post("post"), &[])
+            .await
+            .unwrap();
+
+        assert!(storage.delete_post("post").await.unwrap());
+        assert!(!storage.delete_post("post").await.unwrap());
+
+        // Deleting a post that does not exist does not produce a commit.
+        assert_eq!(storage.get_commits_since(0).await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_increment_post_views_concurrently() {
+        const THREADS: u64 = 8;
+        const INCREMENTS_PER_THREAD: u64 = 50;
+
+        let storage = SqliteStorage::new_memory().unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime
+            .block_on(storage.insert_post(&create_test_post("post"), &[]))
+            .unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .build()
+                        .unwrap();
+                    for _ in 0..INCREMENTS_PER_THREAD {
+                        let views = runtime
+                            .block_on(storage.increment_post_views("post"))
+                            .unwrap();
+                        assert!(views.is_some());
+                    }
+                });
+            }
+        });
+
+        let post = runtime.block_on(storage.get_post("post")).unwrap().unwrap();
+        assert_eq!(post.views, THREADS * INCREMENTS_PER_THREAD);
+
+        let views = runtime
+            .block_on(storage.increment_post_views("missing"))
+            .unwrap();
+        assert_eq!(views, None);
+    }
+
+    #[tokio::test]
+    async fn test_update_post_keeps_views() {
+        let storage = SqliteStorage::new_memory().unwrap();
+        let mut post = create_test_post("post");
+        storage.insert_post(&post, &[]).await.unwrap();
+
+        assert_eq!(storage.increment_post_views("post").await.unwrap(), Some(1));
+        assert_eq!(storage.increment_post_views("post").await.unwrap(), Some(2));
+
+        post.title = String::from("updated");
+        storage.update_post(&post, &[]).await.unwrap();
+
+        let post = storage.get_post("post").await.unwrap().unwrap();
+        assert_eq!(post.title, "updated");
+        assert_eq!(post.views, 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_missing_resource() {
+        let storage = SqliteStorage::new_memory().unwrap();
+        let resource = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("missing"),
+            ty: String::from("text/plain"),
+            data: vec![1, 2, 3],
+        };
+
+        assert!(!storage.update_resource(&resource).await.unwrap());
+        assert!(storage.get_resource(&resource.id).await.unwrap().is_none());
+        assert!(storage.get_commits_since(0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_gc_resources() {
+        let storage = SqliteStorage::new_memory().unwrap();
+
+        let create_resource = |name: &str| Resource {
+            id: Uuid::new_v4(),
+            name: String::from(name),
+            ty: String::from("text/plain"),
+            data: vec![1, 2, 3],
+        };
+        let referenced = create_resource("referenced");
+        let orphan = create_resource("orphan");
+
+        storage
+            .insert_post(&create_test_post("post"), std::slice::from_ref(&referenced))
+            .await
+            .unwrap();
+        storage.insert_resource(&orphan).await.unwrap();
+
+        assert_eq!(storage.gc_resources().await.unwrap(), 1);
+        assert!(storage.get_resource(&orphan.id).await.unwrap().is_none());
+        assert!(storage
+            .get_resource(&referenced.id)
+            .await
+            .unwrap()
+            .is_some());
+
+        let deleted_ids: Vec<_> = storage
+            .get_commits_since(0)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|commit| match commit.payload {
+                CommitPayload::DeleteResource(payload) => Some(payload.id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deleted_ids, vec![orphan.id]);
+
+        assert_eq!(storage.gc_resources().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_apply_delta_verify_chain() {
+        let from = SqliteStorage::new_memory().unwrap();
+        from.insert_post(&create_test_post("post1"), &[])
+            .await
+            .unwrap();
+        from.insert_post(&create_test_post("post2"), &[])
+            .await
+            .unwrap();
+
+        let mut delta = Delta::new();
+        delta.commits = from.get_commits_since(0).await.unwrap();
+        assert_eq!(delta.commits.len(), 2);
+
+        let mut tampered = delta.clone();
+        tampered.commits[1].id[0] ^= 0xff;
+        let to = SqliteStorage::new_memory().unwrap();
+        assert!(matches!(
+            to.apply_delta(&tampered).await,
+            Err(SqliteStorageError::Delta(DeltaError::InvalidCommitId {
+                index: 1
+            }))
+        ));
+        assert!(to.get_latest_commit().await.unwrap().is_none());
+
+        let mut reordered = delta.clone();
+        reordered.commits.swap(0, 1);
+        assert!(matches!(
+            to.apply_delta(&reordered).await,
+            Err(SqliteStorageError::Delta(DeltaError::BrokenChain {
+                index: 0
+            }))
+        ));
+
+        to.apply_delta(&delta).await.unwrap();
+        let latest_commit = to.get_latest_commit().await.unwrap().unwrap();
+        assert_eq!(latest_commit.id, delta.commits[1].id);
+
+        // Applying the same delta again does not follow the latest commit.
+        assert!(matches!(
+            to.apply_delta(&delta).await,
+            Err(SqliteStorageError::Delta(DeltaError::BrokenChain {
+                index: 0
+            }))
+        ));
+    }
+
+    fn get_schema_version(conn: &Connection) -> u32 {
+        conn.query_row("PRAGMA user_version;", (), |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_run_migrations_once() {
+        use std::cell::Cell;
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+
+        type BoxedMigration<'a> = Box<dyn Fn(&Connection) -> Result<(), SqliteStorageError> + 'a>;
+
+        let counts = [Cell::new(0), Cell::new(0), Cell::new(0)];
+        let migrations: Vec<BoxedMigration> = counts
+            .iter()
+            .map(|count| {
+                Box::new(move |_: &Connection| {
+                    count.set(count.get() + 1);
+                    Ok(())
+                }) as BoxedMigration
+            })
+            .collect();
+
+        run_migrations(&conn, &migrations).unwrap();
+        assert_eq!(get_schema_version(&conn), 3);
+        assert_eq!(counts.iter().map(Cell::get).collect::<Vec<_>>(), [0, 1, 1]);
+
+        run_migrations(&conn, &migrations).unwrap();
+        assert_eq!(get_schema_version(&conn), 3);
+        assert_eq!(counts.iter().map(Cell::get).collect::<Vec<_>>(), [0, 1, 1]);
+    }
+
+    #[test]
+    fn test_run_migrations_newer_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+
+        assert!(matches!(
+            SqliteStorage::new(conn),
+            Err(SqliteStorageError::SchemaVersion(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_open_old_version_database() {
+        // Create a database at schema version 1, which does not have the full-text search index.
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn, &MIGRATIONS[..1]).unwrap();
+        assert_eq!(get_schema_version(&conn), 1);
+
+        let mut post = create_test_post("post");
+        post.title = String::from("migration");
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.text("Written before excerpts.");
+        });
+        post.content = builder.finish();
+        conn.execute(
+            "INSERT INTO posts (title, slug, author, create_timestamp, update_timestamp, category, is_special, content)
+             VALUES (?, ?, ?, 0, 0, ?, 0, ?);",
+            (
+                &post.title,
+                &post.slug,
+                &post.author,
+                &post.category,
+                bson::to_vec(&post.content).unwrap(),
+            ),
+        )
+        .unwrap();
+
+        let storage = SqliteStorage::new(conn).unwrap();
+        assert_eq!(get_schema_version(&storage.lock()), MIGRATIONS.len() as u32);
+
+        let pagination = Pagination::from_page_and_size(1, 10);
+        let found = storage
+            .search_posts("migration", &pagination)
+            .await
+            .unwrap();
+        assert_eq!(found.total_count, 1);
+
+        let post = storage.get_post("post").await.unwrap().unwrap();
+        assert_eq!(post.views, 0);
+        assert_eq!(post.excerpt, "Written before excerpts.");
+    }
+}
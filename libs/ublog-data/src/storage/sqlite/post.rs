@@ -2,9 +2,18 @@ use rusqlite::{Connection, Row, ToSql};
 use ublog_doc::DocumentNode;
 use uuid::Uuid;
 
-use crate::models::{Post, Resource};
+use crate::models::{ArchiveBucket, Post, Resource, Taxonomy, TaxonomyTerm};
 use crate::storage::sqlite::{SqliteExt, SqliteStorageError};
-use crate::storage::{PaginatedList, Pagination};
+use crate::storage::{PaginatedList, Pagination, PostSort};
+
+/// The maximum number of characters in the excerpts of posts.
+const EXCERPT_MAX_CHARS: usize = 200;
+
+/// Format version of post contents that are encoded in BSON.
+///
+/// The stored content of a post starts with a byte that tells the format in which the rest of the content is encoded, so
+/// that contents encoded in different formats can coexist.
+const CONTENT_FORMAT_BSON: u8 = 1;
 
 pub(crate) fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
     const INIT_SQL: &str = r#"
@@ -43,12 +52,50 @@ pub(crate) fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError
     Ok(())
 }
 
+pub(crate) fn init_fts_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const INIT_SQL: &str = r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS posts_fts USING fts5 (
+            slug UNINDEXED,
+            title,
+            category,
+            tags,
+            content
+        );
+    "#;
+
+    conn.execute_batch(INIT_SQL)?;
+
+    // Index posts that are inserted before the full-text search index is introduced.
+    // The views and excerpt columns may not exist yet at this schema version, so they are not selected.
+    const SELECT_UNINDEXED_SQL: &str = r#"
+        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special, 0 AS views,
+               '' AS excerpt, content
+        FROM posts
+        WHERE slug NOT IN (SELECT slug FROM posts_fts);
+    "#;
+    // The contents are not prefixed with a format version yet at this schema version, so they are decoded as plain
+    // BSON.
+    let mut unindexed_posts = conn.query_many(SELECT_UNINDEXED_SQL, (), |row| {
+        let mut post = create_post_from_row_no_content(row)?;
+        let content_data: Vec<u8> = row.get("content")?;
+        post.content = bson::from_slice(&content_data)?;
+        Ok(post)
+    })?;
+    for post in &mut unindexed_posts {
+        populate_post_tags(conn, post)?;
+        insert_post_fts(conn, post)?;
+    }
+
+    Ok(())
+}
+
 pub(super) fn get_post(
     conn: &Connection,
     post_slug: &str,
 ) -> Result<Option<Post>, SqliteStorageError> {
     const SELECT_SQL: &str = r#"
-        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special, content
+        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special, views, excerpt,
+               content
         FROM posts
         WHERE slug == ?;
     "#;
@@ -79,15 +126,19 @@ pub(super) fn get_post_with_resources(
 pub(super) fn get_posts(
     conn: &Connection,
     special: bool,
+    sort: PostSort,
     pagination: &Pagination,
 ) -> Result<PaginatedList<Post>, SqliteStorageError> {
-    const SELECT_SQL: &str = r#"
-        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special
+    let select_sql = format!(
+        r#"
+        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special, views, excerpt
         FROM posts
         WHERE is_special == ?
-        ORDER BY create_timestamp DESC
+        ORDER BY {}
         LIMIT ? OFFSET ?;
-    "#;
+        "#,
+        order_by_clause(sort)
+    );
 
     const SELECT_COUNT_SQL: &str = r#"
         SELECT count(*) AS cnt
@@ -106,7 +157,7 @@ pub(super) fn get_posts(
         .unwrap();
 
     let mut posts = conn.query_many(
-        SELECT_SQL,
+        &select_sql,
         (is_special, limit, offset),
         create_post_from_row_no_content,
     )?;
@@ -120,20 +171,358 @@ pub(super) fn get_posts(
     })
 }
 
+/// Get the `ORDER BY` clause that orders posts as specified by the given sort order.
+///
+/// Only the clauses listed here are ever interpolated into SQL statements. Posts that compare equal are ordered by
+/// their slugs so that pagination is stable.
+fn order_by_clause(sort: PostSort) -> &'static str {
+    match sort {
+        PostSort::CreatedDesc => "create_timestamp DESC, slug",
+        PostSort::CreatedAsc => "create_timestamp ASC, slug",
+        PostSort::UpdatedDesc => "update_timestamp DESC, slug",
+        PostSort::TitleAsc => "title ASC, slug",
+    }
+}
+
+pub(super) fn get_posts_by_tag(
+    conn: &Connection,
+    tag: &str,
+    pagination: &Pagination,
+) -> Result<PaginatedList<Post>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT posts.title, posts.slug, posts.author, posts.create_timestamp, posts.update_timestamp, posts.category,
+               posts.is_special, posts.views, posts.excerpt
+        FROM posts_tags INNER JOIN posts ON posts.slug == posts_tags.post_slug
+        WHERE posts_tags.tag_name == ? AND posts.is_special == 0
+        ORDER BY posts.create_timestamp DESC
+        LIMIT ? OFFSET ?;
+    "#;
+
+    const SELECT_COUNT_SQL: &str = r#"
+        SELECT count(*) AS cnt
+        FROM posts_tags INNER JOIN posts ON posts.slug == posts_tags.post_slug
+        WHERE posts_tags.tag_name == ? AND posts.is_special == 0;
+    "#;
+
+    get_filtered_posts(conn, SELECT_SQL, SELECT_COUNT_SQL, tag, pagination)
+}
+
+pub(super) fn get_posts_by_category(
+    conn: &Connection,
+    category: &str,
+    pagination: &Pagination,
+) -> Result<PaginatedList<Post>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special, views, excerpt
+        FROM posts
+        WHERE category == ? AND is_special == 0
+        ORDER BY create_timestamp DESC
+        LIMIT ? OFFSET ?;
+    "#;
+
+    const SELECT_COUNT_SQL: &str = r#"
+        SELECT count(*) AS cnt
+        FROM posts
+        WHERE category == ? AND is_special == 0;
+    "#;
+
+    get_filtered_posts(conn, SELECT_SQL, SELECT_COUNT_SQL, category, pagination)
+}
+
+/// Get the non-special posts updated after the given timestamp, ordered by their update time from the latest to the
+/// earliest.
+pub(super) fn get_posts_updated_since(
+    conn: &Connection,
+    since: i64,
+    pagination: &Pagination,
+) -> Result<PaginatedList<Post>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special, views, excerpt
+        FROM posts
+        WHERE update_timestamp > ? AND is_special == 0
+        ORDER BY update_timestamp DESC
+        LIMIT ? OFFSET ?;
+    "#;
+
+    const SELECT_COUNT_SQL: &str = r#"
+        SELECT count(*) AS cnt
+        FROM posts
+        WHERE update_timestamp > ? AND is_special == 0;
+    "#;
+
+    get_filtered_posts(conn, SELECT_SQL, SELECT_COUNT_SQL, since, pagination)
+}
+
+/// Get the non-special posts immediately older and newer than the given post by their creation time.
+///
+/// Posts created at the same time are ordered by their slugs so that every post has well-defined neighbors.
+pub(super) fn get_adjacent_posts(
+    conn: &Connection,
+    post_slug: &str,
+) -> Result<(Option<Post>, Option<Post>), SqliteStorageError> {
+    const SELECT_TIMESTAMP_SQL: &str = r#"
+        SELECT create_timestamp FROM posts
+        WHERE slug == ?;
+    "#;
+
+    const SELECT_OLDER_SQL: &str = r#"
+        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special, views, excerpt
+        FROM posts
+        WHERE is_special == 0 AND (create_timestamp < ?1 OR (create_timestamp == ?1 AND slug < ?2))
+        ORDER BY create_timestamp DESC, slug DESC
+        LIMIT 1;
+    "#;
+
+    const SELECT_NEWER_SQL: &str = r#"
+        SELECT title, slug, author, create_timestamp, update_timestamp, category, is_special, views, excerpt
+        FROM posts
+        WHERE is_special == 0 AND (create_timestamp > ?1 OR (create_timestamp == ?1 AND slug > ?2))
+        ORDER BY create_timestamp ASC, slug ASC
+        LIMIT 1;
+    "#;
+
+    let create_timestamp: i64 = match conn.query_one(SELECT_TIMESTAMP_SQL, (post_slug,), |row| {
+        row.get(0).map_err(From::from)
+    })? {
+        Some(create_timestamp) => create_timestamp,
+        None => return Ok((None, None)),
+    };
+
+    let mut older = conn.query_one(
+        SELECT_OLDER_SQL,
+        (create_timestamp, post_slug),
+        create_post_from_row_no_content,
+    )?;
+    let mut newer = conn.query_one(
+        SELECT_NEWER_SQL,
+        (create_timestamp, post_slug),
+        create_post_from_row_no_content,
+    )?;
+    for p in older.iter_mut().chain(newer.iter_mut()) {
+        populate_post_tags(conn, p)?;
+    }
+
+    Ok((older, newer))
+}
+
+/// Get at most `limit` non-special posts that share tags with the given post, ordered by the number of shared tags and
+/// then by their creation time.
+pub(super) fn get_related_posts(
+    conn: &Connection,
+    post_slug: &str,
+    limit: usize,
+) -> Result<Vec<Post>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT posts.title, posts.slug, posts.author, posts.create_timestamp, posts.update_timestamp, posts.category,
+               posts.is_special, posts.views, posts.excerpt
+        FROM posts_tags AS post_tags
+        INNER JOIN posts_tags AS other_tags
+            ON other_tags.tag_name == post_tags.tag_name AND other_tags.post_slug != post_tags.post_slug
+        INNER JOIN posts ON posts.slug == other_tags.post_slug
+        WHERE post_tags.post_slug == ? AND posts.is_special == 0
+        GROUP BY posts.slug
+        ORDER BY count(*) DESC, posts.create_timestamp DESC
+        LIMIT ?;
+    "#;
+
+    let mut posts = conn.query_many(
+        SELECT_SQL,
+        (post_slug, limit),
+        create_post_from_row_no_content,
+    )?;
+    for p in &mut posts {
+        populate_post_tags(conn, p)?;
+    }
+
+    Ok(posts)
+}
+
+/// Get a page of posts selected by the given SQL statements, which take the given filter as their first parameter.
+///
+/// `select_sql` additionally takes the limit and the offset of the page as its second and third parameters.
+fn get_filtered_posts<F>(
+    conn: &Connection,
+    select_sql: &str,
+    select_count_sql: &str,
+    filter: F,
+    pagination: &Pagination,
+) -> Result<PaginatedList<Post>, SqliteStorageError>
+where
+    F: ToSql,
+{
+    let limit = pagination.page_size();
+    let offset = pagination.skip_count();
+
+    let total_count: usize = conn
+        .query_one(select_count_sql, (&filter,), |row| {
+            row.get(0).map_err(From::from)
+        })?
+        .unwrap();
+
+    let mut posts = conn.query_many(
+        select_sql,
+        (&filter, limit, offset),
+        create_post_from_row_no_content,
+    )?;
+    for p in &mut posts {
+        populate_post_tags(conn, p)?;
+    }
+
+    Ok(PaginatedList {
+        objects: posts,
+        total_count,
+    })
+}
+
+pub(super) fn get_taxonomy(conn: &Connection) -> Result<Taxonomy, SqliteStorageError> {
+    const SELECT_CATEGORIES_SQL: &str = r#"
+        SELECT category AS name, count(*) AS post_count
+        FROM posts
+        WHERE is_special == 0
+        GROUP BY category
+        ORDER BY category;
+    "#;
+
+    const SELECT_TAGS_SQL: &str = r#"
+        SELECT posts_tags.tag_name AS name, count(*) AS post_count
+        FROM posts_tags
+        INNER JOIN posts ON posts.slug == posts_tags.post_slug
+        WHERE posts.is_special == 0
+        GROUP BY posts_tags.tag_name
+        ORDER BY posts_tags.tag_name;
+    "#;
+
+    let categories = conn.query_many(SELECT_CATEGORIES_SQL, (), create_taxonomy_term_from_row)?;
+    let tags = conn.query_many(SELECT_TAGS_SQL, (), create_taxonomy_term_from_row)?;
+
+    Ok(Taxonomy { categories, tags })
+}
+
+pub(super) fn get_post_archive(
+    conn: &Connection,
+) -> Result<Vec<ArchiveBucket>, SqliteStorageError> {
+    // Creation timestamps are Unix timestamps, so posts are grouped by the months in UTC.
+    const SELECT_SQL: &str = r#"
+        SELECT CAST(strftime('%Y', datetime(create_timestamp, 'unixepoch')) AS INTEGER) AS year,
+               CAST(strftime('%m', datetime(create_timestamp, 'unixepoch')) AS INTEGER) AS month,
+               count(*) AS count
+        FROM posts
+        WHERE is_special == 0
+        GROUP BY year, month
+        ORDER BY year DESC, month DESC;
+    "#;
+
+    conn.query_many(SELECT_SQL, (), |row| {
+        Ok(ArchiveBucket {
+            year: row.get("year")?,
+            month: row.get("month")?,
+            count: row.get("count")?,
+        })
+    })
+}
+
+pub(super) fn get_tag_counts(
+    conn: &Connection,
+) -> Result<Vec<(String, usize)>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT posts_tags.tag_name, count(*)
+        FROM posts_tags INNER JOIN posts ON posts.slug == posts_tags.post_slug
+        WHERE posts.is_special == 0
+        GROUP BY posts_tags.tag_name
+        ORDER BY count(*) DESC, tag_name;
+    "#;
+
+    conn.query_many(SELECT_SQL, (), |row| Ok((row.get(0)?, row.get(1)?)))
+}
+
+pub(super) fn search_posts(
+    conn: &Connection,
+    query: &str,
+    pagination: &Pagination,
+) -> Result<PaginatedList<Post>, SqliteStorageError> {
+    // Title matches weigh the most, followed by category and tag matches, and then content matches. The slug column
+    // is not indexed and its weight is irrelevant.
+    const SELECT_SQL: &str = r#"
+        SELECT posts.title, posts.slug, posts.author, posts.create_timestamp, posts.update_timestamp, posts.category,
+               posts.is_special, posts.views, posts.excerpt
+        FROM posts_fts INNER JOIN posts ON posts.slug == posts_fts.slug
+        WHERE posts_fts MATCH ? AND posts.is_special == 0
+        ORDER BY bm25(posts_fts, 0.0, 10.0, 5.0, 5.0, 1.0)
+        LIMIT ? OFFSET ?;
+    "#;
+
+    const SELECT_COUNT_SQL: &str = r#"
+        SELECT count(*) AS cnt
+        FROM posts_fts INNER JOIN posts ON posts.slug == posts_fts.slug
+        WHERE posts_fts MATCH ? AND posts.is_special == 0;
+    "#;
+
+    let fts_query = match create_fts_query(query) {
+        Some(fts_query) => fts_query,
+        None => {
+            return Ok(PaginatedList {
+                objects: Vec::new(),
+                total_count: 0,
+            });
+        }
+    };
+
+    let limit = pagination.page_size();
+    let offset = pagination.skip_count();
+
+    let total_count: usize = conn
+        .query_one(SELECT_COUNT_SQL, (&fts_query,), |row| {
+            row.get(0).map_err(From::from)
+        })?
+        .unwrap();
+
+    let mut posts = conn.query_many(
+        SELECT_SQL,
+        (&fts_query, limit, offset),
+        create_post_from_row_no_content,
+    )?;
+    for p in &mut posts {
+        populate_post_tags(conn, p)?;
+    }
+
+    Ok(PaginatedList {
+        objects: posts,
+        total_count,
+    })
+}
+
+/// Create an FTS5 query string from the given user query.
+///
+/// Each whitespace-separated term in the user query is quoted as an FTS5 string so that any FTS5 query syntax within
+/// the user query is matched literally. Returns `None` if the user query contains no terms.
+fn create_fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
 pub(super) fn insert_post(
     conn: &Connection,
     post: &Post,
     post_resources: &[Resource],
 ) -> Result<(), SqliteStorageError> {
     const INSERT_POST_SQL: &str = r#"
-        INSERT INTO posts (title, slug, author, create_timestamp, update_timestamp, category, is_special, content)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?);
+        INSERT INTO posts (title, slug, author, create_timestamp, update_timestamp, category, is_special, views,
+                           excerpt, content)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
     "#;
 
     let is_special = if post.is_special { 1 } else { 0 };
-    let content_data = bson::to_vec(&post.content).unwrap();
+    let excerpt = post.content.excerpt(EXCERPT_MAX_CHARS);
+    let content_data = encode_content(&post.content)?;
 
-    // Insert the post object into the database.
+    // Insert the post object into the database. The slug is the only constraint that a post can violate.
     conn.execute(
         INSERT_POST_SQL,
         (
@@ -144,9 +533,17 @@ pub(super) fn insert_post(
             post.update_timestamp,
             &post.category,
             is_special,
+            post.views,
+            &excerpt,
             &content_data,
         ),
-    )?;
+    )
+    .map_err(|err| match err.sqlite_error_code() {
+        Some(rusqlite::ErrorCode::ConstraintViolation) => SqliteStorageError::Duplicate {
+            slug: post.slug.clone(),
+        },
+        _ => SqliteStorageError::from(err),
+    })?;
 
     // Insert tags into the database.
     if !post.tags.is_empty() {
@@ -156,18 +553,170 @@ pub(super) fn insert_post(
     // Insert post resources into the database.
     crate::storage::sqlite::post::insert_post_resources(conn, &post.slug, post_resources)?;
 
+    // Index the post for full-text search.
+    insert_post_fts(conn, post)?;
+
+    Ok(())
+}
+
+/// Add the view count column to the posts table.
+pub(crate) fn init_views_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const INIT_SQL: &str = r#"
+        ALTER TABLE posts ADD COLUMN views INTEGER NOT NULL DEFAULT 0;
+    "#;
+
+    conn.execute_batch(INIT_SQL)?;
+
+    Ok(())
+}
+
+/// Add the excerpt column to the posts table, and generate excerpts for the existing posts.
+pub(crate) fn init_excerpt_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const INIT_SQL: &str = r#"
+        ALTER TABLE posts ADD COLUMN excerpt TEXT NOT NULL DEFAULT '';
+    "#;
+
+    const SELECT_SQL: &str = r#"
+        SELECT slug, content FROM posts;
+    "#;
+
+    const UPDATE_SQL: &str = r#"
+        UPDATE posts SET excerpt = ?
+        WHERE slug == ?;
+    "#;
+
+    conn.execute_batch(INIT_SQL)?;
+
+    let posts = conn.query_many(SELECT_SQL, (), |row| {
+        let slug: String = row.get("slug")?;
+        let content_data: Vec<u8> = row.get("content")?;
+        let content: DocumentNode = bson::from_slice(&content_data)?;
+        Ok((slug, content.excerpt(EXCERPT_MAX_CHARS)))
+    })?;
+    for (slug, excerpt) in posts {
+        conn.execute(UPDATE_SQL, (excerpt, slug))?;
+    }
+
     Ok(())
 }
 
-pub(super) fn delete_post(conn: &Connection, post_slug: &str) -> Result<(), SqliteStorageError> {
+/// Prefix the contents of the existing posts, which are encoded in BSON without a format version, with
+/// [`CONTENT_FORMAT_BSON`].
+pub(crate) fn init_content_format(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const UPDATE_SQL: &str = r#"
+        UPDATE posts SET content = ?
+        WHERE slug == ?;
+    "#;
+
+    let posts = conn.query_many("SELECT slug, content FROM posts;", (), |row| {
+        let slug: String = row.get("slug")?;
+        let content_data: Vec<u8> = row.get("content")?;
+        Ok((slug, content_data))
+    })?;
+    for (slug, content_data) in posts {
+        let mut tagged = Vec::with_capacity(content_data.len() + 1);
+        tagged.push(CONTENT_FORMAT_BSON);
+        tagged.extend_from_slice(&content_data);
+        conn.execute(UPDATE_SQL, (tagged, slug))?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn init_update_timestamp_index(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const INIT_SQL: &str = r#"
+        CREATE INDEX posts_update_timestamp_idx ON posts (update_timestamp);
+    "#;
+
+    conn.execute_batch(INIT_SQL)?;
+
+    Ok(())
+}
+
+/// Get the view count of the specified post. Returns `None` if the post does not exist.
+pub(super) fn get_post_views(
+    conn: &Connection,
+    post_slug: &str,
+) -> Result<Option<u64>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT views FROM posts
+        WHERE slug == ?;
+    "#;
+
+    conn.query_one(SELECT_SQL, (post_slug,), |row| {
+        row.get(0).map_err(From::from)
+    })
+}
+
+/// Set the view count of the specified post.
+pub(super) fn set_post_views(
+    conn: &Connection,
+    post_slug: &str,
+    views: u64,
+) -> Result<(), SqliteStorageError> {
+    const UPDATE_SQL: &str = r#"
+        UPDATE posts SET views = ?
+        WHERE slug == ?;
+    "#;
+
+    conn.execute(UPDATE_SQL, (views, post_slug))?;
+
+    Ok(())
+}
+
+/// Increment the view count of the specified post by one and return the new view count. Returns `None` if the post
+/// does not exist.
+pub(super) fn increment_post_views(
+    conn: &Connection,
+    post_slug: &str,
+) -> Result<Option<u64>, SqliteStorageError> {
+    const UPDATE_SQL: &str = r#"
+        UPDATE posts SET views = views + 1
+        WHERE slug == ?
+        RETURNING views;
+    "#;
+
+    conn.query_one(UPDATE_SQL, (post_slug,), |row| {
+        row.get(0).map_err(From::from)
+    })
+}
+
+/// Delete the post with the given slug. Returns whether the post existed and has been deleted.
+pub(super) fn delete_post(conn: &Connection, post_slug: &str) -> Result<bool, SqliteStorageError> {
     const DELETE_SQL: &str = r#"
         DELETE FROM posts
         WHERE slug == ?;
     "#;
 
+    const DELETE_FTS_SQL: &str = r#"
+        DELETE FROM posts_fts
+        WHERE slug == ?;
+    "#;
+
     delete_post_resources(conn, post_slug)?;
 
-    conn.execute(DELETE_SQL, (post_slug,))?;
+    let deleted_rows = conn.execute(DELETE_SQL, (post_slug,))?;
+    conn.execute(DELETE_FTS_SQL, (post_slug,))?;
+
+    Ok(deleted_rows > 0)
+}
+
+fn insert_post_fts(conn: &Connection, post: &Post) -> Result<(), SqliteStorageError> {
+    const INSERT_SQL: &str = r#"
+        INSERT INTO posts_fts (slug, title, category, tags, content)
+        VALUES (?, ?, ?, ?, ?);
+    "#;
+
+    conn.execute(
+        INSERT_SQL,
+        (
+            &post.slug,
+            &post.title,
+            &post.category,
+            post.tags.join(" "),
+            post.content.to_plain_text(),
+        ),
+    )?;
 
     Ok(())
 }
@@ -215,16 +764,20 @@ fn insert_post_tags(
     Ok(())
 }
 
+/// Insert the given resources and associate them with the given post.
+///
+/// Resources may be shared among posts, so resources that already exist are associated with the post without being
+/// inserted again.
 fn insert_post_resources(
     conn: &Connection,
     post_slug: &str,
     resources: &[Resource],
 ) -> Result<(), SqliteStorageError> {
     for res in resources {
-        crate::storage::sqlite::resource::insert_resource(conn, res)?;
+        crate::storage::sqlite::resource::insert_resource_if_absent(conn, res)?;
 
         const INSERT_RELATION_SQL: &str = r#"
-            INSERT INTO posts_resources (post_slug, res_id)
+            INSERT OR IGNORE INTO posts_resources (post_slug, res_id)
             VALUES (?, ?);
         "#;
         let res_id = format!("{}", res.id.as_hyphenated());
@@ -234,6 +787,7 @@ fn insert_post_resources(
     Ok(())
 }
 
+/// Dissociate all resources from the given post, and delete those that are no longer referenced by any other post.
 fn delete_post_resources(conn: &Connection, post_slug: &str) -> Result<(), SqliteStorageError> {
     const SELECT_RES_ID_SQL: &str = r#"
         SELECT res_id
@@ -246,16 +800,51 @@ fn delete_post_resources(conn: &Connection, post_slug: &str) -> Result<(), Sqlit
         Ok(res_id)
     })?;
 
+    const DELETE_RELATION_SQL: &str = r#"
+        DELETE FROM posts_resources
+        WHERE post_slug == ?;
+    "#;
+    conn.execute(DELETE_RELATION_SQL, (post_slug,))?;
+
+    const SELECT_REF_COUNT_SQL: &str = r#"
+        SELECT count(*)
+        FROM posts_resources
+        WHERE res_id == ?;
+    "#;
     for res_id in res_ids {
-        crate::storage::sqlite::resource::delete_resource(conn, &res_id)?;
+        let res_id_str = format!("{}", res_id.as_hyphenated());
+        let ref_count: usize = conn
+            .query_one(SELECT_REF_COUNT_SQL, (&res_id_str,), |row| {
+                row.get(0).map_err(From::from)
+            })?
+            .unwrap();
+        if ref_count == 0 {
+            crate::storage::sqlite::resource::delete_resource(conn, &res_id)?;
+        }
     }
 
     Ok(())
 }
 
+/// Encode the given post content, prefixed with its format version.
+fn encode_content(content: &DocumentNode) -> Result<Vec<u8>, SqliteStorageError> {
+    let mut data = vec![CONTENT_FORMAT_BSON];
+    data.extend(bson::to_vec(content)?);
+    Ok(data)
+}
+
+/// Decode the post content encoded by [`encode_content`], dispatching on its format version.
+fn decode_content(data: &[u8]) -> Result<DocumentNode, SqliteStorageError> {
+    match data.split_first() {
+        Some((&CONTENT_FORMAT_BSON, content_data)) => Ok(bson::from_slice(content_data)?),
+        Some((&version, _)) => Err(SqliteStorageError::ContentFormat(Some(version))),
+        None => Err(SqliteStorageError::ContentFormat(None)),
+    }
+}
+
 fn create_post_from_row(row: &Row) -> Result<Post, SqliteStorageError> {
     let content_data: Vec<u8> = row.get("content")?;
-    let content = bson::from_slice(&content_data)?;
+    let content = decode_content(&content_data)?;
     Ok(Post {
         title: row.get("title")?,
         slug: row.get("slug")?,
@@ -265,6 +854,8 @@ fn create_post_from_row(row: &Row) -> Result<Post, SqliteStorageError> {
         category: row.get("category")?,
         tags: Vec::new(),
         is_special: row.get::<_, i32>("is_special")? != 0,
+        views: row.get("views")?,
+        excerpt: row.get("excerpt")?,
         content,
     })
 }
@@ -279,10 +870,19 @@ fn create_post_from_row_no_content(row: &Row) -> Result<Post, SqliteStorageError
         category: row.get("category")?,
         tags: Vec::new(),
         is_special: row.get::<_, i32>("is_special")? != 0,
+        views: row.get("views")?,
+        excerpt: row.get("excerpt")?,
         content: DocumentNode::new_empty(),
     })
 }
 
+fn create_taxonomy_term_from_row(row: &Row) -> Result<TaxonomyTerm, SqliteStorageError> {
+    Ok(TaxonomyTerm {
+        name: row.get("name")?,
+        post_count: row.get("post_count")?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -292,7 +892,13 @@ mod tests {
         let conn = Connection::open_in_memory().unwrap();
 
         init_db_schema(&conn).unwrap();
+        init_fts_schema(&conn).unwrap();
+        init_views_schema(&conn).unwrap();
+        init_update_timestamp_index(&conn).unwrap();
+        init_excerpt_schema(&conn).unwrap();
         crate::storage::sqlite::resource::init_db_schema(&conn).unwrap();
+        crate::storage::sqlite::resource::migrate_rowid_table(&conn).unwrap();
+        crate::storage::sqlite::resource::migrate_content_hash(&conn).unwrap();
 
         conn
     }
@@ -322,6 +928,8 @@ mod tests {
             category: String::from("category"),
             tags: Vec::new(),
             is_special: false,
+            views: 0,
+            excerpt: String::new(),
             content: DocumentNode::new_empty(),
         };
         insert_post(&conn, &post, &[]).unwrap();
@@ -340,12 +948,16 @@ mod tests {
             category: String::from("category"),
             tags: Vec::new(),
             is_special: false,
+            views: 0,
+            excerpt: String::new(),
             content: DocumentNode::new_empty(),
         };
         insert_post(&conn, &post, &[]).unwrap();
 
         let insert_res = insert_post(&conn, &post, &[]);
-        assert!(insert_res.is_err());
+        assert!(
+            matches!(insert_res, Err(SqliteStorageError::Duplicate { slug }) if slug == "slug")
+        );
     }
 
     #[test]
@@ -361,6 +973,8 @@ mod tests {
             category: String::from("category"),
             tags: vec![String::from("tag1"), String::from("tag2")],
             is_special: false,
+            views: 0,
+            excerpt: String::new(),
             content: DocumentNode::new_empty(),
         };
         insert_post(&conn, &post, &[]).unwrap();
@@ -385,6 +999,8 @@ mod tests {
             category: String::from("category"),
             tags: vec![String::from("tag1"), String::from("tag2")],
             is_special: false,
+            views: 0,
+            excerpt: String::new(),
             content: DocumentNode::new_empty(),
         };
         insert_post(&conn, &post, &[]).unwrap();
@@ -420,6 +1036,8 @@ mod tests {
             category: String::from("category"),
             tags: vec![String::from("tag1"), String::from("tag2")],
             is_special: false,
+            views: 0,
+            excerpt: String::new(),
             content: DocumentNode::new_empty(),
         };
         insert_post(&conn, &post1, &[]).unwrap();
@@ -440,8 +1058,13 @@ mod tests {
         };
         insert_post(&conn, &post3, &[]).unwrap();
 
-        let selected_posts =
-            get_posts(&conn, false, &Pagination::from_page_and_size(2, 1)).unwrap();
+        let selected_posts = get_posts(
+            &conn,
+            false,
+            PostSort::CreatedDesc,
+            &Pagination::from_page_and_size(2, 1),
+        )
+        .unwrap();
         assert_eq!(selected_posts.objects.len(), 1);
         assert_eq!(selected_posts.total_count, 3);
 
@@ -468,11 +1091,13 @@ mod tests {
             category: String::from("category"),
             tags: Vec::new(),
             is_special: false,
+            views: 0,
+            excerpt: String::new(),
             content: DocumentNode::new_empty(),
         };
         insert_post(&conn, &post, &[]).unwrap();
 
-        delete_post(&conn, "slug").unwrap();
+        assert!(delete_post(&conn, "slug").unwrap());
 
         let selected_post = get_post(&conn, "slug").unwrap();
         assert!(selected_post.is_none());
@@ -481,6 +1106,555 @@ mod tests {
     #[test]
     fn test_delete_not_exist() {
         let conn = init_db_connection();
-        delete_post(&conn, "slug").unwrap();
+        assert!(!delete_post(&conn, "slug").unwrap());
+    }
+
+    #[test]
+    fn test_get_taxonomy() {
+        let conn = init_db_connection();
+
+        let posts = [
+            ("post1", "rust", vec!["async", "sqlite"], false),
+            ("post2", "rust", vec!["async"], false),
+            ("post3", "life", vec![], false),
+            ("about", "pages", vec!["async"], true),
+        ];
+        for (slug, category, tags, is_special) in posts {
+            let post = Post {
+                title: String::from("title"),
+                slug: String::from(slug),
+                author: String::from("msr"),
+                create_timestamp: 0,
+                update_timestamp: 0,
+                category: String::from(category),
+                tags: tags.into_iter().map(String::from).collect(),
+                is_special,
+                views: 0,
+                excerpt: String::new(),
+                content: DocumentNode::new_empty(),
+            };
+            insert_post(&conn, &post, &[]).unwrap();
+        }
+
+        let term = |name: &str, post_count| TaxonomyTerm {
+            name: String::from(name),
+            post_count,
+        };
+        let taxonomy = get_taxonomy(&conn).unwrap();
+        assert_eq!(taxonomy.categories, vec![term("life", 1), term("rust", 2)]);
+        assert_eq!(taxonomy.tags, vec![term("async", 2), term("sqlite", 1)]);
+    }
+
+    fn create_filter_test_posts(conn: &Connection) {
+        let posts = [
+            ("post1", 1, "rust", vec!["async", "sqlite"], false),
+            ("post2", 2, "rust", vec!["async"], false),
+            ("post3", 3, "life", vec!["sqlite"], false),
+            ("post4", 4, "rust", vec!["async"], false),
+            ("about", 5, "rust", vec!["async"], true),
+        ];
+        for (slug, create_timestamp, category, tags, is_special) in posts {
+            let post = Post {
+                title: String::from("title"),
+                slug: String::from(slug),
+                author: String::from("msr"),
+                create_timestamp,
+                update_timestamp: create_timestamp,
+                category: String::from(category),
+                tags: tags.into_iter().map(String::from).collect(),
+                is_special,
+                views: 0,
+                excerpt: String::new(),
+                content: DocumentNode::new_empty(),
+            };
+            insert_post(conn, &post, &[]).unwrap();
+        }
+    }
+
+    fn slugs(posts: &PaginatedList<Post>) -> Vec<&str> {
+        posts
+            .objects
+            .iter()
+            .map(|post| post.slug.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_get_posts_special() {
+        let conn = init_db_connection();
+        create_filter_test_posts(&conn);
+
+        let pagination = Pagination::from_page_and_size(1, 10);
+        let posts = get_posts(&conn, false, PostSort::CreatedDesc, &pagination).unwrap();
+        assert_eq!(posts.total_count, 4);
+        assert_eq!(slugs(&posts), vec!["post4", "post3", "post2", "post1"]);
+        assert!(posts.objects.iter().all(|post| !post.is_special));
+
+        let posts = get_posts(&conn, true, PostSort::CreatedDesc, &pagination).unwrap();
+        assert_eq!(posts.total_count, 1);
+        assert_eq!(slugs(&posts), vec!["about"]);
+        assert!(posts.objects[0].is_special);
+    }
+
+    #[test]
+    fn test_get_post_unknown_content_format() {
+        let conn = init_db_connection();
+        let post = create_search_test_post("post1", "title", "text");
+        insert_post(&conn, &post, &[]).unwrap();
+        insert_post(
+            &conn,
+            &create_search_test_post("post2", "title", "text"),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            get_post(&conn, "post1").unwrap().unwrap().content,
+            post.content
+        );
+
+        conn.execute(
+            "UPDATE posts SET content = X'7F00' WHERE slug == 'post1';",
+            (),
+        )
+        .unwrap();
+        conn.execute("UPDATE posts SET content = X'' WHERE slug == 'post2';", ())
+            .unwrap();
+        assert!(matches!(
+            get_post(&conn, "post1"),
+            Err(SqliteStorageError::ContentFormat(Some(0x7f)))
+        ));
+        assert!(matches!(
+            get_post(&conn, "post2"),
+            Err(SqliteStorageError::ContentFormat(None))
+        ));
+    }
+
+    #[test]
+    fn test_get_post_corrupt_content() {
+        let conn = init_db_connection();
+        insert_post(
+            &conn,
+            &create_search_test_post("post1", "title", "text"),
+            &[],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE posts SET content = X'01DEADBEEF' WHERE slug == 'post1';",
+            (),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            get_post(&conn, "post1"),
+            Err(SqliteStorageError::Bson(_))
+        ));
+
+        // Listing posts does not touch their contents.
+        let pagination = Pagination::from_page_and_size(1, 10);
+        let posts = get_posts(&conn, false, PostSort::CreatedDesc, &pagination).unwrap();
+        assert_eq!(posts.total_count, 1);
+    }
+
+    #[test]
+    fn test_get_posts_excerpt() {
+        let conn = init_db_connection();
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.heading(1, |b| {
+            b.text("Title");
+        });
+        builder.paragraph(|b| {
+            b.text("word ".repeat(100));
+        });
+        let post = Post {
+            title: String::from("title"),
+            slug: String::from("post"),
+            author: String::from("msr"),
+            create_timestamp: 0,
+            update_timestamp: 0,
+            category: String::from("category"),
+            tags: Vec::new(),
+            is_special: false,
+            views: 0,
+            excerpt: String::from("ignored"),
+            content: builder.finish(),
+        };
+        insert_post(&conn, &post, &[]).unwrap();
+
+        let posts = get_posts(
+            &conn,
+            false,
+            PostSort::CreatedDesc,
+            &Pagination::from_page_and_size(1, 10),
+        )
+        .unwrap();
+        let excerpt = &posts.objects[0].excerpt;
+        assert_eq!(posts.objects[0].content, DocumentNode::new_empty());
+        assert!(excerpt.starts_with("word word"));
+        assert!(excerpt.ends_with("word…"));
+        assert!(excerpt.chars().count() <= EXCERPT_MAX_CHARS + 1);
+
+        assert_eq!(get_post(&conn, "post").unwrap().unwrap().excerpt, *excerpt);
+    }
+
+    #[test]
+    fn test_get_posts_sorted() {
+        let conn = init_db_connection();
+        let posts = [
+            ("post1", "Banana", 100, 400),
+            ("post2", "Cherry", 200, 200),
+            ("post3", "Apple", 300, 300),
+            ("post4", "Apple", 300, 100),
+        ];
+        for (slug, title, create_timestamp, update_timestamp) in posts {
+            let post = Post {
+                title: String::from(title),
+                slug: String::from(slug),
+                author: String::from("msr"),
+                create_timestamp,
+                update_timestamp,
+                category: String::from("category"),
+                tags: Vec::new(),
+                is_special: false,
+                views: 0,
+                excerpt: String::new(),
+                content: DocumentNode::new_empty(),
+            };
+            insert_post(&conn, &post, &[]).unwrap();
+        }
+
+        let pagination = Pagination::from_page_and_size(1, 10);
+        let sorted_slugs = |sort: PostSort| {
+            let posts = get_posts(&conn, false, sort, &pagination).unwrap();
+            assert_eq!(posts.total_count, 4);
+            posts
+                .objects
+                .into_iter()
+                .map(|post| post.slug)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sorted_slugs(PostSort::CreatedDesc),
+            vec!["post3", "post4", "post2", "post1"]
+        );
+        assert_eq!(
+            sorted_slugs(PostSort::CreatedAsc),
+            vec!["post1", "post2", "post3", "post4"]
+        );
+        assert_eq!(
+            sorted_slugs(PostSort::UpdatedDesc),
+            vec!["post1", "post3", "post2", "post4"]
+        );
+        assert_eq!(
+            sorted_slugs(PostSort::TitleAsc),
+            vec!["post3", "post4", "post1", "post2"]
+        );
+    }
+
+    #[test]
+    fn test_get_posts_by_tag() {
+        let conn = init_db_connection();
+        create_filter_test_posts(&conn);
+
+        let pagination = Pagination::from_page_and_size(1, 2);
+        let posts = get_posts_by_tag(&conn, "async", &pagination).unwrap();
+        assert_eq!(posts.total_count, 3);
+        assert_eq!(slugs(&posts), vec!["post4", "post2"]);
+
+        let pagination = Pagination::from_page_and_size(2, 2);
+        let posts = get_posts_by_tag(&conn, "async", &pagination).unwrap();
+        assert_eq!(posts.total_count, 3);
+        assert_eq!(slugs(&posts), vec!["post1"]);
+        assert_eq!(posts.objects[0].tags.len(), 2);
+
+        let posts = get_posts_by_tag(&conn, "missing", &pagination).unwrap();
+        assert_eq!(posts.total_count, 0);
+        assert!(posts.objects.is_empty());
+    }
+
+    #[test]
+    fn test_get_posts_by_category() {
+        let conn = init_db_connection();
+        create_filter_test_posts(&conn);
+
+        let pagination = Pagination::from_page_and_size(1, 10);
+        let posts = get_posts_by_category(&conn, "rust", &pagination).unwrap();
+        assert_eq!(posts.total_count, 3);
+        assert_eq!(slugs(&posts), vec!["post4", "post2", "post1"]);
+
+        let posts = get_posts_by_category(&conn, "life", &pagination).unwrap();
+        assert_eq!(posts.total_count, 1);
+        assert_eq!(slugs(&posts), vec!["post3"]);
+    }
+
+    #[test]
+    fn test_get_adjacent_posts() {
+        let conn = init_db_connection();
+        create_filter_test_posts(&conn);
+
+        let adjacent_slugs = |slug: &str| {
+            let (older, newer) = get_adjacent_posts(&conn, slug).unwrap();
+            (older.map(|post| post.slug), newer.map(|post| post.slug))
+        };
+
+        let (older, newer) = get_adjacent_posts(&conn, "post2").unwrap();
+        let (older, newer) = (older.unwrap(), newer.unwrap());
+        assert_eq!(older.slug, "post1");
+        assert_eq!(older.tags.len(), 2);
+        assert_eq!(older.content, DocumentNode::new_empty());
+        assert_eq!(newer.slug, "post3");
+
+        assert_eq!(adjacent_slugs("post1"), (None, Some(String::from("post2"))));
+        // The special post is never a neighbor.
+        assert_eq!(adjacent_slugs("post4"), (Some(String::from("post3")), None));
+        assert_eq!(adjacent_slugs("missing"), (None, None));
+    }
+
+    #[test]
+    fn test_get_posts_updated_since() {
+        let conn = init_db_connection();
+        let posts = [
+            ("post1", 100, false),
+            ("post2", 400, false),
+            ("post3", 200, false),
+            ("post4", 300, false),
+            ("about", 500, true),
+        ];
+        for (slug, update_timestamp, is_special) in posts {
+            let post = Post {
+                title: String::from("title"),
+                slug: String::from(slug),
+                author: String::from("msr"),
+                create_timestamp: 0,
+                update_timestamp,
+                category: String::from("category"),
+                tags: vec![String::from("tag")],
+                is_special,
+                views: 0,
+                excerpt: String::new(),
+                content: DocumentNode::new_empty(),
+            };
+            insert_post(&conn, &post, &[]).unwrap();
+        }
+
+        let slugs = |posts: &PaginatedList<Post>| {
+            posts
+                .objects
+                .iter()
+                .map(|post| post.slug.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let posts =
+            get_posts_updated_since(&conn, 100, &Pagination::from_page_and_size(1, 10)).unwrap();
+        assert_eq!(posts.total_count, 3);
+        assert_eq!(slugs(&posts), vec!["post2", "post4", "post3"]);
+        assert_eq!(posts.objects[0].tags, vec![String::from("tag")]);
+
+        let posts =
+            get_posts_updated_since(&conn, 0, &Pagination::from_page_and_size(2, 2)).unwrap();
+        assert_eq!(posts.total_count, 4);
+        assert_eq!(slugs(&posts), vec!["post3", "post1"]);
+
+        let posts =
+            get_posts_updated_since(&conn, 400, &Pagination::from_page_and_size(1, 10)).unwrap();
+        assert_eq!(posts.total_count, 0);
+        assert!(posts.objects.is_empty());
+    }
+
+    #[test]
+    fn test_get_related_posts() {
+        let conn = init_db_connection();
+        let posts = [
+            ("post", 0, vec!["a", "b", "c"], false),
+            ("two-shared", 1, vec!["a", "b"], false),
+            ("one-shared", 2, vec!["a"], false),
+            ("unrelated", 3, vec!["d"], false),
+            ("about", 4, vec!["a", "b", "c"], true),
+        ];
+        for (slug, create_timestamp, tags, is_special) in posts {
+            let post = Post {
+                title: String::from("title"),
+                slug: String::from(slug),
+                author: String::from("msr"),
+                create_timestamp,
+                update_timestamp: create_timestamp,
+                category: String::from("category"),
+                tags: tags.into_iter().map(String::from).collect(),
+                is_special,
+                views: 0,
+                excerpt: String::new(),
+                content: DocumentNode::new_empty(),
+            };
+            insert_post(&conn, &post, &[]).unwrap();
+        }
+
+        let related = get_related_posts(&conn, "post", 10).unwrap();
+        let related_slugs: Vec<_> = related.iter().map(|post| post.slug.as_str()).collect();
+        assert_eq!(related_slugs, vec!["two-shared", "one-shared"]);
+        assert_eq!(related[0].tags.len(), 2);
+
+        let related = get_related_posts(&conn, "post", 1).unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].slug, "two-shared");
+
+        assert!(get_related_posts(&conn, "missing", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_post_archive() {
+        let conn = init_db_connection();
+        let posts = [
+            // 2022-09-30T23:00:00Z
+            ("september", 1664578800, false),
+            // 2022-10-01T00:00:00Z
+            ("october1", 1664582400, false),
+            // 2022-10-15T00:00:00Z
+            ("october2", 1665792000, false),
+            ("about", 1665792000, true),
+        ];
+        for (slug, create_timestamp, is_special) in posts {
+            let post = Post {
+                title: String::from("title"),
+                slug: String::from(slug),
+                author: String::from("msr"),
+                create_timestamp,
+                update_timestamp: create_timestamp,
+                category: String::from("category"),
+                tags: Vec::new(),
+                is_special,
+                views: 0,
+                excerpt: String::new(),
+                content: DocumentNode::new_empty(),
+            };
+            insert_post(&conn, &post, &[]).unwrap();
+        }
+
+        let bucket = |year, month, count| ArchiveBucket { year, month, count };
+        assert_eq!(
+            get_post_archive(&conn).unwrap(),
+            vec![bucket(2022, 10, 2), bucket(2022, 9, 1)]
+        );
+    }
+
+    #[test]
+    fn test_get_tag_counts() {
+        let conn = init_db_connection();
+        create_filter_test_posts(&conn);
+
+        // The special post "about" is not counted.
+        let counts = get_tag_counts(&conn).unwrap();
+        assert_eq!(
+            counts,
+            vec![(String::from("async"), 3), (String::from("sqlite"), 2)]
+        );
+    }
+
+    fn create_search_test_post(slug: &str, title: &str, text: &str) -> Post {
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.text(text);
+        });
+
+        Post {
+            title: String::from(title),
+            slug: String::from(slug),
+            author: String::from("msr"),
+            create_timestamp: 0,
+            update_timestamp: 0,
+            category: String::from("category"),
+            tags: vec![String::from("tag")],
+            is_special: false,
+            views: 0,
+            excerpt: String::new(),
+            content: builder.finish(),
+        }
+    }
+
+    fn search_slugs(conn: &Connection, query: &str) -> Vec<String> {
+        let pagination = Pagination::from_page_and_size(1, 10);
+        search_posts(conn, query, &pagination)
+            .unwrap()
+            .objects
+            .into_iter()
+            .map(|post| post.slug)
+            .collect()
+    }
+
+    #[test]
+    fn test_search_posts() {
+        let conn = init_db_connection();
+
+        let posts = [
+            create_search_test_post("content", "Unrelated", "all about sqlite internals"),
+            create_search_test_post("title", "SQLite tips", "nothing to see"),
+            create_search_test_post("other", "Other", "rust async"),
+        ];
+        for post in &posts {
+            insert_post(&conn, post, &[]).unwrap();
+        }
+
+        assert_eq!(search_slugs(&conn, "sqlite"), vec!["title", "content"]);
+        assert_eq!(search_slugs(&conn, "rust async"), vec!["other"]);
+        assert_eq!(search_slugs(&conn, "rust sqlite"), Vec::<String>::new());
+        assert_eq!(search_slugs(&conn, "  "), Vec::<String>::new());
+
+        delete_post(&conn, "title").unwrap();
+        assert_eq!(search_slugs(&conn, "sqlite"), vec!["content"]);
+    }
+
+    #[test]
+    fn test_search_posts_escape() {
+        let conn = init_db_connection();
+
+        let post = create_search_test_post("slug", "title", "say \"hello\" OR NOT");
+        insert_post(&conn, &post, &[]).unwrap();
+
+        assert_eq!(search_slugs(&conn, "\"hello"), vec!["slug"]);
+        assert_eq!(search_slugs(&conn, "OR NOT"), vec!["slug"]);
+        assert_eq!(search_slugs(&conn, "title:* AND ("), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_shared_post_resource() {
+        let conn = init_db_connection();
+
+        let res = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("image/png"),
+            data: vec![0, 1, 2, 3],
+        };
+        let first = create_search_test_post("first", "First", "");
+        let second = create_search_test_post("second", "Second", "");
+        insert_post(&conn, &first, std::slice::from_ref(&res)).unwrap();
+        insert_post(&conn, &second, std::slice::from_ref(&res)).unwrap();
+
+        assert_eq!(
+            crate::storage::sqlite::resource::get_resources(&conn)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            get_post_with_resources(&conn, "second")
+                .unwrap()
+                .unwrap()
+                .1
+                .len(),
+            1
+        );
+
+        delete_post(&conn, "first").unwrap();
+        let resources = get_post_with_resources(&conn, "second").unwrap().unwrap().1;
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].data, res.data);
+
+        delete_post(&conn, "second").unwrap();
+        assert!(
+            crate::storage::sqlite::resource::get_resource(&conn, &res.id)
+                .unwrap()
+                .is_none()
+        );
     }
 }
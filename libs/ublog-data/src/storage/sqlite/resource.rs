@@ -1,8 +1,9 @@
-use rusqlite::{Connection, Row};
+use rusqlite::{Connection, DatabaseName, Row};
 use uuid::Uuid;
 
-use crate::models::Resource;
+use crate::models::{compute_content_hash, Resource, ResourceMeta};
 use crate::storage::sqlite::{SqliteExt, SqliteStorageError};
+use crate::storage::{PaginatedList, Pagination};
 
 pub(crate) fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError> {
     const INIT_SQL: &str = r#"
@@ -18,6 +19,60 @@ pub(crate) fn init_db_schema(conn: &Connection) -> Result<(), SqliteStorageError
     Ok(())
 }
 
+/// Convert the resources table into a rowid table so that resource data can be read through incremental blob I/O,
+/// which is not supported on `WITHOUT ROWID` tables.
+pub(crate) fn migrate_rowid_table(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const MIGRATE_SQL: &str = r#"
+        CREATE TABLE resources_rowid (
+            id   TEXT NOT NULL PRIMARY KEY,
+            name TEXT NOT NULL,
+            ty   TEXT NOT NULL,
+            data BLOB NOT NULL
+        );
+
+        INSERT INTO resources_rowid (id, name, ty, data)
+        SELECT id, name, ty, data FROM resources;
+
+        DROP TABLE resources;
+        ALTER TABLE resources_rowid RENAME TO resources;
+    "#;
+    conn.execute_batch(MIGRATE_SQL)?;
+
+    Ok(())
+}
+
+/// Add a column holding the content hash of each resource, together with an index on it, so that resources with
+/// identical data can be found quickly.
+pub(crate) fn migrate_content_hash(conn: &Connection) -> Result<(), SqliteStorageError> {
+    const MIGRATE_SQL: &str = r#"
+        ALTER TABLE resources ADD COLUMN hash TEXT;
+        CREATE INDEX resources_idx_hash ON resources (hash);
+    "#;
+    conn.execute_batch(MIGRATE_SQL)?;
+
+    const SELECT_SQL: &str = r#"
+        SELECT rowid, data
+        FROM resources;
+    "#;
+    const UPDATE_SQL: &str = r#"
+        UPDATE resources
+        SET hash = ?
+        WHERE rowid == ?;
+    "#;
+
+    // Only the hashes are collected so that the data of existing resources are not loaded into memory all at once.
+    let hashes = conn.query_many(SELECT_SQL, (), |row| {
+        let rowid: i64 = row.get("rowid")?;
+        let data: Vec<u8> = row.get("data")?;
+        Ok((rowid, compute_content_hash(&data)))
+    })?;
+    for (rowid, hash) in hashes {
+        conn.execute(UPDATE_SQL, (&hash, rowid))?;
+    }
+
+    Ok(())
+}
+
 pub(crate) fn get_resource(
     conn: &Connection,
     uuid: &Uuid,
@@ -41,6 +96,117 @@ pub(crate) fn get_resources(conn: &Connection) -> Result<Vec<Resource>, SqliteSt
     conn.query_many(SELECT_SQL, (), create_resources_from_row_no_data)
 }
 
+pub(crate) fn get_resource_meta(
+    conn: &Connection,
+    uuid: &Uuid,
+) -> Result<Option<ResourceMeta>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT id, name, ty, length(data) AS size, hash
+        FROM resources
+        WHERE id == ?;
+    "#;
+
+    let uuid_str = format!("{}", uuid.as_hyphenated());
+    conn.query_one(SELECT_SQL, (&uuid_str,), create_resource_meta_from_row)
+}
+
+/// Read at most `len` bytes of the specified resource's data starting at the given offset, without loading the whole
+/// data into memory.
+pub(crate) fn read_resource_chunk(
+    conn: &Connection,
+    uuid: &Uuid,
+    offset: u64,
+    len: usize,
+) -> Result<Option<Vec<u8>>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT rowid
+        FROM resources
+        WHERE id == ?;
+    "#;
+
+    let uuid_str = format!("{}", uuid.as_hyphenated());
+    let rowid: i64 = match conn.query_one(SELECT_SQL, (&uuid_str,), |row| {
+        row.get(0).map_err(From::from)
+    })? {
+        Some(rowid) => rowid,
+        None => return Ok(None),
+    };
+
+    let blob = conn.blob_open(DatabaseName::Main, "resources", "data", rowid, true)?;
+    let start = usize::try_from(offset)
+        .unwrap_or(usize::MAX)
+        .min(blob.len());
+    let end = start.saturating_add(len).min(blob.len());
+
+    let mut chunk = vec![0; end - start];
+    blob.read_at_exact(&mut chunk, start)?;
+
+    Ok(Some(chunk))
+}
+
+/// Find a resource whose data has the given content hash. See [`Resource::content_hash`].
+pub(crate) fn find_resource_by_hash(
+    conn: &Connection,
+    hash: &str,
+) -> Result<Option<ResourceMeta>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT id, name, ty, length(data) AS size, hash
+        FROM resources
+        WHERE hash == ?
+        ORDER BY id
+        LIMIT 1;
+    "#;
+
+    conn.query_one(SELECT_SQL, (hash,), create_resource_meta_from_row)
+}
+
+pub(crate) fn get_resource_metas(
+    conn: &Connection,
+) -> Result<Vec<ResourceMeta>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT id, name, ty, length(data) AS size, hash
+        FROM resources
+        ORDER BY id;
+    "#;
+
+    conn.query_many(SELECT_SQL, (), create_resource_meta_from_row)
+}
+
+pub(crate) fn get_resources_paged(
+    conn: &Connection,
+    pagination: &Pagination,
+) -> Result<PaginatedList<Resource>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT id, name, ty
+        FROM resources
+        ORDER BY id
+        LIMIT ? OFFSET ?;
+    "#;
+
+    const SELECT_COUNT_SQL: &str = r#"
+        SELECT count(*) AS cnt
+        FROM resources;
+    "#;
+
+    let limit = pagination.page_size();
+    let offset = pagination.skip_count();
+
+    let total_count: usize = conn
+        .query_one(SELECT_COUNT_SQL, (), |row| row.get(0).map_err(From::from))?
+        .unwrap();
+
+    let resources = conn.query_many(
+        SELECT_SQL,
+        (limit, offset),
+        create_resources_from_row_no_data,
+    )?;
+
+    Ok(PaginatedList {
+        objects: resources,
+        total_count,
+    })
+}
+
 pub(crate) fn get_post_resources(
     conn: &Connection,
     post_slug: &str,
@@ -59,29 +225,102 @@ pub(crate) fn insert_resource(
     resource: &Resource,
 ) -> Result<(), SqliteStorageError> {
     const INSERT_SQL: &str = r#"
-        INSERT INTO resources (id, name, ty, data)
-        VALUES (?, ?, ?, ?);
+        INSERT INTO resources (id, name, ty, data, hash)
+        VALUES (?, ?, ?, ?, ?);
     "#;
 
     let uuid_str = format!("{}", resource.id.as_hyphenated());
 
     conn.execute(
         INSERT_SQL,
-        (&uuid_str, &resource.name, &resource.ty, &resource.data),
+        (
+            &uuid_str,
+            &resource.name,
+            &resource.ty,
+            &resource.data,
+            &resource.content_hash(),
+        ),
     )?;
     Ok(())
 }
 
-pub(crate) fn delete_resource(conn: &Connection, uuid: &Uuid) -> Result<(), SqliteStorageError> {
+/// Insert the given resource unless a resource with the same ID already exists. Returns whether the resource has been
+/// inserted.
+pub(crate) fn insert_resource_if_absent(
+    conn: &Connection,
+    resource: &Resource,
+) -> Result<bool, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT count(*)
+        FROM resources
+        WHERE id == ?;
+    "#;
+
+    let uuid_str = format!("{}", resource.id.as_hyphenated());
+    let count: usize = conn
+        .query_one(SELECT_SQL, (&uuid_str,), |row| {
+            row.get(0).map_err(From::from)
+        })?
+        .unwrap();
+    if count > 0 {
+        return Ok(false);
+    }
+
+    insert_resource(conn, resource)?;
+    Ok(true)
+}
+
+/// Update the resource with the same ID as the given resource. Returns whether the resource exists.
+pub(crate) fn update_resource(
+    conn: &Connection,
+    resource: &Resource,
+) -> Result<bool, SqliteStorageError> {
+    const UPDATE_SQL: &str = r#"
+        UPDATE resources
+        SET name = ?, ty = ?, data = ?, hash = ?
+        WHERE id == ?;
+    "#;
+
+    let uuid_str = format!("{}", resource.id.as_hyphenated());
+
+    let updated = conn.execute(
+        UPDATE_SQL,
+        (
+            &resource.name,
+            &resource.ty,
+            &resource.data,
+            &resource.content_hash(),
+            &uuid_str,
+        ),
+    )?;
+    Ok(updated > 0)
+}
+
+/// Get the IDs of all resources that are not referenced by any post.
+pub(crate) fn get_orphan_resource_ids(conn: &Connection) -> Result<Vec<Uuid>, SqliteStorageError> {
+    const SELECT_SQL: &str = r#"
+        SELECT id
+        FROM resources
+        WHERE id NOT IN (SELECT res_id FROM posts_resources);
+    "#;
+
+    conn.query_many(SELECT_SQL, (), |row| {
+        let id_str: String = row.get("id")?;
+        Ok(id_str.parse()?)
+    })
+}
+
+/// Delete the resource with the given ID. Returns whether the resource existed and has been deleted.
+pub(crate) fn delete_resource(conn: &Connection, uuid: &Uuid) -> Result<bool, SqliteStorageError> {
     const DELETE_SQL: &str = r#"
         DELETE FROM resources
         WHERE id == ?;
     "#;
 
     let uuid_str = format!("{}", uuid.as_hyphenated());
-    conn.execute(DELETE_SQL, (&uuid_str,))?;
+    let deleted_rows = conn.execute(DELETE_SQL, (&uuid_str,))?;
 
-    Ok(())
+    Ok(deleted_rows > 0)
 }
 
 fn create_resource_from_row(row: &Row) -> Result<Resource, SqliteStorageError> {
@@ -102,7 +341,19 @@ fn create_resources_from_row_no_data(row: &Row) -> Result<Resource, SqliteStorag
         id,
         name: row.get("name")?,
         ty: row.get("ty")?,
-        data: row.get("data")?,
+        data: Vec::new(),
+    })
+}
+
+fn create_resource_meta_from_row(row: &Row) -> Result<ResourceMeta, SqliteStorageError> {
+    let id_str: String = row.get("id")?;
+    let id = id_str.parse()?;
+    Ok(ResourceMeta {
+        id,
+        name: row.get("name")?,
+        ty: row.get("ty")?,
+        size: row.get("size")?,
+        content_hash: row.get("hash")?,
     })
 }
 
@@ -113,6 +364,8 @@ mod tests {
     fn init_db_connection() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
         init_db_schema(&conn).unwrap();
+        migrate_rowid_table(&conn).unwrap();
+        migrate_content_hash(&conn).unwrap();
         conn
     }
 
@@ -193,7 +446,7 @@ mod tests {
         };
         insert_resource(&conn, &res).unwrap();
 
-        delete_resource(&conn, &res.id).unwrap();
+        assert!(delete_resource(&conn, &res.id).unwrap());
 
         let selected_res = get_resource(&conn, &res.id).unwrap();
         assert!(selected_res.is_none());
@@ -203,6 +456,241 @@ mod tests {
     fn test_delete_not_exist() {
         let conn = init_db_connection();
         let id = Uuid::new_v4();
-        delete_resource(&conn, &id).unwrap();
+        assert!(!delete_resource(&conn, &id).unwrap());
+    }
+
+    #[test]
+    fn test_update_resource() {
+        let conn = init_db_connection();
+
+        let mut res = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("text/html"),
+            data: vec![0, 1, 2, 3],
+        };
+        insert_resource(&conn, &res).unwrap();
+
+        res.name = String::from("new-res");
+        res.ty = String::from("text/plain");
+        res.data = vec![4, 5];
+        assert!(update_resource(&conn, &res).unwrap());
+
+        let selected = get_resource(&conn, &res.id).unwrap().unwrap();
+        assert_eq!(selected.name, "new-res");
+        assert_eq!(selected.ty, "text/plain");
+        assert_eq!(selected.data, vec![4, 5]);
+    }
+
+    #[test]
+    fn test_get_resources_paged() {
+        let conn = init_db_connection();
+
+        let mut ids = Vec::new();
+        for idx in 0..5 {
+            let res = Resource {
+                id: Uuid::new_v4(),
+                name: format!("res{}", idx),
+                ty: String::from("text/plain"),
+                data: vec![idx],
+            };
+            insert_resource(&conn, &res).unwrap();
+            ids.push(res.id);
+        }
+        ids.sort();
+
+        let first_page = get_resources_paged(&conn, &Pagination::from_page_and_size(1, 3)).unwrap();
+        let second_page =
+            get_resources_paged(&conn, &Pagination::from_page_and_size(2, 3)).unwrap();
+        assert_eq!(first_page.total_count, 5);
+        assert_eq!(second_page.total_count, 5);
+
+        let paged_ids: Vec<Uuid> = first_page
+            .objects
+            .iter()
+            .chain(second_page.objects.iter())
+            .map(|res| res.id)
+            .collect();
+        assert_eq!(paged_ids, ids);
+        assert!(first_page.objects.iter().all(|res| res.data.is_empty()));
+    }
+
+    #[test]
+    fn test_get_resources_no_data() {
+        let conn = init_db_connection();
+
+        let res = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("text/plain"),
+            data: vec![0, 1, 2, 3],
+        };
+        insert_resource(&conn, &res).unwrap();
+
+        let resources = get_resources(&conn).unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].id, res.id);
+        assert_eq!(resources[0].name, "res");
+        assert!(resources[0].data.is_empty());
+    }
+
+    #[test]
+    fn test_get_resource_metas() {
+        let conn = init_db_connection();
+
+        let mut expected = Vec::new();
+        for size in [0, 3, 1024] {
+            let res = Resource {
+                id: Uuid::new_v4(),
+                name: format!("res{}", size),
+                ty: String::from("application/octet-stream"),
+                data: vec![0; size],
+            };
+            insert_resource(&conn, &res).unwrap();
+            let content_hash = res.content_hash();
+            expected.push(ResourceMeta {
+                id: res.id,
+                name: res.name,
+                ty: res.ty,
+                size: size as u64,
+                content_hash,
+            });
+        }
+        expected.sort_by_key(|meta| meta.id);
+
+        assert_eq!(get_resource_metas(&conn).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_read_resource_chunk() {
+        let conn = init_db_connection();
+
+        let res = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("application/octet-stream"),
+            data: (0..100).collect(),
+        };
+        insert_resource(&conn, &res).unwrap();
+
+        let chunk = read_resource_chunk(&conn, &res.id, 10, 5).unwrap().unwrap();
+        assert_eq!(chunk, vec![10, 11, 12, 13, 14]);
+
+        let chunk = read_resource_chunk(&conn, &res.id, 95, 10)
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk, vec![95, 96, 97, 98, 99]);
+
+        let chunk = read_resource_chunk(&conn, &res.id, 200, 10)
+            .unwrap()
+            .unwrap();
+        assert!(chunk.is_empty());
+
+        let missing = read_resource_chunk(&conn, &Uuid::new_v4(), 0, 10).unwrap();
+        assert!(missing.is_none());
+
+        let meta = get_resource_meta(&conn, &res.id).unwrap().unwrap();
+        assert_eq!(meta, res.meta());
+    }
+
+    #[test]
+    fn test_migrate_rowid_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db_schema(&conn).unwrap();
+
+        let res = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("text/plain"),
+            data: vec![0, 1, 2, 3],
+        };
+        insert_resource_without_hash(&conn, &res);
+
+        migrate_rowid_table(&conn).unwrap();
+        migrate_content_hash(&conn).unwrap();
+
+        let selected = get_resource(&conn, &res.id).unwrap().unwrap();
+        assert_eq!(selected.data, res.data);
+        let chunk = read_resource_chunk(&conn, &res.id, 1, 2).unwrap().unwrap();
+        assert_eq!(chunk, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_migrate_content_hash() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db_schema(&conn).unwrap();
+        migrate_rowid_table(&conn).unwrap();
+
+        let res = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("text/plain"),
+            data: vec![0, 1, 2, 3],
+        };
+        insert_resource_without_hash(&conn, &res);
+
+        migrate_content_hash(&conn).unwrap();
+
+        let found = find_resource_by_hash(&conn, &res.content_hash()).unwrap();
+        assert_eq!(found, Some(res.meta()));
+    }
+
+    #[test]
+    fn test_find_resource_by_hash() {
+        let conn = init_db_connection();
+
+        let mut res = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("image/png"),
+            data: vec![0, 1, 2, 3],
+        };
+        insert_resource(&conn, &res).unwrap();
+
+        let duplicate = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("duplicate"),
+            ty: String::from("image/png"),
+            data: vec![0, 1, 2, 3],
+        };
+        assert_eq!(duplicate.content_hash(), res.content_hash());
+        let found = find_resource_by_hash(&conn, &duplicate.content_hash()).unwrap();
+        assert_eq!(found, Some(res.meta()));
+
+        let old_hash = res.content_hash();
+        res.data = vec![4, 5];
+        update_resource(&conn, &res).unwrap();
+        assert!(find_resource_by_hash(&conn, &old_hash).unwrap().is_none());
+        let found = find_resource_by_hash(&conn, &res.content_hash()).unwrap();
+        assert_eq!(found, Some(res.meta()));
+    }
+
+    #[test]
+    fn test_insert_resource_if_absent() {
+        let conn = init_db_connection();
+
+        let res = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("text/plain"),
+            data: vec![0, 1, 2, 3],
+        };
+        assert!(insert_resource_if_absent(&conn, &res).unwrap());
+        assert!(!insert_resource_if_absent(&conn, &res).unwrap());
+        assert_eq!(get_resources(&conn).unwrap().len(), 1);
+    }
+
+    /// Insert the given resource into a resources table that predates the content hash column.
+    fn insert_resource_without_hash(conn: &Connection, res: &Resource) {
+        conn.execute(
+            "INSERT INTO resources (id, name, ty, data) VALUES (?, ?, ?, ?);",
+            (
+                format!("{}", res.id.as_hyphenated()),
+                &res.name,
+                &res.ty,
+                &res.data,
+            ),
+        )
+        .unwrap();
     }
 }
@@ -5,11 +5,11 @@ use std::fmt::{Display, Formatter};
 use crate::models::{Commit, CommitPayload, Delta};
 use crate::storage::Storage;
 
-/// Synchronize data in `storage_from` to `storage_to`.
+/// Synchronize data in `storage_from` to `storage_to`. Returns the delta applied to `storage_to`.
 pub async fn synchronize_storage<SF, ST>(
     storage_from: &SF,
     storage_to: &ST,
-) -> Result<(), SynchronizeStorageError<SF::Error, ST::Error>>
+) -> Result<Delta, SynchronizeStorageError<SF::Error, ST::Error>>
 where
     SF: ?Sized + Storage,
     ST: ?Sized + Storage,
@@ -20,7 +20,7 @@ where
         .await
         .map_err(SynchronizeStorageError::ToStorage)?;
 
-    Ok(())
+    Ok(delta)
 }
 
 /// Compute the delta required to synchronize data from `storage_from` to `storage_to`.
@@ -54,12 +54,17 @@ where
             return Err(SynchronizeStorageError::DiverseHistory);
         }
     } else if let Some(to_latest_commit) = to_latest_commit.as_ref() {
-        if to_latest_commit.id != from_commits[0].id {
-            return Err(SynchronizeStorageError::DiverseHistory);
-        } else {
-            // The first commit within `from_commits` is actually present in the destication storage.
-            from_commits.remove(0);
-        }
+        // Commits created within the same second share the same timestamp, so the latest commit of the destination
+        // storage is not necessarily the first one within `from_commits`.
+        let synced_count = match from_commits
+            .iter()
+            .position(|commit| commit.id == to_latest_commit.id)
+        {
+            Some(idx) => idx + 1,
+            None => return Err(SynchronizeStorageError::DiverseHistory),
+        };
+        // The commits up to the latest commit of the destination storage are already present there.
+        from_commits.drain(..synced_count);
     }
 
     collect_delta(storage_from, from_commits)
@@ -118,6 +123,14 @@ where
             CommitPayload::CreateResource(payload) => {
                 added_resource_ids.insert(payload.id);
             }
+            CommitPayload::UpdateResource(payload) => {
+                // An update to a resource that is not added within the commits replaces the existing resource in
+                // the destination storage.
+                if !added_resource_ids.contains(&payload.id) {
+                    deleted_resource_ids.insert(payload.id);
+                    added_resource_ids.insert(payload.id);
+                }
+            }
             CommitPayload::DeleteResource(payload) => {
                 if !added_resource_ids.remove(&payload.id) {
                     deleted_resource_ids.insert(payload.id);
@@ -146,3 +159,121 @@ where
     delta.commits = commits;
     Ok(delta)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use uuid::Uuid;
+
+    use crate::models::Resource;
+    use crate::storage::sqlite::SqliteStorage;
+
+    fn create_test_resource() -> Resource {
+        Resource {
+            id: Uuid::new_v4(),
+            name: String::from("res"),
+            ty: String::from("text/plain"),
+            data: vec![1, 2, 3],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_delta_resources() {
+        let from = SqliteStorage::new_memory().unwrap();
+
+        let created = create_test_resource();
+        let deleted = create_test_resource();
+        from.insert_resource(&created).await.unwrap();
+        from.insert_resource(&deleted).await.unwrap();
+
+        // The deleted resource is synchronized to the destination storage before being deleted.
+        let commits = from.get_commits_since(0).await.unwrap();
+        let synced_commits = commits
+            .iter()
+            .filter(|commit| matches!(&commit.payload, CommitPayload::CreateResource(payload) if payload.id == deleted.id))
+            .cloned()
+            .collect();
+        let delta = collect_delta(&from, synced_commits).await.unwrap();
+        assert_eq!(delta.added_resources.len(), 1);
+        assert_eq!(delta.added_resources[0].id, deleted.id);
+
+        assert!(from.delete_resource(&deleted.id).await.unwrap());
+
+        let commits = from
+            .get_commits_since(0)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|commit| match &commit.payload {
+                CommitPayload::CreateResource(payload) => payload.id == created.id,
+                CommitPayload::DeleteResource(payload) => payload.id == deleted.id,
+                _ => false,
+            })
+            .collect();
+        let delta = collect_delta(&from, commits).await.unwrap();
+        assert_eq!(delta.added_resources.len(), 1);
+        assert_eq!(delta.added_resources[0].id, created.id);
+        assert_eq!(delta.added_resources[0].data, created.data);
+        assert_eq!(delta.deleted_resource_ids, vec![deleted.id]);
+        assert!(delta.added_posts.is_empty());
+        assert!(delta.deleted_post_slugs.is_empty());
+        assert_eq!(delta.commits.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_collect_delta_update_resource() {
+        let from = SqliteStorage::new_memory().unwrap();
+        let to = SqliteStorage::new_memory().unwrap();
+
+        let mut res = create_test_resource();
+        from.insert_resource(&res).await.unwrap();
+
+        let commits = from.get_commits_since(0).await.unwrap();
+        let delta = collect_delta(&from, commits).await.unwrap();
+        to.apply_delta(&delta).await.unwrap();
+
+        res.data = vec![4, 5];
+        from.update_resource(&res).await.unwrap();
+
+        let commits = from
+            .get_commits_since(0)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|commit| matches!(commit.payload, CommitPayload::UpdateResource(_)))
+            .collect();
+        let delta = collect_delta(&from, commits).await.unwrap();
+        assert_eq!(delta.deleted_resource_ids, vec![res.id]);
+        assert_eq!(delta.added_resources.len(), 1);
+        assert_eq!(delta.added_resources[0].data, vec![4, 5]);
+
+        to.apply_delta(&delta).await.unwrap();
+        let synced = to.get_resource(&res.id).await.unwrap().unwrap();
+        assert_eq!(synced.data, vec![4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_get_delta_same_timestamp() {
+        let from = SqliteStorage::new_memory().unwrap();
+        let to = SqliteStorage::new_memory().unwrap();
+
+        // Both commits are created within the same second and thus share the same timestamp.
+        from.insert_resource(&create_test_resource()).await.unwrap();
+        from.insert_resource(&create_test_resource()).await.unwrap();
+
+        let delta = synchronize_storage(&from, &to).await.unwrap();
+        assert_eq!(delta.added_resources.len(), 2);
+        assert_eq!(delta.commits.len(), 2);
+
+        let delta = synchronize_storage(&from, &to).await.unwrap();
+        assert!(delta.added_resources.is_empty());
+        assert!(delta.commits.is_empty());
+
+        to.insert_resource(&create_test_resource()).await.unwrap();
+        assert!(matches!(
+            get_delta(&from, &to).await,
+            Err(SynchronizeStorageError::DiverseHistory)
+        ));
+    }
+}
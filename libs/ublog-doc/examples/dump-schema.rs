@@ -0,0 +1,10 @@
+//! Print the JSON schema of document trees to the standard output.
+//!
+//! Front-ends can generate their types of document trees from the schema, e.g. with `json-schema-to-typescript`.
+
+use ublog_doc::DocumentNode;
+
+fn main() {
+    let schema = schemars::schema_for!(DocumentNode);
+    println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+}
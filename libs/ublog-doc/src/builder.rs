@@ -0,0 +1,201 @@
+//! This module provides a builder for constructing whole document trees.
+//!
+//! # Example
+//!
+//! ```
+//! use ublog_doc::builder::DocumentTreeBuilder;
+//!
+//! let mut builder = DocumentTreeBuilder::new();
+//! builder
+//!     .heading(1, |b| {
+//!         b.text("Hello, world");
+//!     })
+//!     .paragraph(|b| {
+//!         b.text("The following program prints ").inline_code("Hello, world").text(".");
+//!     })
+//!     .code("rust", r#"fn main() { println!("Hello, world"); }"#);
+//! let root = builder.finish();
+//!
+//! assert_eq!(root.children.len(), 3);
+//! ```
+
+use crate::{DocumentNode, DocumentNodeTag};
+
+/// A builder that builds a document tree node by node.
+///
+/// The builder maintains a stack of the container nodes that are currently being built. Nodes are always appended as
+/// children of the innermost container node, which initially is the root node of the document tree.
+#[derive(Debug)]
+pub struct DocumentTreeBuilder {
+    stack: Vec<DocumentNode>,
+}
+
+impl DocumentTreeBuilder {
+    /// Create a new `DocumentTreeBuilder` that starts from an empty document tree.
+    pub fn new() -> Self {
+        Self {
+            stack: vec![DocumentNode::new_empty()],
+        }
+    }
+
+    /// Append the given node as a child of the innermost container node.
+    pub fn node(&mut self, node: DocumentNode) -> &mut Self {
+        self.current().children.push(node);
+        self
+    }
+
+    /// Append a container node with the given tag, whose children are built by the given function.
+    pub fn container<F>(&mut self, tag: DocumentNodeTag, build_children: F) -> &mut Self
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.stack.push(DocumentNode::new(tag));
+        build_children(self);
+
+        let node = self.stack.pop().unwrap();
+        self.node(node)
+    }
+
+    /// Append a paragraph node, whose children are built by the given function.
+    pub fn paragraph<F>(&mut self, build_children: F) -> &mut Self
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.container(DocumentNodeTag::Paragraph, build_children)
+    }
+
+    /// Append a heading node of the given level, whose children are built by the given function.
+    ///
+    /// The heading does not have an explicit ID, so its ID is generated from its text.
+    pub fn heading<F>(&mut self, level: i32, build_children: F) -> &mut Self
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.container(DocumentNodeTag::Heading { level, id: None }, build_children)
+    }
+
+    /// Append a quote node, whose children are built by the given function.
+    pub fn quote<F>(&mut self, build_children: F) -> &mut Self
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.container(DocumentNodeTag::Quote, build_children)
+    }
+
+    /// Append a list node, whose children are built by the given function.
+    ///
+    /// Use [`item`](Self::item) to add list items to the list.
+    pub fn list<F>(&mut self, is_ordered: bool, build_children: F) -> &mut Self
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.container(DocumentNodeTag::List { is_ordered }, build_children)
+    }
+
+    /// Append a list item node, whose children are built by the given function.
+    pub fn item<F>(&mut self, build_children: F) -> &mut Self
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.container(DocumentNodeTag::ListItem, build_children)
+    }
+
+    /// Append a code block node without caption.
+    pub fn code<L, C>(&mut self, language: L, code: C) -> &mut Self
+    where
+        L: Into<String>,
+        C: Into<String>,
+    {
+        self.node(DocumentNode::new(DocumentNodeTag::Code {
+            language: language.into(),
+            caption: None,
+            code: code.into(),
+        }))
+    }
+
+    /// Append a divider node.
+    pub fn divider(&mut self) -> &mut Self {
+        self.node(DocumentNode::new(DocumentNodeTag::Divider))
+    }
+
+    /// Append an inline text node.
+    pub fn text<T>(&mut self, text: T) -> &mut Self
+    where
+        T: Into<String>,
+    {
+        self.node(DocumentNode::new(DocumentNodeTag::InlineText {
+            text: text.into(),
+        }))
+    }
+
+    /// Append an inline code node.
+    pub fn inline_code<T>(&mut self, code: T) -> &mut Self
+    where
+        T: Into<String>,
+    {
+        self.node(DocumentNode::new(DocumentNodeTag::InlineCode {
+            code: code.into(),
+        }))
+    }
+
+    /// Finish building and get the root node of the built document tree.
+    pub fn finish(mut self) -> DocumentNode {
+        assert_eq!(self.stack.len(), 1);
+        self.stack.pop().unwrap()
+    }
+
+    fn current(&mut self) -> &mut DocumentNode {
+        self.stack.last_mut().unwrap()
+    }
+}
+
+impl Default for DocumentTreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_nested() {
+        let mut builder = DocumentTreeBuilder::new();
+        builder
+            .list(false, |b| {
+                b.item(|b| {
+                    b.text("first");
+                })
+                .item(|b| {
+                    b.quote(|b| {
+                        b.paragraph(|b| {
+                            b.text("second");
+                        });
+                    });
+                });
+            })
+            .divider();
+        let root = builder.finish();
+
+        assert!(root.validate().is_ok());
+        assert_eq!(root.children.len(), 2);
+        assert!(matches!(
+            root.children[0].tag,
+            DocumentNodeTag::List { is_ordered: false }
+        ));
+        assert!(matches!(root.children[1].tag, DocumentNodeTag::Divider));
+
+        let items = &root.children[0].children;
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items[0].children,
+            [DocumentNode::new(DocumentNodeTag::InlineText {
+                text: String::from("first")
+            })]
+        );
+        let quote = &items[1].children[0];
+        assert!(matches!(quote.tag, DocumentNodeTag::Quote));
+        assert!(matches!(quote.children[0].tag, DocumentNodeTag::Paragraph));
+    }
+}
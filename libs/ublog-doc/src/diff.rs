@@ -0,0 +1,135 @@
+use crate::DocumentNode;
+
+impl DocumentNode {
+    /// Compare the document tree rooted at this node against the document tree rooted at `other`.
+    ///
+    /// The two trees are compared positionally: the `i`-th child of a node is compared against the `i`-th child of the
+    /// corresponding node in the other tree. If two corresponding nodes have different tags, the whole subtree is
+    /// reported as modified and its children are not compared any further. An empty list is returned if the two trees
+    /// are equal.
+    pub fn diff(&self, other: &DocumentNode) -> Vec<DocumentChange> {
+        let mut changes = Vec::new();
+
+        let mut stack = vec![(self, other, Vec::new())];
+        while let Some((old, new, path)) = stack.pop() {
+            if old.tag != new.tag {
+                changes.push(DocumentChange::Modified { path });
+                continue;
+            }
+
+            let common_len = old.children.len().min(new.children.len());
+            for idx in common_len..old.children.len() {
+                changes.push(DocumentChange::Removed {
+                    path: child_path(&path, idx),
+                });
+            }
+            for idx in common_len..new.children.len() {
+                changes.push(DocumentChange::Added {
+                    path: child_path(&path, idx),
+                });
+            }
+
+            for idx in (0..common_len).rev() {
+                stack.push((
+                    &old.children[idx],
+                    &new.children[idx],
+                    child_path(&path, idx),
+                ));
+            }
+        }
+
+        changes
+    }
+}
+
+fn child_path(path: &[usize], idx: usize) -> Vec<usize> {
+    let mut child_path = Vec::with_capacity(path.len() + 1);
+    child_path.extend_from_slice(path);
+    child_path.push(idx);
+    child_path
+}
+
+/// A change between two document trees.
+///
+/// Each change carries a path, which is a list of child indices that leads from the root node to the changed subtree.
+/// An empty path refers to the root node itself.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DocumentChange {
+    /// A subtree is present in the new tree but not in the old tree.
+    Added { path: Vec<usize> },
+
+    /// A subtree is present in the old tree but not in the new tree.
+    Removed { path: Vec<usize> },
+
+    /// The root of a subtree has a different tag in the two trees.
+    Modified { path: Vec<usize> },
+}
+
+impl DocumentChange {
+    /// Get the path to the changed subtree.
+    pub fn path(&self) -> &[usize] {
+        match self {
+            Self::Added { path } | Self::Removed { path } | Self::Modified { path } => path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocumentNodeTag;
+
+    fn create_paragraph(texts: &[&str]) -> DocumentNode {
+        let mut para = DocumentNode::new(DocumentNodeTag::Paragraph);
+        para.children = texts
+            .iter()
+            .map(|text| {
+                DocumentNode::new(DocumentNodeTag::InlineText {
+                    text: String::from(*text),
+                })
+            })
+            .collect();
+        para
+    }
+
+    fn create_root(children: Vec<DocumentNode>) -> DocumentNode {
+        let mut root = DocumentNode::new_empty();
+        root.children = children;
+        root
+    }
+
+    #[test]
+    fn test_diff_equal() {
+        let old = create_root(vec![create_paragraph(&["a", "b"])]);
+        let new = old.clone();
+
+        assert_eq!(old, new);
+        assert!(old.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn test_diff_modified() {
+        let old = create_root(vec![create_paragraph(&["a", "b"])]);
+        let new = create_root(vec![create_paragraph(&["a", "c"])]);
+
+        assert_ne!(old, new);
+        assert_eq!(
+            old.diff(&new),
+            vec![DocumentChange::Modified { path: vec![0, 1] }]
+        );
+    }
+
+    #[test]
+    fn test_diff_added_and_removed() {
+        let old = create_root(vec![create_paragraph(&["a"]), create_paragraph(&["b"])]);
+        let new = create_root(vec![create_paragraph(&["a", "c"])]);
+
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                DocumentChange::Removed { path: vec![1] },
+                DocumentChange::Added { path: vec![0, 1] },
+            ]
+        );
+    }
+}
@@ -1,9 +1,28 @@
 //! This crate defines the document tree.
+//!
+//! The renderers in the `render` module are only available with the `render` feature, which is enabled by default.
+//! The document tree types are always available. With the `json-schema` feature, the document tree types implement
+//! `schemars::JsonSchema`; run `cargo run -p ublog-doc --example dump-schema --features json-schema` to dump the JSON
+//! schema of document trees.
+
+pub mod builder;
+pub mod diff;
+#[cfg(feature = "markdown")]
+pub mod parse;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod validate;
 
 use serde::{Deserialize, Serialize};
 
+/// The default maximum depth of document trees.
+///
+/// The root node of a document tree is at depth 1.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
 /// A node on the document tree.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 pub struct DocumentNode {
     /// Tag of the node.
     ///
@@ -28,14 +47,141 @@ impl DocumentNode {
         Self::new(DocumentNodeTag::Root)
     }
 
+    /// Create a preview of the document tree rooted at this node.
+    ///
+    /// The preview is a new root node containing copies of the first `max_blocks` block children of this node. It can
+    /// be rendered like any other document tree to produce a truncated but styled excerpt of the document.
+    pub fn preview_subtree(&self, max_blocks: usize) -> DocumentNode {
+        let mut preview = Self::new_empty();
+        preview.children = self
+            .children
+            .iter()
+            .filter(|child| !child.tag.is_inline())
+            .take(max_blocks)
+            .cloned()
+            .collect();
+        preview
+    }
+
+    /// Flatten the document tree rooted at this node into plain text.
+    ///
+    /// Texts of inline nodes are concatenated, while texts of different block nodes are separated by line breaks.
+    /// Code, equations and captions are included, but styles and links are dropped.
+    pub fn to_plain_text(&self) -> String {
+        let mut text = String::new();
+        for node in self.iter() {
+            if !node.tag.is_inline() && !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+
+            match &node.tag {
+                DocumentNodeTag::InlineText { text: node_text } => text.push_str(node_text),
+                DocumentNodeTag::InlineCode { code } => text.push_str(code),
+                DocumentNodeTag::InlineEquation { expr } => text.push_str(expr),
+                DocumentNodeTag::Code { caption, code, .. } => {
+                    text.push_str(code);
+                    push_caption(caption, &mut text);
+                }
+                DocumentNodeTag::Equation { expr, caption } => {
+                    text.push_str(expr);
+                    push_caption(caption, &mut text);
+                }
+                DocumentNodeTag::Image { caption, .. } | DocumentNodeTag::Table { caption } => {
+                    push_caption(caption, &mut text);
+                }
+                DocumentNodeTag::Toggle { summary } => text.push_str(summary),
+                _ => {}
+            }
+        }
+        text
+    }
+
+    /// Create a plain-text excerpt of the document tree rooted at this node.
+    ///
+    /// The excerpt is the plain text of the first paragraph in the tree. If the text is longer than `max_chars`
+    /// characters, it is truncated to at most `max_chars` characters on a word boundary and followed by an ellipsis.
+    /// A word that alone is longer than `max_chars` characters is cut in the middle. Returns an empty string if the
+    /// tree contains no paragraphs.
+    pub fn excerpt(&self, max_chars: usize) -> String {
+        let paragraph = match self
+            .iter()
+            .find(|node| matches!(node.tag, DocumentNodeTag::Paragraph))
+        {
+            Some(paragraph) => paragraph,
+            None => return String::new(),
+        };
+
+        let text = paragraph.to_plain_text();
+        let text = text.trim();
+        let cut = match text.char_indices().nth(max_chars) {
+            Some((cut, _)) => cut,
+            None => return String::from(text),
+        };
+
+        // Cut before the word that straddles the limit, unless the limit falls on a word boundary already.
+        let (head, tail) = text.split_at(cut);
+        let head = if tail.starts_with(char::is_whitespace) {
+            head
+        } else {
+            match head.rfind(char::is_whitespace) {
+                Some(boundary) => &head[..boundary],
+                None => head,
+            }
+        };
+
+        let mut excerpt = String::from(head.trim_end());
+        excerpt.push('…');
+        excerpt
+    }
+
+    /// Count the words in the document tree rooted at this node.
+    ///
+    /// Words are counted over the plain text given by [`to_plain_text`](Self::to_plain_text). Runs of characters
+    /// separated by whitespaces count as one word each, except that every CJK character counts as a word on its own
+    /// since CJK text does not separate words with whitespaces.
+    pub fn word_count(&self) -> usize {
+        let mut count = 0;
+        let mut in_word = false;
+        for ch in self.to_plain_text().chars() {
+            if is_cjk(ch) {
+                count += 1;
+                in_word = false;
+            } else if ch.is_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                count += 1;
+                in_word = true;
+            }
+        }
+        count
+    }
+
+    /// Get the anchor ID of this node if it is a heading node.
+    ///
+    /// The explicit ID of the heading is returned if present. Otherwise, an ID is generated by slugifying the plain
+    /// text of the heading. Returns `None` if this node is not a heading node, or if the ID is empty, e.g. when the
+    /// text of the heading consists of punctuations only.
+    pub fn heading_id(&self) -> Option<String> {
+        let id = match &self.tag {
+            DocumentNodeTag::Heading { id: Some(id), .. } => id.clone(),
+            DocumentNodeTag::Heading { id: None, .. } => slugify(&self.to_plain_text()),
+            _ => return None,
+        };
+        Some(id).filter(|id| !id.is_empty())
+    }
+
+    /// Get an iterator that iterates over all nodes in the document tree rooted at this document node in pre-order.
+    pub fn iter(&self) -> DocumentNodeIter<'_> {
+        DocumentNodeIter { stack: vec![self] }
+    }
+
     /// Visit all nodes in the document tree rooted at this document node.
     pub fn visit<V>(&self, visitor: &mut V)
     where
         V: ?Sized + DocumentNodeVisitor,
     {
-        visitor.visit(self);
-        for child in &self.children {
-            child.visit(visitor);
+        for node in self.iter() {
+            visitor.visit(node);
         }
     }
 
@@ -44,15 +190,224 @@ impl DocumentNode {
     where
         V: ?Sized + DocumentNodeVisitor,
     {
-        visitor.visit_mut(self);
-        for child in &mut self.children {
-            child.visit_mut(visitor);
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            visitor.visit_mut(node);
+            stack.extend(node.children.iter_mut().rev());
+        }
+    }
+
+    /// Merge adjacent inline nodes within the document tree rooted at this document node, so that the tree renders the
+    /// same with fewer nodes.
+    ///
+    /// Adjacent [`InlineText`](DocumentNodeTag::InlineText) siblings are merged into a single node, and so are adjacent
+    /// [`Inline`](DocumentNodeTag::Inline) siblings with the same style and link, whose children are then merged in
+    /// turn. Inline nodes with different styles or links are never merged.
+    ///
+    /// The tree is traversed without recursion, so it is safe to call on arbitrarily deep trees.
+    pub fn coalesce_inline(&mut self) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            node.coalesce_inline_children();
+            stack.extend(node.children.iter_mut());
+        }
+    }
+
+    /// Merge adjacent inline children of this node, without descending into the children.
+    fn coalesce_inline_children(&mut self) {
+        let children = std::mem::take(&mut self.children);
+        let mut coalesced: Vec<DocumentNode> = Vec::with_capacity(children.len());
+        for mut child in children {
+            if let Some(last) = coalesced.last_mut() {
+                if let (
+                    DocumentNodeTag::InlineText { text },
+                    DocumentNodeTag::InlineText { text: next_text },
+                ) = (&mut last.tag, &child.tag)
+                {
+                    text.push_str(next_text);
+                    continue;
+                }
+
+                if matches!(last.tag, DocumentNodeTag::Inline { .. }) && last.tag == child.tag {
+                    last.children.append(&mut child.children);
+                    continue;
+                }
+            }
+            coalesced.push(child);
+        }
+
+        self.children = coalesced;
+    }
+
+    /// Remove empty paragraphs and inline nodes from the document tree rooted at this document node.
+    ///
+    /// A paragraph is empty if its subtree contains nothing but whitespaces, i.e. no other text and no content such as
+    /// inline code, equations or images. An inline node is empty if its subtree contains no text at all; inline nodes
+    /// that contain only whitespaces are kept since they separate the words around them. Nodes of other kinds, e.g.
+    /// dividers and table cells, are never removed even if they are empty.
+    ///
+    /// The tree is traversed without recursion, so it is safe to call on arbitrarily deep trees.
+    pub fn prune_empty(&mut self) {
+        // Nodes are pruned in post-order. A node is put back into its parent once all of its children are pruned, and
+        // the content of the kept children is tracked along the way so that no subtree is scanned twice.
+        let mut stack = vec![PruneFrame::new(None, std::mem::take(&mut self.children))];
+        while let Some(frame) = stack.last_mut() {
+            if let Some(mut child) = frame.remaining.next() {
+                let grandchildren = std::mem::take(&mut child.children);
+                stack.push(PruneFrame::new(Some(child), grandchildren));
+                continue;
+            }
+
+            let PruneFrame {
+                node,
+                kept,
+                content,
+                ..
+            } = stack.pop().unwrap();
+            let (mut node, parent) = match (node, stack.last_mut()) {
+                (Some(node), Some(parent)) => (node, parent),
+                _ => {
+                    self.children = kept;
+                    break;
+                }
+            };
+            node.children = kept;
+
+            let (is_empty, content) = match &node.tag {
+                DocumentNodeTag::Paragraph => (!content.non_blank, content),
+                DocumentNodeTag::Inline { .. } => (!content.non_empty, content),
+                DocumentNodeTag::InlineText { text } => (
+                    false,
+                    PruneContent {
+                        non_blank: !text.trim().is_empty(),
+                        non_empty: !text.is_empty(),
+                    },
+                ),
+                _ => (
+                    false,
+                    PruneContent {
+                        non_blank: true,
+                        non_empty: true,
+                    },
+                ),
+            };
+            if !is_empty {
+                parent.kept.push(node);
+                parent.content.non_blank |= content.non_blank;
+                parent.content.non_empty |= content.non_empty;
+            }
         }
     }
 }
 
+/// A node whose children are being pruned by [`DocumentNode::prune_empty`].
+struct PruneFrame {
+    /// The node, whose children are taken out. This is `None` for the node that `prune_empty` is called on, which is
+    /// never removed.
+    node: Option<DocumentNode>,
+
+    /// The children that are not pruned yet.
+    remaining: std::vec::IntoIter<DocumentNode>,
+
+    /// The pruned children that are kept.
+    kept: Vec<DocumentNode>,
+
+    /// The content of the subtrees of the kept children.
+    content: PruneContent,
+}
+
+impl PruneFrame {
+    fn new(node: Option<DocumentNode>, children: Vec<DocumentNode>) -> Self {
+        Self {
+            node,
+            remaining: children.into_iter(),
+            kept: Vec::new(),
+            content: PruneContent::default(),
+        }
+    }
+}
+
+/// The content of a subtree, as far as [`DocumentNode::prune_empty`] is concerned.
+#[derive(Clone, Copy, Debug, Default)]
+struct PruneContent {
+    /// Whether the subtree contains any text other than whitespaces, or any content other than texts.
+    non_blank: bool,
+
+    /// Whether the subtree contains any text, or any content other than texts.
+    non_empty: bool,
+}
+
+/// Convert the given text into a slug that is suitable for URL fragments and HTML IDs.
+///
+/// Alphanumeric characters are lowercased and kept, and every run of other characters is replaced with a single `-`.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    if slug.ends_with('-') {
+        slug.pop();
+    }
+    slug
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{3040}'..='\u{30ff}' // Hiragana and Katakana
+            | '\u{3400}'..='\u{4dbf}' // CJK Unified Ideographs Extension A
+            | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+            | '\u{ac00}'..='\u{d7af}' // Hangul Syllables
+            | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+            | '\u{20000}'..='\u{2fa1f}' // Supplementary ideographs
+    )
+}
+
+fn push_caption(caption: &Option<String>, text: &mut String) {
+    if let Some(caption) = caption {
+        if !caption.is_empty() {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(caption);
+        }
+    }
+}
+
+impl Drop for DocumentNode {
+    fn drop(&mut self) {
+        // Drop the subtrees iteratively so that dropping an excessively deep tree does not overflow the stack.
+        let mut pending = std::mem::take(&mut self.children);
+        while let Some(mut node) = pending.pop() {
+            pending.append(&mut node.children);
+        }
+    }
+}
+
+/// A pre-order iterator over the nodes in a document tree.
+#[derive(Clone, Debug)]
+pub struct DocumentNodeIter<'a> {
+    stack: Vec<&'a DocumentNode>,
+}
+
+impl<'a> Iterator for DocumentNodeIter<'a> {
+    type Item = &'a DocumentNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
+
 /// A document tree node's tag.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum DocumentNodeTag {
     #[serde(rename = "root")]
@@ -62,7 +417,15 @@ pub enum DocumentNodeTag {
     Paragraph,
 
     #[serde(rename = "heading")]
-    Heading { level: i32 },
+    Heading {
+        level: i32,
+
+        /// Anchor ID of the heading.
+        ///
+        /// If absent, an ID is generated from the heading's text. See [`DocumentNode::heading_id`].
+        #[serde(default)]
+        id: Option<String>,
+    },
 
     #[serde(rename = "callout")]
     Callout { emoji: Option<String> },
@@ -76,6 +439,9 @@ pub enum DocumentNodeTag {
     #[serde(rename = "listItem")]
     ListItem,
 
+    #[serde(rename = "todoItem")]
+    TodoItem { checked: bool },
+
     #[serde(rename = "code")]
     Code {
         language: String,
@@ -107,6 +473,21 @@ pub enum DocumentNodeTag {
     #[serde(rename = "divider")]
     Divider,
 
+    /// A collapsible section whose children are hidden until the reader expands it.
+    #[serde(rename = "toggle")]
+    Toggle {
+        /// Text that is always shown for the section.
+        summary: String,
+    },
+
+    /// A layout that places its children, which are [`DocumentNodeTag::Column`] nodes, side by side.
+    #[serde(rename = "columns")]
+    Columns,
+
+    /// A single column within a [`DocumentNodeTag::Columns`] layout.
+    #[serde(rename = "column")]
+    Column,
+
     #[serde(rename = "inline")]
     Inline {
         style: Option<InlineStyle>,
@@ -123,7 +504,21 @@ pub enum DocumentNodeTag {
     InlineEquation { expr: String },
 }
 
+impl DocumentNodeTag {
+    /// Determine whether this tag represents an inline node.
+    pub fn is_inline(&self) -> bool {
+        matches!(
+            self,
+            Self::Inline { .. }
+                | Self::InlineText { .. }
+                | Self::InlineCode { .. }
+                | Self::InlineEquation { .. }
+        )
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum DocumentResourceLink {
     #[serde(rename = "external")]
@@ -135,6 +530,7 @@ pub enum DocumentResourceLink {
 
 /// Style settings of an inlined document tree element.
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct InlineStyle {
     pub bold: bool,
@@ -156,3 +552,344 @@ pub trait DocumentNodeVisitor {
     fn visit(&mut self, _node: &DocumentNode) {}
     fn visit_mut(&mut self, _node: &mut DocumentNode) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_plain_text() {
+        let mut builder = crate::builder::DocumentTreeBuilder::new();
+        builder
+            .heading(1, |b| {
+                b.text("Title");
+            })
+            .paragraph(|b| {
+                b.text("Call ").inline_code("main").text(" first.");
+            })
+            .code("rust", "fn main() {}");
+
+        assert_eq!(
+            builder.finish().to_plain_text(),
+            "Title\nCall main first.\nfn main() {}"
+        );
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let mut builder = crate::builder::DocumentTreeBuilder::new();
+        builder
+            .heading(1, |b| {
+                b.text("Title");
+            })
+            .paragraph(|b| {
+                b.text("The quick brown ")
+                    .inline_code("fox")
+                    .text(" jumps.");
+            })
+            .paragraph(|b| {
+                b.text("Second paragraph.");
+            });
+        let doc = builder.finish();
+
+        assert_eq!(doc.excerpt(100), "The quick brown fox jumps.");
+        // The limit falls in the middle of "brown", which is dropped as a whole.
+        assert_eq!(doc.excerpt(12), "The quick…");
+        // The limit falls right after "quick".
+        assert_eq!(doc.excerpt(9), "The quick…");
+        assert_eq!(doc.excerpt(2), "Th…");
+
+        let mut builder = crate::builder::DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.text("你好世界，你好");
+        });
+        assert_eq!(builder.finish().excerpt(4), "你好世界…");
+
+        assert_eq!(DocumentNode::new_empty().excerpt(10), "");
+    }
+
+    #[test]
+    fn test_word_count() {
+        let mut builder = crate::builder::DocumentTreeBuilder::new();
+        builder
+            .heading(1, |b| {
+                b.text("Hello, world!");
+            })
+            .paragraph(|b| {
+                b.text("Call ")
+                    .inline_code("main()")
+                    .text(" first. 你好世界");
+            });
+        assert_eq!(builder.finish().word_count(), 9);
+
+        let mut builder = crate::builder::DocumentTreeBuilder::new();
+        for _ in 0..100 {
+            builder.paragraph(|b| {
+                b.text("the quick brown fox jumps over the lazy dog ");
+            });
+        }
+        builder.code("rust", "fn main() {\n    println!(\"hi\");\n}");
+        assert_eq!(builder.finish().word_count(), 900 + 5);
+
+        assert_eq!(DocumentNode::new_empty().word_count(), 0);
+    }
+
+    #[test]
+    fn test_heading_id() {
+        let mut builder = crate::builder::DocumentTreeBuilder::new();
+        builder.heading(2, |b| {
+            b.text("Hello, World! ").inline_code("main()");
+        });
+        let generated = builder.finish();
+        assert_eq!(
+            generated.children[0].heading_id().as_deref(),
+            Some("hello-world-main")
+        );
+
+        let mut explicit = DocumentNode::new(DocumentNodeTag::Heading {
+            level: 2,
+            id: Some(String::from("custom-anchor")),
+        });
+        explicit
+            .children
+            .push(DocumentNode::new(DocumentNodeTag::InlineText {
+                text: String::from("Hello"),
+            }));
+        assert_eq!(explicit.heading_id().as_deref(), Some("custom-anchor"));
+
+        let mut punctuation = crate::builder::DocumentTreeBuilder::new();
+        punctuation.heading(2, |b| {
+            b.text("?!");
+        });
+        assert_eq!(punctuation.finish().children[0].heading_id(), None);
+
+        assert_eq!(DocumentNode::new_empty().heading_id(), None);
+    }
+
+    #[test]
+    fn test_preview_subtree() {
+        let mut root = DocumentNode::new_empty();
+        for idx in 0..5 {
+            let mut para = DocumentNode::new(DocumentNodeTag::Paragraph);
+            para.children
+                .push(DocumentNode::new(DocumentNodeTag::InlineText {
+                    text: format!("paragraph {}", idx),
+                }));
+            root.children.push(para);
+        }
+
+        let preview = root.preview_subtree(2);
+        assert!(matches!(preview.tag, DocumentNodeTag::Root));
+        assert_eq!(preview.children, root.children[..2]);
+
+        #[cfg(feature = "render")]
+        assert_eq!(
+            crate::render::html::render_html(&preview).unwrap(),
+            "<p>paragraph 0</p><p>paragraph 1</p>"
+        );
+    }
+
+    fn create_text(text: &str) -> DocumentNode {
+        DocumentNode::new(DocumentNodeTag::InlineText {
+            text: String::from(text),
+        })
+    }
+
+    fn create_styled(bold: bool, children: Vec<DocumentNode>) -> DocumentNode {
+        let mut node = DocumentNode::new(DocumentNodeTag::Inline {
+            style: Some(InlineStyle {
+                bold,
+                ..InlineStyle::default()
+            }),
+            link: None,
+        });
+        node.children = children;
+        node
+    }
+
+    #[test]
+    fn test_coalesce_inline_texts() {
+        let mut paragraph = DocumentNode::new(DocumentNodeTag::Paragraph);
+        paragraph.children = vec![
+            create_text("Hello, "),
+            create_text("world"),
+            create_text("!"),
+        ];
+
+        paragraph.coalesce_inline();
+        assert_eq!(paragraph.children, [create_text("Hello, world!")]);
+    }
+
+    #[test]
+    fn test_coalesce_inline_styles() {
+        let mut paragraph = DocumentNode::new(DocumentNodeTag::Paragraph);
+        paragraph.children = vec![
+            create_styled(true, vec![create_text("a")]),
+            create_styled(true, vec![create_text("b")]),
+            create_styled(false, vec![create_text("c")]),
+            create_text("d"),
+            DocumentNode::new(DocumentNodeTag::InlineCode {
+                code: String::from("e"),
+            }),
+            create_text("f"),
+        ];
+
+        paragraph.coalesce_inline();
+        assert_eq!(
+            paragraph.children,
+            [
+                create_styled(true, vec![create_text("ab")]),
+                create_styled(false, vec![create_text("c")]),
+                create_text("d"),
+                DocumentNode::new(DocumentNodeTag::InlineCode {
+                    code: String::from("e"),
+                }),
+                create_text("f"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coalesce_inline_pathologically_deep() {
+        let mut node = DocumentNode::new(DocumentNodeTag::Paragraph);
+        node.children = vec![create_text("a"), create_text("b")];
+        for _ in 0..1_000_000 {
+            node = create_styled(true, vec![node]);
+        }
+
+        node.coalesce_inline();
+        let leaves: Vec<_> = node.iter().filter(|n| n.children.is_empty()).collect();
+        assert_eq!(leaves.len(), 1);
+        assert_eq!(*leaves[0], create_text("ab"));
+    }
+
+    #[test]
+    fn test_prune_empty_paragraphs() {
+        let mut builder = crate::builder::DocumentTreeBuilder::new();
+        builder
+            .paragraph(|b| {
+                b.text(" ").node(create_styled(true, vec![create_text("")]));
+            })
+            .paragraph(|b| {
+                b.text("Hello").text(" ").text("world");
+            })
+            .paragraph(|b| {
+                b.inline_code("");
+            })
+            .divider();
+        let mut root = builder.finish();
+
+        root.prune_empty();
+        assert_eq!(root.children.len(), 3);
+        assert_eq!(root.children[0].children.len(), 3);
+        assert!(matches!(
+            root.children[1].children[0].tag,
+            DocumentNodeTag::InlineCode { .. }
+        ));
+        assert_eq!(root.children[2].tag, DocumentNodeTag::Divider);
+    }
+
+    #[test]
+    fn test_prune_empty_pathologically_deep() {
+        let depth = DEFAULT_MAX_DEPTH * 1000;
+        let mut empty = DocumentNode::new(DocumentNodeTag::Paragraph);
+        let mut kept = create_text("kept");
+        for _ in 0..depth {
+            empty = create_styled(true, vec![empty]);
+            kept = create_styled(true, vec![kept]);
+        }
+        let mut root = DocumentNode::new_empty();
+        let mut paragraph = DocumentNode::new(DocumentNodeTag::Paragraph);
+        paragraph.children = vec![empty, kept];
+        root.children.push(paragraph);
+        assert!(root.check_depth(DEFAULT_MAX_DEPTH).is_err());
+
+        root.prune_empty();
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].children.len(), 1);
+        assert_eq!(root.iter().count(), depth + 3);
+        assert_eq!(root.to_plain_text(), "kept");
+    }
+
+    #[test]
+    fn test_prune_empty_keeps_table_cells() {
+        let mut empty_paragraph = DocumentNode::new(DocumentNodeTag::Paragraph);
+        empty_paragraph.children.push(create_text("  "));
+        let mut empty_cell = DocumentNode::new(DocumentNodeTag::TableCell);
+        empty_cell.children.push(empty_paragraph);
+        let mut cell = DocumentNode::new(DocumentNodeTag::TableCell);
+        cell.children.push(create_text("cell"));
+        let mut row = DocumentNode::new(DocumentNodeTag::TableRow);
+        row.children = vec![empty_cell, cell];
+        let mut table = DocumentNode::new(DocumentNodeTag::Table { caption: None });
+        table.children.push(row);
+
+        table.prune_empty();
+        let row = &table.children[0];
+        assert_eq!(row.children.len(), 2);
+        assert!(row.children[0].children.is_empty());
+        assert_eq!(row.children[1].children, [create_text("cell")]);
+    }
+}
+
+/// Checks that the document tree types work without the renderers, which is what consumers that turn off the `render`
+/// feature get. Run with `cargo test -p ublog-doc --no-default-features`, which also turns off the Markdown parser.
+#[cfg(all(test, not(feature = "render")))]
+mod no_render_tests {
+    #[test]
+    fn test_document_tree_without_render() {
+        let mut builder = crate::builder::DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.text("Hello");
+        });
+        let root = builder.finish();
+        assert!(root.validate().is_ok());
+        assert_eq!(root.to_plain_text(), "Hello");
+        assert_eq!(root.excerpt(10), "Hello");
+    }
+}
+
+#[cfg(all(test, feature = "json-schema"))]
+mod json_schema_tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema_contains_all_tags() {
+        let schema = schemars::schema_for!(DocumentNode);
+        let schema = serde_json::to_string(&schema).unwrap();
+
+        let tags = [
+            "root",
+            "paragraph",
+            "heading",
+            "callout",
+            "quote",
+            "list",
+            "listItem",
+            "todoItem",
+            "code",
+            "equation",
+            "image",
+            "table",
+            "tableRow",
+            "tableCell",
+            "divider",
+            "toggle",
+            "columns",
+            "column",
+            "inline",
+            "inlineText",
+            "inlineCode",
+            "inlineEquation",
+            "external",
+            "embedded",
+        ];
+        for tag in tags {
+            assert!(
+                schema.contains(&format!(r#""enum":["{}"]"#, tag)),
+                "tag {} is missing from the schema",
+                tag
+            );
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+
+use crate::validate::DocumentTooDeepError;
+use crate::{DocumentNode, DocumentNodeTag, DocumentResourceLink, InlineStyle};
+
+/// The language of code blocks that do not specify a language.
+const DEFAULT_CODE_LANGUAGE: &str = "plain text";
+
+/// Parse the given Markdown document into a document tree.
+///
+/// CommonMark is supported, together with the table, strikethrough, task list and heading attribute extensions. The
+/// conversion is not lossless: table alignments and the distinction between table headers and table bodies are
+/// dropped, raw HTML is kept as plain text, and images inside links or styled text are replaced by their alt texts.
+pub fn parse_markdown(input: &str) -> Result<DocumentNode, MarkdownParseError> {
+    let mut parser = MarkdownParser::new();
+    for event in Parser::new_ext(input, markdown_options()) {
+        parser.handle_event(event);
+    }
+
+    let root = parser.finish();
+    root.check_depth(crate::DEFAULT_MAX_DEPTH)?;
+    Ok(root)
+}
+
+/// Errors that can occur when parsing Markdown documents.
+#[derive(Debug)]
+pub enum MarkdownParseError {
+    /// The parsed document tree is nested deeper than the default maximum depth.
+    TooDeep(DocumentTooDeepError),
+}
+
+impl Display for MarkdownParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooDeep(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for MarkdownParseError {}
+
+impl From<DocumentTooDeepError> for MarkdownParseError {
+    fn from(err: DocumentTooDeepError) -> Self {
+        Self::TooDeep(err)
+    }
+}
+
+fn markdown_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+}
+
+/// Builds a document tree from a stream of Markdown events.
+///
+/// Each start event pushes a new node onto the stack, and the matching end event pops it and appends it to its
+/// parent. The bottom of the stack is always the root node.
+struct MarkdownParser {
+    stack: Vec<DocumentNode>,
+}
+
+impl MarkdownParser {
+    fn new() -> Self {
+        Self {
+            stack: vec![DocumentNode::new_empty()],
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(_) => self.end(),
+            Event::Text(text) => self.push_text(&text),
+            Event::Code(code) => self.push_node(DocumentNode::new(DocumentNodeTag::InlineCode {
+                code: code.into_string(),
+            })),
+            Event::Html(html) => self.push_text(&html),
+            Event::FootnoteReference(label) => self.push_text(&format!("[^{}]", label)),
+            Event::SoftBreak => self.push_text(" "),
+            Event::HardBreak => self.push_text("\n"),
+            Event::Rule => self.push_node(DocumentNode::new(DocumentNodeTag::Divider)),
+            Event::TaskListMarker(checked) => self.mark_todo_item(checked),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        let tag = match tag {
+            Tag::Paragraph => DocumentNodeTag::Paragraph,
+            Tag::Heading(level, id, _) => DocumentNodeTag::Heading {
+                level: level as i32,
+                id: id.map(String::from),
+            },
+            Tag::BlockQuote => DocumentNodeTag::Quote,
+            Tag::CodeBlock(kind) => DocumentNodeTag::Code {
+                language: code_language(&kind),
+                caption: None,
+                code: String::new(),
+            },
+            Tag::List(start) => DocumentNodeTag::List {
+                is_ordered: start.is_some(),
+            },
+            Tag::Item => DocumentNodeTag::ListItem,
+            // Footnotes are not enabled, but keep the content of footnote definitions if any.
+            Tag::FootnoteDefinition(_) => DocumentNodeTag::Paragraph,
+            Tag::Table(_) => DocumentNodeTag::Table { caption: None },
+            Tag::TableHead | Tag::TableRow => DocumentNodeTag::TableRow,
+            Tag::TableCell => DocumentNodeTag::TableCell,
+            Tag::Emphasis => styled_inline(|style| style.italic = true),
+            Tag::Strong => styled_inline(|style| style.bold = true),
+            Tag::Strikethrough => styled_inline(|style| style.strike_through = true),
+            Tag::Link(_, url, _) => DocumentNodeTag::Inline {
+                style: None,
+                link: Some(url.into_string()),
+            },
+            Tag::Image(_, url, _) => DocumentNodeTag::Image {
+                link: DocumentResourceLink::External {
+                    url: url.into_string(),
+                },
+                caption: None,
+            },
+        };
+
+        self.stack.push(DocumentNode::new(tag));
+    }
+
+    fn end(&mut self) {
+        let mut node = self.stack.pop().unwrap();
+        if let DocumentNodeTag::Code { code, .. } = &mut node.tag {
+            if code.ends_with('\n') {
+                code.pop();
+            }
+        } else if is_image(&node) && self.current().tag.is_inline() {
+            // Document trees have no inline images, so an image nested in a link or in styled text, such as a linked
+            // badge, is kept as its alt text.
+            if let DocumentNodeTag::Image {
+                caption: Some(caption),
+                ..
+            } = &node.tag
+            {
+                let caption = caption.clone();
+                self.push_text(&caption);
+            }
+            return;
+        } else if is_single_image(&node) {
+            // An image that stands alone in a paragraph is an image block.
+            node = node.children.pop().unwrap();
+        } else if is_task_list(&node) {
+            // Task lists become a sequence of todo items, which are not wrapped in lists.
+            for item in std::mem::take(&mut node.children) {
+                self.push_node(item);
+            }
+            return;
+        }
+
+        self.push_node(node);
+    }
+
+    fn push_node(&mut self, node: DocumentNode) {
+        self.current().children.push(node);
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let current = self.current();
+        match &mut current.tag {
+            DocumentNodeTag::Code { code, .. } => code.push_str(text),
+            DocumentNodeTag::Image { caption, .. } => {
+                caption.get_or_insert_with(String::new).push_str(text)
+            }
+            _ => {
+                // The parser may split a piece of text into several text events, so merge them back.
+                if let Some(DocumentNodeTag::InlineText { text: last }) =
+                    current.children.last_mut().map(|last| &mut last.tag)
+                {
+                    last.push_str(text);
+                } else {
+                    current
+                        .children
+                        .push(DocumentNode::new(DocumentNodeTag::InlineText {
+                            text: String::from(text),
+                        }));
+                }
+            }
+        }
+    }
+
+    fn mark_todo_item(&mut self, checked: bool) {
+        let item = self
+            .stack
+            .iter_mut()
+            .rev()
+            .find(|node| matches!(node.tag, DocumentNodeTag::ListItem));
+        if let Some(item) = item {
+            item.tag = DocumentNodeTag::TodoItem { checked };
+        }
+    }
+
+    fn current(&mut self) -> &mut DocumentNode {
+        self.stack.last_mut().unwrap()
+    }
+
+    fn finish(mut self) -> DocumentNode {
+        debug_assert_eq!(self.stack.len(), 1);
+        self.stack.pop().unwrap()
+    }
+}
+
+fn code_language(kind: &CodeBlockKind) -> String {
+    let language = match kind {
+        CodeBlockKind::Fenced(info) => info.split_whitespace().next(),
+        CodeBlockKind::Indented => None,
+    };
+    String::from(language.unwrap_or(DEFAULT_CODE_LANGUAGE))
+}
+
+fn styled_inline<F>(set_style: F) -> DocumentNodeTag
+where
+    F: FnOnce(&mut InlineStyle),
+{
+    let mut style = InlineStyle::new();
+    set_style(&mut style);
+    DocumentNodeTag::Inline {
+        style: Some(style),
+        link: None,
+    }
+}
+
+fn is_image(node: &DocumentNode) -> bool {
+    matches!(node.tag, DocumentNodeTag::Image { .. })
+}
+
+fn is_single_image(node: &DocumentNode) -> bool {
+    matches!(node.tag, DocumentNodeTag::Paragraph)
+        && node.children.len() == 1
+        && is_image(&node.children[0])
+}
+
+fn is_task_list(node: &DocumentNode) -> bool {
+    matches!(node.tag, DocumentNodeTag::List { .. })
+        && !node.children.is_empty()
+        && node
+            .children
+            .iter()
+            .all(|item| matches!(item.tag, DocumentNodeTag::TodoItem { .. }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::builder::DocumentTreeBuilder;
+
+    fn parse(input: &str) -> DocumentNode {
+        let root = parse_markdown(input).unwrap();
+        root.validate().unwrap();
+        root
+    }
+
+    fn styled(set_style: fn(&mut InlineStyle)) -> DocumentNodeTag {
+        styled_inline(set_style)
+    }
+
+    #[test]
+    fn test_parse_headings() {
+        let root = parse("# Title\n\n## Section {#intro}\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder.heading(1, |b| {
+            b.text("Title");
+        });
+        builder.container(
+            DocumentNodeTag::Heading {
+                level: 2,
+                id: Some(String::from("intro")),
+            },
+            |b| {
+                b.text("Section");
+            },
+        );
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_paragraphs() {
+        let root = parse("first\nline\n\nsecond  \nline\n\n---\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder
+            .paragraph(|b| {
+                b.text("first line");
+            })
+            .paragraph(|b| {
+                b.text("second\nline");
+            })
+            .divider();
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_lists() {
+        let root = parse("- a\n- b\n  1. c\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder.list(false, |b| {
+            b.item(|b| {
+                b.text("a");
+            })
+            .item(|b| {
+                b.text("b").list(true, |b| {
+                    b.item(|b| {
+                        b.text("c");
+                    });
+                });
+            });
+        });
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_task_list() {
+        let root = parse("- [x] done\n- [ ] todo\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder
+            .container(DocumentNodeTag::TodoItem { checked: true }, |b| {
+                b.text("done");
+            })
+            .container(DocumentNodeTag::TodoItem { checked: false }, |b| {
+                b.text("todo");
+            });
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_code_blocks() {
+        let root = parse("```rust\nfn main() {}\n```\n\n    indented\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder
+            .code("rust", "fn main() {}")
+            .code(DEFAULT_CODE_LANGUAGE, "indented");
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_quote() {
+        let root = parse("> quoted\n> text\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder.quote(|b| {
+            b.paragraph(|b| {
+                b.text("quoted text");
+            });
+        });
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let root = parse("| a | b |\n|---|---|\n| 1 | 2 |\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder.container(DocumentNodeTag::Table { caption: None }, |b| {
+            for row in [["a", "b"], ["1", "2"]] {
+                b.container(DocumentNodeTag::TableRow, |b| {
+                    for cell in row {
+                        b.container(DocumentNodeTag::TableCell, |b| {
+                            b.text(cell);
+                        });
+                    }
+                });
+            }
+        });
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_inlines() {
+        let root = parse("*a* **b** ~~c~~ `d` [e](https://example.com)\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.container(styled(|s| s.italic = true), |b| {
+                b.text("a");
+            })
+            .text(" ")
+            .container(styled(|s| s.bold = true), |b| {
+                b.text("b");
+            })
+            .text(" ")
+            .container(styled(|s| s.strike_through = true), |b| {
+                b.text("c");
+            })
+            .text(" ")
+            .inline_code("d")
+            .text(" ")
+            .container(
+                DocumentNodeTag::Inline {
+                    style: None,
+                    link: Some(String::from("https://example.com")),
+                },
+                |b| {
+                    b.text("e");
+                },
+            );
+        });
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_image() {
+        let root = parse("![a cat](https://example.com/cat.png)\n");
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder.node(DocumentNode::new(DocumentNodeTag::Image {
+            link: DocumentResourceLink::External {
+                url: String::from("https://example.com/cat.png"),
+            },
+            caption: Some(String::from("a cat")),
+        }));
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_linked_image() {
+        let root = parse(
+            "[![build](https://example.com/badge.svg)](https://example.com/ci) *![](a.png)*\n",
+        );
+
+        let mut builder = DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.container(
+                DocumentNodeTag::Inline {
+                    style: None,
+                    link: Some(String::from("https://example.com/ci")),
+                },
+                |b| {
+                    b.text("build");
+                },
+            )
+            .text(" ")
+            .container(styled(|s| s.italic = true), |_| {});
+        });
+        assert_eq!(root, builder.finish());
+    }
+
+    #[test]
+    fn test_parse_too_deep() {
+        let input = ">".repeat(crate::DEFAULT_MAX_DEPTH + 1);
+        assert!(matches!(
+            parse_markdown(&input),
+            Err(MarkdownParseError::TooDeep(_))
+        ));
+    }
+}
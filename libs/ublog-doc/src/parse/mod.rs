@@ -0,0 +1,3 @@
+//! This module provides parsers that convert documents in other formats into document trees.
+
+pub mod markdown;
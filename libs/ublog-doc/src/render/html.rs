@@ -0,0 +1,730 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use crate::render::{ResourceUrlResolver, TemplateResourceUrlResolver};
+use crate::validate::DocumentTooDeepError;
+use crate::{DocumentNode, DocumentNodeTag, DocumentResourceLink, InlineStyle};
+
+/// Options that control how document trees are rendered into HTML.
+#[derive(Clone)]
+pub struct HtmlRenderOptions {
+    /// Resolver that maps embedded resources to their URLs.
+    pub resource_url_resolver: Arc<dyn ResourceUrlResolver + Send + Sync>,
+
+    /// The maximum depth of the document trees that can be rendered.
+    ///
+    /// Rendering a document tree deeper than this fails with a [`DocumentTooDeepError`].
+    pub max_depth: usize,
+}
+
+impl HtmlRenderOptions {
+    /// Create a new `HtmlRenderOptions` object that renders embedded resources with URLs given by the resolver.
+    pub fn with_resource_url_resolver<R>(resolver: R) -> Self
+    where
+        R: ResourceUrlResolver + Send + Sync + 'static,
+    {
+        Self {
+            resource_url_resolver: Arc::new(resolver),
+            max_depth: crate::DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Create a new `HtmlRenderOptions` object that renders embedded resources with URLs given by the template. See
+    /// [`TemplateResourceUrlResolver`] for the syntax of the template.
+    pub fn with_resource_url_template<T>(template: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self::with_resource_url_resolver(TemplateResourceUrlResolver::new(template))
+    }
+
+    /// Get the URL of the given resource link. Embedded resources are resolved with the resource URL resolver.
+    pub fn resource_url(&self, link: &DocumentResourceLink) -> String {
+        match link {
+            DocumentResourceLink::External { url } => url.clone(),
+            DocumentResourceLink::Embedded { uuid } => self.resource_url_resolver.resolve(uuid),
+        }
+    }
+}
+
+impl Debug for HtmlRenderOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HtmlRenderOptions")
+            .field("max_depth", &self.max_depth)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for HtmlRenderOptions {
+    fn default() -> Self {
+        Self::with_resource_url_resolver(TemplateResourceUrlResolver::default())
+    }
+}
+
+/// Render the document tree rooted at the given node into an HTML string, using the default render options.
+pub fn render_html(root: &DocumentNode) -> Result<String, DocumentTooDeepError> {
+    render_html_with_options(root, &HtmlRenderOptions::default())
+}
+
+/// Render the document tree rooted at the given node into an HTML string, using the given render options.
+pub fn render_html_with_options(
+    root: &DocumentNode,
+    options: &HtmlRenderOptions,
+) -> Result<String, DocumentTooDeepError> {
+    // The renderer is recursive, so reject excessively deep trees before rendering them.
+    root.check_depth(options.max_depth)?;
+
+    let renderer = HtmlRenderer {
+        options,
+        heading_ids: RefCell::default(),
+    };
+    let mut output = String::new();
+    renderer.render_node(root, &mut output);
+    Ok(output)
+}
+
+struct HtmlRenderer<'o> {
+    options: &'o HtmlRenderOptions,
+    heading_ids: RefCell<HeadingIds>,
+}
+
+impl<'o> HtmlRenderer<'o> {
+    fn render_node(&self, node: &DocumentNode, output: &mut String) {
+        match &node.tag {
+            DocumentNodeTag::Root => self.render_children(node, output),
+            DocumentNodeTag::Paragraph => self.render_container("p", node, output),
+            DocumentNodeTag::Heading { level, .. } => {
+                let tag = heading_tag(*level);
+                output.push('<');
+                output.push_str(tag);
+                if let Some(id) = node.heading_id() {
+                    let id = self.heading_ids.borrow_mut().make_unique(id);
+                    output.push_str(" id=\"");
+                    push_attr(&id, output);
+                    output.push('"');
+                }
+                output.push('>');
+                self.render_children(node, output);
+                output.push_str("</");
+                output.push_str(tag);
+                output.push('>');
+            }
+            DocumentNodeTag::Callout { emoji } => {
+                output.push_str(r#"<div class="callout">"#);
+                if let Some(emoji) = emoji {
+                    output.push_str(r#"<span class="callout-emoji">"#);
+                    push_text(emoji, output);
+                    output.push_str("</span>");
+                }
+                self.render_children(node, output);
+                output.push_str("</div>");
+            }
+            DocumentNodeTag::Quote => self.render_container("blockquote", node, output),
+            DocumentNodeTag::List { is_ordered } => {
+                let tag = if *is_ordered { "ol" } else { "ul" };
+                self.render_container(tag, node, output);
+            }
+            DocumentNodeTag::ListItem => self.render_container("li", node, output),
+            DocumentNodeTag::TodoItem { checked } => {
+                output.push_str(r#"<div class="todo-item"><input type="checkbox" disabled"#);
+                if *checked {
+                    output.push_str(" checked");
+                }
+                output.push('>');
+                self.render_children(node, output);
+                output.push_str("</div>");
+            }
+            DocumentNodeTag::Code {
+                language,
+                caption,
+                code,
+            } => {
+                self.render_with_caption(caption, output, |output| {
+                    output.push_str("<pre><code class=\"language-");
+                    push_attr(language, output);
+                    output.push_str("\">");
+                    push_text(code, output);
+                    output.push_str("</code></pre>");
+                });
+            }
+            DocumentNodeTag::Equation { expr, caption } => {
+                self.render_with_caption(caption, output, |output| {
+                    output.push_str(r#"<div class="equation">$$"#);
+                    push_text(expr, output);
+                    output.push_str("$$</div>");
+                });
+            }
+            DocumentNodeTag::Image { link, caption } => {
+                self.render_with_caption(caption, output, |output| {
+                    output.push_str("<img src=\"");
+                    push_attr(&self.options.resource_url(link), output);
+                    output.push_str("\" alt=\"");
+                    push_attr(caption.as_deref().unwrap_or_default(), output);
+                    output.push_str("\">");
+                });
+            }
+            DocumentNodeTag::Table { caption } => {
+                output.push_str("<table>");
+                if let Some(caption) = caption {
+                    output.push_str("<caption>");
+                    push_text(caption, output);
+                    output.push_str("</caption>");
+                }
+                self.render_children(node, output);
+                output.push_str("</table>");
+            }
+            DocumentNodeTag::TableRow => self.render_container("tr", node, output),
+            DocumentNodeTag::TableCell => self.render_container("td", node, output),
+            DocumentNodeTag::Divider => output.push_str("<hr>"),
+            DocumentNodeTag::Toggle { summary } => {
+                output.push_str("<details><summary>");
+                push_text(summary, output);
+                output.push_str("</summary>");
+                self.render_children(node, output);
+                output.push_str("</details>");
+            }
+            DocumentNodeTag::Columns => {
+                output.push_str(r#"<div class="columns">"#);
+                self.render_children(node, output);
+                output.push_str("</div>");
+            }
+            DocumentNodeTag::Column => {
+                output.push_str(r#"<div class="column">"#);
+                self.render_children(node, output);
+                output.push_str("</div>");
+            }
+            DocumentNodeTag::Inline { style, link } => {
+                self.render_inline(style, link, node, output)
+            }
+            DocumentNodeTag::InlineText { text } => push_text(text, output),
+            DocumentNodeTag::InlineCode { code } => {
+                output.push_str("<code>");
+                push_text(code, output);
+                output.push_str("</code>");
+            }
+            DocumentNodeTag::InlineEquation { expr } => {
+                output.push_str(r#"<span class="inline-equation">\("#);
+                push_text(expr, output);
+                output.push_str(r#"\)</span>"#);
+            }
+        }
+    }
+
+    fn render_children(&self, node: &DocumentNode, output: &mut String) {
+        for child in &node.children {
+            self.render_node(child, output);
+        }
+    }
+
+    fn render_container(&self, tag: &str, node: &DocumentNode, output: &mut String) {
+        output.push('<');
+        output.push_str(tag);
+        output.push('>');
+        self.render_children(node, output);
+        output.push_str("</");
+        output.push_str(tag);
+        output.push('>');
+    }
+
+    fn render_with_caption<F>(
+        &self,
+        caption: &Option<String>,
+        output: &mut String,
+        render_content: F,
+    ) where
+        F: FnOnce(&mut String),
+    {
+        let caption = match caption {
+            Some(caption) if !caption.is_empty() => caption,
+            _ => {
+                render_content(output);
+                return;
+            }
+        };
+
+        output.push_str("<figure>");
+        render_content(output);
+        output.push_str("<figcaption>");
+        push_text(caption, output);
+        output.push_str("</figcaption></figure>");
+    }
+
+    fn render_inline(
+        &self,
+        style: &Option<InlineStyle>,
+        link: &Option<String>,
+        node: &DocumentNode,
+        output: &mut String,
+    ) {
+        let mut open_tags = Vec::new();
+
+        // Links with disallowed schemes are dropped, so that their text is rendered as plain text.
+        if let Some(link) = link.as_ref().filter(|link| is_allowed_link(link)) {
+            output.push_str("<a href=\"");
+            push_attr(link, output);
+            output.push_str("\">");
+            open_tags.push("a");
+        }
+
+        if let Some(color) = style.as_ref().and_then(|style| style.color.as_ref()) {
+            output.push_str("<span class=\"");
+            push_attr(&crate::render::styles::color_to_css(color), output);
+            output.push_str("\">");
+            open_tags.push("span");
+        }
+
+        if let Some(style) = style {
+            for tag in inline_style_tags(style) {
+                output.push('<');
+                output.push_str(tag);
+                output.push('>');
+                open_tags.push(tag);
+            }
+        }
+
+        self.render_children(node, output);
+
+        for tag in open_tags.into_iter().rev() {
+            output.push_str("</");
+            output.push_str(tag);
+            output.push('>');
+        }
+    }
+}
+
+/// The IDs of the headings rendered into a single document.
+#[derive(Clone, Debug, Default)]
+pub struct HeadingIds {
+    used: HashSet<String>,
+}
+
+impl HeadingIds {
+    /// Make the given heading ID unique within the document by appending `-1`, `-2`, etc. to it if it is already used,
+    /// and then mark the resulting ID as used.
+    pub fn make_unique(&mut self, id: String) -> String {
+        let mut unique = id.clone();
+        let mut suffix = 0;
+        while self.used.contains(&unique) {
+            suffix += 1;
+            unique = format!("{}-{}", id, suffix);
+        }
+
+        self.used.insert(unique.clone());
+        unique
+    }
+}
+
+/// URL schemes that rendered links may use. Links without a scheme, i.e. relative and fragment links, are also allowed.
+const ALLOWED_LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Determine whether the given URL can be rendered as a link, i.e. whether it is relative or uses one of the
+/// [`ALLOWED_LINK_SCHEMES`].
+fn is_allowed_link(url: &str) -> bool {
+    // Browsers ignore leading spaces and control characters, as well as tabs and newlines anywhere in URLs, so they must
+    // be ignored here too for `java\tscript:` not to slip through.
+    let url: String = url
+        .trim_start_matches(|c: char| c <= ' ')
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+
+    match url.find([':', '/', '?', '#']) {
+        Some(idx) if url[idx..].starts_with(':') => {
+            let scheme = &url[..idx];
+            ALLOWED_LINK_SCHEMES
+                .iter()
+                .any(|allowed| scheme.eq_ignore_ascii_case(allowed))
+        }
+        _ => true,
+    }
+}
+
+/// Get the names of the HTML elements that apply the given inline style, from the outermost to the innermost.
+pub fn inline_style_tags(style: &InlineStyle) -> impl Iterator<Item = &'static str> {
+    [
+        (style.bold, "strong"),
+        (style.italic, "em"),
+        (style.underline, "u"),
+        (style.strike_through, "s"),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, tag)| tag)
+}
+
+/// Get the name of the HTML element of a heading at the given level. Levels out of `1..=6` are clamped.
+pub fn heading_tag(level: i32) -> &'static str {
+    match level {
+        i32::MIN..=1 => "h1",
+        2 => "h2",
+        3 => "h3",
+        4 => "h4",
+        5 => "h5",
+        _ => "h6",
+    }
+}
+
+fn push_text(text: &str, output: &mut String) {
+    output.push_str(&html_escape::encode_text(text));
+}
+
+fn push_attr(value: &str, output: &mut String) {
+    output.push_str(&html_escape::encode_double_quoted_attribute(value));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inline_text(text: &str) -> DocumentNode {
+        DocumentNode::new(DocumentNodeTag::InlineText {
+            text: String::from(text),
+        })
+    }
+
+    #[test]
+    fn test_render_paragraph() {
+        let mut para = DocumentNode::new(DocumentNodeTag::Paragraph);
+        para.children.push(inline_text("a < b"));
+
+        assert_eq!(render_html(&para).unwrap(), "<p>a &lt; b</p>");
+    }
+
+    #[test]
+    fn test_render_todo_item() {
+        let mut unchecked = DocumentNode::new(DocumentNodeTag::TodoItem { checked: false });
+        unchecked.children.push(inline_text("todo"));
+        assert_eq!(
+            render_html(&unchecked).unwrap(),
+            r#"<div class="todo-item"><input type="checkbox" disabled>todo</div>"#
+        );
+
+        let mut checked = DocumentNode::new(DocumentNodeTag::TodoItem { checked: true });
+        checked.children.push(inline_text("done"));
+        assert_eq!(
+            render_html(&checked).unwrap(),
+            r#"<div class="todo-item"><input type="checkbox" disabled checked>done</div>"#
+        );
+    }
+
+    #[test]
+    fn test_render_inline_color() {
+        let mut inline = DocumentNode::new(DocumentNodeTag::Inline {
+            style: Some(InlineStyle {
+                bold: true,
+                color: Some(String::from("blue_background")),
+                ..InlineStyle::default()
+            }),
+            link: None,
+        });
+        inline.children.push(inline_text("text"));
+
+        assert_eq!(
+            render_html(&inline).unwrap(),
+            r#"<span class="color-blue-background"><strong>text</strong></span>"#
+        );
+    }
+
+    #[test]
+    fn test_render_heading_id() {
+        let mut generated = DocumentNode::new(DocumentNodeTag::Heading { level: 2, id: None });
+        generated.children.push(inline_text("Getting Started"));
+        assert_eq!(
+            render_html(&generated).unwrap(),
+            r#"<h2 id="getting-started">Getting Started</h2>"#
+        );
+
+        let mut explicit = DocumentNode::new(DocumentNodeTag::Heading {
+            level: 2,
+            id: Some(String::from("intro")),
+        });
+        explicit.children.push(inline_text("Getting Started"));
+        assert_eq!(
+            render_html(&explicit).unwrap(),
+            r#"<h2 id="intro">Getting Started</h2>"#
+        );
+
+        let mut punctuation = DocumentNode::new(DocumentNodeTag::Heading { level: 2, id: None });
+        punctuation.children.push(inline_text("?!"));
+        assert_eq!(render_html(&punctuation).unwrap(), "<h2>?!</h2>");
+    }
+
+    #[test]
+    fn test_render_duplicate_heading_ids() {
+        let heading = |text: &str| {
+            container(
+                DocumentNodeTag::Heading { level: 2, id: None },
+                vec![inline_text(text)],
+            )
+        };
+        let root = container(
+            DocumentNodeTag::Root,
+            vec![
+                heading("Notes"),
+                heading("Notes"),
+                heading("Notes 1"),
+                heading("Notes"),
+            ],
+        );
+        assert_eq!(
+            render_html(&root).unwrap(),
+            concat!(
+                r#"<h2 id="notes">Notes</h2>"#,
+                r#"<h2 id="notes-1">Notes</h2>"#,
+                r#"<h2 id="notes-1-1">Notes 1</h2>"#,
+                r#"<h2 id="notes-2">Notes</h2>"#,
+            )
+        );
+    }
+
+    fn container(tag: DocumentNodeTag, children: Vec<DocumentNode>) -> DocumentNode {
+        let mut node = DocumentNode::new(tag);
+        node.children = children;
+        node
+    }
+
+    #[test]
+    fn test_render_block_containers() {
+        let cases = [
+            (
+                container(DocumentNodeTag::Quote, vec![inline_text("quote")]),
+                "<blockquote>quote</blockquote>",
+            ),
+            (
+                container(
+                    DocumentNodeTag::List { is_ordered: false },
+                    vec![container(DocumentNodeTag::ListItem, vec![inline_text("a")])],
+                ),
+                "<ul><li>a</li></ul>",
+            ),
+            (
+                container(
+                    DocumentNodeTag::List { is_ordered: true },
+                    vec![container(DocumentNodeTag::ListItem, vec![inline_text("b")])],
+                ),
+                "<ol><li>b</li></ol>",
+            ),
+            (
+                container(
+                    DocumentNodeTag::Callout {
+                        emoji: Some(String::from("💡")),
+                    },
+                    vec![inline_text("tip")],
+                ),
+                r#"<div class="callout"><span class="callout-emoji">💡</span>tip</div>"#,
+            ),
+            (
+                container(
+                    DocumentNodeTag::Callout { emoji: None },
+                    vec![inline_text("note")],
+                ),
+                r#"<div class="callout">note</div>"#,
+            ),
+            (
+                container(
+                    DocumentNodeTag::Toggle {
+                        summary: String::from("<more>"),
+                    },
+                    vec![inline_text("hidden")],
+                ),
+                "<details><summary>&lt;more&gt;</summary>hidden</details>",
+            ),
+            (
+                container(
+                    DocumentNodeTag::Columns,
+                    vec![
+                        container(DocumentNodeTag::Column, vec![inline_text("left")]),
+                        container(DocumentNodeTag::Column, vec![inline_text("right")]),
+                    ],
+                ),
+                r#"<div class="columns"><div class="column">left</div><div class="column">right</div></div>"#,
+            ),
+            (DocumentNode::new(DocumentNodeTag::Divider), "<hr>"),
+        ];
+
+        for (node, expected) in cases {
+            assert_eq!(render_html(&node).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_render_code() {
+        let code = DocumentNode::new(DocumentNodeTag::Code {
+            language: String::from("rust"),
+            caption: None,
+            code: String::from("if a < b && c {}"),
+        });
+        assert_eq!(
+            render_html(&code).unwrap(),
+            r#"<pre><code class="language-rust">if a &lt; b &amp;&amp; c {}</code></pre>"#
+        );
+
+        let captioned = DocumentNode::new(DocumentNodeTag::Code {
+            language: String::from("c\"+c++"),
+            caption: Some(String::from("main.cpp")),
+            code: String::from("int main();"),
+        });
+        assert_eq!(
+            render_html(&captioned).unwrap(),
+            r#"<figure><pre><code class="language-c&quot;+c++">int main();</code></pre><figcaption>main.cpp</figcaption></figure>"#
+        );
+    }
+
+    #[test]
+    fn test_render_equations() {
+        let equation = DocumentNode::new(DocumentNodeTag::Equation {
+            expr: String::from("a<b"),
+            caption: None,
+        });
+        assert_eq!(
+            render_html(&equation).unwrap(),
+            r#"<div class="equation">$$a&lt;b$$</div>"#
+        );
+
+        let inline = DocumentNode::new(DocumentNodeTag::InlineEquation {
+            expr: String::from("e^{i\\pi}"),
+        });
+        assert_eq!(
+            render_html(&inline).unwrap(),
+            r#"<span class="inline-equation">\(e^{i\pi}\)</span>"#
+        );
+    }
+
+    #[test]
+    fn test_render_image() {
+        let embedded = DocumentNode::new(DocumentNodeTag::Image {
+            link: DocumentResourceLink::Embedded {
+                uuid: String::from("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            },
+            caption: None,
+        });
+        assert_eq!(
+            render_html(&embedded).unwrap(),
+            r#"<img src="/api/resources/67e55044-10b1-426f-9247-bb680e5fe0c8" alt="">"#
+        );
+
+        let external = DocumentNode::new(DocumentNodeTag::Image {
+            link: DocumentResourceLink::External {
+                url: String::from("https://example.com/a.png?x=1&y=\"2\""),
+            },
+            caption: Some(String::from("An image")),
+        });
+        assert_eq!(
+            render_html(&external).unwrap(),
+            r#"<figure><img src="https://example.com/a.png?x=1&amp;y=&quot;2&quot;" alt="An image"><figcaption>An image</figcaption></figure>"#
+        );
+    }
+
+    #[test]
+    fn test_render_image_resolved_url() {
+        let image = DocumentNode::new(DocumentNodeTag::Image {
+            link: DocumentResourceLink::Embedded {
+                uuid: String::from("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            },
+            caption: None,
+        });
+
+        let options = HtmlRenderOptions::with_resource_url_template(
+            "https://cdn.example.com/${uuid}.bin?v=1&${uuid}",
+        );
+        assert_eq!(
+            render_html_with_options(&image, &options).unwrap(),
+            concat!(
+                r#"<img src="https://cdn.example.com/67e55044-10b1-426f-9247-bb680e5fe0c8.bin"#,
+                r#"?v=1&amp;67e55044-10b1-426f-9247-bb680e5fe0c8" alt="">"#
+            )
+        );
+
+        let options = HtmlRenderOptions::with_resource_url_resolver(|uuid: &str| {
+            format!("/r/{}", &uuid[..8])
+        });
+        assert_eq!(
+            render_html_with_options(&image, &options).unwrap(),
+            r#"<img src="/r/67e55044" alt="">"#
+        );
+    }
+
+    #[test]
+    fn test_render_table() {
+        let table = container(
+            DocumentNodeTag::Table {
+                caption: Some(String::from("Results")),
+            },
+            vec![container(
+                DocumentNodeTag::TableRow,
+                vec![
+                    container(DocumentNodeTag::TableCell, vec![inline_text("1")]),
+                    container(DocumentNodeTag::TableCell, vec![inline_text("2")]),
+                ],
+            )],
+        );
+        assert_eq!(
+            render_html(&table).unwrap(),
+            "<table><caption>Results</caption><tr><td>1</td><td>2</td></tr></table>"
+        );
+    }
+
+    #[test]
+    fn test_render_inline_nodes() {
+        let code = DocumentNode::new(DocumentNodeTag::InlineCode {
+            code: String::from("Vec<u8>"),
+        });
+        assert_eq!(render_html(&code).unwrap(), "<code>Vec&lt;u8&gt;</code>");
+
+        let inline = container(
+            DocumentNodeTag::Inline {
+                style: Some(InlineStyle {
+                    bold: true,
+                    italic: true,
+                    underline: true,
+                    strike_through: true,
+                    color: None,
+                }),
+                link: Some(String::from("https://example.com/?a=1&b=2")),
+            },
+            vec![inline_text("link")],
+        );
+        assert_eq!(
+            render_html(&inline).unwrap(),
+            r#"<a href="https://example.com/?a=1&amp;b=2"><strong><em><u><s>link</s></u></em></strong></a>"#
+        );
+    }
+
+    #[test]
+    fn test_render_disallowed_links() {
+        let link = |url: &str| {
+            container(
+                DocumentNodeTag::Inline {
+                    style: None,
+                    link: Some(String::from(url)),
+                },
+                vec![inline_text("link")],
+            )
+        };
+
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            " java\tscript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+        ] {
+            assert_eq!(render_html(&link(url)).unwrap(), "link", "{}", url);
+        }
+
+        for url in [
+            "http://example.com",
+            "mailto:me@example.com",
+            "/posts/hello",
+            "../hello?a=b:c",
+            "#section",
+        ] {
+            assert!(
+                render_html(&link(url)).unwrap().starts_with("<a href="),
+                "{}",
+                url
+            );
+        }
+    }
+}
@@ -0,0 +1,60 @@
+pub mod html;
+pub mod styles;
+
+/// Map embedded resources to the URLs that serve them.
+///
+/// Renderers call the resolver when they render a [`DocumentResourceLink::Embedded`](crate::DocumentResourceLink)
+/// link. Closures that take the UUID of the resource and return its URL are resolvers, too.
+pub trait ResourceUrlResolver {
+    /// Get the URL of the embedded resource with the given UUID.
+    fn resolve(&self, uuid: &str) -> String;
+}
+
+impl<F> ResourceUrlResolver for F
+where
+    F: Fn(&str) -> String,
+{
+    fn resolve(&self, uuid: &str) -> String {
+        self(uuid)
+    }
+}
+
+/// A [`ResourceUrlResolver`] that substitutes the UUID of the resource into a URL template.
+///
+/// Every occurrence of `${uuid}` in the template is replaced with the UUID of the resource.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TemplateResourceUrlResolver {
+    template: String,
+}
+
+impl TemplateResourceUrlResolver {
+    /// The default URL template of embedded resources.
+    pub const DEFAULT_TEMPLATE: &'static str = "/api/resources/${uuid}";
+
+    /// Create a new `TemplateResourceUrlResolver` that resolves resource URLs with the given template.
+    pub fn new<T>(template: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    /// Get the URL template.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+}
+
+impl Default for TemplateResourceUrlResolver {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TEMPLATE)
+    }
+}
+
+impl ResourceUrlResolver for TemplateResourceUrlResolver {
+    fn resolve(&self, uuid: &str) -> String {
+        self.template.replace("${uuid}", uuid)
+    }
+}
@@ -0,0 +1,76 @@
+/// Names of all colors in Notion's palette.
+///
+/// Each foreground color `name` comes with a background variant `name_background`, except for `default`.
+const PALETTE: &[&str] = &[
+    "default", "gray", "brown", "orange", "yellow", "green", "blue", "purple", "pink", "red",
+];
+
+/// Get the CSS class name corresponding to the given color name.
+///
+/// Color names follow Notion's palette, e.g. `gray` for a foreground color and `gray_background` for a background
+/// color. Unknown color names are mapped to the class of the default color.
+pub fn color_to_css<T>(name: T) -> String
+where
+    T: AsRef<str>,
+{
+    let name = name.as_ref();
+    if !is_palette_color(name) {
+        return String::from("color-default");
+    }
+
+    format!("color-{}", name.replace('_', "-"))
+}
+
+/// Normalize the given color name into the representation stored in [`InlineStyle::color`](crate::InlineStyle).
+///
+/// Color names are matched against Notion's palette case-insensitively. Background colors keep their `_background`
+/// suffix so they can be told apart from foreground colors. Returns `None` for the default color and for unknown
+/// color names.
+pub fn normalize_color<T>(name: T) -> Option<String>
+where
+    T: AsRef<str>,
+{
+    let name = name.as_ref().trim().to_ascii_lowercase();
+    if name == "default" || !is_palette_color(&name) {
+        return None;
+    }
+
+    Some(name)
+}
+
+fn is_palette_color(name: &str) -> bool {
+    let base_name = name.strip_suffix("_background").unwrap_or(name);
+    PALETTE.contains(&base_name) && !(base_name == "default" && base_name != name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_to_css_foreground() {
+        assert_eq!(color_to_css("red"), "color-red");
+    }
+
+    #[test]
+    fn test_color_to_css_background() {
+        assert_eq!(color_to_css("gray_background"), "color-gray-background");
+    }
+
+    #[test]
+    fn test_color_to_css_unknown() {
+        assert_eq!(color_to_css("default_background"), "color-default");
+        assert_eq!(color_to_css("\"><script>"), "color-default");
+    }
+
+    #[test]
+    fn test_normalize_color() {
+        assert_eq!(normalize_color("red").as_deref(), Some("red"));
+        assert_eq!(
+            normalize_color("Gray_Background").as_deref(),
+            Some("gray_background")
+        );
+        assert_eq!(normalize_color("default"), None);
+        assert_eq!(normalize_color("magenta"), None);
+    }
+}
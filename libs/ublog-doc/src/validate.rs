@@ -0,0 +1,368 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::{DocumentNode, DocumentNodeTag};
+
+impl DocumentNode {
+    /// Validate the document tree rooted at this node, using the default maximum tree depth
+    /// [`DEFAULT_MAX_DEPTH`](crate::DEFAULT_MAX_DEPTH).
+    ///
+    /// The following structural invariants are checked:
+    /// - Table cells must be direct children of table rows, and table rows must be direct children of tables;
+    /// - List items must be direct children of lists;
+    /// - Columns must be direct children of column layouts;
+    /// - Inline nodes must not contain any block nodes.
+    ///
+    /// All violations found in the document tree are returned.
+    pub fn validate(&self) -> Result<(), Vec<DocumentValidationError>> {
+        self.validate_with_max_depth(crate::DEFAULT_MAX_DEPTH)
+    }
+
+    /// Validate the document tree rooted at this node, using the given maximum tree depth.
+    ///
+    /// All violations found in the document tree are returned. Subtrees below the maximum depth are not examined.
+    pub fn validate_with_max_depth(
+        &self,
+        max_depth: usize,
+    ) -> Result<(), Vec<DocumentValidationError>> {
+        let mut errors = Vec::new();
+
+        let mut stack: Vec<(&DocumentNode, Option<&DocumentNode>, Vec<usize>)> =
+            vec![(self, None, Vec::new())];
+        while let Some((node, parent, path)) = stack.pop() {
+            if path.len() >= max_depth {
+                errors.push(DocumentValidationError {
+                    path,
+                    kind: DocumentValidationErrorKind::TooDeep { max_depth },
+                });
+                continue;
+            }
+
+            if let Some(kind) = check_parent(node, parent) {
+                errors.push(DocumentValidationError {
+                    path: path.clone(),
+                    kind,
+                });
+            }
+
+            for (idx, child) in node.children.iter().enumerate().rev() {
+                let mut child_path = path.clone();
+                child_path.push(idx);
+                stack.push((child, Some(node), child_path));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check whether the depth of the document tree rooted at this node exceeds the given maximum depth.
+    ///
+    /// The check is performed without recursion, so it is safe to call on arbitrarily deep trees.
+    pub fn check_depth(&self, max_depth: usize) -> Result<(), DocumentTooDeepError> {
+        let mut stack = vec![(self, 1usize)];
+        while let Some((node, depth)) = stack.pop() {
+            if depth > max_depth {
+                return Err(DocumentTooDeepError { max_depth });
+            }
+
+            stack.extend(node.children.iter().map(|child| (child, depth + 1)));
+        }
+
+        Ok(())
+    }
+}
+
+fn check_parent(
+    node: &DocumentNode,
+    parent: Option<&DocumentNode>,
+) -> Option<DocumentValidationErrorKind> {
+    let parent_tag = parent.map(|parent| &parent.tag);
+    match (&node.tag, parent_tag) {
+        (DocumentNodeTag::TableCell, Some(DocumentNodeTag::TableRow)) => None,
+        (DocumentNodeTag::TableCell, _) => Some(DocumentValidationErrorKind::TableCellOutsideRow),
+        (DocumentNodeTag::TableRow, Some(DocumentNodeTag::Table { .. })) => None,
+        (DocumentNodeTag::TableRow, _) => Some(DocumentValidationErrorKind::TableRowOutsideTable),
+        (DocumentNodeTag::ListItem, Some(DocumentNodeTag::List { .. })) => None,
+        (DocumentNodeTag::ListItem, _) => Some(DocumentValidationErrorKind::ListItemOutsideList),
+        (DocumentNodeTag::Column, Some(DocumentNodeTag::Columns)) => None,
+        (DocumentNodeTag::Column, _) => Some(DocumentValidationErrorKind::ColumnOutsideColumns),
+        (tag, Some(parent_tag)) if parent_tag.is_inline() && !tag.is_inline() => {
+            Some(DocumentValidationErrorKind::BlockInsideInline)
+        }
+        _ => None,
+    }
+}
+
+/// A violation found when validating a document tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DocumentValidationError {
+    /// Path to the offending node.
+    ///
+    /// The path is a list of child indices that leads from the root node to the offending node. An empty path refers
+    /// to the root node itself.
+    pub path: Vec<usize>,
+
+    /// Kind of the violation.
+    pub kind: DocumentValidationErrorKind,
+}
+
+impl Display for DocumentValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "node at /")?;
+        let path: Vec<String> = self.path.iter().map(|idx| format!("{}", idx)).collect();
+        write!(f, "{}: {}", path.join("/"), self.kind)
+    }
+}
+
+impl Error for DocumentValidationError {}
+
+/// Kinds of violations found when validating a document tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DocumentValidationErrorKind {
+    /// The node is nested deeper than the maximum allowed depth.
+    TooDeep { max_depth: usize },
+
+    /// A table cell node is not a direct child of a table row node.
+    TableCellOutsideRow,
+
+    /// A table row node is not a direct child of a table node.
+    TableRowOutsideTable,
+
+    /// A list item node is not a direct child of a list node.
+    ListItemOutsideList,
+
+    /// A column node is not a direct child of a column layout node.
+    ColumnOutsideColumns,
+
+    /// A block node is a child of an inline node.
+    BlockInsideInline,
+}
+
+impl Display for DocumentValidationErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooDeep { max_depth } => {
+                write!(f, "node is nested deeper than {} levels", max_depth)
+            }
+            Self::TableCellOutsideRow => write!(f, "table cell is not inside a table row"),
+            Self::TableRowOutsideTable => write!(f, "table row is not inside a table"),
+            Self::ListItemOutsideList => write!(f, "list item is not inside a list"),
+            Self::ColumnOutsideColumns => write!(f, "column is not inside a column layout"),
+            Self::BlockInsideInline => write!(f, "block node is inside an inline node"),
+        }
+    }
+}
+
+/// Error that indicates a document tree is deeper than the maximum allowed depth.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DocumentTooDeepError {
+    /// The maximum allowed depth.
+    pub max_depth: usize,
+}
+
+impl Display for DocumentTooDeepError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "document tree is deeper than {} levels", self.max_depth)
+    }
+}
+
+impl Error for DocumentTooDeepError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_deep_tree(depth: usize) -> DocumentNode {
+        let mut node = DocumentNode::new(DocumentNodeTag::InlineText {
+            text: String::from("deep"),
+        });
+        for _ in 1..depth {
+            let mut parent = DocumentNode::new(DocumentNodeTag::Quote);
+            parent.children.push(node);
+            node = parent;
+        }
+        node
+    }
+
+    fn create_node(tag: DocumentNodeTag, children: Vec<DocumentNode>) -> DocumentNode {
+        let mut node = DocumentNode::new(tag);
+        node.children = children;
+        node
+    }
+
+    fn create_text(text: &str) -> DocumentNode {
+        DocumentNode::new(DocumentNodeTag::InlineText {
+            text: String::from(text),
+        })
+    }
+
+    fn error_kinds(node: &DocumentNode) -> Vec<(Vec<usize>, DocumentValidationErrorKind)> {
+        node.validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|err| (err.path, err.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_ok() {
+        let table = create_node(
+            DocumentNodeTag::Table { caption: None },
+            vec![create_node(
+                DocumentNodeTag::TableRow,
+                vec![create_node(
+                    DocumentNodeTag::TableCell,
+                    vec![create_text("cell")],
+                )],
+            )],
+        );
+        let list = create_node(
+            DocumentNodeTag::List { is_ordered: false },
+            vec![create_node(
+                DocumentNodeTag::ListItem,
+                vec![create_text("item")],
+            )],
+        );
+        let root = create_node(DocumentNodeTag::Root, vec![table, list]);
+
+        assert!(root.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_table_cell_outside_row() {
+        let table = create_node(
+            DocumentNodeTag::Table { caption: None },
+            vec![create_node(DocumentNodeTag::TableCell, Vec::new())],
+        );
+        let root = create_node(DocumentNodeTag::Root, vec![table]);
+
+        assert_eq!(
+            error_kinds(&root),
+            vec![(vec![0, 0], DocumentValidationErrorKind::TableCellOutsideRow)]
+        );
+    }
+
+    #[test]
+    fn test_validate_table_row_outside_table() {
+        let row = create_node(
+            DocumentNodeTag::TableRow,
+            vec![create_node(DocumentNodeTag::TableCell, Vec::new())],
+        );
+        let root = create_node(DocumentNodeTag::Root, vec![row]);
+
+        assert_eq!(
+            error_kinds(&root),
+            vec![(vec![0], DocumentValidationErrorKind::TableRowOutsideTable)]
+        );
+    }
+
+    #[test]
+    fn test_validate_list_item_outside_list() {
+        let quote = create_node(
+            DocumentNodeTag::Quote,
+            vec![create_node(DocumentNodeTag::ListItem, Vec::new())],
+        );
+        let root = create_node(DocumentNodeTag::Root, vec![quote]);
+
+        assert_eq!(
+            error_kinds(&root),
+            vec![(vec![0, 0], DocumentValidationErrorKind::ListItemOutsideList)]
+        );
+    }
+
+    #[test]
+    fn test_validate_column_outside_columns() {
+        let columns = create_node(
+            DocumentNodeTag::Columns,
+            vec![create_node(DocumentNodeTag::Column, vec![create_text("a")])],
+        );
+        let stray = create_node(DocumentNodeTag::Column, Vec::new());
+        let root = create_node(DocumentNodeTag::Root, vec![columns, stray]);
+
+        assert_eq!(
+            error_kinds(&root),
+            vec![(vec![1], DocumentValidationErrorKind::ColumnOutsideColumns)]
+        );
+    }
+
+    #[test]
+    fn test_validate_block_inside_inline() {
+        let inline = create_node(
+            DocumentNodeTag::Inline {
+                style: None,
+                link: None,
+            },
+            vec![
+                create_text("text"),
+                create_node(DocumentNodeTag::Paragraph, Vec::new()),
+            ],
+        );
+        let root = create_node(DocumentNodeTag::Root, vec![inline]);
+
+        assert_eq!(
+            error_kinds(&root),
+            vec![(vec![0, 1], DocumentValidationErrorKind::BlockInsideInline)]
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_all_violations() {
+        let root = create_node(
+            DocumentNodeTag::Root,
+            vec![
+                create_node(DocumentNodeTag::ListItem, Vec::new()),
+                create_node(DocumentNodeTag::TableCell, Vec::new()),
+            ],
+        );
+
+        assert_eq!(
+            error_kinds(&root),
+            vec![
+                (vec![0], DocumentValidationErrorKind::ListItemOutsideList),
+                (vec![1], DocumentValidationErrorKind::TableCellOutsideRow),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_depth() {
+        let tree = create_deep_tree(3);
+        assert!(tree.check_depth(3).is_ok());
+        assert_eq!(
+            tree.check_depth(2),
+            Err(DocumentTooDeepError { max_depth: 2 })
+        );
+    }
+
+    #[test]
+    fn test_validate_too_deep() {
+        let tree = create_deep_tree(3);
+        assert!(tree.validate_with_max_depth(3).is_ok());
+
+        let errors = tree.validate_with_max_depth(2).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![DocumentValidationError {
+                path: vec![0, 0],
+                kind: DocumentValidationErrorKind::TooDeep { max_depth: 2 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_validate_pathologically_deep() {
+        let tree = create_deep_tree(1_000_000);
+
+        let errors = tree.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path.len(), crate::DEFAULT_MAX_DEPTH);
+
+        assert!(tree.check_depth(crate::DEFAULT_MAX_DEPTH).is_err());
+        #[cfg(feature = "render")]
+        assert!(crate::render::html::render_html(&tree).is_err());
+    }
+}
@@ -7,7 +7,8 @@ use super::models::BlockVariants;
 /// A normalized block tree differs from a raw block tree (see [`RawBlockTree`]) in:
 /// - A root node that represents the entire page is added in the normalized block tree;
 /// - Adjacent blocks that represent list items of the same list type are grouped into special list node in the
-///   normalized block tree.
+///   normalized block tree;
+/// - Synced blocks are replaced by their children in the normalized block tree.
 ///
 /// To convert a raw block tree into a block tree, use the [`normalize`] function.
 #[derive(Clone, Debug)]
@@ -69,6 +70,58 @@ impl BlockTree {
             None
         }
     }
+
+    /// Visit all nodes in the block tree rooted at this node in pre-order, including list nodes.
+    pub fn visit<V>(&self, visitor: &mut V)
+    where
+        V: ?Sized + BlockTreeVisitor,
+    {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            visitor.visit(node);
+            stack.extend(node.children.iter().rev());
+        }
+    }
+
+    /// Visit all nodes in the block tree rooted at this node in pre-order, including list nodes.
+    pub fn visit_mut<V>(&mut self, visitor: &mut V)
+    where
+        V: ?Sized + BlockTreeVisitor,
+    {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            visitor.visit_mut(node);
+            stack.extend(node.children.iter_mut().rev());
+        }
+    }
+
+    /// Remove all descendants of this node for which the given predicate returns `false`, together with their
+    /// subtrees.
+    ///
+    /// Descendants are tested from the top down, so the predicate never sees the children of a removed node. List
+    /// nodes whose items are all removed are kept as empty list nodes.
+    pub fn retain_children<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&BlockTree) -> bool,
+    {
+        self.retain_children_impl(&mut predicate);
+    }
+
+    fn retain_children_impl<F>(&mut self, predicate: &mut F)
+    where
+        F: FnMut(&BlockTree) -> bool,
+    {
+        self.children.retain(|child| predicate(child));
+        for child in &mut self.children {
+            child.retain_children_impl(predicate);
+        }
+    }
+}
+
+/// Visitor that visits each node within a block tree.
+pub trait BlockTreeVisitor {
+    fn visit(&mut self, _node: &BlockTree) {}
+    fn visit_mut(&mut self, _node: &mut BlockTree) {}
 }
 
 /// Variants of a normalized block tree node.
@@ -137,6 +190,8 @@ impl RawBlockTree {
 }
 
 /// Normalize raw block trees into a block tree.
+///
+/// Archived blocks are deleted blocks that Notion has not purged yet, so they are dropped together with their children.
 pub fn normalize(raw: Vec<RawBlockTree>) -> BlockTree {
     let mut root = BlockTree::new_page_root();
     normalize_as_children(raw, &mut root);
@@ -146,7 +201,7 @@ pub fn normalize(raw: Vec<RawBlockTree>) -> BlockTree {
 fn normalize_as_children(raw: Vec<RawBlockTree>, root: &mut BlockTree) {
     let mut active_tree: Option<BlockTree> = None;
 
-    for raw_tree in raw {
+    for raw_tree in inline_synced_blocks(raw) {
         let mut tree = BlockTree::new_block(raw_tree.block);
         normalize_as_children(raw_tree.children, &mut tree);
 
@@ -181,3 +236,140 @@ fn normalize_as_children(raw: Vec<RawBlockTree>, root: &mut BlockTree) {
         root.children.push(tree);
     }
 }
+
+/// Replace the synced blocks in the given raw block trees with their children, and drop the archived blocks.
+///
+/// Archived blocks are dropped before list items are grouped, so list items around an archived block stay in the same
+/// list.
+fn inline_synced_blocks(raw: Vec<RawBlockTree>) -> Vec<RawBlockTree> {
+    let mut inlined = Vec::with_capacity(raw.len());
+    for raw_tree in raw {
+        if raw_tree.block.archived {
+            continue;
+        }
+
+        if let BlockVariants::SyncedBlock { .. } = &raw_tree.block.variant {
+            inlined.extend(inline_synced_blocks(raw_tree.children));
+        } else {
+            inlined.push(raw_tree);
+        }
+    }
+    inlined
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn create_block(ty: &str, archived: bool) -> Block {
+        serde_json::from_value(json!({
+            "id": "c0a8e0b4-2a8e-4c5e-9f1e-3b5f4c1d2e3f",
+            "created_time": "2022-10-01T00:00:00.000Z",
+            "last_edited_time": "2022-10-01T00:00:00.000Z",
+            "archived": archived,
+            "has_children": false,
+            "type": ty,
+            ty: { "rich_text": [], "color": "default" },
+        }))
+        .unwrap()
+    }
+
+    /// Create a tree with a paragraph, a bulleted list of two items where the second one is archived and has a nested
+    /// paragraph, and another archived paragraph.
+    fn create_test_tree() -> BlockTree {
+        let mut archived_item = BlockTree::new_block(create_block("bulleted_list_item", true));
+        archived_item
+            .children
+            .push(BlockTree::new_block(create_block("paragraph", false)));
+        let mut list = BlockTree::new_bulleted_list();
+        list.children.push(BlockTree::new_block(create_block(
+            "bulleted_list_item",
+            false,
+        )));
+        list.children.push(archived_item);
+
+        let mut root = BlockTree::new_page_root();
+        root.children
+            .push(BlockTree::new_block(create_block("paragraph", false)));
+        root.children.push(list);
+        root.children
+            .push(BlockTree::new_block(create_block("paragraph", true)));
+        root
+    }
+
+    #[derive(Default)]
+    struct NodeCounter {
+        blocks: usize,
+        lists: usize,
+        roots: usize,
+    }
+
+    impl BlockTreeVisitor for NodeCounter {
+        fn visit(&mut self, node: &BlockTree) {
+            match &node.variant {
+                BlockTreeNodeVariants::PageRoot => self.roots += 1,
+                BlockTreeNodeVariants::Block(_) => self.blocks += 1,
+                BlockTreeNodeVariants::BulletedList | BlockTreeNodeVariants::NumberedList => {
+                    self.lists += 1
+                }
+            }
+        }
+
+        fn visit_mut(&mut self, node: &mut BlockTree) {
+            self.visit(node);
+        }
+    }
+
+    #[test]
+    fn test_visit_counts_all_nodes() {
+        let mut tree = create_test_tree();
+
+        let mut counter = NodeCounter::default();
+        tree.visit(&mut counter);
+        assert_eq!(counter.roots, 1);
+        assert_eq!(counter.lists, 1);
+        assert_eq!(counter.blocks, 5);
+
+        let mut counter = NodeCounter::default();
+        tree.visit_mut(&mut counter);
+        assert_eq!(counter.roots + counter.lists + counter.blocks, 7);
+    }
+
+    #[test]
+    fn test_retain_children_removes_archived_blocks() {
+        let mut tree = create_test_tree();
+        tree.retain_children(|node| node.block().is_none_or(|block| !block.archived));
+
+        let mut counter = NodeCounter::default();
+        tree.visit(&mut counter);
+        assert_eq!(counter.lists, 1);
+        assert_eq!(counter.blocks, 2);
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.children[1].children.len(), 1);
+    }
+
+    #[test]
+    fn test_normalize_skips_archived_blocks() {
+        let mut archived_item = RawBlockTree::new(create_block("bulleted_list_item", true));
+        archived_item
+            .children
+            .push(RawBlockTree::new(create_block("paragraph", false)));
+        let tree = normalize(vec![
+            RawBlockTree::new(create_block("bulleted_list_item", false)),
+            archived_item,
+            RawBlockTree::new(create_block("bulleted_list_item", false)),
+            RawBlockTree::new(create_block("paragraph", true)),
+        ]);
+
+        assert_eq!(tree.children.len(), 1);
+        let list = &tree.children[0];
+        assert!(list.variant.is_bulleted_list());
+        assert_eq!(list.children.len(), 2);
+        assert!(list
+            .children
+            .iter()
+            .all(|item| !item.block().unwrap().archived && item.children.is_empty()));
+    }
+}
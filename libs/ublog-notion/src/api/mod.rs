@@ -1,19 +1,24 @@
 pub mod block_tree;
 pub mod models;
-mod requests;
+pub(crate) mod requests;
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use async_recursion::async_recursion;
 use reqwest::Method;
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
 use spdlog::Logger;
+use tokio::sync::{OnceCell, Semaphore};
 
 use crate::api::block_tree::RawBlockTree;
-use crate::api::models::{Block, Database, Page};
-use crate::api::requests::NotionRequestExecutor;
+use crate::api::models::{Block, BlockVariants, Database, Page, SyncedBlock, SyncedFrom};
+use crate::api::requests::{NotionRequestExecutor, DEFAULT_REQUEST_TIMEOUT};
 
 /// Result type of Notion APIs.
 pub type NotionApiResult<T> = Result<T, NotionApiError>;
@@ -21,23 +26,84 @@ pub type NotionApiResult<T> = Result<T, NotionApiError>;
 /// Provide access to the Notion public API.
 pub struct NotionApi {
     logger: Logger,
+    base_url: String,
     exec: NotionRequestExecutor,
+    block_fetch_semaphore: Semaphore,
 }
 
 impl NotionApi {
     const BASE_URL: &'static str = "https://api.notion.com";
 
+    /// The default maximum number of block children requests that are sent concurrently while fetching block trees.
+    pub const DEFAULT_MAX_CONCURRENT_BLOCK_FETCHES: usize = 8;
+
     /// Create a new NotionApi with the given access token.
     pub fn new<T>(token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self::new_with_policy(token, RetryPolicy::default())
+    }
+
+    /// Create a new NotionApi with the given access token and the given policy for retrying failed requests.
+    pub fn new_with_policy<T>(token: T, retry_policy: RetryPolicy) -> Self
+    where
+        T: Into<String>,
+    {
+        Self::from_parts(
+            token,
+            reqwest::Client::new(),
+            retry_policy,
+            Some(DEFAULT_REQUEST_TIMEOUT),
+        )
+    }
+
+    /// Create a new NotionApi with the given access token that sends requests through the given HTTP client.
+    ///
+    /// This allows callers to configure proxies, connection pools and timeouts of the client. The access token and the
+    /// `Notion-Version` header are still applied to each request, so the client does not need to set them. Requests
+    /// only carry a timeout of their own if one is set through [`set_request_timeout`](Self::set_request_timeout), so
+    /// the timeout configured on the client applies otherwise.
+    pub fn with_client<T>(token: T, client: reqwest::Client) -> Self
+    where
+        T: Into<String>,
+    {
+        Self::from_parts(token, client, RetryPolicy::default(), None)
+    }
+
+    fn from_parts<T>(
+        token: T,
+        client: reqwest::Client,
+        retry_policy: RetryPolicy,
+        request_timeout: Option<Duration>,
+    ) -> Self
     where
         T: Into<String>,
     {
         Self {
             logger: crate::create_logger("NotionApi"),
-            exec: NotionRequestExecutor::new(token),
+            base_url: String::from(Self::BASE_URL),
+            exec: NotionRequestExecutor::new(token, client, retry_policy, request_timeout),
+            block_fetch_semaphore: Semaphore::new(Self::DEFAULT_MAX_CONCURRENT_BLOCK_FETCHES),
         }
     }
 
+    /// Set the timeout of each request sent to Notion, overriding the timeout of the HTTP client if any. Requests time
+    /// out after 30 seconds by default, unless the `NotionApi` is created [`with_client`](Self::with_client).
+    ///
+    /// A request that times out fails with [`NotionApiError::Timeout`] and is not retried.
+    pub fn set_request_timeout(&mut self, timeout: Duration) {
+        self.exec.set_request_timeout(timeout);
+    }
+
+    /// Set the maximum number of block children requests that are sent concurrently while fetching block trees,
+    /// which defaults to [`DEFAULT_MAX_CONCURRENT_BLOCK_FETCHES`](Self::DEFAULT_MAX_CONCURRENT_BLOCK_FETCHES).
+    ///
+    /// The limit is shared by all block trees fetched through this `NotionApi`. A limit of 0 is taken as 1.
+    pub fn set_max_concurrent_block_fetches(&mut self, max_concurrent_fetches: usize) {
+        self.block_fetch_semaphore = Semaphore::new(max_concurrent_fetches.max(1));
+    }
+
     /// Get Notion database schema.
     pub async fn get_database<T>(&self, database_id: T) -> NotionApiResult<Database>
     where
@@ -46,7 +112,7 @@ impl NotionApi {
         let database_id = database_id.as_ref();
         spdlog::trace!(logger: self.logger, "get database: {}", database_id);
 
-        let url = format!("{}/v1/databases/{}", Self::BASE_URL, database_id);
+        let url = format!("{}/v1/databases/{}", self.base_url, database_id);
         let request = self.exec.build_notion_request(Method::GET, url).build()?;
         let db = self.exec.execute(request).await?.json().await?;
         Ok(db)
@@ -64,7 +130,7 @@ impl NotionApi {
         let database_id = database_id.as_ref();
         spdlog::trace!(logger: self.logger, "query database: {}", database_id);
 
-        let url = format!("{}/v1/databases/{}/query", Self::BASE_URL, database_id);
+        let url = format!("{}/v1/databases/{}/query", self.base_url, database_id);
 
         self.get_paginated_list(|pagination| {
             let params = params.clone();
@@ -91,7 +157,7 @@ impl NotionApi {
         let block_id = block_id.as_ref();
         spdlog::trace!(logger: self.logger, "get block: {}", block_id);
 
-        let url = format!("{}/v1/blocks/{}", Self::BASE_URL, block_id);
+        let url = format!("{}/v1/blocks/{}", self.base_url, block_id);
         let request = self.exec.build_notion_request(Method::GET, url).build()?;
         let block = self.exec.execute(request).await?.json().await?;
         Ok(block)
@@ -105,7 +171,7 @@ impl NotionApi {
         let block_id = block_id.as_ref();
         spdlog::trace!(logger: self.logger, "get block children: {}", block_id);
 
-        let url = format!("{}/v1/blocks/{}/children", Self::BASE_URL, block_id);
+        let url = format!("{}/v1/blocks/{}/children", self.base_url, block_id);
 
         self.get_paginated_list(|pagination| {
             let url = url.clone();
@@ -137,7 +203,8 @@ impl NotionApi {
         spdlog::trace!(logger: self.logger, "get block tree: {}", root_block_id);
 
         let root_block = self.get_block(root_block_id).await?;
-        self.get_block_tree_impl(root_block).await
+        let cache = BlockChildrenCache::default();
+        self.get_block_tree_impl(root_block, &cache).await
     }
 
     /// Get a list of raw block trees that represents the whole contents of the specified page.
@@ -148,10 +215,11 @@ impl NotionApi {
         let page_id = page_id.as_ref();
         spdlog::trace!(logger: self.logger, "get page content: {}", page_id);
 
-        let direct_blocks = self.get_block_children(page_id).await?;
+        let cache = BlockChildrenCache::default();
+        let direct_blocks = self.get_block_children_cached(page_id, &cache).await?;
         let get_tree_futures = direct_blocks
             .into_iter()
-            .map(|blk| self.get_block_tree_impl(blk));
+            .map(|blk| self.get_block_tree_impl(blk, &cache));
         futures::future::join_all(get_tree_futures)
             .await
             .into_iter()
@@ -185,13 +253,66 @@ impl NotionApi {
         Ok(results)
     }
 
+    /// Get child blocks of the specified block, fetching them from Notion only if they are not in the given cache.
+    ///
+    /// Concurrent calls for the same block share a single fetch: calls that find a fetch in flight wait for its
+    /// result instead of sending another request.
+    async fn get_block_children_cached(
+        &self,
+        block_id: &str,
+        cache: &BlockChildrenCache,
+    ) -> NotionApiResult<Vec<Block>> {
+        let cell = {
+            let mut cache = cache.lock().unwrap();
+            match cache.get(block_id) {
+                Some(cell) => {
+                    spdlog::trace!(logger: self.logger, "block children cache hit: {}", block_id);
+                    cell.clone()
+                }
+                None => {
+                    let cell = Arc::new(OnceCell::new());
+                    cache.insert(String::from(block_id), cell.clone());
+                    cell
+                }
+            }
+        };
+
+        let children = cell
+            .get_or_try_init(|| async {
+                // The permit is only held while fetching the children, so that the fetches of the child trees, which
+                // acquire permits of their own, can never wait for their parents.
+                let _permit = self.block_fetch_semaphore.acquire().await.unwrap();
+                self.get_block_children(block_id).await
+            })
+            .await?;
+        Ok(children.clone())
+    }
+
     #[async_recursion]
-    async fn get_block_tree_impl(&self, root_block: Block) -> NotionApiResult<RawBlockTree> {
-        let child_blocks = self.get_block_children(&root_block.id).await?;
+    async fn get_block_tree_impl(
+        &self,
+        root_block: Block,
+        cache: &BlockChildrenCache,
+    ) -> NotionApiResult<RawBlockTree> {
+        // The children of a duplicate synced block are the children of the original synced block.
+        let children_source = match &root_block.variant {
+            BlockVariants::SyncedBlock {
+                synced_block:
+                    SyncedBlock {
+                        synced_from: Some(SyncedFrom { block_id }),
+                    },
+            } => Some(block_id),
+            _ if root_block.has_children => Some(&root_block.id),
+            _ => None,
+        };
+        let child_blocks = match children_source {
+            Some(block_id) => self.get_block_children_cached(block_id, cache).await?,
+            None => Vec::new(),
+        };
 
         let mut get_child_tree_futures = Vec::with_capacity(child_blocks.len());
         for child_blk in child_blocks {
-            get_child_tree_futures.push(self.get_block_tree_impl(child_blk));
+            get_child_tree_futures.push(self.get_block_tree_impl(child_blk, cache));
         }
 
         let mut tree = RawBlockTree::new(root_block);
@@ -212,12 +333,61 @@ impl Debug for NotionApi {
     }
 }
 
+/// Specify how requests rejected by Notion with a `429` or `5xx` status are retried.
+///
+/// The wait time before the n-th retry is `base_backoff * 2^(n - 1)`, capped at `max_backoff`, unless Notion
+/// specifies the wait time in a `Retry-After` header. A small random delay is added to the wait time.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of retries of a single request. The last error is returned after this many retries fail.
+    pub max_retries: u32,
+
+    /// The wait time before the first retry.
+    pub base_backoff: Duration,
+
+    /// The maximum wait time before a retry.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Get the wait time before the given retry, which starts from 1.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 10,
+            base_backoff: Duration::from_secs(3),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Child blocks fetched within a single call to [`NotionApi`], keyed by the ID of their parent block.
+///
+/// The children of a block are fetched more than once within a call when an original synced block and its duplicates
+/// appear in the same page, since the duplicates take their children from the original block. Each entry is filled by
+/// the first fetch of the children, and later lookups, including those made while the fetch is in flight, wait for it.
+///
+/// The cache is created for each call that builds block trees and dropped when the call returns, so changes made in
+/// Notion between calls are always observed.
+type BlockChildrenCache = Mutex<HashMap<String, Arc<OnceCell<Vec<Block>>>>>;
+
 /// Error returned from Notion APIs.
 #[derive(Debug)]
 pub enum NotionApiError {
     /// Network errors.
     Network(reqwest::Error),
 
+    /// The request timed out.
+    Timeout(reqwest::Error),
+
     /// Notion errors.
     Notion(NotionError),
 }
@@ -226,6 +396,7 @@ impl Display for NotionApiError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Network(err) => write!(f, "network error: {}", err),
+            Self::Timeout(err) => write!(f, "request timed out: {}", err),
             Self::Notion(err) => write!(f, "notion error: {}", err),
         }
     }
@@ -235,7 +406,11 @@ impl Error for NotionApiError {}
 
 impl From<reqwest::Error> for NotionApiError {
     fn from(err: reqwest::Error) -> Self {
-        Self::Network(err)
+        if err.is_timeout() {
+            Self::Timeout(err)
+        } else {
+            Self::Network(err)
+        }
     }
 }
 
@@ -272,8 +447,7 @@ pub struct QueryDatabaseParams {
 }
 
 /// Query filter on Notion database entries.
-#[derive(Clone, Debug, Serialize)]
-#[serde(untagged)]
+#[derive(Clone, Debug)]
 pub enum QueryDatabaseFilter {
     /// Filter on a specific database property.
     Property(QueryDatabasePropertyFilter),
@@ -283,6 +457,36 @@ pub enum QueryDatabaseFilter {
 
     /// Compound filter `and`.
     And(Vec<QueryDatabaseFilter>),
+
+    /// Filter on the time when database entries are last edited.
+    LastEditedTime(QueryDatabaseDateFilter),
+}
+
+impl Serialize for QueryDatabaseFilter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Property filters are serialized as is, while compound filters are serialized into objects whose only key is
+        // the name of the compound filter, e.g. `{"and": [...]}`.
+        // Timestamp filters are serialized into objects that name the timestamp, e.g.
+        // `{"timestamp": "last_edited_time", "last_edited_time": {...}}`.
+        let (key, filters) = match self {
+            Self::Property(filter) => return filter.serialize(serializer),
+            Self::Or(filters) => ("or", filters),
+            Self::And(filters) => ("and", filters),
+            Self::LastEditedTime(filter) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("timestamp", "last_edited_time")?;
+                map.serialize_entry("last_edited_time", filter)?;
+                return map.end();
+            }
+        };
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(key, filters)?;
+        map.end()
+    }
 }
 
 /// Specify how database entries should be sorted.
@@ -346,6 +550,81 @@ impl QueryDatabasePropertyFilter {
             ),
         }
     }
+
+    /// Create a new `QueryDatabasePropertyFilter` that selects database entries whose specified select property is set
+    /// to the specified option.
+    pub fn select_equals<T1, T2>(property_name: T1, option: T2) -> Self
+    where
+        T1: Into<String>,
+        T2: Into<String>,
+    {
+        Self {
+            property: property_name.into(),
+            variant: QueryDatabasePropertyFilterVariants::Select(
+                QueryDatabaseSelectFilter::Equals(option.into()),
+            ),
+        }
+    }
+
+    /// Create a new `QueryDatabasePropertyFilter` that selects database entries whose specified multi-select property
+    /// contains the specified option.
+    pub fn multi_select_contains<T1, T2>(property_name: T1, option: T2) -> Self
+    where
+        T1: Into<String>,
+        T2: Into<String>,
+    {
+        Self {
+            property: property_name.into(),
+            variant: QueryDatabasePropertyFilterVariants::MultiSelect(
+                QueryDatabaseMultiSelectFilter::Contains(option.into()),
+            ),
+        }
+    }
+
+    /// Create a new `QueryDatabasePropertyFilter` that selects database entries whose specified date property is on or
+    /// after the specified ISO 8601 date.
+    pub fn date_on_or_after<T1, T2>(property_name: T1, date: T2) -> Self
+    where
+        T1: Into<String>,
+        T2: Into<String>,
+    {
+        Self {
+            property: property_name.into(),
+            variant: QueryDatabasePropertyFilterVariants::Date(QueryDatabaseDateFilter::OnOrAfter(
+                date.into(),
+            )),
+        }
+    }
+
+    /// Create a new `QueryDatabasePropertyFilter` that selects database entries whose specified date property is
+    /// before the specified ISO 8601 date.
+    pub fn date_before<T1, T2>(property_name: T1, date: T2) -> Self
+    where
+        T1: Into<String>,
+        T2: Into<String>,
+    {
+        Self {
+            property: property_name.into(),
+            variant: QueryDatabasePropertyFilterVariants::Date(QueryDatabaseDateFilter::Before(
+                date.into(),
+            )),
+        }
+    }
+
+    /// Create a new `QueryDatabasePropertyFilter` that selects database entries whose specified status property is
+    /// set to the specified status.
+    pub fn status_equals<T1, T2>(property_name: T1, status: T2) -> Self
+    where
+        T1: Into<String>,
+        T2: Into<String>,
+    {
+        Self {
+            property: property_name.into(),
+            variant: QueryDatabasePropertyFilterVariants::Status(
+                QueryDatabaseStatusFilter::Equals(status.into()),
+            ),
+        }
+    }
 }
 
 /// Provide actual filter definitions corresponding to different types of properties.
@@ -354,6 +633,22 @@ pub enum QueryDatabasePropertyFilterVariants {
     /// Filter on a checkbox property.
     #[serde(rename = "checkbox")]
     Checkbox(QueryDatabaseCheckboxFilter),
+
+    /// Filter on a status property.
+    #[serde(rename = "status")]
+    Status(QueryDatabaseStatusFilter),
+
+    /// Filter on a select property.
+    #[serde(rename = "select")]
+    Select(QueryDatabaseSelectFilter),
+
+    /// Filter on a multi-select property.
+    #[serde(rename = "multi_select")]
+    MultiSelect(QueryDatabaseMultiSelectFilter),
+
+    /// Filter on a date property.
+    #[serde(rename = "date")]
+    Date(QueryDatabaseDateFilter),
 }
 
 /// A database property filter that filters on a checkbox property.
@@ -365,6 +660,44 @@ pub enum QueryDatabaseCheckboxFilter {
     DoesNotEqual(bool),
 }
 
+/// A database property filter that filters on a select property.
+#[derive(Clone, Debug, Serialize)]
+pub enum QueryDatabaseSelectFilter {
+    #[serde(rename = "equals")]
+    Equals(String),
+    #[serde(rename = "does_not_equal")]
+    DoesNotEqual(String),
+}
+
+/// A database property filter that filters on a multi-select property.
+#[derive(Clone, Debug, Serialize)]
+pub enum QueryDatabaseMultiSelectFilter {
+    #[serde(rename = "contains")]
+    Contains(String),
+    #[serde(rename = "does_not_contain")]
+    DoesNotContain(String),
+}
+
+/// A database property filter that filters on a date property.
+///
+/// Dates are given as ISO 8601 strings, e.g. `2022-10-01` or `2022-10-01T12:00:00Z`.
+#[derive(Clone, Debug, Serialize)]
+pub enum QueryDatabaseDateFilter {
+    #[serde(rename = "on_or_after")]
+    OnOrAfter(String),
+    #[serde(rename = "before")]
+    Before(String),
+}
+
+/// A database property filter that filters on a status property.
+#[derive(Clone, Debug, Serialize)]
+pub enum QueryDatabaseStatusFilter {
+    #[serde(rename = "equals")]
+    Equals(String),
+    #[serde(rename = "does_not_equal")]
+    DoesNotEqual(String),
+}
+
 #[derive(Clone, Debug, Serialize)]
 struct NotionPagination {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -387,3 +720,248 @@ struct QueryDatabasePayload {
     #[serde(flatten)]
     params: QueryDatabaseParams,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use hyper::Body;
+
+    use super::*;
+
+    fn create_block_json(id: &str, has_children: bool) -> String {
+        format!(
+            r#"{{"id":"{}","created_time":"","last_edited_time":"","archived":false,"has_children":{},"type":"divider","divider":{{}}}}"#,
+            id, has_children
+        )
+    }
+
+    #[tokio::test]
+    async fn test_get_page_content_fetch_children_once() {
+        let requested_paths = Arc::new(Mutex::new(Vec::new()));
+        let addr = {
+            let requested_paths = requested_paths.clone();
+            crate::api::requests::tests::serve(move |req| {
+                let path = req.uri().path().to_owned();
+                let children = match path.as_str() {
+                    "/v1/blocks/page/children" => {
+                        vec![create_block_json("a", true), create_block_json("b", false)]
+                    }
+                    "/v1/blocks/a/children" => vec![create_block_json("c", false)],
+                    _ => Vec::new(),
+                };
+                requested_paths.lock().unwrap().push(path);
+
+                let body = format!(r#"{{"has_more":false,"results":[{}]}}"#, children.join(","));
+                hyper::Response::new(Body::from(body))
+            })
+        };
+
+        let mut api = NotionApi::new("token");
+        api.base_url = format!("http://{}", addr);
+
+        let trees = api.get_page_content("page").await.unwrap();
+        assert_eq!(trees.len(), 2);
+        assert_eq!(trees[0].children.len(), 1);
+        assert_eq!(trees[0].children[0].block.id, "c");
+        assert!(trees[1].children.is_empty());
+
+        let mut requested_paths = requested_paths.lock().unwrap().clone();
+        requested_paths.sort();
+        assert_eq!(
+            requested_paths,
+            vec!["/v1/blocks/a/children", "/v1/blocks/page/children"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_page_content_synced_block_duplicate() {
+        let requested_paths = Arc::new(Mutex::new(Vec::new()));
+        let addr = {
+            let requested_paths = requested_paths.clone();
+            crate::api::requests::tests::serve(move |req| {
+                let path = req.uri().path().to_owned();
+                let children = match path.as_str() {
+                    "/v1/blocks/page/children" => vec![String::from(
+                        r#"{"id":"dup","created_time":"","last_edited_time":"","archived":false,"has_children":true,"type":"synced_block","synced_block":{"synced_from":{"type":"block_id","block_id":"orig"}}}"#,
+                    )],
+                    "/v1/blocks/orig/children" => vec![create_block_json("c", false)],
+                    _ => Vec::new(),
+                };
+                requested_paths.lock().unwrap().push(path);
+
+                let body = format!(r#"{{"has_more":false,"results":[{}]}}"#, children.join(","));
+                hyper::Response::new(Body::from(body))
+            })
+        };
+
+        let mut api = NotionApi::new("token");
+        api.base_url = format!("http://{}", addr);
+
+        let trees = api.get_page_content("page").await.unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].children.len(), 1);
+        assert_eq!(trees[0].children[0].block.id, "c");
+
+        let requested_paths = requested_paths.lock().unwrap().clone();
+        assert_eq!(
+            requested_paths,
+            vec!["/v1/blocks/page/children", "/v1/blocks/orig/children"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_page_content_synced_block_original_and_duplicate() {
+        let requested_paths = Arc::new(Mutex::new(Vec::new()));
+        let addr = {
+            let requested_paths = requested_paths.clone();
+            crate::api::requests::tests::serve(move |req| {
+                let path = req.uri().path().to_owned();
+                let children = match path.as_str() {
+                    "/v1/blocks/page/children" => vec![
+                        String::from(
+                            r#"{"id":"orig","created_time":"","last_edited_time":"","archived":false,"has_children":true,"type":"synced_block","synced_block":{"synced_from":null}}"#,
+                        ),
+                        String::from(
+                            r#"{"id":"dup","created_time":"","last_edited_time":"","archived":false,"has_children":true,"type":"synced_block","synced_block":{"synced_from":{"type":"block_id","block_id":"orig"}}}"#,
+                        ),
+                    ],
+                    "/v1/blocks/orig/children" => vec![create_block_json("c", false)],
+                    _ => Vec::new(),
+                };
+                requested_paths.lock().unwrap().push(path);
+
+                let body = format!(r#"{{"has_more":false,"results":[{}]}}"#, children.join(","));
+                hyper::Response::new(Body::from(body))
+            })
+        };
+
+        let mut api = NotionApi::new("token");
+        api.base_url = format!("http://{}", addr);
+
+        let trees = api.get_page_content("page").await.unwrap();
+        assert_eq!(trees.len(), 2);
+        for tree in &trees {
+            assert_eq!(tree.children.len(), 1);
+            assert_eq!(tree.children[0].block.id, "c");
+        }
+
+        // The children of the original synced block are fetched once and shared with its duplicate.
+        let requested_paths = requested_paths.lock().unwrap().clone();
+        assert_eq!(
+            requested_paths,
+            vec!["/v1/blocks/page/children", "/v1/blocks/orig/children"]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_get_page_content_bounded_concurrency() {
+        const MAX_CONCURRENT_FETCHES: usize = 2;
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let addr = {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            crate::api::requests::tests::serve(move |req| {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+
+                let children = if req.uri().path() == "/v1/blocks/page/children" {
+                    (0..8)
+                        .map(|idx| create_block_json(&format!("block{}", idx), true))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                // Keep the request in flight for a while so that concurrent requests overlap.
+                std::thread::sleep(Duration::from_millis(20));
+
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                let body = format!(r#"{{"has_more":false,"results":[{}]}}"#, children.join(","));
+                hyper::Response::new(Body::from(body))
+            })
+        };
+
+        let mut api = NotionApi::new("token");
+        api.base_url = format!("http://{}", addr);
+        api.set_max_concurrent_block_fetches(MAX_CONCURRENT_FETCHES);
+
+        let trees = api.get_page_content("page").await.unwrap();
+        assert_eq!(trees.len(), 8);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= MAX_CONCURRENT_FETCHES);
+    }
+
+    #[test]
+    fn test_serialize_property_filters() {
+        let to_json = |filter: QueryDatabasePropertyFilter| serde_json::to_value(filter).unwrap();
+
+        assert_eq!(
+            to_json(QueryDatabasePropertyFilter::checkbox_checked("published")),
+            serde_json::json!({ "property": "published", "checkbox": { "equals": true } })
+        );
+        assert_eq!(
+            to_json(QueryDatabasePropertyFilter::select_equals(
+                "category", "rust"
+            )),
+            serde_json::json!({ "property": "category", "select": { "equals": "rust" } })
+        );
+        assert_eq!(
+            to_json(QueryDatabasePropertyFilter::multi_select_contains(
+                "tags", "async"
+            )),
+            serde_json::json!({ "property": "tags", "multi_select": { "contains": "async" } })
+        );
+        assert_eq!(
+            to_json(QueryDatabasePropertyFilter::date_on_or_after(
+                "update_date",
+                "2022-10-01"
+            )),
+            serde_json::json!({ "property": "update_date", "date": { "on_or_after": "2022-10-01" } })
+        );
+        assert_eq!(
+            to_json(QueryDatabasePropertyFilter::date_before(
+                "update_date",
+                "2022-10-01"
+            )),
+            serde_json::json!({ "property": "update_date", "date": { "before": "2022-10-01" } })
+        );
+    }
+
+    #[test]
+    fn test_serialize_compound_filter() {
+        let filter = QueryDatabaseFilter::And(vec![
+            QueryDatabaseFilter::Property(QueryDatabasePropertyFilter::checkbox_checked(
+                "published",
+            )),
+            QueryDatabaseFilter::Property(QueryDatabasePropertyFilter::select_equals(
+                "category", "rust",
+            )),
+        ]);
+
+        assert_eq!(
+            serde_json::to_value(filter).unwrap(),
+            serde_json::json!({
+                "and": [
+                    { "property": "published", "checkbox": { "equals": true } },
+                    { "property": "category", "select": { "equals": "rust" } }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize_last_edited_time_filter() {
+        let filter = QueryDatabaseFilter::LastEditedTime(QueryDatabaseDateFilter::OnOrAfter(
+            String::from("2022-10-01T12:00:00Z"),
+        ));
+
+        assert_eq!(
+            serde_json::to_value(filter).unwrap(),
+            serde_json::json!({
+                "timestamp": "last_edited_time",
+                "last_edited_time": { "on_or_after": "2022-10-01T12:00:00Z" }
+            })
+        );
+    }
+}
@@ -45,10 +45,10 @@ pub enum PropertyValue {
     RichText { rich_text: Vec<RichText> },
 
     #[serde(rename = "number")]
-    Number { number: f64 },
+    Number { number: Option<f64> },
 
     #[serde(rename = "select")]
-    Select { select: SelectPropertyValue },
+    Select { select: Option<SelectPropertyValue> },
 
     #[serde(rename = "multi_select")]
     MultiSelect {
@@ -56,10 +56,16 @@ pub enum PropertyValue {
     },
 
     #[serde(rename = "date")]
-    Date { date: DatePropertyValue },
+    Date { date: Option<DatePropertyValue> },
 
     #[serde(rename = "checkbox")]
     Checkbox { checkbox: bool },
+
+    #[serde(rename = "status")]
+    Status { status: SelectPropertyValue },
+
+    #[serde(rename = "url")]
+    Url { url: Option<String> },
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -163,11 +169,14 @@ pub enum BlockVariants {
     #[serde(rename = "numbered_list_item")]
     NumberedListItem { numbered_list_item: ListItemBlock },
 
+    #[serde(rename = "to_do")]
+    ToDo { to_do: ToDoBlock },
+
     #[serde(rename = "code")]
     Code { code: CodeBlock },
 
     #[serde(rename = "image")]
-    Image { image: File },
+    Image { image: ImageBlock },
 
     #[serde(rename = "equation")]
     Equation { equation: EquationBlock },
@@ -180,6 +189,24 @@ pub enum BlockVariants {
 
     #[serde(rename = "table_row")]
     TableRow { table_row: TableRowBlock },
+
+    #[serde(rename = "column_list")]
+    ColumnList { column_list: ColumnListBlock },
+
+    #[serde(rename = "column")]
+    Column { column: ColumnBlock },
+
+    #[serde(rename = "synced_block")]
+    SyncedBlock { synced_block: SyncedBlock },
+
+    #[serde(rename = "toggle")]
+    Toggle { toggle: ToggleBlock },
+
+    #[serde(rename = "bookmark")]
+    Bookmark { bookmark: BookmarkBlock },
+
+    #[serde(rename = "link_preview")]
+    LinkPreview { link_preview: LinkPreviewBlock },
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -213,6 +240,13 @@ pub struct ListItemBlock {
     pub color: String,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ToDoBlock {
+    pub rich_text: Vec<RichText>,
+    pub checked: bool,
+    pub color: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CodeBlock {
     pub rich_text: Vec<RichText>,
@@ -220,6 +254,14 @@ pub struct CodeBlock {
     pub language: String,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ImageBlock {
+    #[serde(default)]
+    pub caption: Vec<RichText>,
+    #[serde(flatten)]
+    pub file: File,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct EquationBlock {
     pub expression: String,
@@ -237,6 +279,42 @@ pub struct TableRowBlock {
     pub cells: Vec<Vec<RichText>>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ColumnListBlock {}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ColumnBlock {}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SyncedBlock {
+    /// The original synced block that this block duplicates, or `None` if this block is the original.
+    #[serde(default)]
+    pub synced_from: Option<SyncedFrom>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SyncedFrom {
+    pub block_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ToggleBlock {
+    pub rich_text: Vec<RichText>,
+    pub color: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BookmarkBlock {
+    pub url: String,
+    #[serde(default)]
+    pub caption: Vec<RichText>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LinkPreviewBlock {
+    pub url: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "type")]
 pub enum File {
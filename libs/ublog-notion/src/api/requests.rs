@@ -6,16 +6,28 @@ use reqwest::{Client, IntoUrl, Method, Request, RequestBuilder, Response, Status
 use serde::{Deserialize, Serialize};
 use spdlog::Logger;
 
-use crate::api::{NotionApiError, NotionError};
+use crate::api::{NotionApiError, NotionError, RetryPolicy};
+
+/// The default timeout of each request sent to Notion through an HTTP client built by ublog.
+pub(super) const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub(super) struct NotionRequestExecutor {
     logger: Logger,
     token: String,
     http_client: Client,
+    retry_policy: RetryPolicy,
+    /// Timeout applied to each request. Requests carry no timeout of their own if this is `None`, so that the timeout
+    /// configured on the HTTP client, if any, applies.
+    request_timeout: Option<Duration>,
 }
 
 impl NotionRequestExecutor {
-    pub(super) fn new<T>(token: T) -> Self
+    pub(super) fn new<T>(
+        token: T,
+        http_client: Client,
+        retry_policy: RetryPolicy,
+        request_timeout: Option<Duration>,
+    ) -> Self
     where
         T: Into<String>,
     {
@@ -23,10 +35,16 @@ impl NotionRequestExecutor {
         Self {
             logger: crate::create_logger("NotionRequestExecutor"),
             token,
-            http_client: Client::new(),
+            http_client,
+            retry_policy,
+            request_timeout,
         }
     }
 
+    pub(super) fn set_request_timeout(&mut self, timeout: Duration) {
+        self.request_timeout = Some(timeout);
+    }
+
     pub(super) fn build_notion_request<T>(&self, method: Method, url: T) -> RequestBuilder
     where
         T: IntoUrl,
@@ -34,10 +52,15 @@ impl NotionRequestExecutor {
         const NOTION_VERSION_HEADER_NAME: &str = "Notion-Version";
         const NOTION_VERSION_HEADER_VALUE: &str = "2022-06-28";
 
-        self.http_client
+        let builder = self
+            .http_client
             .request(method, url)
             .bearer_auth(&self.token)
-            .header(NOTION_VERSION_HEADER_NAME, NOTION_VERSION_HEADER_VALUE)
+            .header(NOTION_VERSION_HEADER_NAME, NOTION_VERSION_HEADER_VALUE);
+        match self.request_timeout {
+            Some(timeout) => builder.timeout(timeout),
+            None => builder,
+        }
     }
 
     pub(super) async fn execute(&self, mut req: Request) -> Result<Response, NotionApiError> {
@@ -46,6 +69,7 @@ impl NotionRequestExecutor {
 
         spdlog::debug!(logger: self.logger, "-> {} {}", method.as_str(), url);
 
+        let mut retry = 0;
         loop {
             let request_backup = req.try_clone().unwrap();
 
@@ -57,39 +81,38 @@ impl NotionRequestExecutor {
                 return Ok(response);
             }
 
-            match response_status {
-                StatusCode::TOO_MANY_REQUESTS => {
-                    self.handle_notion_rate_limit(response).await;
-                }
-                _ => {
-                    let err = self.extract_notion_error(response).await;
-                    return Err(err);
-                }
+            let retryable = response_status == StatusCode::TOO_MANY_REQUESTS
+                || response_status.is_server_error();
+            if !retryable || retry >= self.retry_policy.max_retries {
+                let err = self.extract_notion_error(response).await;
+                return Err(err);
             }
 
+            retry += 1;
+            self.wait_before_retry(response, retry).await;
+
             req = request_backup;
         }
     }
 
-    async fn handle_notion_rate_limit(&self, response: Response) {
+    async fn wait_before_retry(&self, response: Response, retry: u32) {
         // The Retry-After header value should gives the amount of time in seconds to be waited before sending
         // requests again.
-        const DEFAULT_WAIT_MILLISECONDS: u64 = 3000;
         const MAX_RANDOM_DELAY_MILLISECONDS: u64 = 200;
 
-        let mut retry_after_ms = response
+        let mut retry_after = response
             .headers()
             .get("Retry-After")
             .and_then(|hv| hv.to_str().ok())
             .and_then(|s| s.parse::<u64>().ok())
-            .map(|sec| sec * 1000)
-            .unwrap_or(DEFAULT_WAIT_MILLISECONDS);
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| self.retry_policy.backoff(retry));
 
         // Add a random delay to the wait time.
-        retry_after_ms += rand::thread_rng().gen_range(0..=MAX_RANDOM_DELAY_MILLISECONDS);
-
-        let retry_after = Duration::from_millis(retry_after_ms);
+        retry_after +=
+            Duration::from_millis(rand::thread_rng().gen_range(0..=MAX_RANDOM_DELAY_MILLISECONDS));
 
+        spdlog::debug!(logger: self.logger, "retry #{} after {:?}", retry, retry_after);
         tokio::time::sleep(retry_after).await;
     }
 
@@ -97,7 +120,7 @@ impl NotionRequestExecutor {
         let error_model: NotionErrorModel = match response.json().await {
             Ok(model) => model,
             Err(err) => {
-                return NotionApiError::Network(err);
+                return err.into();
             }
         };
 
@@ -110,6 +133,8 @@ impl Debug for NotionRequestExecutor {
         f.debug_struct("NotionRequestExecutor")
             .field("token", &self.token)
             .field("http_client", &self.http_client)
+            .field("retry_policy", &self.retry_policy)
+            .field("request_timeout", &self.request_timeout)
             .finish()
     }
 }
@@ -129,3 +154,174 @@ impl Into<NotionError> for NotionErrorModel {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Server};
+
+    use super::*;
+
+    /// Start a local HTTP server that responds to each request with the response produced by the given handler.
+    /// Returns the address of the server.
+    pub(crate) fn serve<F>(handler: F) -> SocketAddr
+    where
+        F: Fn(hyper::Request<Body>) -> hyper::Response<Body> + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        let make_svc = make_service_fn(move |_| {
+            let handler = handler.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let response = handler(req);
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        addr
+    }
+
+    /// Start a local server that responds with the given statuses in order, and then `200` for all later requests.
+    /// Returns the address of the server and the number of requests received so far.
+    fn start_mock_server(statuses: Vec<StatusCode>) -> (SocketAddr, Arc<AtomicUsize>) {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let addr = {
+            let counter = counter.clone();
+            serve(move |_| {
+                let idx = counter.fetch_add(1, Ordering::SeqCst);
+                let status = statuses.get(idx).copied().unwrap_or(StatusCode::OK);
+                let body = if status.is_success() {
+                    r#"{"ok":true}"#
+                } else {
+                    r#"{"code":"rate_limited","message":"slow down"}"#
+                };
+                hyper::Response::builder()
+                    .status(status)
+                    .body(Body::from(body))
+                    .unwrap()
+            })
+        };
+
+        (addr, counter)
+    }
+
+    fn create_executor(max_retries: u32) -> NotionRequestExecutor {
+        let policy = RetryPolicy {
+            max_retries,
+            base_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        };
+        NotionRequestExecutor::new(
+            "token",
+            Client::new(),
+            policy,
+            Some(DEFAULT_REQUEST_TIMEOUT),
+        )
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_execute_retry_rate_limit() {
+        let (addr, counter) = start_mock_server(vec![
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::TOO_MANY_REQUESTS,
+        ]);
+
+        let exec = create_executor(3);
+        let req = exec
+            .build_notion_request(Method::GET, format!("http://{}/", addr))
+            .build()
+            .unwrap();
+        let response = exec.execute(req).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_execute_retry_exhausted() {
+        let (addr, counter) = start_mock_server(vec![
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::TOO_MANY_REQUESTS,
+        ]);
+
+        let exec = create_executor(2);
+        let req = exec
+            .build_notion_request(Method::GET, format!("http://{}/", addr))
+            .build()
+            .unwrap();
+        let err = exec.execute(req).await.unwrap_err();
+
+        assert!(matches!(err, NotionApiError::Notion(err) if err.code == "rate_limited"));
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_build_notion_request_custom_client() {
+        let client = Client::builder().user_agent("ublog-test").build().unwrap();
+        let mut exec = NotionRequestExecutor::new("token", client, RetryPolicy::default(), None);
+        let req = exec
+            .build_notion_request(Method::GET, "https://api.notion.com/v1/blocks/id")
+            .build()
+            .unwrap();
+
+        assert_eq!(req.headers()["Notion-Version"], "2022-06-28");
+        assert_eq!(req.headers()["Authorization"], "Bearer token");
+        // The timeout configured on the custom client is not overridden.
+        assert_eq!(req.timeout(), None);
+
+        exec.set_request_timeout(Duration::from_secs(5));
+        let req = exec
+            .build_notion_request(Method::GET, "https://api.notion.com/v1/blocks/id")
+            .build()
+            .unwrap();
+        assert_eq!(req.timeout(), Some(&Duration::from_secs(5)));
+    }
+
+    #[tokio::test]
+    async fn test_execute_timeout() {
+        let make_svc = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|_| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok::<_, Infallible>(hyper::Response::new(Body::empty()))
+            }))
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let mut exec = create_executor(3);
+        exec.set_request_timeout(Duration::from_millis(100));
+        let req = exec
+            .build_notion_request(Method::GET, format!("http://{}/", addr))
+            .build()
+            .unwrap();
+        let err = exec.execute(req).await.unwrap_err();
+
+        assert!(matches!(err, NotionApiError::Timeout(_)));
+    }
+}
@@ -1,27 +1,43 @@
 pub mod schema;
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 
 use lazy_static::lazy_static;
+use reqwest::StatusCode;
 use spdlog::Logger;
 use ublog_data::models::{Post, Resource};
 use ublog_doc::{DocumentNode, DocumentNodeTag, DocumentNodeVisitor, DocumentResourceLink};
 use uuid::Uuid;
 
-use crate::api::{NotionApi, NotionApiError};
+use crate::api::{NotionApi, NotionApiError, RetryPolicy};
 
 lazy_static! {
     static ref LOGGER: Logger = crate::create_logger("NotionBlog");
 }
 
+/// The default timeout of each resource download request.
+const DEFAULT_RESOURCE_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The policy for retrying resource downloads that fail with transient errors.
+const RESOURCE_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_retries: 3,
+    base_backoff: Duration::from_secs(1),
+    max_backoff: Duration::from_secs(10),
+};
+
 /// Get a list of posts from the specified Notion database.
 ///
 /// The content of the returned posts are not fetched. To fetch the content of an individual post, use the
 /// [`get_post_content`] function.
+///
+/// Pages that have invalid property values are logged and skipped.
 pub async fn get_posts<T>(
     api: &NotionApi,
     posts_db_id: T,
+    filter: &PostsFilter,
 ) -> Result<Vec<NotionPost>, NotionBlogError>
 where
     T: AsRef<str>,
@@ -29,21 +45,30 @@ where
     let posts_db_id = posts_db_id.as_ref();
     spdlog::trace!(logger: LOGGER, "get posts: {}", posts_db_id);
 
-    crate::blog::schema::validate_posts_db_schema(api, posts_db_id).await?;
+    let posts_db = crate::blog::schema::validate_posts_db_schema(api, posts_db_id).await?;
     spdlog::debug!(
         logger: LOGGER,
         "successfully validated Notion blog database schema for database {}",
         posts_db_id
     );
 
-    let query_posts_params = crate::blog::schema::get_query_posts_db_params();
+    let query_posts_params = crate::blog::schema::get_query_posts_db_params(&posts_db, filter);
     let posts_pages = api.query_database(posts_db_id, &query_posts_params).await?;
 
-    posts_pages
+    // Skip pages with invalid property values so that a single malformed page does not fail the whole listing.
+    let posts = posts_pages
         .iter()
-        .map(crate::blog::schema::create_post_from_notion_page)
-        .collect::<Result<_, _>>()
-        .map_err(NotionBlogError::from)
+        .filter_map(
+            |page| match crate::blog::schema::create_post_from_notion_page(page) {
+                Ok(post) => Some(post),
+                Err(err) => {
+                    spdlog::error!(logger: LOGGER, "skipped page {}: {}", page.id, err);
+                    None
+                }
+            },
+        )
+        .collect();
+    Ok(posts)
 }
 
 /// Get the content of the specified post from the corresponding Notion page.
@@ -62,16 +87,24 @@ pub async fn get_post_content(
     let content_tree = crate::api::block_tree::normalize(raw_content_trees);
 
     post.post.content = crate::render::block::render_block_tree(&content_tree);
+    // Notion splits texts into many spans, most of which can be merged.
+    post.post.content.coalesce_inline();
 
     Ok(())
 }
 
 /// Extract all referenced resources by the specified Notion post.
 ///
-/// This function also updates the corresponding documentation node to refer to the extracted resources.
+/// This function also updates the corresponding documentation node to refer to the extracted resources. Downloads that
+/// fail with transient errors are retried. Resources that still cannot be fetched are reported in the returned
+/// [`ExtractedResources`], and the document nodes referring to them are left unchanged.
+///
+/// Resources hosted on the external domains specified in the given options are not fetched, and the document nodes
+/// referring to them keep linking to their URLs.
 pub async fn extract_notion_resources(
     post: &mut NotionPost,
-) -> Result<Vec<Resource>, NotionBlogError> {
+    options: &ExtractResourcesOptions,
+) -> ExtractedResources {
     spdlog::trace!(
         logger: LOGGER,
         "extract notion resources: {} - {}",
@@ -79,14 +112,18 @@ pub async fn extract_notion_resources(
         post.notion_page_id
     );
 
-    #[derive(Default)]
-    struct Visitor {
+    struct Visitor<'a> {
+        options: &'a ExtractResourcesOptions,
         resources: Vec<NotionResource>,
     }
 
-    impl DocumentNodeVisitor for Visitor {
+    impl<'a> DocumentNodeVisitor for Visitor<'a> {
         fn visit_mut(&mut self, node: &mut DocumentNode) {
             if let Some((link, url)) = extract_notion_res_link_in_doc_node(node) {
+                if self.options.is_external(&url) {
+                    return;
+                }
+
                 if let Some(resource) = NotionResource::new(url) {
                     let uuid = format!("{}", resource.id.as_hyphenated());
                     self.resources.push(resource);
@@ -96,20 +133,118 @@ pub async fn extract_notion_resources(
         }
     }
 
-    let mut visitor = Visitor::default();
+    let mut visitor = Visitor {
+        options,
+        resources: Vec::new(),
+    };
     post.post.content.visit_mut(&mut visitor);
 
-    let resources = futures::future::join_all(
-        visitor
-            .resources
-            .into_iter()
-            .map(|res| fetch_notion_resource(&post.post.slug, res)),
-    )
-    .await
-    .into_iter()
-    .collect::<Result<_, _>>()?;
+    let client = options.create_client();
+    let results =
+        futures::future::join_all(visitor.resources.iter().map(|res| {
+            fetch_notion_resource(&client, &post.post.slug, res, &RESOURCE_RETRY_POLICY)
+        }))
+        .await;
+
+    let mut extracted = ExtractedResources::default();
+    let mut failed_urls = HashMap::new();
+    for (res, result) in visitor.resources.into_iter().zip(results) {
+        match result {
+            Ok(resource) => extracted.resources.push(resource),
+            Err(err) => {
+                failed_urls.insert(format!("{}", res.id.as_hyphenated()), res.url.clone());
+                extracted.failures.push(ResourceFetchError {
+                    url: res.url,
+                    error: err,
+                });
+            }
+        }
+    }
+
+    if !failed_urls.is_empty() {
+        restore_external_links(&mut post.post.content, &failed_urls);
+    }
+
+    extracted
+}
+
+/// Options that control how [`extract_notion_resources`] extracts resources.
+#[derive(Clone, Debug)]
+pub struct ExtractResourcesOptions {
+    /// Domains whose resources are linked externally rather than fetched and embedded.
+    ///
+    /// A domain also covers all of its subdomains, e.g. `example.com` covers `images.example.com`.
+    pub external_domains: Vec<String>,
+
+    /// Timeout of each request that downloads a resource, which defaults to 30 seconds. A download that times out is
+    /// retried like other transient failures.
+    pub request_timeout: Duration,
+}
 
-    Ok(resources)
+impl Default for ExtractResourcesOptions {
+    fn default() -> Self {
+        Self {
+            external_domains: Vec::new(),
+            request_timeout: DEFAULT_RESOURCE_REQUEST_TIMEOUT,
+        }
+    }
+}
+
+impl ExtractResourcesOptions {
+    /// Create the HTTP client that downloads resources.
+    fn create_client(&self) -> reqwest::Client {
+        // Like `reqwest::Client::new`, building the client only fails if the TLS backend cannot be initialized.
+        reqwest::Client::builder()
+            .timeout(self.request_timeout)
+            .build()
+            .expect("cannot build HTTP client")
+    }
+
+    /// Determine whether the resource at the given URL should be linked externally.
+    fn is_external(&self, url: &str) -> bool {
+        let host = match reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        {
+            Some(host) => host,
+            None => return false,
+        };
+
+        self.external_domains.iter().any(|domain| {
+            let domain = domain.trim_matches('.').to_ascii_lowercase();
+            host == domain
+                || host
+                    .strip_suffix(&domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+/// Resources extracted from a Notion post by [`extract_notion_resources`].
+#[derive(Debug, Default)]
+pub struct ExtractedResources {
+    /// The resources that have been fetched.
+    pub resources: Vec<Resource>,
+
+    /// The resources that cannot be fetched.
+    pub failures: Vec<ResourceFetchError>,
+}
+
+/// Optional conditions that restrict which posts [`get_posts`] lists.
+#[derive(Clone, Debug, Default)]
+pub struct PostsFilter {
+    /// Only list posts in this category.
+    pub category: Option<String>,
+
+    /// Only list posts updated on or after this ISO 8601 date, e.g. `2022-10-01`.
+    pub updated_since: Option<String>,
+
+    /// Only list posts whose Notion pages are last edited on or after this ISO 8601 date time, e.g.
+    /// `2022-10-01T12:00:00Z`.
+    ///
+    /// Unlike [`updated_since`](Self::updated_since), which filters on the update date property of the posts, this
+    /// filters on the edit time that Notion maintains for each page.
+    pub edited_since: Option<String>,
 }
 
 /// A post published via Notion.
@@ -120,6 +255,9 @@ pub struct NotionPost {
 
     /// The post data model.
     pub post: Post,
+
+    /// The canonical URL of the post, if the posts database has a `canonical_url` property and it is set.
+    pub canonical_url: Option<String>,
 }
 
 /// Error type used in the notion blog module.
@@ -131,6 +269,9 @@ pub enum NotionBlogError {
     /// Notion database schema validation errors.
     InvalidSchema(InvalidSchemaError),
 
+    /// A page in the Notion database has an invalid property value.
+    PropertyValue(PropertyValueError),
+
     /// Error originating from the HTTP client.
     Http(reqwest::Error),
 }
@@ -140,6 +281,7 @@ impl Display for NotionBlogError {
         match self {
             Self::NotionApi(err) => write!(f, "Notion API error: {}", err),
             Self::InvalidSchema(err) => write!(f, "schema validation failed: {}", err),
+            Self::PropertyValue(err) => write!(f, "invalid property value: {}", err),
             Self::Http(err) => write!(f, "HTTP error: {}", err),
         }
     }
@@ -159,6 +301,12 @@ impl From<InvalidSchemaError> for NotionBlogError {
     }
 }
 
+impl From<PropertyValueError> for NotionBlogError {
+    fn from(err: PropertyValueError) -> Self {
+        Self::PropertyValue(err)
+    }
+}
+
 impl From<reqwest::Error> for NotionBlogError {
     fn from(err: reqwest::Error) -> Self {
         Self::Http(err)
@@ -250,6 +398,49 @@ impl Display for InvalidSchemaPropertyTypeError {
 
 impl Error for InvalidSchemaPropertyTypeError {}
 
+/// Error that represents a page has an invalid value on one of its properties.
+#[derive(Debug)]
+pub struct PropertyValueError {
+    /// ID of the page.
+    pub page_id: String,
+
+    /// Name of the property.
+    pub prop: String,
+
+    /// Description of what is wrong with the value.
+    pub message: String,
+}
+
+impl Display for PropertyValueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "property \"{}\" of page {}: {}",
+            self.prop, self.page_id, self.message
+        )
+    }
+}
+
+impl Error for PropertyValueError {}
+
+/// Error that represents a resource referenced by a post cannot be fetched.
+#[derive(Debug)]
+pub struct ResourceFetchError {
+    /// URL of the resource.
+    pub url: String,
+
+    /// The error of the last attempt to fetch the resource.
+    pub error: reqwest::Error,
+}
+
+impl Display for ResourceFetchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot fetch resource {}: {}", self.url, self.error)
+    }
+}
+
+impl Error for ResourceFetchError {}
+
 fn extract_notion_res_link_in_doc_node(
     node: &mut DocumentNode,
 ) -> Option<(&mut DocumentResourceLink, String)> {
@@ -285,22 +476,82 @@ impl NotionResource {
     }
 }
 
-async fn fetch_notion_resource<T>(
-    post_slug: T,
-    resource: NotionResource,
-) -> Result<Resource, reqwest::Error>
-where
-    T: Into<String>,
-{
-    let post_slug = post_slug.into();
+/// Make the document nodes that refer to the given embedded resources refer to their original URLs again.
+///
+/// The given map maps the UUIDs of the embedded resources to their original URLs.
+fn restore_external_links(root: &mut DocumentNode, urls: &HashMap<String, String>) {
+    struct Visitor<'a> {
+        urls: &'a HashMap<String, String>,
+    }
+
+    impl<'a> DocumentNodeVisitor for Visitor<'a> {
+        fn visit_mut(&mut self, node: &mut DocumentNode) {
+            if let DocumentNodeTag::Image { link, .. } = &mut node.tag {
+                if let DocumentResourceLink::Embedded { uuid } = link {
+                    if let Some(url) = self.urls.get(uuid) {
+                        *link = DocumentResourceLink::External { url: url.clone() };
+                    }
+                }
+            }
+        }
+    }
+
+    root.visit_mut(&mut Visitor { urls });
+}
+
+/// Fetch the given resource, retrying on transient errors according to the given policy.
+async fn fetch_notion_resource(
+    client: &reqwest::Client,
+    post_slug: &str,
+    resource: &NotionResource,
+    retry_policy: &RetryPolicy,
+) -> Result<Resource, reqwest::Error> {
     spdlog::trace!(
+        logger: LOGGER,
         "fetch notion resource: {}/{} from {}",
         post_slug,
         resource.id,
         resource.url
     );
 
-    let response = reqwest::get(&resource.url).await?;
+    let mut retry = 0;
+    loop {
+        let err = match try_fetch_notion_resource(client, &resource.url).await {
+            Ok((ty, data)) => {
+                return Ok(Resource {
+                    id: resource.id,
+                    name: Resource::file_name(&resource.id, &ty),
+                    ty,
+                    data,
+                });
+            }
+            Err(err) => err,
+        };
+
+        if !is_transient_error(&err) || retry >= retry_policy.max_retries {
+            return Err(err);
+        }
+
+        retry += 1;
+        let backoff = retry_policy.backoff(retry);
+        spdlog::debug!(
+            logger: LOGGER,
+            "retry #{} of fetching {} after {:?}: {}",
+            retry,
+            resource.url,
+            backoff,
+            err
+        );
+        tokio::time::sleep(backoff).await;
+    }
+}
+
+/// Fetch the resource at the given URL. Returns the MIME type and the data of the resource.
+async fn try_fetch_notion_resource(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<(String, Vec<u8>), reqwest::Error> {
+    let response = client.get(url).send().await?.error_for_status()?;
     let content_type = response
         .headers()
         .get("Content-Type")
@@ -309,10 +560,202 @@ where
         .unwrap_or_else(|| String::from("application/octet-stream"));
     let data = response.bytes().await?.into_iter().collect();
 
-    Ok(Resource {
-        id: resource.id,
-        name: String::from(""),
-        ty: content_type,
-        data,
-    })
+    Ok((content_type, data))
+}
+
+/// Determine whether the given error is transient, i.e. whether the failed request may succeed if sent again.
+fn is_transient_error(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+        None => err.is_timeout() || err.is_connect() || err.is_body(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    const NO_BACKOFF_POLICY: RetryPolicy = RetryPolicy {
+        max_retries: 3,
+        base_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+    };
+
+    /// Start a local server that serves a PNG image, except that the first `failures` requests are answered with the
+    /// given error status. Returns the URL of the image and the number of requests received so far.
+    fn serve_image(failures: usize, status: u16) -> (String, Arc<AtomicUsize>) {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let addr = {
+            let counter = counter.clone();
+            crate::api::requests::tests::serve(move |_| {
+                let idx = counter.fetch_add(1, Ordering::SeqCst);
+                let builder = hyper::Response::builder();
+                if idx < failures {
+                    builder.status(status).body("error".into()).unwrap()
+                } else {
+                    builder
+                        .header("Content-Type", "image/png")
+                        .body("image".into())
+                        .unwrap()
+                }
+            })
+        };
+        (format!("http://{}/image.png", addr), counter)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_notion_resource_retry() {
+        let (url, counter) = serve_image(1, 503);
+        let resource = NotionResource::new(url).unwrap();
+
+        let fetched = fetch_notion_resource(
+            &ExtractResourcesOptions::default().create_client(),
+            "slug",
+            &resource,
+            &NO_BACKOFF_POLICY,
+        )
+        .await
+        .unwrap();
+        assert_eq!(fetched.id, resource.id);
+        assert_eq!(fetched.ty, "image/png");
+        assert_eq!(fetched.name, format!("{}.png", resource.id.as_hyphenated()));
+        assert_eq!(fetched.data, b"image");
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_notion_resource_not_transient() {
+        let (url, counter) = serve_image(1, 404);
+        let resource = NotionResource::new(url).unwrap();
+
+        let err = fetch_notion_resource(
+            &ExtractResourcesOptions::default().create_client(),
+            "slug",
+            &resource,
+            &NO_BACKOFF_POLICY,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    fn create_post_with_images(urls: &[&str]) -> NotionPost {
+        let mut content = DocumentNode::new_empty();
+        for url in urls {
+            content
+                .children
+                .push(DocumentNode::new(DocumentNodeTag::Image {
+                    link: DocumentResourceLink::External {
+                        url: String::from(*url),
+                    },
+                    caption: None,
+                }));
+        }
+
+        NotionPost {
+            notion_page_id: String::from("page"),
+            post: Post {
+                title: String::from("title"),
+                slug: String::from("slug"),
+                author: String::from("msr"),
+                create_timestamp: 0,
+                update_timestamp: 0,
+                category: String::from("category"),
+                tags: Vec::new(),
+                is_special: false,
+                views: 0,
+                excerpt: String::new(),
+                content,
+            },
+            canonical_url: None,
+        }
+    }
+
+    fn image_links(post: &NotionPost) -> Vec<DocumentResourceLink> {
+        post.post
+            .content
+            .children
+            .iter()
+            .map(|node| match &node.tag {
+                DocumentNodeTag::Image { link, .. } => link.clone(),
+                tag => panic!("unexpected node: {:?}", tag),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_extract_notion_resources_embed() {
+        let (url, counter) = serve_image(0, 404);
+        let mut post = create_post_with_images(&[&url]);
+
+        let extracted =
+            extract_notion_resources(&mut post, &ExtractResourcesOptions::default()).await;
+        assert_eq!(extracted.resources.len(), 1);
+        assert!(extracted.failures.is_empty());
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            image_links(&post),
+            vec![DocumentResourceLink::Embedded {
+                uuid: format!("{}", extracted.resources[0].id.as_hyphenated()),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extract_notion_resources_keep_external() {
+        let (url, counter) = serve_image(0, 404);
+        let mut post = create_post_with_images(&[&url]);
+
+        let options = ExtractResourcesOptions {
+            external_domains: vec![String::from("127.0.0.1")],
+            ..Default::default()
+        };
+        let extracted = extract_notion_resources(&mut post, &options).await;
+        assert!(extracted.resources.is_empty());
+        assert!(extracted.failures.is_empty());
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            image_links(&post),
+            vec![DocumentResourceLink::External { url }]
+        );
+    }
+
+    #[test]
+    fn test_extract_resources_options_is_external() {
+        let options = ExtractResourcesOptions {
+            external_domains: vec![String::from("Example.com")],
+            ..Default::default()
+        };
+        assert!(options.is_external("https://example.com/a.png"));
+        assert!(options.is_external("https://images.example.com/a.png"));
+        assert!(!options.is_external("https://notexample.com/a.png"));
+        assert!(!options.is_external("https://example.com.evil.org/a.png"));
+        assert!(!options.is_external("not a url"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_notion_resources_partial_failure() {
+        let (ok_url, _) = serve_image(0, 404);
+        let (missing_url, _) = serve_image(usize::MAX, 404);
+        let mut post = create_post_with_images(&[&ok_url, &missing_url]);
+
+        let extracted =
+            extract_notion_resources(&mut post, &ExtractResourcesOptions::default()).await;
+        assert_eq!(extracted.resources.len(), 1);
+        assert_eq!(extracted.failures.len(), 1);
+        assert_eq!(extracted.failures[0].url, missing_url);
+        assert_eq!(
+            image_links(&post),
+            vec![
+                DocumentResourceLink::Embedded {
+                    uuid: format!("{}", extracted.resources[0].id.as_hyphenated()),
+                },
+                DocumentResourceLink::External { url: missing_url },
+            ]
+        );
+    }
 }
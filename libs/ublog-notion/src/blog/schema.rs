@@ -5,16 +5,21 @@ use ublog_doc::DocumentNode;
 
 use crate::api::models::{Database, Page, PropertyValue};
 use crate::api::{
-    NotionApi, QueryDatabaseFilter, QueryDatabaseParams, QueryDatabasePropertyFilter,
-    QueryDatabaseSort,
+    NotionApi, QueryDatabaseDateFilter, QueryDatabaseFilter, QueryDatabaseParams,
+    QueryDatabasePropertyFilter, QueryDatabaseSort,
 };
-use crate::blog::{InvalidSchemaError, NotionBlogError, NotionPost};
+use crate::blog::{
+    InvalidSchemaError, NotionBlogError, NotionPost, PostsFilter, PropertyValueError,
+};
+
+/// The status of published posts, used when the posts database has a `status` property.
+const PUBLISHED_STATUS: &str = "Published";
 
-/// Validate posts database schema.
+/// Validate posts database schema. Returns the validated posts database.
 pub async fn validate_posts_db_schema<T>(
     api: &NotionApi,
     posts_db_id: T,
-) -> Result<(), NotionBlogError>
+) -> Result<Database, NotionBlogError>
 where
     T: AsRef<str>,
 {
@@ -23,29 +28,77 @@ where
 
     validate_posts_db_schema_on(&db)?;
 
-    Ok(())
+    Ok(db)
 }
 
-/// Get a [`QueryDatabaseParams`] object that can be used for querying post pages from the posts database.
-pub fn get_query_posts_db_params() -> QueryDatabaseParams {
+/// Get a [`QueryDatabaseParams`] object that can be used for querying post pages from the given posts database.
+///
+/// Posts must have the `published` checkbox checked. If the posts database has a `status` property, posts must also
+/// have the `Published` status. Posts are further restricted by the given filter.
+pub fn get_query_posts_db_params(db: &Database, filter: &PostsFilter) -> QueryDatabaseParams {
+    let mut filters = vec![QueryDatabasePropertyFilter::checkbox_checked(
+        PUBLISHED_PROPERTY.name,
+    )];
+
+    if db.properties.contains_key(STATUS_PROPERTY.name) {
+        filters.push(QueryDatabasePropertyFilter::status_equals(
+            STATUS_PROPERTY.name,
+            PUBLISHED_STATUS,
+        ));
+    }
+
+    if let Some(category) = &filter.category {
+        filters.push(QueryDatabasePropertyFilter::select_equals(
+            CATEGORY_PROPERTY.name,
+            category,
+        ));
+    }
+
+    if let Some(updated_since) = &filter.updated_since {
+        filters.push(QueryDatabasePropertyFilter::date_on_or_after(
+            UPDATE_DATE_PROPERTY.name,
+            updated_since,
+        ));
+    }
+
+    let mut filters: Vec<_> = filters
+        .into_iter()
+        .map(QueryDatabaseFilter::Property)
+        .collect();
+
+    if let Some(edited_since) = &filter.edited_since {
+        filters.push(QueryDatabaseFilter::LastEditedTime(
+            QueryDatabaseDateFilter::OnOrAfter(edited_since.clone()),
+        ));
+    }
+
+    let filter = if filters.len() == 1 {
+        filters.pop().unwrap()
+    } else {
+        QueryDatabaseFilter::And(filters)
+    };
+
     QueryDatabaseParams {
-        filter: Some(QueryDatabaseFilter::Property(
-            QueryDatabasePropertyFilter::checkbox_checked(PUBLISHED_PROPERTY.name),
-        )),
+        filter: Some(filter),
         sorts: vec![QueryDatabaseSort::descending_on(CREATE_DATE_PROPERTY.name)],
     }
 }
 
 /// Create a [`Post`] object in the blog posts namespace from the corresponding Notion page.
 pub fn create_post_from_notion_page(page: &Page) -> Result<NotionPost, NotionBlogError> {
-    let title = TITLE_PROPERTY.get_str_value(page);
-    let slug = SLUG_PROPERTY.get_str_value(page);
-    let author = AUTHOR_PROPERTY.get_str_value(page);
-    let create_timestamp = CREATE_DATE_PROPERTY.get_timestamp_value(page);
-    let update_timestamp = UPDATE_DATE_PROPERTY.get_timestamp_value(page);
-    let category = CATEGORY_PROPERTY.get_str_value(page);
-    let tags = TAGS_PROPERTY.get_str_list_value(page);
-    let is_special = SPECIAL_PROPERTY.get_checkbox_value(page);
+    let title = TITLE_PROPERTY.get_str_value(page)?;
+    let slug = SLUG_PROPERTY.get_str_value(page)?;
+    let author = AUTHOR_PROPERTY.get_str_value(page)?;
+    let create_timestamp = CREATE_DATE_PROPERTY.get_timestamp_value(page)?;
+    let update_timestamp = UPDATE_DATE_PROPERTY.get_timestamp_value(page)?;
+    let category = CATEGORY_PROPERTY.get_str_value(page)?;
+    let tags = TAGS_PROPERTY.get_str_list_value(page)?;
+    let is_special = SPECIAL_PROPERTY.get_checkbox_value(page)?;
+    let canonical_url = if page.properties.contains_key(CANONICAL_URL_PROPERTY.name) {
+        CANONICAL_URL_PROPERTY.get_url_value(page)?
+    } else {
+        None
+    };
 
     let post = NotionPost {
         notion_page_id: page.id.clone(),
@@ -58,8 +111,11 @@ pub fn create_post_from_notion_page(page: &Page) -> Result<NotionPost, NotionBlo
             category,
             tags,
             is_special,
+            views: 0,
+            excerpt: String::new(),
             content: DocumentNode::new_empty(),
         },
+        canonical_url,
     };
     Ok(post)
 }
@@ -83,6 +139,18 @@ fn validate_posts_db_schema_on(db: &Database) -> Result<(), InvalidSchemaError>
         }
     }
 
+    for prop_desc in OPTIONAL_SCHEMA_PROPERTIES {
+        if let Some(prop) = db.properties.get(prop_desc.name) {
+            if prop.ty != prop_desc.expected_type.name() {
+                return Err(InvalidSchemaError::invalid_property_type(
+                    prop_desc.name,
+                    prop_desc.expected_type.name(),
+                    prop.ty.clone(),
+                ));
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -104,6 +172,8 @@ declare_property_desc!(CATEGORY_PROPERTY, category, Select);
 declare_property_desc!(TAGS_PROPERTY, tags, MultiSelect);
 declare_property_desc!(PUBLISHED_PROPERTY, published, Checkbox);
 declare_property_desc!(SPECIAL_PROPERTY, special, Checkbox);
+declare_property_desc!(STATUS_PROPERTY, status, Status);
+declare_property_desc!(CANONICAL_URL_PROPERTY, canonical_url, Url);
 
 const SCHEMA_PROPERTIES: &[SchemaPropertyDescriptor] = &[
     TITLE_PROPERTY,
@@ -117,6 +187,10 @@ const SCHEMA_PROPERTIES: &[SchemaPropertyDescriptor] = &[
     SPECIAL_PROPERTY,
 ];
 
+/// Properties that the posts database may omit. If present, they must have the expected types.
+const OPTIONAL_SCHEMA_PROPERTIES: &[SchemaPropertyDescriptor] =
+    &[STATUS_PROPERTY, CANONICAL_URL_PROPERTY];
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 struct SchemaPropertyDescriptor {
     name: &'static str,
@@ -124,51 +198,88 @@ struct SchemaPropertyDescriptor {
 }
 
 impl SchemaPropertyDescriptor {
-    fn get_str_value(&self, page: &Page) -> String {
-        let prop = self.get_property_value(page);
-        match prop {
-            PropertyValue::Title { title } => {
-                crate::render::rich_text::render_rich_texts_to_plain_text(title)
-            }
-            PropertyValue::RichText { rich_text } => {
-                crate::render::rich_text::render_rich_texts_to_plain_text(rich_text)
-            }
-            PropertyValue::Select { select } => select.name.clone(),
-            _ => unreachable!(),
+    fn get_str_value(&self, page: &Page) -> Result<String, PropertyValueError> {
+        match self.get_property_value(page)? {
+            PropertyValue::Title { title } => Ok(
+                crate::render::rich_text::render_rich_texts_to_plain_text(title),
+            ),
+            PropertyValue::RichText { rich_text } => Ok(
+                crate::render::rich_text::render_rich_texts_to_plain_text(rich_text),
+            ),
+            PropertyValue::Select {
+                select: Some(select),
+            } => Ok(select.name.clone()),
+            PropertyValue::Select { select: None } => Err(self.value_error(page, "value is empty")),
+            _ => Err(self.unexpected_type_error(page)),
         }
     }
 
-    fn get_str_list_value(&self, page: &Page) -> Vec<String> {
-        let prop = self.get_property_value(page);
-        match prop {
+    fn get_str_list_value(&self, page: &Page) -> Result<Vec<String>, PropertyValueError> {
+        match self.get_property_value(page)? {
             PropertyValue::MultiSelect { multi_select } => {
-                multi_select.iter().map(|s| s.name.clone()).collect()
+                Ok(multi_select.iter().map(|s| s.name.clone()).collect())
             }
-            _ => unreachable!(),
+            _ => Err(self.unexpected_type_error(page)),
         }
     }
 
-    fn get_timestamp_value(&self, page: &Page) -> i64 {
-        let prop = self.get_property_value(page);
-        let date_prop = match prop {
-            PropertyValue::Date { date } => date,
-            _ => unreachable!(),
+    fn get_timestamp_value(&self, page: &Page) -> Result<i64, PropertyValueError> {
+        let date_prop = match self.get_property_value(page)? {
+            PropertyValue::Date { date: Some(date) } => date,
+            PropertyValue::Date { date: None } => {
+                return Err(self.value_error(page, "value is empty"));
+            }
+            _ => return Err(self.unexpected_type_error(page)),
         };
 
-        let date = Date::parse(&date_prop.start, &Iso8601::DEFAULT).unwrap();
-        date.midnight().assume_utc().unix_timestamp()
+        let date = Date::parse(&date_prop.start, &Iso8601::DEFAULT).map_err(|err| {
+            self.value_error(
+                page,
+                format!("invalid date \"{}\": {}", date_prop.start, err),
+            )
+        })?;
+        Ok(date.midnight().assume_utc().unix_timestamp())
     }
 
-    fn get_checkbox_value(&self, page: &Page) -> bool {
-        let prop = self.get_property_value(page);
-        match prop {
-            PropertyValue::Checkbox { checkbox } => *checkbox,
-            _ => unreachable!(),
+    fn get_url_value(&self, page: &Page) -> Result<Option<String>, PropertyValueError> {
+        match self.get_property_value(page)? {
+            PropertyValue::Url { url } => Ok(url.clone()),
+            _ => Err(self.unexpected_type_error(page)),
         }
     }
 
-    fn get_property_value<'p>(&self, page: &'p Page) -> &'p PropertyValue {
-        page.properties.get(self.name).unwrap()
+    fn get_checkbox_value(&self, page: &Page) -> Result<bool, PropertyValueError> {
+        match self.get_property_value(page)? {
+            PropertyValue::Checkbox { checkbox } => Ok(*checkbox),
+            _ => Err(self.unexpected_type_error(page)),
+        }
+    }
+
+    fn get_property_value<'p>(
+        &self,
+        page: &'p Page,
+    ) -> Result<&'p PropertyValue, PropertyValueError> {
+        page.properties
+            .get(self.name)
+            .ok_or_else(|| self.value_error(page, "property is missing"))
+    }
+
+    fn value_error<T>(&self, page: &Page, message: T) -> PropertyValueError
+    where
+        T: Into<String>,
+    {
+        PropertyValueError {
+            page_id: page.id.clone(),
+            prop: String::from(self.name),
+            message: message.into(),
+        }
+    }
+
+    fn unexpected_type_error(&self, page: &Page) -> PropertyValueError {
+        self.value_error(
+            page,
+            format!("value should have type \"{}\"", self.expected_type.name()),
+        )
     }
 }
 
@@ -180,6 +291,8 @@ enum NotionPropertyTypes {
     MultiSelect,
     Date,
     Checkbox,
+    Status,
+    Url,
 }
 
 impl NotionPropertyTypes {
@@ -191,6 +304,182 @@ impl NotionPropertyTypes {
             Self::MultiSelect => "multi_select",
             Self::Date => "date",
             Self::Checkbox => "checkbox",
+            Self::Status => "status",
+            Self::Url => "url",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn rich_text_json(text: &str) -> Value {
+        json!([{
+            "type": "text",
+            "text": { "content": text, "link": null },
+            "annotations": {
+                "bold": false,
+                "italic": false,
+                "strikethrough": false,
+                "underline": false,
+                "code": false,
+                "color": "default"
+            },
+            "plain_text": text,
+            "href": null
+        }])
+    }
+
+    fn create_page_json() -> Value {
+        let select = |name: &str| json!({ "id": name, "name": name, "color": "default" });
+        json!({
+            "id": "page",
+            "created_time": "2022-10-01T00:00:00.000Z",
+            "last_edited_time": "2022-10-01T00:00:00.000Z",
+            "archived": false,
+            "url": "https://www.notion.so/page",
+            "properties": {
+                "title": { "type": "title", "title": rich_text_json("Hello") },
+                "slug": { "type": "rich_text", "rich_text": rich_text_json("hello") },
+                "author": { "type": "rich_text", "rich_text": rich_text_json("msr") },
+                "create_date": { "type": "date", "date": { "start": "2022-10-01" } },
+                "update_date": { "type": "date", "date": { "start": "2022-10-02" } },
+                "category": { "type": "select", "select": select("test") },
+                "tags": { "type": "multi_select", "multi_select": [select("a")] },
+                "published": { "type": "checkbox", "checkbox": true },
+                "special": { "type": "checkbox", "checkbox": false }
+            }
+        })
+    }
+
+    fn create_post(page_json: Value) -> Result<NotionPost, NotionBlogError> {
+        let page: Page = serde_json::from_value(page_json).unwrap();
+        create_post_from_notion_page(&page)
+    }
+
+    #[test]
+    fn test_create_post_from_notion_page() {
+        let post = create_post(create_page_json()).unwrap();
+        assert_eq!(post.notion_page_id, "page");
+        assert_eq!(post.post.title, "Hello");
+        assert_eq!(post.post.slug, "hello");
+        assert_eq!(post.post.create_timestamp, 1664582400);
+        assert_eq!(post.post.tags, vec!["a"]);
+    }
+
+    #[test]
+    fn test_create_post_missing_date() {
+        let mut page_json = create_page_json();
+        page_json["properties"]["create_date"]["date"] = Value::Null;
+
+        let err = create_post(page_json).unwrap_err();
+        match err {
+            NotionBlogError::PropertyValue(err) => {
+                assert_eq!(err.page_id, "page");
+                assert_eq!(err.prop, "create_date");
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_create_post_wrong_typed_title() {
+        let mut page_json = create_page_json();
+        page_json["properties"]["title"] = json!({ "type": "checkbox", "checkbox": true });
+
+        let err = create_post(page_json).unwrap_err();
+        match err {
+            NotionBlogError::PropertyValue(err) => {
+                assert_eq!(err.prop, "title");
+                assert_eq!(err.message, "value should have type \"title\"");
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_status_property_value() {
+        let value: PropertyValue = serde_json::from_value(json!({
+            "id": "status",
+            "type": "status",
+            "status": { "id": "done", "name": "Published", "color": "green" }
+        }))
+        .unwrap();
+
+        match value {
+            PropertyValue::Status { status } => assert_eq!(status.name, "Published"),
+            _ => panic!("unexpected property value: {:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_url_property_value() {
+        let value: PropertyValue = serde_json::from_value(json!({
+            "id": "url",
+            "type": "url",
+            "url": "https://lancern.xyz/posts/hello"
+        }))
+        .unwrap();
+        assert!(
+            matches!(value, PropertyValue::Url { url: Some(url) } if url == "https://lancern.xyz/posts/hello")
+        );
+
+        let value: PropertyValue =
+            serde_json::from_value(json!({ "id": "url", "type": "url", "url": null })).unwrap();
+        assert!(matches!(value, PropertyValue::Url { url: None }));
+    }
+
+    #[test]
+    fn test_create_post_canonical_url() {
+        let post = create_post(create_page_json()).unwrap();
+        assert_eq!(post.canonical_url, None);
+
+        let mut page_json = create_page_json();
+        page_json["properties"]["canonical_url"] =
+            json!({ "type": "url", "url": "https://lancern.xyz/posts/hello" });
+        let post = create_post(page_json).unwrap();
+        assert_eq!(
+            post.canonical_url.as_deref(),
+            Some("https://lancern.xyz/posts/hello")
+        );
+    }
+
+    #[test]
+    fn test_get_query_posts_db_params_edited_since() {
+        let db: Database = serde_json::from_value(json!({
+            "id": "db",
+            "created_time": "2022-10-01T00:00:00.000Z",
+            "last_edited_time": "2022-10-01T00:00:00.000Z",
+            "title": [],
+            "description": [],
+            "icon": { "type": "emoji", "emoji": "📝" },
+            "cover": { "type": "external", "external": { "url": "https://example.com/cover.png" } },
+            "url": "https://www.notion.so/db",
+            "properties": {},
+            "archived": false,
+            "is_inline": false
+        }))
+        .unwrap();
+
+        let filter = PostsFilter {
+            edited_since: Some(String::from("2022-10-01T12:00:00Z")),
+            ..PostsFilter::default()
+        };
+        let params = serde_json::to_value(get_query_posts_db_params(&db, &filter)).unwrap();
+        assert_eq!(
+            params["filter"],
+            json!({
+                "and": [
+                    { "property": PUBLISHED_PROPERTY.name, "checkbox": { "equals": true } },
+                    {
+                        "timestamp": "last_edited_time",
+                        "last_edited_time": { "on_or_after": "2022-10-01T12:00:00Z" }
+                    }
+                ]
+            })
+        );
+    }
+}
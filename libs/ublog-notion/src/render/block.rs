@@ -39,12 +39,21 @@ pub fn render_block(b: &Block) -> DocumentNode {
         BlockVariants::NumberedListItem { numbered_list_item } => {
             render_list_item_block(numbered_list_item)
         }
+        BlockVariants::ToDo { to_do } => render_to_do_block(to_do),
         BlockVariants::Code { code } => render_code_block(code),
         BlockVariants::Image { image } => render_image_block(image),
         BlockVariants::Equation { equation } => render_equation_block(equation),
         BlockVariants::Divider => render_divider_block(),
         BlockVariants::Table { table } => render_table_block(table),
         BlockVariants::TableRow { table_row } => render_table_row_block(table_row),
+        BlockVariants::ColumnList { .. } => DocumentNode::new(DocumentNodeTag::Columns),
+        BlockVariants::Column { .. } => DocumentNode::new(DocumentNodeTag::Column),
+        // Synced blocks are replaced by their children when normalizing block trees, so a synced block only reaches
+        // here if it is rendered on its own. Render it into a root node that holds its children.
+        BlockVariants::SyncedBlock { .. } => DocumentNode::new_empty(),
+        BlockVariants::Toggle { toggle } => render_toggle_block(toggle),
+        BlockVariants::Bookmark { bookmark } => render_bookmark_block(bookmark),
+        BlockVariants::LinkPreview { link_preview } => render_link_block(&link_preview.url, &[]),
     }
 }
 
@@ -52,12 +61,16 @@ fn render_paragraph_block(b: &ParagraphBlock) -> DocumentNode {
     render_rich_text_container_block(&b.rich_text, DocumentNodeTag::Paragraph)
 }
 
+/// Render a heading block. Levels outside of the range of HTML headings are clamped into the range, so that headings
+/// of unexpected levels still render.
 fn render_heading_block(b: &HeadingBlock, level: i32) -> DocumentNode {
-    debug_assert!((1..=3).contains(&level));
-
-    render_rich_text_container_block(&b.rich_text, DocumentNodeTag::Heading { level })
+    let level = level.clamp(1, MAX_HEADING_LEVEL);
+    render_rich_text_container_block(&b.rich_text, DocumentNodeTag::Heading { level, id: None })
 }
 
+/// The deepest heading level of HTML.
+const MAX_HEADING_LEVEL: i32 = 6;
+
 fn render_callout_block(b: &CalloutBlock) -> DocumentNode {
     let emoji = match &b.icon {
         FileOrEmoji::Emoji { emoji } => Some(emoji.clone()),
@@ -75,27 +88,58 @@ fn render_list_item_block(b: &ListItemBlock) -> DocumentNode {
     render_rich_text_container_block(&b.rich_text, DocumentNodeTag::ListItem)
 }
 
+fn render_to_do_block(b: &ToDoBlock) -> DocumentNode {
+    render_rich_text_container_block(
+        &b.rich_text,
+        DocumentNodeTag::TodoItem { checked: b.checked },
+    )
+}
+
 fn render_code_block(b: &CodeBlock) -> DocumentNode {
     let caption = crate::render::rich_text::render_rich_texts_to_plain_text(&b.caption);
     let code = crate::render::rich_text::render_rich_texts_to_plain_text(&b.rich_text);
     DocumentNode::new(DocumentNodeTag::Code {
-        language: b.language.clone(),
+        language: normalize_code_language(&b.language),
         caption: Some(caption),
         code,
     })
 }
 
-fn render_image_block(image_file: &File) -> DocumentNode {
-    let image_url = match &image_file {
+/// Map the language of a Notion code block to the identifier that syntax highlighters know the language by.
+///
+/// Languages whose Notion names already are the identifiers are kept as-is.
+fn normalize_code_language(language: &str) -> String {
+    let normalized = match language {
+        "plain text" => "text",
+        "c++" => "cpp",
+        "c#" => "csharp",
+        "f#" => "fsharp",
+        "objective-c" => "objectivec",
+        "visual basic" | "vb.net" => "vbnet",
+        "java/c/c++/c#" => "clike",
+        "docker" => "dockerfile",
+        "webassembly" => "wasm",
+        _ => language,
+    };
+    String::from(normalized)
+}
+
+fn render_image_block(b: &ImageBlock) -> DocumentNode {
+    let image_url = match &b.file {
         File::ExternalFile { external } => &external.url,
         File::NotionHostedFile { file } => &file.url,
     };
 
+    let caption = crate::render::rich_text::render_rich_texts_to_plain_text(&b.caption);
     DocumentNode::new(DocumentNodeTag::Image {
         link: DocumentResourceLink::External {
             url: image_url.clone(),
         },
-        caption: None,
+        caption: if caption.is_empty() {
+            None
+        } else {
+            Some(caption)
+        },
     })
 }
 
@@ -125,6 +169,50 @@ fn render_table_row_block(b: &TableRowBlock) -> DocumentNode {
     node
 }
 
+fn render_toggle_block(b: &ToggleBlock) -> DocumentNode {
+    let summary = crate::render::rich_text::render_rich_texts_to_plain_text(&b.rich_text);
+    DocumentNode::new(DocumentNodeTag::Toggle { summary })
+}
+
+fn render_bookmark_block(b: &BookmarkBlock) -> DocumentNode {
+    render_link_block(&b.url, &b.caption)
+}
+
+/// URL schemes that links rendered from bookmark and link preview blocks may use.
+const ALLOWED_LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Render a block that links to the given URL into a paragraph holding the link. The link text is the given caption, or
+/// the URL itself if the caption is empty.
+///
+/// The link is dropped, leaving only the link text, if the URL does not use one of the [`ALLOWED_LINK_SCHEMES`], so
+/// that URLs such as `javascript:` ones never reach the rendered pages.
+fn render_link_block(url: &str, caption: &[RichText]) -> DocumentNode {
+    let link_text = if caption.is_empty() {
+        DocumentNode::new(DocumentNodeTag::InlineText {
+            text: String::from(url),
+        })
+    } else {
+        crate::render::rich_text::render_rich_text_array(caption)
+    };
+
+    let link_url = is_allowed_link(url).then(|| String::from(url));
+    let mut link = DocumentNode::new(DocumentNodeTag::Inline {
+        style: None,
+        link: link_url,
+    });
+    link.children.push(link_text);
+
+    let mut node = DocumentNode::new(DocumentNodeTag::Paragraph);
+    node.children.push(link);
+    node
+}
+
+fn is_allowed_link(url: &str) -> bool {
+    url::Url::parse(url)
+        .map(|url| ALLOWED_LINK_SCHEMES.contains(&url.scheme()))
+        .unwrap_or(false)
+}
+
 fn render_rich_text_container_block(
     rt: &[RichText],
     block_node_tag: DocumentNodeTag,
@@ -134,3 +222,302 @@ fn render_rich_text_container_block(
     node.children = vec![content];
     node
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::api::block_tree::RawBlockTree;
+
+    fn create_to_do_block(checked: bool) -> Block {
+        Block {
+            id: String::from("block"),
+            created_time: String::new(),
+            last_edited_time: String::new(),
+            archived: false,
+            has_children: false,
+            variant: BlockVariants::ToDo {
+                to_do: ToDoBlock {
+                    rich_text: Vec::new(),
+                    checked,
+                    color: String::from("default"),
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_render_to_do_block() {
+        for checked in [false, true] {
+            let node = render_block(&create_to_do_block(checked));
+            assert!(
+                matches!(node.tag, DocumentNodeTag::TodoItem { checked: c } if c == checked),
+                "{:?}",
+                node.tag
+            );
+        }
+    }
+
+    /// Parse raw block trees from a fixture, in which each block lists its child blocks in a `children` field.
+    fn parse_raw_block_trees(value: &Value) -> Vec<RawBlockTree> {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block_value| {
+                let block: Block = serde_json::from_value(block_value.clone()).unwrap();
+                let mut tree = RawBlockTree::new(block);
+                if let Some(children) = block_value.get("children") {
+                    tree.children = parse_raw_block_trees(children);
+                }
+                tree
+            })
+            .collect()
+    }
+
+    fn render_fixture(fixture: &str) -> DocumentNode {
+        let value: Value = serde_json::from_str(fixture).unwrap();
+        let tree = crate::api::block_tree::normalize(parse_raw_block_trees(&value));
+        let doc = render_block_tree(&tree);
+        assert!(doc.validate().is_ok());
+        doc
+    }
+
+    #[test]
+    fn test_render_two_column_layout() {
+        let doc = render_fixture(include_str!("../../fixtures/two_column_layout.json"));
+        assert_eq!(doc.children.len(), 2);
+        assert_eq!(doc.children[0].to_plain_text(), "Two columns:");
+
+        let columns = &doc.children[1];
+        assert!(matches!(columns.tag, DocumentNodeTag::Columns));
+        let column_texts: Vec<_> = columns
+            .children
+            .iter()
+            .map(|column| {
+                assert!(matches!(column.tag, DocumentNodeTag::Column));
+                column.to_plain_text()
+            })
+            .collect();
+        assert_eq!(column_texts, ["Left\nSynced", "Right"]);
+    }
+
+    #[test]
+    fn test_render_bookmark_block() {
+        let block: Block = serde_json::from_str(
+            r#"{
+                "object": "block",
+                "id": "c0a8e0b4-2a8e-4c5e-9f1e-3b5f4c1d2e3f",
+                "created_time": "2022-10-01T00:00:00.000Z",
+                "last_edited_time": "2022-10-01T00:00:00.000Z",
+                "archived": false,
+                "has_children": false,
+                "type": "bookmark",
+                "bookmark": {
+                    "caption": [
+                        {
+                            "type": "text",
+                            "text": { "content": "The Rust site", "link": null },
+                            "annotations": {
+                                "bold": false,
+                                "italic": false,
+                                "strikethrough": false,
+                                "underline": false,
+                                "code": false,
+                                "color": "default"
+                            },
+                            "plain_text": "The Rust site",
+                            "href": null
+                        }
+                    ],
+                    "url": "https://www.rust-lang.org/"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let node = render_block(&block);
+        assert!(matches!(node.tag, DocumentNodeTag::Paragraph));
+        assert_eq!(node.children.len(), 1);
+        assert_eq!(
+            node.children[0].tag,
+            DocumentNodeTag::Inline {
+                style: None,
+                link: Some(String::from("https://www.rust-lang.org/")),
+            }
+        );
+        assert_eq!(node.to_plain_text(), "The Rust site");
+    }
+
+    #[test]
+    fn test_render_link_preview_block() {
+        let block: Block = serde_json::from_str(
+            r#"{
+                "object": "block",
+                "id": "d1b9f1c5-3b9f-4d6f-8a2f-4c6a5d2e3f4a",
+                "created_time": "2022-10-01T00:00:00.000Z",
+                "last_edited_time": "2022-10-01T00:00:00.000Z",
+                "archived": false,
+                "has_children": false,
+                "type": "link_preview",
+                "link_preview": { "url": "https://github.com/Lancern/ublog" }
+            }"#,
+        )
+        .unwrap();
+
+        let node = render_block(&block);
+        assert!(matches!(node.tag, DocumentNodeTag::Paragraph));
+        assert_eq!(
+            node.children[0].tag,
+            DocumentNodeTag::Inline {
+                style: None,
+                link: Some(String::from("https://github.com/Lancern/ublog")),
+            }
+        );
+        assert_eq!(node.to_plain_text(), "https://github.com/Lancern/ublog");
+    }
+
+    #[test]
+    fn test_render_link_block_disallowed_scheme() {
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "data:text/html,x",
+            "/relative",
+        ] {
+            let html = ublog_doc::render::html::render_html(&render_link_block(url, &[])).unwrap();
+            assert!(!html.contains("href"), "{}", html);
+        }
+
+        let html =
+            ublog_doc::render::html::render_html(&render_link_block("mailto:me@example.com", &[]))
+                .unwrap();
+        assert_eq!(
+            html,
+            r#"<p><a href="mailto:me@example.com">mailto:me@example.com</a></p>"#
+        );
+    }
+
+    fn rich_text_json(text: &str) -> Value {
+        json!([{
+            "type": "text",
+            "text": { "content": text, "link": null },
+            "annotations": {
+                "bold": false,
+                "italic": false,
+                "strikethrough": false,
+                "underline": false,
+                "code": false,
+                "color": "default"
+            },
+            "plain_text": text,
+            "href": null
+        }])
+    }
+
+    fn block_json(id: &str, ty: &str, content: Value, children: Vec<Value>) -> Value {
+        json!({
+            "object": "block",
+            "id": id,
+            "created_time": "2022-10-01T00:00:00.000Z",
+            "last_edited_time": "2022-10-01T00:00:00.000Z",
+            "archived": false,
+            "has_children": !children.is_empty(),
+            "type": ty,
+            ty: content,
+            "children": children
+        })
+    }
+
+    #[test]
+    fn test_render_captioned_image_block() {
+        let image = block_json(
+            "image",
+            "image",
+            json!({
+                "caption": rich_text_json("A cat"),
+                "type": "external",
+                "external": { "url": "https://example.com/cat.png" }
+            }),
+            Vec::new(),
+        );
+        let block: Block = serde_json::from_value(image).unwrap();
+
+        assert_eq!(
+            render_block(&block).tag,
+            DocumentNodeTag::Image {
+                link: DocumentResourceLink::External {
+                    url: String::from("https://example.com/cat.png"),
+                },
+                caption: Some(String::from("A cat")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_toggle_with_nested_list() {
+        let list_item = |id: &str, text: &str| {
+            block_json(
+                id,
+                "bulleted_list_item",
+                json!({ "rich_text": rich_text_json(text), "color": "default" }),
+                Vec::new(),
+            )
+        };
+        let toggle = block_json(
+            "toggle",
+            "toggle",
+            json!({ "rich_text": rich_text_json("Details"), "color": "default" }),
+            vec![list_item("a", "First"), list_item("b", "Second")],
+        );
+
+        let doc = render_fixture(&json!([toggle]).to_string());
+        assert_eq!(doc.children.len(), 1);
+
+        let toggle = &doc.children[0];
+        assert!(matches!(&toggle.tag, DocumentNodeTag::Toggle { summary } if summary == "Details"));
+        assert_eq!(toggle.children.len(), 1);
+
+        let list = &toggle.children[0];
+        assert!(matches!(list.tag, DocumentNodeTag::List { .. }));
+        let item_texts: Vec<_> = list
+            .children
+            .iter()
+            .map(|item| item.to_plain_text())
+            .collect();
+        assert_eq!(item_texts, ["First", "Second"]);
+    }
+
+    #[test]
+    fn test_render_heading_block_out_of_range_levels() {
+        let heading: HeadingBlock = serde_json::from_value(json!({
+            "rich_text": [],
+            "color": "default",
+        }))
+        .unwrap();
+
+        for (level, expected) in [(0, 1), (3, 3), (5, 5), (7, 6)] {
+            let node = render_heading_block(&heading, level);
+            assert!(matches!(
+                node.tag,
+                DocumentNodeTag::Heading { level, .. } if level == expected
+            ));
+            let html = ublog_doc::render::html::render_html(&node).unwrap();
+            // The heading has no text, so it renders without an ID.
+            assert_eq!(html, format!("<h{0}></h{0}>", expected));
+        }
+    }
+
+    #[test]
+    fn test_normalize_code_language() {
+        assert_eq!(normalize_code_language("plain text"), "text");
+        assert_eq!(normalize_code_language("c++"), "cpp");
+        assert_eq!(normalize_code_language("c#"), "csharp");
+        assert_eq!(normalize_code_language("rust"), "rust");
+        assert_eq!(
+            normalize_code_language("some new language"),
+            "some new language"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// A node in the HTML document tree.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -13,7 +13,7 @@ impl HtmlNode {
         match self {
             Self::Text(text) => {
                 let escaped = html_escape::encode_text(text);
-                output.push_str(&*escaped);
+                output.push_str(&escaped);
             }
             Self::Element(element) => {
                 element.to_html_str(output);
@@ -29,7 +29,9 @@ pub struct HtmlElement {
     pub tag: &'static str,
 
     /// The properties of the element.
-    pub props: HashMap<String, String>,
+    ///
+    /// Properties are rendered in the order of their names, so the rendered output is deterministic.
+    pub props: BTreeMap<String, String>,
 
     /// The child nodes of the element.
     pub children: Vec<HtmlNode>,
@@ -40,10 +42,19 @@ impl HtmlElement {
     pub fn to_html_str(&self, output: &mut String) {
         let mut tags = vec![String::from(self.tag)];
         for (k, v) in &self.props {
-            tags.push(format!("{}=\"{}\"", k, v));
+            tags.push(format!(
+                "{}=\"{}\"",
+                k,
+                html_escape::encode_double_quoted_attribute(v)
+            ));
         }
 
-        if self.children.is_empty() {
+        if self.is_void() {
+            debug_assert!(
+                self.children.is_empty(),
+                "void element <{}> cannot have children",
+                self.tag
+            );
             output.push_str(&format!("<{}/>", tags.join(" ")));
             return;
         }
@@ -59,18 +70,35 @@ impl HtmlElement {
 }
 
 impl HtmlElement {
+    /// Determine whether this element is a void element, i.e. an element that cannot have any contents.
+    ///
+    /// Void elements are rendered as self-closing tags, while all other elements are rendered with explicit opening
+    /// and closing tags even if they are empty. Void elements must not have any children, which would be lost in the
+    /// rendered output.
+    pub fn is_void(&self) -> bool {
+        VOID_ELEMENTS.contains(&self.tag)
+    }
+
     /// Create a new HTML element with the given tag name.
     ///
     /// The new element does not have any properties or children.
     pub fn new(tag: &'static str) -> Self {
         Self {
             tag,
-            props: HashMap::new(),
+            props: BTreeMap::new(),
             children: Vec::new(),
         }
     }
 }
 
+/// Tag names of void elements.
+///
+/// See <https://html.spec.whatwg.org/multipage/syntax.html#void-elements>.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,6 +125,17 @@ mod tests {
         assert_eq!(rendered, r#"<hr class="divider"/>"#);
     }
 
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "void element <br> cannot have children")]
+    fn test_render_html_void_element_with_children() {
+        let mut el = HtmlElement::new("br");
+        el.children.push(HtmlNode::Text(String::from("lost")));
+
+        let mut rendered = String::new();
+        el.to_html_str(&mut rendered);
+    }
+
     #[test]
     fn test_render_html_element_with_children() {
         let mut el = HtmlElement::new("div");
@@ -123,4 +162,71 @@ mod tests {
             r#"<div class="paragraph"><span>hello</span><span>world</span></div>"#
         );
     }
+
+    #[test]
+    fn test_render_html_element_props_order() {
+        let mut rendered_outputs = Vec::new();
+        for _ in 0..8 {
+            let mut el = HtmlElement::new("img");
+            for (k, v) in [
+                ("src", "a.png"),
+                ("alt", "a"),
+                ("width", "10"),
+                ("class", "c"),
+            ] {
+                el.props.insert(String::from(k), String::from(v));
+            }
+
+            let mut rendered = String::new();
+            el.to_html_str(&mut rendered);
+            rendered_outputs.push(rendered);
+        }
+
+        for rendered in &rendered_outputs {
+            assert_eq!(
+                rendered,
+                r#"<img alt="a" class="c" src="a.png" width="10"/>"#
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_html_element_escape_props() {
+        let mut el = HtmlElement::new("a");
+        el.props.insert(
+            String::from("href"),
+            String::from(r#"https://example.com/?q="<script>""#),
+        );
+        el.children.push(HtmlNode::Text(String::from("link")));
+
+        let mut rendered = String::new();
+        el.to_html_str(&mut rendered);
+
+        assert_eq!(
+            rendered,
+            r#"<a href="https://example.com/?q=&quot;&lt;script&gt;&quot;">link</a>"#
+        );
+    }
+
+    #[test]
+    fn test_render_html_empty_non_void_element() {
+        let mut el = HtmlElement::new("div");
+        el.props
+            .insert(String::from("class"), String::from("empty"));
+
+        let mut rendered = String::new();
+        el.to_html_str(&mut rendered);
+
+        assert_eq!(rendered, r#"<div class="empty"></div>"#);
+    }
+
+    #[test]
+    fn test_render_html_void_element() {
+        let el = HtmlElement::new("hr");
+
+        let mut rendered = String::new();
+        el.to_html_str(&mut rendered);
+
+        assert_eq!(rendered, "<hr/>");
+    }
 }
@@ -0,0 +1,298 @@
+use std::cell::RefCell;
+
+use ublog_doc::render::html::{heading_tag, inline_style_tags, HeadingIds, HtmlRenderOptions};
+use ublog_doc::{DocumentNode, DocumentNodeTag, InlineStyle};
+
+use crate::render::html::{HtmlElement, HtmlNode};
+
+impl<'a> From<&'a DocumentNode> for HtmlNode {
+    /// Convert a document tree into an HTML document tree, using the default render options.
+    ///
+    /// See [`HtmlNode::from_document`] for details.
+    fn from(node: &'a DocumentNode) -> Self {
+        Self::from_document(node, &HtmlRenderOptions::default())
+    }
+}
+
+impl HtmlNode {
+    /// Convert a document tree into an HTML document tree. Embedded resources are linked to the URLs given by the
+    /// resource URL resolver in the render options.
+    ///
+    /// The produced HTML closely follows the output of the HTML renderer in `ublog_doc::render::html`, except that the
+    /// root node and unlinked inline nodes are wrapped in `div` and `span` elements, respectively. The conversion is
+    /// recursive, so callers should validate the depth of untrusted document trees beforehand.
+    pub fn from_document(node: &DocumentNode, options: &HtmlRenderOptions) -> Self {
+        DocumentConverter {
+            options,
+            heading_ids: RefCell::default(),
+        }
+        .convert(node)
+    }
+}
+
+struct DocumentConverter<'o> {
+    options: &'o HtmlRenderOptions,
+    heading_ids: RefCell<HeadingIds>,
+}
+
+impl<'o> DocumentConverter<'o> {
+    fn convert(&self, node: &DocumentNode) -> HtmlNode {
+        match &node.tag {
+            DocumentNodeTag::Root => self.create_container("div", node),
+            DocumentNodeTag::Paragraph => self.create_container("p", node),
+            DocumentNodeTag::Heading { level, .. } => {
+                let mut el = self.create_element(heading_tag(*level), node);
+                if let Some(id) = node.heading_id() {
+                    let id = self.heading_ids.borrow_mut().make_unique(id);
+                    set_prop(&mut el, "id", &id);
+                }
+                HtmlNode::Element(el)
+            }
+            DocumentNodeTag::Callout { emoji } => {
+                let mut el = self.create_element("div", node);
+                set_prop(&mut el, "class", "callout");
+                if let Some(emoji) = emoji {
+                    let mut emoji_el = HtmlElement::new("span");
+                    set_prop(&mut emoji_el, "class", "callout-emoji");
+                    emoji_el.children.push(HtmlNode::Text(emoji.clone()));
+                    el.children.insert(0, HtmlNode::Element(emoji_el));
+                }
+                HtmlNode::Element(el)
+            }
+            DocumentNodeTag::Quote => self.create_container("blockquote", node),
+            DocumentNodeTag::List { is_ordered } => {
+                self.create_container(if *is_ordered { "ol" } else { "ul" }, node)
+            }
+            DocumentNodeTag::ListItem => self.create_container("li", node),
+            DocumentNodeTag::TodoItem { checked } => {
+                let mut checkbox_el = HtmlElement::new("input");
+                set_prop(&mut checkbox_el, "type", "checkbox");
+                set_prop(&mut checkbox_el, "disabled", "disabled");
+                if *checked {
+                    set_prop(&mut checkbox_el, "checked", "checked");
+                }
+
+                let mut el = self.create_element("div", node);
+                set_prop(&mut el, "class", "todo-item");
+                el.children.insert(0, HtmlNode::Element(checkbox_el));
+                HtmlNode::Element(el)
+            }
+            DocumentNodeTag::Code {
+                language,
+                caption,
+                code,
+            } => {
+                let mut code_el = HtmlElement::new("code");
+                set_prop(&mut code_el, "class", &format!("language-{}", language));
+                code_el.children.push(HtmlNode::Text(code.clone()));
+
+                let mut pre_el = HtmlElement::new("pre");
+                pre_el.children.push(HtmlNode::Element(code_el));
+                with_caption(HtmlNode::Element(pre_el), caption)
+            }
+            DocumentNodeTag::Equation { expr, caption } => {
+                let mut el = HtmlElement::new("div");
+                set_prop(&mut el, "class", "equation");
+                el.children.push(HtmlNode::Text(format!("$${}$$", expr)));
+                with_caption(HtmlNode::Element(el), caption)
+            }
+            DocumentNodeTag::Image { link, caption } => {
+                let mut el = HtmlElement::new("img");
+                set_prop(&mut el, "src", &self.options.resource_url(link));
+                set_prop(&mut el, "alt", caption.as_deref().unwrap_or_default());
+                with_caption(HtmlNode::Element(el), caption)
+            }
+            DocumentNodeTag::Table { caption } => {
+                let mut el = self.create_element("table", node);
+                if let Some(caption) = caption {
+                    let mut caption_el = HtmlElement::new("caption");
+                    caption_el.children.push(HtmlNode::Text(caption.clone()));
+                    el.children.insert(0, HtmlNode::Element(caption_el));
+                }
+                HtmlNode::Element(el)
+            }
+            DocumentNodeTag::TableRow => self.create_container("tr", node),
+            DocumentNodeTag::TableCell => self.create_container("td", node),
+            DocumentNodeTag::Divider => HtmlNode::Element(HtmlElement::new("hr")),
+            DocumentNodeTag::Toggle { summary } => {
+                let mut summary_el = HtmlElement::new("summary");
+                summary_el.children.push(HtmlNode::Text(summary.clone()));
+
+                let mut el = self.create_element("details", node);
+                el.children.insert(0, HtmlNode::Element(summary_el));
+                HtmlNode::Element(el)
+            }
+            DocumentNodeTag::Columns => {
+                let mut el = self.create_element("div", node);
+                set_prop(&mut el, "class", "columns");
+                HtmlNode::Element(el)
+            }
+            DocumentNodeTag::Column => {
+                let mut el = self.create_element("div", node);
+                set_prop(&mut el, "class", "column");
+                HtmlNode::Element(el)
+            }
+            DocumentNodeTag::Inline { style, link } => self.create_inline(style, link, node),
+            DocumentNodeTag::InlineText { text } => HtmlNode::Text(text.clone()),
+            DocumentNodeTag::InlineCode { code } => {
+                let mut el = HtmlElement::new("code");
+                el.children.push(HtmlNode::Text(code.clone()));
+                HtmlNode::Element(el)
+            }
+            DocumentNodeTag::InlineEquation { expr } => {
+                let mut el = HtmlElement::new("span");
+                set_prop(&mut el, "class", "inline-equation");
+                el.children.push(HtmlNode::Text(format!("\\({}\\)", expr)));
+                HtmlNode::Element(el)
+            }
+        }
+    }
+
+    fn convert_children(&self, node: &DocumentNode) -> Vec<HtmlNode> {
+        node.children
+            .iter()
+            .map(|child| self.convert(child))
+            .collect()
+    }
+
+    fn create_element(&self, tag: &'static str, node: &DocumentNode) -> HtmlElement {
+        let mut el = HtmlElement::new(tag);
+        el.children = self.convert_children(node);
+        el
+    }
+
+    fn create_container(&self, tag: &'static str, node: &DocumentNode) -> HtmlNode {
+        HtmlNode::Element(self.create_element(tag, node))
+    }
+
+    fn create_inline(
+        &self,
+        style: &Option<InlineStyle>,
+        link: &Option<String>,
+        node: &DocumentNode,
+    ) -> HtmlNode {
+        let mut children = self.convert_children(node);
+        if let Some(style) = style {
+            let tags: Vec<_> = inline_style_tags(style).collect();
+            for tag in tags.into_iter().rev() {
+                let mut el = HtmlElement::new(tag);
+                el.children = children;
+                children = vec![HtmlNode::Element(el)];
+            }
+        }
+
+        let mut el = HtmlElement::new(if link.is_some() { "a" } else { "span" });
+        if let Some(link) = link {
+            set_prop(&mut el, "href", link);
+        }
+        if let Some(color) = style.as_ref().and_then(|style| style.color.as_ref()) {
+            set_prop(
+                &mut el,
+                "class",
+                &crate::render::styles::get_color_style(color),
+            );
+        }
+        el.children = children;
+        HtmlNode::Element(el)
+    }
+}
+
+fn with_caption(content: HtmlNode, caption: &Option<String>) -> HtmlNode {
+    let caption = match caption {
+        Some(caption) if !caption.is_empty() => caption,
+        _ => return content,
+    };
+
+    let mut caption_el = HtmlElement::new("figcaption");
+    caption_el.children.push(HtmlNode::Text(caption.clone()));
+
+    let mut el = HtmlElement::new("figure");
+    el.children.push(content);
+    el.children.push(HtmlNode::Element(caption_el));
+    HtmlNode::Element(el)
+}
+
+fn set_prop(el: &mut HtmlElement, name: &str, value: &str) {
+    el.props.insert(String::from(name), String::from(value));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ublog_doc::builder::DocumentTreeBuilder;
+
+    #[test]
+    fn test_convert_document_tree() {
+        let mut builder = DocumentTreeBuilder::new();
+        builder
+            .heading(2, |b| {
+                b.text("Title");
+            })
+            .paragraph(|b| {
+                b.text("a < b").node(DocumentNode {
+                    tag: DocumentNodeTag::Inline {
+                        style: Some(InlineStyle {
+                            bold: true,
+                            ..InlineStyle::default()
+                        }),
+                        link: Some(String::from("https://example.com/?a=\"b\"")),
+                    },
+                    children: vec![DocumentNode::new(DocumentNodeTag::InlineText {
+                        text: String::from("link"),
+                    })],
+                });
+            });
+        let root = builder.finish();
+
+        let mut rendered = String::new();
+        HtmlNode::from(&root).to_html_str(&mut rendered);
+
+        assert_eq!(
+            rendered,
+            concat!(
+                r#"<div><h2 id="title">Title</h2><p>a &lt; b"#,
+                r#"<a href="https://example.com/?a=&quot;b&quot;"><strong>link</strong></a>"#,
+                "</p></div>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_convert_duplicate_heading_ids() {
+        let mut builder = DocumentTreeBuilder::new();
+        for _ in 0..2 {
+            builder.heading(2, |b| {
+                b.text("Title");
+            });
+        }
+        let root = builder.finish();
+
+        let mut rendered = String::new();
+        HtmlNode::from(&root).to_html_str(&mut rendered);
+
+        assert_eq!(
+            rendered,
+            r#"<div><h2 id="title">Title</h2><h2 id="title-1">Title</h2></div>"#
+        );
+    }
+
+    #[test]
+    fn test_convert_resolved_resource_url() {
+        let image = DocumentNode::new(DocumentNodeTag::Image {
+            link: ublog_doc::DocumentResourceLink::Embedded {
+                uuid: String::from("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            },
+            caption: None,
+        });
+        let options = HtmlRenderOptions::with_resource_url_template("/blog/api/resources/${uuid}");
+
+        let mut rendered = String::new();
+        HtmlNode::from_document(&image, &options).to_html_str(&mut rendered);
+
+        assert_eq!(
+            rendered,
+            r#"<img alt="" src="/blog/api/resources/67e55044-10b1-426f-9247-bb680e5fe0c8"/>"#
+        );
+    }
+}
@@ -1,4 +1,6 @@
 pub mod block;
 pub mod html;
+#[cfg(feature = "doc-html")]
+mod html_doc;
 pub mod rich_text;
 pub mod styles;
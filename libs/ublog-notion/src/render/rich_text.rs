@@ -28,7 +28,13 @@ pub fn render_rich_text(rt: &RichText) -> DocumentNode {
         RichTextVariants::Equation { equation } => render_equation_rich_text(equation),
     };
 
-    render_style(&rt.annotations, &rt.href, rendered)
+    // Notion usually mirrors the link of a text into `href`, but fall back to the text's own link in case it does not.
+    let link = rt.href.clone().or_else(|| match &rt.variants {
+        RichTextVariants::Text { text } => text.link.as_ref().map(|link| link.url.clone()),
+        _ => None,
+    });
+
+    render_style(&rt.annotations, &link, rendered)
 }
 
 /// Render the given rich text into plain text, discarding any style settings.
@@ -86,6 +92,8 @@ fn render_style(
         style.strike_through = true;
     }
 
+    style.color = ublog_doc::render::styles::normalize_color(&annot.color);
+
     if style == InlineStyle::new() && link.is_none() {
         inner_rendered
     } else {
@@ -98,3 +106,108 @@ fn render_style(
         wrapper
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_rich_text(color: &str) -> RichText {
+        RichText {
+            plain_text: String::from("text"),
+            href: None,
+            annotations: RichTextAnnotations {
+                bold: false,
+                italic: false,
+                strikethrough: false,
+                underline: false,
+                code: false,
+                color: String::from(color),
+            },
+            variants: RichTextVariants::Text {
+                text: TextRichText {
+                    content: String::from("text"),
+                    link: None,
+                },
+            },
+        }
+    }
+
+    fn rendered_color(node: &DocumentNode) -> Option<&str> {
+        match &node.tag {
+            DocumentNodeTag::Inline {
+                style: Some(style), ..
+            } => style.color.as_deref(),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_render_rich_text_foreground_color() {
+        let node = render_rich_text(&create_rich_text("red"));
+        assert_eq!(rendered_color(&node), Some("red"));
+    }
+
+    #[test]
+    fn test_render_rich_text_background_color() {
+        let node = render_rich_text(&create_rich_text("gray_background"));
+        assert_eq!(rendered_color(&node), Some("gray_background"));
+    }
+
+    #[test]
+    fn test_render_rich_text_default_color() {
+        let node = render_rich_text(&create_rich_text("default"));
+        assert!(matches!(node.tag, DocumentNodeTag::InlineText { .. }));
+    }
+
+    #[test]
+    fn test_render_rich_text_bold_link() {
+        let mut rt = create_rich_text("default");
+        rt.annotations.bold = true;
+        rt.variants = RichTextVariants::Text {
+            text: TextRichText {
+                content: String::from("text"),
+                link: Some(Link {
+                    url: String::from("https://example.com/"),
+                }),
+            },
+        };
+
+        let node = render_rich_text(&rt);
+        let mut expected_style = InlineStyle::new();
+        expected_style.bold = true;
+        assert_eq!(
+            node.tag,
+            DocumentNodeTag::Inline {
+                style: Some(expected_style),
+                link: Some(String::from("https://example.com/")),
+            }
+        );
+        assert_eq!(
+            node.children,
+            vec![DocumentNode::new(DocumentNodeTag::InlineText {
+                text: String::from("text")
+            })]
+        );
+    }
+
+    #[test]
+    fn test_render_rich_text_code() {
+        let mut rt = create_rich_text("default");
+        rt.annotations.code = true;
+        let node = render_rich_text(&rt);
+        assert_eq!(
+            node.tag,
+            DocumentNodeTag::InlineCode {
+                code: String::from("text")
+            }
+        );
+
+        let node = render_rich_text(&create_rich_text("default"));
+        assert_eq!(
+            node.tag,
+            DocumentNodeTag::InlineText {
+                text: String::from("text")
+            }
+        );
+    }
+}
@@ -3,8 +3,7 @@ pub fn get_color_style<T>(color: T) -> String
 where
     T: AsRef<str>,
 {
-    let color = color.as_ref();
-    format!("color-{}", color.replace('_', "-"))
+    ublog_doc::render::styles::color_to_css(color)
 }
 
 #[cfg(test)]
@@ -0,0 +1,174 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+use ublog_data::db::Database;
+use ublog_data::models::Delta;
+use ublog_data::storage::sqlite::SqliteStorage;
+use ublog_data::storage::Storage;
+
+use crate::{fallible_step, ExportArgs, ImportArgs};
+
+/// Version of the archive format written by the `export` command.
+const ARCHIVE_VERSION: u32 = 1;
+
+/// A portable archive of a whole database.
+///
+/// An archive is stored as a single BSON document.
+#[derive(Debug, Deserialize, Serialize)]
+struct Archive {
+    version: u32,
+    delta: Delta,
+}
+
+pub(crate) async fn export(args: &ExportArgs) -> Result<(), Box<dyn Error>> {
+    fallible_step!(
+        "initialize logger",
+        crate::utils::logging::init_basic_logger(args.debug)
+    );
+
+    let db_storage = fallible_step!(
+        "initialize database storage",
+        SqliteStorage::new_file(&args.database)
+    );
+    let db = Database::new(db_storage);
+
+    let archive_data = export_archive(&db).await?;
+    fallible_step!(
+        "write archive",
+        tokio::fs::write(&args.output, &archive_data).await
+    );
+    spdlog::info!(
+        "Database exported to {} ({} bytes).",
+        args.output.display(),
+        archive_data.len()
+    );
+
+    Ok(())
+}
+
+pub(crate) async fn import(args: &ImportArgs) -> Result<(), Box<dyn Error>> {
+    fallible_step!(
+        "initialize logger",
+        crate::utils::logging::init_basic_logger(args.debug)
+    );
+
+    let db_storage = fallible_step!(
+        "initialize database storage",
+        SqliteStorage::new_file(&args.database)
+    );
+    let db = Database::new(db_storage);
+
+    let archive_data = fallible_step!("read archive", tokio::fs::read(&args.input).await);
+    import_archive(&archive_data, &db).await?;
+    spdlog::info!("Database imported from {}.", args.input.display());
+
+    Ok(())
+}
+
+/// Export the given database into an archive. Returns the encoded archive.
+async fn export_archive<S>(db: &Database<S>) -> Result<Vec<u8>, Box<dyn Error>>
+where
+    S: Storage,
+{
+    let delta = fallible_step!("export database", db.export_delta().await);
+    spdlog::info!(
+        "{} posts, {} standalone resources and {} commits exported.",
+        delta.added_posts.len(),
+        delta.added_resources.len(),
+        delta.commits.len()
+    );
+
+    let archive = Archive {
+        version: ARCHIVE_VERSION,
+        delta,
+    };
+    let archive_data = fallible_step!("encode archive", bson::to_vec(&archive));
+    Ok(archive_data)
+}
+
+/// Import the given encoded archive into the given database.
+///
+/// The database should be empty, since the commits in the archive must follow the latest commit in the database.
+async fn import_archive<S>(archive_data: &[u8], db: &Database<S>) -> Result<(), Box<dyn Error>>
+where
+    S: Storage,
+{
+    let archive: Archive = fallible_step!("decode archive", bson::from_slice(archive_data));
+    if archive.version != ARCHIVE_VERSION {
+        return Err(Box::<dyn Error>::from(format!(
+            "unsupported archive version {}, expected {}",
+            archive.version, ARCHIVE_VERSION
+        )));
+    }
+
+    fallible_step!("import database", db.apply_delta(&archive.delta).await);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ublog_data::models::{Post, Resource};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn create_post(slug: &str, special: bool) -> Post {
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.text(slug);
+        });
+
+        Post {
+            title: String::from(slug),
+            slug: String::from(slug),
+            author: String::from("msr"),
+            create_timestamp: 1664582400,
+            update_timestamp: 1664582400,
+            category: String::from("category"),
+            tags: vec![String::from("tag")],
+            is_special: special,
+            views: 3,
+            excerpt: String::new(),
+            content: builder.finish(),
+        }
+    }
+
+    fn create_resource(name: &str) -> Resource {
+        Resource {
+            id: Uuid::new_v4(),
+            name: String::from(name),
+            ty: String::from("image/png"),
+            data: name.as_bytes().to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let source = Database::new(SqliteStorage::new_memory().unwrap());
+        source
+            .insert_post(&create_post("normal", false), &[create_resource("image")])
+            .await
+            .unwrap();
+        source
+            .insert_post(&create_post("special", true), &[])
+            .await
+            .unwrap();
+        source
+            .insert_resource(&create_resource("standalone"))
+            .await
+            .unwrap();
+
+        let archive_data = export_archive(&source).await.unwrap();
+        let target = Database::new(SqliteStorage::new_memory().unwrap());
+        import_archive(&archive_data, &target).await.unwrap();
+
+        let to_json = |delta: Delta| serde_json::to_value(delta).unwrap();
+        assert_eq!(
+            to_json(target.export_delta().await.unwrap()),
+            to_json(source.export_delta().await.unwrap())
+        );
+
+        // Importing into a database that already has commits breaks the commit chain.
+        assert!(import_archive(&archive_data, &target).await.is_err());
+    }
+}
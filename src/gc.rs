@@ -0,0 +1,24 @@
+use std::error::Error;
+
+use ublog_data::db::Database;
+use ublog_data::storage::sqlite::SqliteStorage;
+
+use crate::{fallible_step, GcArgs};
+
+pub(crate) async fn gc(args: &GcArgs) -> Result<(), Box<dyn Error>> {
+    fallible_step!(
+        "initialize logger",
+        crate::utils::logging::init_basic_logger(args.debug)
+    );
+
+    let db_storage = fallible_step!(
+        "initialize database storage",
+        SqliteStorage::new_file(&args.database)
+    );
+    let db = Database::new(db_storage);
+
+    let deleted = fallible_step!("collect unreferenced resources", db.gc_resources().await);
+    spdlog::info!("{} unreferenced resources deleted.", deleted);
+
+    Ok(())
+}
@@ -1,5 +1,10 @@
+mod archive;
+mod gc;
+mod markdown;
 mod notion;
 mod server;
+mod storage;
+mod sync;
 mod utils;
 
 use std::error::Error;
@@ -37,6 +42,11 @@ fn main_impl() -> Result<(), Box<dyn Error>> {
     runtime.block_on(async {
         match args {
             UblogArgs::FetchNotion(args) => crate::notion::fetch_notion(&args).await,
+            UblogArgs::ImportMarkdown(args) => crate::markdown::import_markdown(&args).await,
+            UblogArgs::Gc(args) => crate::gc::gc(&args).await,
+            UblogArgs::Export(args) => crate::archive::export(&args).await,
+            UblogArgs::Import(args) => crate::archive::import(&args).await,
+            UblogArgs::Sync(args) => crate::sync::sync(&args).await,
             UblogArgs::Serve(args) => crate::server::serve(&args).await,
         }
     })
@@ -51,6 +61,12 @@ fn main_impl() -> Result<(), Box<dyn Error>> {
 )]
 enum UblogArgs {
     FetchNotion(FetchNotionArgs),
+    #[structopt(name = "import-md")]
+    ImportMarkdown(ImportMarkdownArgs),
+    Gc(GcArgs),
+    Export(ExportArgs),
+    Import(ImportArgs),
+    Sync(SyncArgs),
     Serve(ServerArgs),
 }
 
@@ -68,6 +84,122 @@ struct FetchNotionArgs {
     /// Target Notion database ID.
     notion_database_id: String,
 
+    /// Timeout of each request sent to Notion or sent to download a resource, in seconds.
+    #[structopt(long, default_value = "30")]
+    timeout: u64,
+
+    /// Only fetch posts in this category.
+    #[structopt(long)]
+    category: Option<String>,
+
+    /// Only fetch posts updated on or after this date, in the form of `YYYY-MM-DD`.
+    #[structopt(long)]
+    updated_since: Option<String>,
+
+    /// Link images hosted on this domain or its subdomains directly instead of downloading them. Can be specified
+    /// multiple times.
+    #[structopt(long = "external-domain")]
+    external_domains: Vec<String>,
+
+    /// Fetch all posts rather than only the posts edited since the last fetch.
+    #[structopt(long)]
+    full: bool,
+
+    /// Remove empty paragraphs from the content of the fetched posts.
+    #[structopt(long)]
+    prune_empty: bool,
+
+    /// Report the posts that would be written into the database without writing them.
+    #[structopt(long)]
+    dry_run: bool,
+
+    /// Enable debug output.
+    #[structopt(long)]
+    debug: bool,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "import-md", about = "Import Markdown files as posts")]
+struct ImportMarkdownArgs {
+    /// Path to the ublog database.
+    #[structopt(short, long, default_value = "ublog.db")]
+    database: PathBuf,
+
+    /// Path to the directory containing the Markdown files.
+    #[structopt(long)]
+    dir: PathBuf,
+
+    /// Author of the posts that do not specify an author in their front matter.
+    #[structopt(long)]
+    author: Option<String>,
+
+    /// Enable debug output.
+    #[structopt(long)]
+    debug: bool,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "gc",
+    about = "Delete resources that are not referenced by any post"
+)]
+struct GcArgs {
+    /// Path to the ublog database.
+    #[structopt(short, long, default_value = "ublog.db")]
+    database: PathBuf,
+
+    /// Enable debug output.
+    #[structopt(long)]
+    debug: bool,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "export", about = "Export the database into a portable archive")]
+struct ExportArgs {
+    /// Path to the ublog database.
+    #[structopt(short, long, default_value = "ublog.db")]
+    database: PathBuf,
+
+    /// Path to the archive file to be written.
+    #[structopt(short, long)]
+    output: PathBuf,
+
+    /// Enable debug output.
+    #[structopt(long)]
+    debug: bool,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "import",
+    about = "Import an archive written by the export command into an empty database"
+)]
+struct ImportArgs {
+    /// Path to the ublog database.
+    #[structopt(short, long, default_value = "ublog.db")]
+    database: PathBuf,
+
+    /// Path to the archive file to be imported.
+    #[structopt(short, long)]
+    input: PathBuf,
+
+    /// Enable debug output.
+    #[structopt(long)]
+    debug: bool,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "sync",
+    about = "Synchronize the data in one storage to another storage"
+)]
+struct SyncArgs {
+    /// The source storage, either a path to a ublog database or `tcp://<host>:<port>` for a remote storage server.
+    source: String,
+
+    /// The destination storage, in the same form as the source storage.
+    destination: String,
+
     /// Enable debug output.
     #[structopt(long)]
     debug: bool,
@@ -92,6 +224,12 @@ struct ServerArgs {
     #[structopt(short, long, default_value = "ublog.db")]
     database: PathBuf,
 
+    /// Address of a remote storage server to serve the database from, in the form of `host:port`.
+    ///
+    /// When specified, the local database is not used and the server acts as a read replica of the remote one.
+    #[structopt(long)]
+    remote: Option<String>,
+
     /// Enable debug output.
     #[structopt(long)]
     debug: bool,
@@ -0,0 +1,230 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, OffsetDateTime};
+use ublog_data::db::Database;
+use ublog_data::models::Post;
+use ublog_data::storage::sqlite::SqliteStorage;
+use ublog_data::storage::Storage;
+
+use crate::{fallible_step, ImportMarkdownArgs};
+
+/// The delimiter line that surrounds the front matter of Markdown files.
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+pub(crate) async fn import_markdown(args: &ImportMarkdownArgs) -> Result<(), Box<dyn Error>> {
+    fallible_step!(
+        "initialize logger",
+        crate::utils::logging::init_basic_logger(args.debug)
+    );
+
+    let db_storage = fallible_step!(
+        "initialize database storage",
+        SqliteStorage::new_file(&args.database)
+    );
+    let db = Database::new(db_storage);
+
+    import_markdown_dir(&args.dir, args.author.as_deref(), &db).await
+}
+
+/// Import all Markdown files directly under the given directory as posts.
+///
+/// Every file is imported independently, so a failure to import one file does not prevent the other files from being
+/// imported. An error is returned if any file fails to import.
+async fn import_markdown_dir<S>(
+    dir: &Path,
+    default_author: Option<&str>,
+    db: &Database<S>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage,
+{
+    let paths = fallible_step!("list Markdown files", list_markdown_files(dir).await);
+    spdlog::info!("{} Markdown files found in {}.", paths.len(), dir.display());
+
+    let mut failed_count = 0;
+    for path in &paths {
+        match import_markdown_file(path, default_author, db).await {
+            Ok(slug) => spdlog::info!("Imported post: {} - {}", slug, path.display()),
+            Err(err) => {
+                spdlog::error!("Failed to import {}: {}", path.display(), err);
+                failed_count += 1;
+            }
+        }
+    }
+
+    if failed_count > 0 {
+        return Err(Box::<dyn Error>::from(format!(
+            "{} of {} Markdown files failed to import",
+            failed_count,
+            paths.len()
+        )));
+    }
+
+    Ok(())
+}
+
+async fn list_markdown_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "md") && entry.file_type().await?.is_file() {
+            paths.push(path);
+        }
+    }
+
+    // Import the files in a deterministic order.
+    paths.sort();
+    Ok(paths)
+}
+
+/// Import the given Markdown file as a post. Returns the slug of the imported post.
+async fn import_markdown_file<S>(
+    path: &Path,
+    default_author: Option<&str>,
+    db: &Database<S>,
+) -> Result<String, Box<dyn Error>>
+where
+    S: Storage,
+{
+    let input = fallible_step!("read file", tokio::fs::read_to_string(path).await);
+    let post = parse_markdown_post(&input, default_author)?;
+    fallible_step!("insert post", db.insert_post(&post, &[]).await);
+    Ok(post.slug)
+}
+
+/// Front matter of Markdown posts.
+#[derive(Debug, Deserialize)]
+struct FrontMatter {
+    title: String,
+    slug: String,
+    category: String,
+    date: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    special: bool,
+}
+
+/// Parse the given Markdown document, which starts with a YAML front matter, into a post.
+fn parse_markdown_post(input: &str, default_author: Option<&str>) -> Result<Post, Box<dyn Error>> {
+    let (front_matter, body) = split_front_matter(input)
+        .ok_or("missing front matter: the file must start with a `---` delimited YAML block")?;
+    let front_matter: FrontMatter =
+        fallible_step!("parse front matter", serde_yaml::from_str(front_matter));
+
+    let author = front_matter
+        .author
+        .or_else(|| default_author.map(String::from))
+        .ok_or("missing author: specify `author` in the front matter or pass --author")?;
+    let timestamp = fallible_step!("parse date", parse_date(&front_matter.date));
+    let content = fallible_step!(
+        "parse Markdown",
+        ublog_doc::parse::markdown::parse_markdown(body)
+    );
+
+    Ok(Post {
+        title: front_matter.title,
+        slug: front_matter.slug,
+        author,
+        create_timestamp: timestamp,
+        update_timestamp: timestamp,
+        category: front_matter.category,
+        tags: front_matter.tags,
+        is_special: front_matter.special,
+        views: 0,
+        excerpt: String::new(),
+        content,
+    })
+}
+
+/// Split the given Markdown document into its front matter and its body.
+fn split_front_matter(input: &str) -> Option<(&str, &str)> {
+    let mut lines = input.split_inclusive('\n');
+    if lines.next()?.trim_end() != FRONT_MATTER_DELIMITER {
+        return None;
+    }
+
+    let front_matter_start = input.find('\n')? + 1;
+    let mut offset = front_matter_start;
+    for line in lines {
+        if line.trim_end() == FRONT_MATTER_DELIMITER {
+            let front_matter = &input[front_matter_start..offset];
+            let body = &input[offset + line.len()..];
+            return Some((front_matter, body));
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Parse the given date into a Unix timestamp.
+///
+/// The date is either an RFC 3339 date time or a date in the form of `YYYY-MM-DD`, which is taken as midnight UTC.
+fn parse_date(date: &str) -> Result<i64, time::error::Parse> {
+    if let Ok(date_time) = OffsetDateTime::parse(date, &Rfc3339) {
+        return Ok(date_time.unix_timestamp());
+    }
+
+    let format = time::format_description::parse("[year]-[month]-[day]").unwrap();
+    let date = Date::parse(date, &format)?;
+    Ok(date.midnight().assume_utc().unix_timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_POST: &str = r#"---
+title: Hello Markdown
+slug: hello-markdown
+category: test
+tags: [a, b]
+date: 2022-10-01
+---
+# Hello
+
+This is a **test**.
+"#;
+
+    #[tokio::test]
+    async fn test_import_markdown_dir() {
+        let dir = std::env::temp_dir().join(format!("ublog-import-md-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("hello.md"), TEST_POST).unwrap();
+        std::fs::write(dir.join("ignored.txt"), "not a post").unwrap();
+
+        let db = Database::new(SqliteStorage::new_memory().unwrap());
+        let ret = import_markdown_dir(&dir, Some("tester"), &db).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        ret.unwrap();
+
+        let post = db.get_post("hello-markdown").await.unwrap().unwrap();
+        assert_eq!(post.title, "Hello Markdown");
+        assert_eq!(post.author, "tester");
+        assert_eq!(post.category, "test");
+        assert_eq!(post.tags, vec!["a", "b"]);
+        assert_eq!(post.create_timestamp, 1664582400);
+        assert_eq!(post.content.to_plain_text(), "Hello\nThis is a test.");
+    }
+
+    #[test]
+    fn test_parse_markdown_post_missing_field() {
+        let input = TEST_POST.replace("slug: hello-markdown\n", "");
+        let err = parse_markdown_post(&input, Some("tester")).unwrap_err();
+        assert!(err.to_string().contains("missing field `slug`"));
+
+        let err = parse_markdown_post(TEST_POST, None).unwrap_err();
+        assert!(err.to_string().contains("missing author"));
+
+        let err = parse_markdown_post("# Hello\n", Some("tester")).unwrap_err();
+        assert!(err.to_string().contains("missing front matter"));
+    }
+}
@@ -1,11 +1,18 @@
+use std::collections::HashMap;
 use std::error::Error;
+use std::time::Duration;
 
-use ublog_data::db::Database;
-use ublog_data::models::Resource;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use ublog_data::db::{Database, DatabaseError};
+use ublog_data::models::{Post, Resource};
 use ublog_data::storage::sqlite::SqliteStorage;
 use ublog_data::storage::Storage;
+use ublog_doc::{DocumentNode, DocumentNodeTag, DocumentNodeVisitor, DocumentResourceLink};
 use ublog_notion::api::NotionApi;
-use ublog_notion::blog::NotionPost;
+use ublog_notion::blog::{ExtractResourcesOptions, NotionPost, PostsFilter};
+use uuid::Uuid;
 
 use crate::{fallible_step, FetchNotionArgs};
 
@@ -15,7 +22,8 @@ pub(crate) async fn fetch_notion(args: &FetchNotionArgs) -> Result<(), Box<dyn E
         crate::utils::logging::init_basic_logger(args.debug)
     );
 
-    let notion_api = NotionApi::new(&args.token);
+    let mut notion_api = NotionApi::new(&args.token);
+    notion_api.set_request_timeout(Duration::from_secs(args.timeout));
 
     let db_storage = fallible_step!(
         "initialize database storage",
@@ -23,9 +31,14 @@ pub(crate) async fn fetch_notion(args: &FetchNotionArgs) -> Result<(), Box<dyn E
     );
     let db = Database::new(db_storage);
 
+    let sync_start = OffsetDateTime::now_utc();
+    let filter = fallible_step!("load sync cursor", create_posts_filter(args, &db).await);
+    if let Some(edited_since) = &filter.edited_since {
+        spdlog::info!("Fetching posts edited since {}.", edited_since);
+    }
     let posts = fallible_step!(
         "fetch posts list",
-        ublog_notion::blog::get_posts(&notion_api, &args.notion_database_id).await
+        ublog_notion::blog::get_posts(&notion_api, &args.notion_database_id, &filter).await
     );
     spdlog::info!(
         "{} posts listed in the target Notion database.",
@@ -42,15 +55,181 @@ pub(crate) async fn fetch_notion(args: &FetchNotionArgs) -> Result<(), Box<dyn E
         updated_posts
     );
 
-    futures::future::join_all(
+    let extract_options = ExtractResourcesOptions {
+        external_domains: args.external_domains.clone(),
+        request_timeout: Duration::from_secs(args.timeout),
+    };
+    let prepared_posts = futures::future::join_all(
         diff_posts
             .into_iter()
-            .map(|post| apply_diff_post(post, &notion_api, &db)),
+            .map(|post| prepare_diff_post(post, &notion_api, &extract_options, args.prune_empty)),
     )
     .await
     .into_iter()
     .collect::<Result<Vec<_>, _>>()?;
 
+    let sync_cursor = if is_full_listing(args) {
+        Some((args.notion_database_id.as_str(), sync_start))
+    } else {
+        None
+    };
+    write_prepared_posts(prepared_posts, sync_cursor, args.dry_run, &db).await
+}
+
+/// Write the given prepared posts and their resources into the database, and then save the sync cursor if one is
+/// given as the Notion database ID and the start time of the fetch.
+///
+/// Posts that are skipped during preparation are given as `None`. The sync cursor is not saved if any post is skipped,
+/// so that the skipped posts are fetched again by the next sync.
+///
+/// If `dry_run` is set, the changes that would be made are only reported, and neither the posts nor the sync cursor are
+/// written.
+async fn write_prepared_posts<S>(
+    prepared_posts: Vec<Option<(DiffPost, Vec<Resource>)>>,
+    sync_cursor: Option<(&str, OffsetDateTime)>,
+    dry_run: bool,
+    db: &Database<S>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage,
+{
+    let skipped_posts = prepared_posts.iter().filter(|p| p.is_none()).count();
+    let mut prepared_posts: Vec<_> = prepared_posts.into_iter().flatten().collect();
+
+    dedup_post_resources(&mut prepared_posts, db).await?;
+    retain_changed_posts(&mut prepared_posts);
+
+    if dry_run {
+        report_prepared_posts(&prepared_posts);
+        return Ok(());
+    }
+
+    apply_prepared_posts(prepared_posts, db).await?;
+
+    if let Some((notion_database_id, sync_start)) = sync_cursor {
+        if skipped_posts == 0 {
+            save_sync_cursor(notion_database_id, sync_start, db).await?;
+        } else {
+            spdlog::warn!(
+                "{} posts are skipped, so the sync cursor is left unchanged.",
+                skipped_posts
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the given prepared posts and their resources into the database.
+async fn apply_prepared_posts<S>(
+    prepared_posts: Vec<(DiffPost, Vec<Resource>)>,
+    db: &Database<S>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage,
+{
+    let mut posts_to_insert = Vec::new();
+    let mut posts_to_update = Vec::new();
+    for (post, resources) in prepared_posts {
+        match post {
+            DiffPost::New(p) => posts_to_insert.push((p, resources)),
+            DiffPost::Updated(p, _) => posts_to_update.push((p, resources)),
+        }
+    }
+
+    // New posts are inserted within a single batch to avoid the overhead of per-post transactions.
+    insert_posts(posts_to_insert, db).await?;
+
+    futures::future::join_all(
+        posts_to_update
+            .iter()
+            .map(|(post, resources)| update_post(post, resources, db)),
+    )
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(())
+}
+
+/// Report the changes that the given prepared posts would make to the database, without making them.
+fn report_prepared_posts(prepared_posts: &[(DiffPost, Vec<Resource>)]) {
+    for (post, resources) in prepared_posts {
+        let action = if post.is_new() { "New" } else { "Updated" };
+        let p = post.post();
+        spdlog::info!(
+            "[dry run] {} post: {} - {} ({} resources)",
+            action,
+            p.post.slug,
+            p.notion_page_id,
+            resources.len()
+        );
+    }
+
+    spdlog::info!(
+        "[dry run] {} posts would be written. The database is left unchanged.",
+        prepared_posts.len()
+    );
+}
+
+/// Key of the metadata entry that records when posts were last fully fetched from the given Notion database.
+fn sync_cursor_key(notion_database_id: &str) -> String {
+    format!("notion.sync_cursor.{}", notion_database_id)
+}
+
+/// Notion rounds the last edited time of pages down to minutes, so pages edited shortly before a fetch may appear to be
+/// edited before the fetch starts. The sync cursor is moved back by this margin so that such pages are not missed.
+const SYNC_CURSOR_MARGIN: time::Duration = time::Duration::minutes(2);
+
+/// Determine whether the given arguments list all posts in the Notion database, apart from the ones that have not
+/// been edited since the last fetch.
+///
+/// Only such fetches advance the sync cursor, since posts left out by other filters may have been edited.
+fn is_full_listing(args: &FetchNotionArgs) -> bool {
+    args.category.is_none() && args.updated_since.is_none()
+}
+
+/// Create the filter of posts to be fetched.
+///
+/// Unless a full fetch is requested, only posts edited since the sync cursor of the Notion database are fetched.
+async fn create_posts_filter<S>(
+    args: &FetchNotionArgs,
+    db: &Database<S>,
+) -> Result<PostsFilter, DatabaseError<S::Error>>
+where
+    S: Storage,
+{
+    let edited_since = if args.full {
+        None
+    } else {
+        db.get_metadata(&sync_cursor_key(&args.notion_database_id))
+            .await?
+    };
+
+    Ok(PostsFilter {
+        category: args.category.clone(),
+        updated_since: args.updated_since.clone(),
+        edited_since,
+    })
+}
+
+async fn save_sync_cursor<S>(
+    notion_database_id: &str,
+    sync_start: OffsetDateTime,
+    db: &Database<S>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Storage,
+{
+    let cursor = fallible_step!(
+        "format sync cursor",
+        (sync_start - SYNC_CURSOR_MARGIN).format(&Rfc3339)
+    );
+    fallible_step!(
+        "save sync cursor",
+        db.set_metadata(&sync_cursor_key(notion_database_id), &cursor)
+            .await
+    );
     Ok(())
 }
 
@@ -65,7 +244,7 @@ where
         match db.get_post(&p.post.slug).await {
             Ok(Some(post)) => {
                 if p.post.update_timestamp > post.update_timestamp {
-                    Ok(Some(DiffPost::Updated(p)))
+                    Ok(Some(DiffPost::Updated(p, Box::new(post))))
                 } else {
                     Ok(None)
                 }
@@ -89,7 +268,7 @@ where
 #[derive(Debug)]
 enum DiffPost {
     New(NotionPost),
-    Updated(NotionPost),
+    Updated(NotionPost, Box<Post>),
 }
 
 impl DiffPost {
@@ -98,46 +277,207 @@ impl DiffPost {
     }
 
     fn is_updated(&self) -> bool {
-        matches!(self, Self::Updated(_))
+        matches!(self, Self::Updated(..))
     }
 
     fn post(&self) -> &NotionPost {
         match self {
             Self::New(p) => p,
-            Self::Updated(p) => p,
+            Self::Updated(p, _) => p,
         }
     }
 
     fn post_mut(&mut self) -> &mut NotionPost {
         match self {
             Self::New(p) => p,
-            Self::Updated(p) => p,
+            Self::Updated(p, _) => p,
         }
     }
 }
 
-async fn apply_diff_post<S>(
+/// Fetch the content and the resources of the given diff post.
+///
+/// Empty paragraphs are removed from the content of the post if `prune_empty` is set. Returns `None` if some resources
+/// of the post cannot be fetched.
+async fn prepare_diff_post(
     mut post: DiffPost,
     api: &NotionApi,
+    extract_options: &ExtractResourcesOptions,
+    prune_empty: bool,
+) -> Result<Option<(DiffPost, Vec<Resource>)>, Box<dyn Error>> {
+    fallible_step!(
+        format!("fetch content of post {}", post.post().post.slug),
+        ublog_notion::blog::get_post_content(api, post.post_mut()).await
+    );
+    if prune_empty {
+        post.post_mut().post.content.prune_empty();
+    }
+
+    Ok(extract_post_resources(post, extract_options).await)
+}
+
+/// Fetch the resources of the given diff post, or return `None` if some of them cannot be fetched.
+///
+/// The content of a post whose resources cannot be fetched links to the original URLs of the resources, which Notion
+/// expires after a while. Such posts are skipped so that they are fetched again by the next sync.
+async fn extract_post_resources(
+    mut post: DiffPost,
+    extract_options: &ExtractResourcesOptions,
+) -> Option<(DiffPost, Vec<Resource>)> {
+    let extracted =
+        ublog_notion::blog::extract_notion_resources(post.post_mut(), extract_options).await;
+    if extracted.failures.is_empty() {
+        return Some((post, extracted.resources));
+    }
+
+    for failure in &extracted.failures {
+        spdlog::warn!(
+            "Failed to fetch resource of post {}: {}",
+            post.post().post.slug,
+            failure
+        );
+    }
+    spdlog::warn!("Skipped post {}.", post.post().post.slug);
+    None
+}
+
+/// Make the resources of the given posts reuse existing resources with identical data.
+///
+/// A resource is identical to another resource if they have the same content hash. Identical resources within the
+/// given posts, as well as resources identical to a resource already in the database, are given the ID of the first
+/// such resource, and the links in the post contents are updated accordingly. Resources that are not in the database
+/// yet are kept in the resource lists so that they are inserted together with the posts.
+async fn dedup_post_resources<S>(
+    posts: &mut [(DiffPost, Vec<Resource>)],
     db: &Database<S>,
 ) -> Result<(), Box<dyn Error>>
 where
     S: Storage,
 {
-    fallible_step!(
-        format!("fetch content of post {}", post.post().post.slug),
-        ublog_notion::blog::get_post_content(api, post.post_mut()).await
-    );
+    let mut known_ids: HashMap<String, Uuid> = HashMap::new();
 
-    let resources = fallible_step!(
-        format!("extract resources in post {}", post.post().post.slug),
-        ublog_notion::blog::extract_notion_resources(post.post_mut()).await
-    );
+    for (post, resources) in posts {
+        let mut replaced_ids = HashMap::new();
+        for res in resources.iter_mut() {
+            let hash = res.content_hash();
+            let existing_id = match known_ids.get(&hash) {
+                Some(id) => Some(*id),
+                None => fallible_step!(
+                    format!("find resource {}", hash),
+                    db.find_resource_by_hash(&hash).await
+                )
+                .map(|meta| meta.id),
+            };
+
+            match existing_id {
+                Some(id) if id != res.id => {
+                    spdlog::debug!(
+                        "Reusing resource {} for {}/{}",
+                        id,
+                        post.post().post.slug,
+                        res.name
+                    );
+                    replaced_ids.insert(res.id, id);
+                    res.id = id;
+                    res.name = Resource::file_name(&id, &res.ty);
+                }
+                Some(_) => {}
+                None => {
+                    known_ids.insert(hash, res.id);
+                }
+            }
+        }
+
+        // A post may refer to the same resource multiple times, but it only needs to be stored once.
+        let mut seen_ids = Vec::with_capacity(resources.len());
+        resources.retain(|res| {
+            if seen_ids.contains(&res.id) {
+                return false;
+            }
+            seen_ids.push(res.id);
+            true
+        });
+
+        if !replaced_ids.is_empty() {
+            replace_embedded_links(&mut post.post_mut().post.content, &replaced_ids);
+        }
+    }
+
+    Ok(())
+}
 
-    match &post {
-        DiffPost::New(p) => insert_post(p, &resources, db).await,
-        DiffPost::Updated(p) => update_post(p, &resources, db).await,
+fn replace_embedded_links(root: &mut DocumentNode, replaced_ids: &HashMap<Uuid, Uuid>) {
+    struct Visitor<'a> {
+        replaced_ids: &'a HashMap<Uuid, Uuid>,
     }
+
+    impl<'a> DocumentNodeVisitor for Visitor<'a> {
+        fn visit_mut(&mut self, node: &mut DocumentNode) {
+            if let DocumentNodeTag::Image {
+                link: DocumentResourceLink::Embedded { uuid },
+                ..
+            } = &mut node.tag
+            {
+                let new_id = Uuid::try_parse(uuid)
+                    .ok()
+                    .and_then(|id| self.replaced_ids.get(&id));
+                if let Some(new_id) = new_id {
+                    *uuid = format!("{}", new_id.as_hyphenated());
+                }
+            }
+        }
+    }
+
+    root.visit_mut(&mut Visitor { replaced_ids });
+}
+
+/// Drop the updated posts that turn out to be unchanged and do not need to be written into the database.
+///
+/// The posts must have gone through [`dedup_post_resources`] so that images whose data is unchanged link to the
+/// resources already stored in the database.
+fn retain_changed_posts(posts: &mut Vec<(DiffPost, Vec<Resource>)>) {
+    posts.retain(|(post, _)| match post {
+        DiffPost::New(_) => true,
+        DiffPost::Updated(p, old_post) => {
+            let changed = is_post_changed(old_post, &p.post);
+            if !changed {
+                spdlog::info!(
+                    "Skipped unchanged post: {} - {}",
+                    p.post.slug,
+                    p.notion_page_id
+                );
+            }
+            changed
+        }
+    });
+}
+
+/// Determine whether the freshly fetched post differs from the post stored in the database.
+///
+/// Notion bumps the last edited time of a page even if nothing visible changes, so the post metadata and content are
+/// compared instead. Embedded images are identified by the content hashes of their data, which
+/// [`dedup_post_resources`] turns into the UUIDs of the stored resources.
+fn is_post_changed(old: &Post, new: &Post) -> bool {
+    if old.title != new.title
+        || old.author != new.author
+        || old.create_timestamp != new.create_timestamp
+        || old.category != new.category
+        || old.tags != new.tags
+        || old.is_special != new.is_special
+    {
+        return true;
+    }
+
+    let changes = old.content.diff(&new.content);
+    if !changes.is_empty() {
+        spdlog::debug!(
+            "{} content changes found in post {}",
+            changes.len(),
+            new.slug
+        );
+    }
+
+    !changes.is_empty()
 }
 
 async fn update_post<S>(
@@ -166,28 +506,251 @@ where
     Ok(())
 }
 
-async fn insert_post<S>(
-    post: &NotionPost,
-    resources: &[Resource],
+async fn insert_posts<S>(
+    posts: Vec<(NotionPost, Vec<Resource>)>,
     db: &Database<S>,
 ) -> Result<(), Box<dyn Error>>
 where
     S: Storage,
 {
+    if posts.is_empty() {
+        return Ok(());
+    }
+
+    let mut notion_page_ids = Vec::with_capacity(posts.len());
+    let mut db_posts = Vec::with_capacity(posts.len());
+    for (post, resources) in posts {
+        notion_page_ids.push(post.notion_page_id);
+        db_posts.push((post.post, resources));
+    }
+
     fallible_step!(
-        format!("insert post {}", post.post.slug),
-        db.insert_post(&post.post, resources).await
+        format!("insert {} new posts", db_posts.len()),
+        db.insert_posts(&db_posts).await
     );
 
-    spdlog::info!("New post: {} - {}", post.post.slug, post.notion_page_id);
-    for r in resources {
-        spdlog::info!(
-            "New post resource: {}/{} - {}",
-            post.post.slug,
-            r.name,
-            r.ty
-        );
+    for ((post, resources), notion_page_id) in db_posts.iter().zip(&notion_page_ids) {
+        spdlog::info!("New post: {} - {}", post.slug, notion_page_id);
+        for r in resources {
+            spdlog::info!("New post resource: {}/{} - {}", post.slug, r.name, r.ty);
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_post_with_image(slug: &str, data: &[u8]) -> (DiffPost, Vec<Resource>) {
+        let resource = Resource {
+            id: Uuid::new_v4(),
+            name: format!("{}.png", slug),
+            ty: String::from("image/png"),
+            data: data.to_vec(),
+        };
+
+        let mut content = DocumentNode::new_empty();
+        content
+            .children
+            .push(DocumentNode::new(DocumentNodeTag::Image {
+                link: DocumentResourceLink::Embedded {
+                    uuid: format!("{}", resource.id.as_hyphenated()),
+                },
+                caption: None,
+            }));
+
+        let post = NotionPost {
+            notion_page_id: format!("page-{}", slug),
+            post: Post {
+                title: String::from(slug),
+                slug: String::from(slug),
+                author: String::from("msr"),
+                create_timestamp: 0,
+                update_timestamp: 0,
+                category: String::from("category"),
+                tags: Vec::new(),
+                is_special: false,
+                views: 0,
+                excerpt: String::new(),
+                content,
+            },
+            canonical_url: None,
+        };
+        (DiffPost::New(post), vec![resource])
+    }
+
+    fn embedded_uuid(post: &Post) -> String {
+        match &post.content.children[0].tag {
+            DocumentNodeTag::Image {
+                link: DocumentResourceLink::Embedded { uuid },
+                ..
+            } => uuid.clone(),
+            tag => panic!("unexpected node: {:?}", tag),
+        }
+    }
+
+    fn create_fetch_args() -> FetchNotionArgs {
+        FetchNotionArgs {
+            token: String::from("token"),
+            database: std::path::PathBuf::from("ublog.db"),
+            notion_database_id: String::from("notion-db"),
+            timeout: 30,
+            category: None,
+            updated_since: None,
+            external_domains: Vec::new(),
+            full: false,
+            prune_empty: false,
+            dry_run: false,
+            debug: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_posts_filter_sync_cursor() {
+        let db = Database::new(SqliteStorage::new_memory().unwrap());
+        let args = create_fetch_args();
+
+        // The first fetch lists all posts.
+        let filter = create_posts_filter(&args, &db).await.unwrap();
+        assert!(filter.edited_since.is_none());
+
+        let sync_start = OffsetDateTime::parse("2022-10-01T12:00:00Z", &Rfc3339).unwrap();
+        save_sync_cursor(&args.notion_database_id, sync_start, &db)
+            .await
+            .unwrap();
+
+        // The second fetch only lists posts edited since the first fetch.
+        let filter = create_posts_filter(&args, &db).await.unwrap();
+        assert_eq!(filter.edited_since.as_deref(), Some("2022-10-01T11:58:00Z"));
+
+        let full_args = FetchNotionArgs {
+            full: true,
+            ..create_fetch_args()
+        };
+        let filter = create_posts_filter(&full_args, &db).await.unwrap();
+        assert!(filter.edited_since.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_leaves_database_unchanged() {
+        let db = Database::new(SqliteStorage::new_memory().unwrap());
+        let args = create_fetch_args();
+        let sync_start = OffsetDateTime::parse("2022-10-01T12:00:00Z", &Rfc3339).unwrap();
+        let sync_cursor = Some((args.notion_database_id.as_str(), sync_start));
+        let cursor_key = sync_cursor_key(&args.notion_database_id);
+
+        let prepared_posts = vec![Some(create_post_with_image("first", b"image"))];
+        write_prepared_posts(prepared_posts, sync_cursor, true, &db)
+            .await
+            .unwrap();
+        assert!(db.get_post("first").await.unwrap().is_none());
+        assert!(db.get_resource_metas().await.unwrap().is_empty());
+        assert!(db.get_metadata(&cursor_key).await.unwrap().is_none());
+
+        let prepared_posts = vec![Some(create_post_with_image("first", b"image"))];
+        write_prepared_posts(prepared_posts, sync_cursor, false, &db)
+            .await
+            .unwrap();
+        assert!(db.get_post("first").await.unwrap().is_some());
+        assert_eq!(db.get_resource_metas().await.unwrap().len(), 1);
+        assert!(db.get_metadata(&cursor_key).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failed_resource_leaves_sync_cursor_unchanged() {
+        let db = Database::new(SqliteStorage::new_memory().unwrap());
+        let args = create_fetch_args();
+        let sync_start = OffsetDateTime::parse("2022-10-01T12:00:00Z", &Rfc3339).unwrap();
+        let sync_cursor = Some((args.notion_database_id.as_str(), sync_start));
+        let cursor_key = sync_cursor_key(&args.notion_database_id);
+
+        // The URL cannot be parsed, so fetching the resource fails without being retried.
+        let (mut failed_post, _) = create_post_with_image("failed", b"image");
+        failed_post.post_mut().post.content.children[0].tag = DocumentNodeTag::Image {
+            link: DocumentResourceLink::External {
+                url: String::from("not a url"),
+            },
+            caption: None,
+        };
+        let failed_post =
+            extract_post_resources(failed_post, &ExtractResourcesOptions::default()).await;
+        assert!(failed_post.is_none());
+
+        let prepared_posts = vec![Some(create_post_with_image("first", b"image")), failed_post];
+        write_prepared_posts(prepared_posts, sync_cursor, false, &db)
+            .await
+            .unwrap();
+        assert!(db.get_post("first").await.unwrap().is_some());
+        assert!(db.get_post("failed").await.unwrap().is_none());
+        assert!(db.get_metadata(&cursor_key).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dedup_post_resources() {
+        let db = Database::new(SqliteStorage::new_memory().unwrap());
+
+        // Both posts refer to the same image URL, so the downloaded resources have identical data.
+        let mut posts = vec![
+            create_post_with_image("first", b"image"),
+            create_post_with_image("second", b"image"),
+        ];
+        dedup_post_resources(&mut posts, &db).await.unwrap();
+
+        let posts = posts
+            .into_iter()
+            .map(|(post, resources)| match post {
+                DiffPost::New(p) => (p, resources),
+                DiffPost::Updated(..) => unreachable!(),
+            })
+            .collect();
+        insert_posts(posts, &db).await.unwrap();
+
+        let resources = db.get_resource_metas().await.unwrap();
+        assert_eq!(resources.len(), 1);
+        let shared_id = format!("{}", resources[0].id.as_hyphenated());
+        for slug in ["first", "second"] {
+            let post = db.get_post(slug).await.unwrap().unwrap();
+            assert_eq!(embedded_uuid(&post), shared_id);
+        }
+
+        // Resources fetched by later syncs reuse the stored resource as well.
+        let mut posts = vec![create_post_with_image("third", b"image")];
+        dedup_post_resources(&mut posts, &db).await.unwrap();
+        assert_eq!(posts[0].1[0].id, resources[0].id);
+        assert_eq!(embedded_uuid(&posts[0].0.post().post), shared_id);
+    }
+
+    async fn refetch_post_with_image(
+        db: &Database<SqliteStorage>,
+        data: &[u8],
+    ) -> Vec<(DiffPost, Vec<Resource>)> {
+        let old_post = db.get_post("post").await.unwrap().unwrap();
+        let (post, resources) = create_post_with_image("post", data);
+        let post = match post {
+            DiffPost::New(p) => DiffPost::Updated(p, Box::new(old_post)),
+            DiffPost::Updated(..) => unreachable!(),
+        };
+        vec![(post, resources)]
+    }
+
+    #[tokio::test]
+    async fn test_retain_changed_posts_compares_images() {
+        let db = Database::new(SqliteStorage::new_memory().unwrap());
+        let prepared_posts = vec![create_post_with_image("post", b"image")];
+        apply_prepared_posts(prepared_posts, &db).await.unwrap();
+
+        // Re-downloading an unchanged image does not count as a change.
+        let mut posts = refetch_post_with_image(&db, b"image").await;
+        dedup_post_resources(&mut posts, &db).await.unwrap();
+        retain_changed_posts(&mut posts);
+        assert!(posts.is_empty());
+
+        // Replacing the image does, even though nothing else in the post changes.
+        let mut posts = refetch_post_with_image(&db, b"new image").await;
+        dedup_post_resources(&mut posts, &db).await.unwrap();
+        retain_changed_posts(&mut posts);
+        assert_eq!(posts.len(), 1);
+    }
+}
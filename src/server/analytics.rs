@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use ublog_data::db::Database;
+use ublog_data::models::PostView;
+use ublog_data::storage::Storage;
+
+use crate::server::ServerContext;
+
+/// Interval between two flushes of the buffered post views.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum length, in bytes, of the referrer stored with a post view. Longer referrers are truncated.
+const MAX_REFERRER_LEN: usize = 512;
+
+/// Buffers post views recorded by the server, so that they are written to the database in batches instead of on each
+/// request.
+#[derive(Debug, Default)]
+pub(super) struct ViewRecorder {
+    pending: Mutex<Vec<PostView>>,
+}
+
+impl ViewRecorder {
+    /// Record a view of the post with the given slug, happening now.
+    pub(super) fn record(&self, slug: &str, referrer: Option<&str>) {
+        self.pending.lock().unwrap().push(PostView {
+            slug: String::from(slug),
+            timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            referrer: referrer.map(|referrer| String::from(truncate_referrer(referrer))),
+        });
+    }
+
+    /// Write all buffered post views to the given database.
+    ///
+    /// Views that cannot be written are logged and dropped, so that a failing database does not make the buffer grow
+    /// without bound.
+    pub(super) async fn flush<S>(&self, db: &Database<S>)
+    where
+        S: Storage,
+    {
+        let views = std::mem::take(&mut *self.pending.lock().unwrap());
+        if views.is_empty() {
+            return;
+        }
+
+        if let Err(err) = db.record_views(&views).await {
+            spdlog::error!("Record {} post views failed: {}", views.len(), err);
+        }
+    }
+}
+
+/// Truncate the given referrer to at most [`MAX_REFERRER_LEN`] bytes, without splitting a character.
+fn truncate_referrer(referrer: &str) -> &str {
+    if referrer.len() <= MAX_REFERRER_LEN {
+        return referrer;
+    }
+
+    let mut end = MAX_REFERRER_LEN;
+    while !referrer.is_char_boundary(end) {
+        end -= 1;
+    }
+    &referrer[..end]
+}
+
+/// Flush the post views buffered in the given context periodically. Never returns.
+pub(super) async fn flush_views_periodically(ctx: Arc<ServerContext>) {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        ctx.views.flush(&ctx.db).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_referrer() {
+        assert_eq!(
+            truncate_referrer("https://example.com/"),
+            "https://example.com/"
+        );
+
+        let long = "a".repeat(MAX_REFERRER_LEN + 1);
+        assert_eq!(truncate_referrer(&long).len(), MAX_REFERRER_LEN);
+
+        let long = format!("{}é", "a".repeat(MAX_REFERRER_LEN - 1));
+        assert_eq!(truncate_referrer(&long), &long[..MAX_REFERRER_LEN - 1]);
+    }
+}
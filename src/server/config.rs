@@ -1,4 +1,10 @@
+use std::num::NonZeroUsize;
+
+use http::header::InvalidHeaderValue;
+use http::HeaderValue;
 use serde::{Deserialize, Serialize};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use ublog_doc::render::html::HtmlRenderOptions;
 
 /// Provide information about the served site.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -10,4 +16,262 @@ pub(crate) struct SiteConfig {
     pub(crate) url: String,
     pub(crate) copyright: String,
     pub(crate) post_url_template: String,
+
+    /// Path prefix of all API routes, e.g. `/api`.
+    #[serde(default = "default_api_prefix")]
+    pub(crate) api_prefix: String,
+
+    /// Origins that are allowed to make cross-origin requests to the API routes.
+    ///
+    /// An empty list allows same-origin requests only, and the origin `*` allows any origin.
+    #[serde(default)]
+    pub(crate) allowed_origins: Vec<String>,
+
+    /// Bearer token that authorizes write requests to the API routes.
+    ///
+    /// Write requests are rejected if no token is configured.
+    #[serde(default)]
+    pub(crate) api_token: Option<String>,
+
+    /// The number of latest posts included in the RSS and Atom feeds. Configuring zero items is rejected.
+    #[serde(default)]
+    pub(crate) rss_item_count: Option<NonZeroUsize>,
+
+    /// Expire time of the cached feeds and sitemap, in seconds. Cached feeds are also dropped whenever a post is written.
+    #[serde(default)]
+    pub(crate) feed_cache_seconds: Option<u64>,
+
+    /// The maximum number of items that clients can request on a single page of a paginated API route.
+    #[serde(default)]
+    pub(crate) max_items_per_page: Option<usize>,
+
+    /// URL template of embedded resources in rendered documents, in which `${uuid}` is replaced with the UUID of the
+    /// resource.
+    ///
+    /// Resources are served by the resource API route if no template is configured.
+    #[serde(default)]
+    pub(crate) resource_url_template: Option<String>,
+}
+
+impl SiteConfig {
+    /// Get the path prefix of all API routes, without any trailing slashes.
+    ///
+    /// An empty string is returned if the API routes are mounted at the root path.
+    pub(crate) fn api_prefix(&self) -> &str {
+        self.api_prefix.trim_end_matches('/')
+    }
+
+    /// Get the path of the specified API route under the configured API path prefix.
+    pub(crate) fn api_path(&self, path: &str) -> String {
+        format!("{}{}", self.api_prefix(), path)
+    }
+
+    /// Get the number of latest posts included in the RSS and Atom feeds.
+    pub(crate) fn rss_item_count(&self) -> usize {
+        self.rss_item_count
+            .map_or(DEFAULT_RSS_ITEM_COUNT, NonZeroUsize::get)
+    }
+
+    /// Get the expire time of the cached feeds and sitemap, in seconds.
+    pub(crate) fn feed_cache_seconds(&self) -> u64 {
+        self.feed_cache_seconds
+            .unwrap_or(DEFAULT_FEED_CACHE_SECONDS)
+    }
+
+    /// Get the maximum number of items that clients can request on a single page of a paginated API route.
+    pub(crate) fn max_items_per_page(&self) -> usize {
+        self.max_items_per_page
+            .unwrap_or(DEFAULT_MAX_ITEMS_PER_PAGE)
+    }
+
+    /// Determine whether the given bearer token authorizes write requests.
+    pub(crate) fn is_authorized(&self, token: &str) -> bool {
+        match &self.api_token {
+            Some(api_token) => constant_time_eq(api_token.as_bytes(), token.as_bytes()),
+            None => false,
+        }
+    }
+
+    /// Build the CORS layer that allows cross-origin requests from the configured origins.
+    ///
+    /// Fails if any of the configured origins is not a valid header value.
+    pub(crate) fn cors_layer(&self) -> Result<CorsLayer, InvalidHeaderValue> {
+        let layer = CorsLayer::new().allow_methods(Any);
+        if self.allowed_origins.is_empty() {
+            return Ok(layer);
+        }
+        if self.allowed_origins.iter().any(|origin| origin == "*") {
+            return Ok(layer.allow_origin(Any));
+        }
+
+        let origins = self
+            .allowed_origins
+            .iter()
+            .map(|origin| HeaderValue::from_str(origin))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(layer.allow_origin(AllowOrigin::list(origins)))
+    }
+
+    /// Get the absolute URL of the specified API route under the site's URL.
+    pub(crate) fn api_url(&self, path: &str) -> String {
+        self.absolute_url(&self.api_path(path))
+    }
+
+    /// Get the absolute URL of the given URL. URLs that start with `/` are resolved under the site's URL, and other
+    /// URLs are returned as-is.
+    pub(crate) fn absolute_url(&self, url: &str) -> String {
+        if url.starts_with('/') {
+            format!("{}{}", self.url.trim_end_matches('/'), url)
+        } else {
+            String::from(url)
+        }
+    }
+
+    /// Get the URL template of embedded resources in rendered documents.
+    pub(crate) fn resource_url_template(&self) -> String {
+        match &self.resource_url_template {
+            Some(template) => template.clone(),
+            None => self.api_path("/resources/${uuid}"),
+        }
+    }
+
+    /// Get the options for rendering documents into HTML that match the routes served under this site config.
+    pub(crate) fn html_render_options(&self) -> HtmlRenderOptions {
+        HtmlRenderOptions::with_resource_url_template(self.resource_url_template())
+    }
+}
+
+/// Compare the given byte strings in time that does not depend on their contents, so that the API token cannot be
+/// guessed byte by byte through timing.
+fn constant_time_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    if lhs.len() != rhs.len() {
+        return false;
+    }
+    lhs.iter().zip(rhs).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
+fn default_api_prefix() -> String {
+    String::from("/api")
+}
+
+const DEFAULT_RSS_ITEM_COUNT: usize = 50;
+
+// Feed cache expire time is 10 minutes by default.
+const DEFAULT_FEED_CACHE_SECONDS: u64 = 600;
+
+const DEFAULT_MAX_ITEMS_PER_PAGE: usize = 100;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN};
+    use http::Request;
+    use tower::ServiceExt;
+
+    fn create_test_config(allowed_origins: &[&str]) -> SiteConfig {
+        let mut config = crate::server::create_test_site_config();
+        config.allowed_origins = allowed_origins.iter().map(|o| String::from(*o)).collect();
+        config
+    }
+
+    async fn get_allowed_origin(config: &SiteConfig, origin: &str) -> Option<HeaderValue> {
+        let router = Router::new()
+            .route("/", get(|| async { "" }))
+            .layer(config.cors_layer().unwrap());
+        let response = router
+            .oneshot(
+                Request::get("/")
+                    .header(ORIGIN, origin)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).cloned()
+    }
+
+    #[tokio::test]
+    async fn test_cors_layer() {
+        let config = create_test_config(&["https://a.example.com", "https://b.example.com"]);
+        for origin in ["https://a.example.com", "https://b.example.com"] {
+            assert_eq!(get_allowed_origin(&config, origin).await.unwrap(), origin);
+        }
+        assert_eq!(
+            get_allowed_origin(&config, "https://evil.example.com").await,
+            None
+        );
+
+        let config = create_test_config(&[]);
+        assert_eq!(
+            get_allowed_origin(&config, "https://a.example.com").await,
+            None
+        );
+
+        let config = create_test_config(&["*"]);
+        assert_eq!(
+            get_allowed_origin(&config, "https://a.example.com")
+                .await
+                .unwrap(),
+            "*"
+        );
+    }
+
+    #[test]
+    fn test_cors_layer_invalid_origin() {
+        let config = create_test_config(&["https://a.example.com\n"]);
+        assert!(config.cors_layer().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_feed_options() {
+        let mut config_json = serde_json::json!({
+            "title": "Test Blog",
+            "owner": "tester",
+            "ownerEmail": "tester@example.com",
+            "url": "https://example.com",
+            "copyright": "Copyright (c) tester",
+            "postUrlTemplate": "https://example.com/posts/${slug}",
+        });
+
+        let config: SiteConfig = serde_json::from_value(config_json.clone()).unwrap();
+        assert_eq!(config.rss_item_count, None);
+        assert_eq!(config.feed_cache_seconds, None);
+        assert_eq!(config.rss_item_count(), DEFAULT_RSS_ITEM_COUNT);
+        assert_eq!(config.feed_cache_seconds(), DEFAULT_FEED_CACHE_SECONDS);
+
+        assert_eq!(config.resource_url_template(), "/api/resources/${uuid}");
+
+        config_json["rssItemCount"] = serde_json::json!(10);
+        config_json["feedCacheSeconds"] = serde_json::json!(30);
+        let config: SiteConfig = serde_json::from_value(config_json.clone()).unwrap();
+        assert_eq!(config.rss_item_count(), 10);
+        assert_eq!(config.feed_cache_seconds(), 30);
+
+        config_json["rssItemCount"] = serde_json::json!(0);
+        assert!(serde_json::from_value::<SiteConfig>(config_json).is_err());
+    }
+
+    #[test]
+    fn test_resource_url_template() {
+        let mut config = create_test_config(&[]);
+        config.api_prefix = String::from("/blog-api/");
+        assert_eq!(
+            config.resource_url_template(),
+            "/blog-api/resources/${uuid}"
+        );
+        assert_eq!(
+            config.absolute_url(&config.resource_url_template()),
+            "https://example.com/blog-api/resources/${uuid}"
+        );
+
+        config.resource_url_template = Some(String::from("https://cdn.example.com/${uuid}"));
+        assert_eq!(
+            config.absolute_url(&config.resource_url_template()),
+            "https://cdn.example.com/${uuid}"
+        );
+    }
 }
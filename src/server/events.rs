@@ -0,0 +1,44 @@
+use axum::extract::ws::{Message, WebSocket};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use ublog_data::models::PostEvent;
+
+/// Capacity of the channel that broadcasts post events to WebSocket clients. Clients that fall behind by more than
+/// this many events miss the oldest ones.
+pub(super) const POST_EVENTS_CAPACITY: usize = 64;
+
+/// Push post events received from the given channel to the WebSocket client, until the client disconnects or the
+/// channel is closed.
+pub(super) async fn push_post_events(mut socket: WebSocket, mut events: Receiver<PostEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let message = match serde_json::to_string(&event) {
+                        Ok(message) => message,
+                        Err(err) => {
+                            spdlog::error!("Failed to serialize post event: {}", err);
+                            continue;
+                        }
+                    };
+                    if socket.send(Message::Text(message)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    spdlog::warn!("WebSocket client lagged behind, {} post events skipped", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                // Messages sent by the client are ignored. Pings are answered by the underlying WebSocket
+                // implementation.
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    // The client may have already gone away, in which case closing the socket fails harmlessly.
+    let _ = socket.close().await;
+}
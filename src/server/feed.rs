@@ -1,35 +1,65 @@
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::sync::Arc;
 
+use atom_syndication::{
+    Category as AtomCategory, Entry as AtomEntry, Feed as AtomFeed, FixedDateTime,
+    Generator as AtomGenerator, Link as AtomLink, Person as AtomPerson,
+};
 use rss::{
     CategoryBuilder as RssCategoryBuilder, Channel as RssChannel,
     ChannelBuilder as RssChannelBuilder, ItemBuilder as RssItemBuilder,
 };
-use time::format_description::well_known::Rfc2822;
+use serde::Serialize;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
 use time::OffsetDateTime;
 use ublog_data::models::Post;
-use ublog_data::storage::Pagination;
+use ublog_data::storage::{Pagination, PostSort};
+use ublog_doc::render::html::{render_html_with_options, HtmlRenderOptions};
 
 use crate::server::config::SiteConfig;
 use crate::server::ServerContext;
 
 pub(super) async fn compute_rss(ctx: Arc<ServerContext>) -> Result<RssChannel, Box<dyn Error>> {
-    let pagination = Pagination::from_page_and_size(1, RSS_POSTS);
-    let posts = ctx.db.get_posts(false, &pagination).await.map_err(|err| {
-        spdlog::error!("Get posts list from database for RSS failed: {}", err);
-        Box::<dyn Error>::from(err)
-    })?;
+    let pagination = Pagination::from_page_and_size(1, ctx.site.rss_item_count());
+    let posts = ctx
+        .db
+        .get_posts(false, PostSort::CreatedDesc, &pagination)
+        .await
+        .map_err(|err| {
+            spdlog::error!("Get posts list from database for RSS failed: {}", err);
+            Box::<dyn Error>::from(err)
+        })?;
 
     let mut channel_builder = RssChannelBuilder::default();
+    let render_options = create_feed_render_options(&ctx.site);
 
     channel_builder
         .title(ctx.site.title.clone())
         .link(ctx.site.url.clone())
         .copyright(ctx.site.copyright.clone())
         .last_build_date(OffsetDateTime::now_utc().format(&Rfc2822).unwrap())
-        .generator(String::from("ublog"));
+        .generator(String::from("ublog"))
+        .namespaces(BTreeMap::from([(
+            String::from("content"),
+            String::from(RSS_CONTENT_NAMESPACE),
+        )]));
 
     for p in &posts.objects {
+        // The posts list does not carry the contents of the posts, so each post is fetched on its own.
+        let content = ctx.db.get_post(&p.slug).await.map_err(|err| {
+            spdlog::error!(
+                "Get post from database for RSS failed: {} (slug {})",
+                err,
+                p.slug
+            );
+            Box::<dyn Error>::from(err)
+        })?;
+        let content_html = match content {
+            Some(post) => render_html_with_options(&post.content, &render_options)?,
+            None => String::new(),
+        };
+
         let item = RssItemBuilder::default()
             .title(p.title.clone())
             .link(create_post_url(&ctx.site, p))
@@ -45,6 +75,8 @@ pub(super) async fn compute_rss(ctx: Arc<ServerContext>) -> Result<RssChannel, B
                     .format(&Rfc2822)
                     .unwrap(),
             )
+            .description(content_html.clone())
+            .content(content_html)
             .build();
 
         channel_builder.item(item);
@@ -54,8 +86,191 @@ pub(super) async fn compute_rss(ctx: Arc<ServerContext>) -> Result<RssChannel, B
     Ok(channel)
 }
 
-fn create_post_url(site: &SiteConfig, post: &Post) -> String {
+pub(super) async fn compute_atom(ctx: Arc<ServerContext>) -> Result<String, Box<dyn Error>> {
+    let pagination = Pagination::from_page_and_size(1, ctx.site.rss_item_count());
+    let posts = ctx
+        .db
+        .get_posts(false, PostSort::CreatedDesc, &pagination)
+        .await
+        .map_err(|err| {
+            spdlog::error!("Get posts list from database for Atom failed: {}", err);
+            Box::<dyn Error>::from(err)
+        })?;
+
+    let author = AtomPerson {
+        name: ctx.site.owner.clone(),
+        email: Some(ctx.site.owner_email.clone()),
+        uri: None,
+    };
+
+    let entries = posts
+        .objects
+        .iter()
+        .map(|p| {
+            let url = create_post_url(&ctx.site, p);
+            AtomEntry {
+                title: p.title.clone().into(),
+                id: url.clone(),
+                updated: create_atom_date_time(p.update_timestamp),
+                published: Some(create_atom_date_time(p.create_timestamp)),
+                authors: vec![AtomPerson {
+                    name: p.author.clone(),
+                    email: None,
+                    uri: None,
+                }],
+                categories: vec![AtomCategory {
+                    term: p.category.clone(),
+                    scheme: None,
+                    label: None,
+                }],
+                links: vec![AtomLink {
+                    href: url,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    // The feed is updated whenever any of its entries is updated.
+    let updated = posts
+        .objects
+        .iter()
+        .map(|p| p.update_timestamp)
+        .max()
+        .map(create_atom_date_time)
+        .unwrap_or_else(|| create_atom_date_time(OffsetDateTime::now_utc().unix_timestamp()));
+
+    let feed = AtomFeed {
+        title: ctx.site.title.clone().into(),
+        id: ctx.site.url.clone(),
+        updated,
+        authors: vec![author],
+        generator: Some(AtomGenerator {
+            value: String::from("ublog"),
+            uri: None,
+            version: None,
+        }),
+        links: vec![
+            AtomLink {
+                href: ctx.site.url.clone(),
+                ..Default::default()
+            },
+            AtomLink {
+                href: ctx.site.api_url("/atom"),
+                rel: String::from("self"),
+                ..Default::default()
+            },
+        ],
+        rights: Some(ctx.site.copyright.clone().into()),
+        entries,
+        ..Default::default()
+    };
+
+    Ok(feed.to_string())
+}
+
+/// A feed in the JSON Feed 1.1 format. See <https://www.jsonfeed.org/version/1.1/>.
+#[derive(Clone, Debug, Serialize)]
+struct JsonFeed {
+    version: &'static str,
+    title: String,
+    home_page_url: String,
+    feed_url: String,
+    authors: Vec<JsonFeedAuthor>,
+    items: Vec<JsonFeedItem>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct JsonFeedAuthor {
+    name: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct JsonFeedItem {
+    id: String,
+    url: String,
+    title: String,
+    content_html: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    summary: String,
+    date_published: String,
+    date_modified: String,
+    tags: Vec<String>,
+}
+
+pub(super) async fn compute_json_feed(ctx: Arc<ServerContext>) -> Result<String, Box<dyn Error>> {
+    let pagination = Pagination::from_page_and_size(1, ctx.site.rss_item_count());
+    let posts = ctx
+        .db
+        .get_posts(false, PostSort::CreatedDesc, &pagination)
+        .await
+        .map_err(|err| {
+            spdlog::error!("Get posts list from database for JSON Feed failed: {}", err);
+            Box::<dyn Error>::from(err)
+        })?;
+
+    let render_options = create_feed_render_options(&ctx.site);
+    let mut items = Vec::with_capacity(posts.objects.len());
+    for p in posts.objects {
+        // The posts list does not carry the contents of the posts, so each post is fetched on its own.
+        let content = ctx.db.get_post(&p.slug).await.map_err(|err| {
+            spdlog::error!(
+                "Get post from database for JSON Feed failed: {} (slug {})",
+                err,
+                p.slug
+            );
+            Box::<dyn Error>::from(err)
+        })?;
+        let content_html = match content {
+            Some(post) => render_html_with_options(&post.content, &render_options)?,
+            None => String::new(),
+        };
+
+        let url = create_post_url(&ctx.site, &p);
+        items.push(JsonFeedItem {
+            id: url.clone(),
+            url,
+            content_html,
+            date_published: create_rfc3339_date_time(p.create_timestamp),
+            date_modified: create_rfc3339_date_time(p.update_timestamp),
+            title: p.title,
+            summary: p.excerpt,
+            tags: p.tags,
+        });
+    }
+
+    let feed = JsonFeed {
+        version: JSON_FEED_VERSION,
+        title: ctx.site.title.clone(),
+        home_page_url: ctx.site.url.clone(),
+        feed_url: ctx.site.api_url("/feed.json"),
+        authors: vec![JsonFeedAuthor {
+            name: ctx.site.owner.clone(),
+        }],
+        items,
+    };
+    Ok(serde_json::to_string(&feed)?)
+}
+
+/// Get the options for rendering post contents in feeds.
+fn create_feed_render_options(site: &SiteConfig) -> HtmlRenderOptions {
+    // Feed readers fetch embedded resources from outside of the site, so resource URLs must be absolute.
+    HtmlRenderOptions::with_resource_url_template(site.absolute_url(&site.resource_url_template()))
+}
+
+fn create_rfc3339_date_time(timestamp: i64) -> String {
+    let date_time = OffsetDateTime::from_unix_timestamp(timestamp).unwrap();
+    date_time.format(&Rfc3339).unwrap()
+}
+
+fn create_atom_date_time(timestamp: i64) -> FixedDateTime {
+    create_rfc3339_date_time(timestamp).parse().unwrap()
+}
+
+pub(super) fn create_post_url(site: &SiteConfig, post: &Post) -> String {
     site.post_url_template.replace("${slug}", &post.slug)
 }
 
-const RSS_POSTS: usize = 50;
+const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+const RSS_CONTENT_NAMESPACE: &str = "http://purl.org/rss/1.0/modules/content/";
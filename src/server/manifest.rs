@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use serde::Serialize;
+use ublog_data::models::{Post, Taxonomy};
+use ublog_data::storage::{PaginatedList, Pagination, PostSort};
+
+use crate::server::config::SiteConfig;
+use crate::server::ServerContext;
+
+/// A bundle of everything a front-end needs to render the landing page of the site.
+#[derive(Clone, Debug, Serialize)]
+pub(super) struct SiteManifest {
+    site: SiteInfo,
+    taxonomy: Taxonomy,
+    posts: PaginatedList<Post>,
+}
+
+/// The public part of the site config.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SiteInfo {
+    title: String,
+    owner: String,
+    url: String,
+    copyright: String,
+    api_prefix: String,
+}
+
+impl<'a> From<&'a SiteConfig> for SiteInfo {
+    fn from(site: &'a SiteConfig) -> Self {
+        Self {
+            title: site.title.clone(),
+            owner: site.owner.clone(),
+            url: site.url.clone(),
+            copyright: site.copyright.clone(),
+            api_prefix: String::from(site.api_prefix()),
+        }
+    }
+}
+
+pub(super) async fn compute_manifest(
+    ctx: Arc<ServerContext>,
+) -> Result<SiteManifest, Box<dyn Error>> {
+    let taxonomy = ctx.db.get_taxonomy().await.map_err(|err| {
+        spdlog::error!("Get taxonomy from database for manifest failed: {}", err);
+        Box::<dyn Error>::from(err)
+    })?;
+
+    let pagination = Pagination::from_page_and_size(1, MANIFEST_POSTS);
+    let posts = ctx
+        .db
+        .get_posts(false, PostSort::CreatedDesc, &pagination)
+        .await
+        .map_err(|err| {
+            spdlog::error!("Get posts list from database for manifest failed: {}", err);
+            Box::<dyn Error>::from(err)
+        })?;
+
+    Ok(SiteManifest {
+        site: SiteInfo::from(&ctx.site),
+        taxonomy,
+        posts,
+    })
+}
+
+const MANIFEST_POSTS: usize = crate::server::router::DEFAULT_ITEMS_PER_PAGE;
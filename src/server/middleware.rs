@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use axum::extract::{FromRequest, RequestParts};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use http::{HeaderValue, Request, StatusCode};
+use uuid::Uuid;
+
+use crate::server::ServerContext;
+
+/// Name of the response header that carries the ID of the request.
+pub(super) const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Log the method, path, response status and elapsed time of every request.
+///
+/// Every request is assigned a random ID, which is included in the log and returned to the client in the
+/// `x-request-id` response header so that client-side failures can be matched with the server logs.
+pub(super) async fn log_request<B>(req: Request<B>, next: Next<B>) -> Response {
+    let request_id = Uuid::new_v4();
+    let method = req.method().clone();
+    let path = String::from(req.uri().path());
+    let start = Instant::now();
+
+    let mut response = next.run(req).await;
+
+    let elapsed = start.elapsed();
+    let status = response.status();
+    if status.is_server_error() {
+        spdlog::error!(
+            "{} {} {} {:.3}ms (request {})",
+            method,
+            path,
+            status.as_u16(),
+            elapsed.as_secs_f64() * 1000.0,
+            request_id
+        );
+    } else {
+        spdlog::info!(
+            "{} {} {} {:.3}ms (request {})",
+            method,
+            path,
+            status.as_u16(),
+            elapsed.as_secs_f64() * 1000.0,
+            request_id
+        );
+    }
+
+    response.headers_mut().insert(
+        REQUEST_ID_HEADER,
+        HeaderValue::from_str(&request_id.to_string()).unwrap(),
+    );
+    response
+}
+
+/// An extractor that rejects requests that do not carry the configured API token as a bearer token.
+///
+/// Put it before any extractor that reads the request body, so that unauthorized requests are rejected before their
+/// bodies are received.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Authorized;
+
+#[async_trait]
+impl<B> FromRequest<B> for Authorized
+where
+    B: Send,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let ctx = req
+            .extensions()
+            .get::<Arc<ServerContext>>()
+            .expect("server context is added to every request");
+        let token = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if token.is_some_and(|token| ctx.site.is_authorized(token)) {
+            return Ok(Self);
+        }
+
+        spdlog::warn!(
+            "Unauthorized request: {} {}",
+            req.method(),
+            req.uri().path()
+        );
+        Err((
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))],
+        )
+            .into_response())
+    }
+}
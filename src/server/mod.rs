@@ -1,6 +1,11 @@
+mod analytics;
 pub(crate) mod config;
+mod events;
 mod feed;
+mod manifest;
+mod middleware;
 mod router;
+mod sitemap;
 
 use std::error::Error;
 use std::net::{IpAddr, SocketAddr};
@@ -9,11 +14,16 @@ use std::sync::Arc;
 
 use axum::Server;
 use hyper::server::conn::AddrIncoming;
-use rss::Channel as RssChannel;
+use tokio::sync::broadcast::Sender;
 use ublog_data::db::Database;
+use ublog_data::models::PostEvent;
+use ublog_data::storage::caching::CachingStorage;
 use ublog_data::storage::sqlite::SqliteStorage;
 
+use crate::server::analytics::ViewRecorder;
 use crate::server::config::SiteConfig;
+use crate::server::manifest::SiteManifest;
+use crate::storage::AnyStorage;
 use crate::utils::cache::Cache;
 use crate::{fallible_step, ServerArgs};
 
@@ -25,17 +35,22 @@ pub(crate) async fn serve(args: &ServerArgs) -> Result<(), Box<dyn Error>> {
 
     let site = fallible_step!("load site config", load_site_config(&args.site).await);
 
-    let storage = fallible_step!(
-        "initialize database storage",
-        SqliteStorage::new_file(&args.database)
-    );
-
-    let ctx = ServerContext {
-        site,
-        db: Database::new(storage),
-        rss_cache: Cache::new(RSS_CACHE_EXPIRE),
+    let storage = match &args.remote {
+        Some(remote_addr) => {
+            spdlog::info!("Serving from remote storage at {}", remote_addr);
+            AnyStorage::connect_tcp(remote_addr.clone())
+        }
+        None => AnyStorage::Sqlite(fallible_step!(
+            "initialize database storage",
+            SqliteStorage::new_file(&args.database)
+        )),
     };
-    let router = crate::server::router::create_router(Arc::new(ctx));
+
+    let ctx = Arc::new(ServerContext::new(site, storage));
+    let router = crate::server::router::create_router(ctx.clone());
+    let view_flusher = tokio::spawn(crate::server::analytics::flush_views_periodically(
+        ctx.clone(),
+    ));
 
     let addr: IpAddr = fallible_step!("parse server address", args.addr.parse());
     let server_addr = SocketAddr::new(addr, args.port);
@@ -46,27 +61,153 @@ pub(crate) async fn serve(args: &ServerArgs) -> Result<(), Box<dyn Error>> {
         "run server",
         Server::builder(acceptor)
             .serve(router.into_make_service())
+            .with_graceful_shutdown(shutdown_signal())
             .await
     );
 
+    view_flusher.abort();
+    let _ = view_flusher.await;
+    ctx.views.flush(&ctx.db).await;
+
+    // The server and the view flusher have dropped all their references to the context after in-flight requests have
+    // completed, so dropping the last reference here closes the database connection.
+    drop(ctx);
+    spdlog::info!("Server stopped");
+    spdlog::default_logger().flush();
+
     Ok(())
 }
 
+/// Wait until the process receives SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            spdlog::error!("Failed to listen for SIGINT: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                spdlog::error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    futures::future::select(Box::pin(interrupt), Box::pin(terminate)).await;
+    spdlog::info!("Shutting down server, waiting for in-flight requests to complete");
+}
+
 async fn load_site_config<P>(path: P) -> Result<SiteConfig, Box<dyn Error>>
 where
     P: AsRef<Path>,
 {
     let config_json = fallible_step!("read site config", tokio::fs::read_to_string(path).await);
-    let config = fallible_step!("parse site config", serde_json::from_str(&config_json));
+    let config: SiteConfig =
+        fallible_step!("parse site config", serde_json::from_str(&config_json));
+    // Invalid allowed origins are reported here instead of when the router is created.
+    let _ = fallible_step!("validate allowed origins", config.cors_layer());
     Ok(config)
 }
 
 #[derive(Debug)]
 struct ServerContext {
     site: SiteConfig,
-    db: Database<SqliteStorage>,
-    rss_cache: Cache<RssChannel>,
+    db: Database<CachingStorage<AnyStorage>>,
+    feed_cache: Arc<Cache<Feed, String>>,
+    manifest_cache: Arc<Cache<(), SiteManifest>>,
+
+    /// Rendered HTML contents of posts, keyed by the canonical slugs and the update timestamps of the posts, so that
+    /// entries of updated posts are never served.
+    post_html_cache: Cache<(String, i64), String>,
+
+    /// Broadcasts posts written through `db` to the WebSocket clients.
+    post_events: Sender<PostEvent>,
+
+    /// Views of posts that are not yet written to `db`.
+    views: ViewRecorder,
 }
 
-// RSS cache expire time is 10 minutes.
-const RSS_CACHE_EXPIRE: u64 = 600;
+impl ServerContext {
+    fn new(site: SiteConfig, storage: AnyStorage) -> Self {
+        let feed_cache_expire = site.feed_cache_seconds();
+
+        let feed_cache = Arc::new(Cache::new(feed_cache_expire));
+        let manifest_cache = Arc::new(Cache::new(MANIFEST_CACHE_EXPIRE));
+        let (post_events, _) = tokio::sync::broadcast::channel(events::POST_EVENTS_CAPACITY);
+        let mut db = Database::new(CachingStorage::new(storage, POST_CACHE_EXPIRE));
+
+        // All writes to posts go through the database, so this is the only place that needs to invalidate the caches.
+        let listener_feed_cache = feed_cache.clone();
+        let listener_manifest_cache = manifest_cache.clone();
+        let listener_events = post_events.clone();
+        db.add_post_listener(move |event| {
+            listener_feed_cache.invalidate();
+            listener_manifest_cache.invalidate();
+            // Sending fails only if no client is listening, which is fine.
+            let _ = listener_events.send(event.clone());
+        });
+
+        Self {
+            site,
+            db,
+            feed_cache,
+            manifest_cache,
+            post_html_cache: Cache::with_capacity(POST_HTML_CACHE_EXPIRE, POST_HTML_CACHE_CAPACITY),
+            post_events,
+            views: ViewRecorder::default(),
+        }
+    }
+}
+
+/// Documents computed from all posts, which are cached in [`ServerContext::feed_cache`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Feed {
+    Rss,
+    Atom,
+    Json,
+    Sitemap,
+}
+
+#[cfg(test)]
+fn create_test_site_config() -> SiteConfig {
+    serde_json::from_value(serde_json::json!({
+        "title": "Test Blog",
+        "owner": "tester",
+        "ownerEmail": "tester@example.com",
+        "url": "https://example.com",
+        "copyright": "Copyright (c) tester",
+        "postUrlTemplate": "https://example.com/posts/${slug}",
+    }))
+    .unwrap()
+}
+
+#[cfg(test)]
+fn create_test_context(site: SiteConfig) -> Arc<ServerContext> {
+    Arc::new(ServerContext::new(
+        site,
+        AnyStorage::Sqlite(SqliteStorage::new_memory().unwrap()),
+    ))
+}
+
+// Manifest cache expire time is 1 minute.
+const MANIFEST_CACHE_EXPIRE: u64 = 60;
+
+// Post cache expire time is 1 minute.
+const POST_CACHE_EXPIRE: u64 = 60;
+
+// Rendered post HTML cache expire time is 1 hour.
+const POST_HTML_CACHE_EXPIRE: u64 = 3600;
+
+// At most 256 rendered posts are cached.
+const POST_HTML_CACHE_CAPACITY: usize = 256;
@@ -1,29 +1,81 @@
+use std::error::Error;
+use std::future::Future;
+use std::ops::Range;
 use std::sync::Arc;
 
+use axum::body::{Bytes, StreamBody};
+use axum::extract::ws::WebSocketUpgrade;
 use axum::extract::{Path, Query};
+use axum::handler::Handler;
 use axum::response::{IntoResponse, Response};
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
+use http::header::{
+    ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RANGE, REFERER,
+};
 use http::{HeaderMap, HeaderValue};
 use hyper::StatusCode;
-use rss::Channel as RssChannel;
-use serde::Deserialize;
-use tower_http::cors::{Any, CorsLayer};
-use ublog_data::models::{Post, Resource};
-use ublog_data::storage::{PaginatedList, Pagination};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use tower_http::limit::RequestBodyLimitLayer;
+use ublog_data::db::DatabaseError;
+use ublog_data::models::{
+    ArchiveBucket, DailyViewCount, Post, Resource, ResourceMeta, TaxonomyTerm,
+};
+use ublog_data::storage::sqlite::SqliteStorageError;
+use ublog_data::storage::{PaginatedList, Pagination, PostSort};
 use uuid::Uuid;
 
-use crate::server::ServerContext;
+use crate::server::middleware::Authorized;
+use crate::server::{Feed, ServerContext};
+use crate::storage::AnyStorageError;
 
 /// Create a router for the server.
 pub(super) fn create_router(ctx: Arc<ServerContext>) -> Router {
+    let site = &ctx.site;
     Router::new()
-        .route("/api/posts", get(get_posts))
-        .route("/api/posts/:slug", get(get_post))
-        .route("/api/resources/:id", get(get_resource))
-        .route("/api/rss", get(get_rss))
-        .layer(CorsLayer::new().allow_methods(Any).allow_origin(Any))
+        .route(
+            &site.api_path("/posts"),
+            get(get_posts)
+                .post(create_post.layer(RequestBodyLimitLayer::new(MAX_CREATE_POST_BODY_SIZE))),
+        )
+        .route(&site.api_path("/posts/:slug"), get(get_post))
+        .route(&site.api_path("/posts/:slug/html"), get(get_post_html))
+        .route(&site.api_path("/posts/:slug/view"), post(view_post))
+        .route(&site.api_path("/posts/:slug/stats"), get(get_post_stats))
+        .route(
+            &site.api_path("/posts/:slug/adjacent"),
+            get(get_adjacent_posts),
+        )
+        .route(
+            &site.api_path("/posts/:slug/related"),
+            get(get_related_posts),
+        )
+        .route(&site.api_path("/tags"), get(get_tag_counts))
+        .route(&site.api_path("/archive"), get(get_post_archive))
+        .route(&site.api_path("/tags/:tag/posts"), get(get_posts_by_tag))
+        .route(
+            &site.api_path("/categories/:category/posts"),
+            get(get_posts_by_category),
+        )
+        .route(&site.api_path("/search"), get(search_posts))
+        .route(&site.api_path("/resources"), get(get_resource_metas))
+        .route(&site.api_path("/resources/:id"), get(get_resource))
+        .route(&site.api_path("/rss"), get(get_rss))
+        .route(&site.api_path("/atom"), get(get_atom))
+        .route(&site.api_path("/feed.json"), get(get_json_feed))
+        .route(&site.api_path("/manifest"), get(get_manifest))
+        .route(&site.api_path("/ws"), get(subscribe_post_events))
+        .route("/sitemap.xml", get(get_sitemap))
+        .layer(
+            site.cors_layer()
+                .expect("allowed origins are validated when the site config is loaded"),
+        )
         .layer(Extension(ctx))
+        .layer(axum::middleware::from_fn(
+            crate::server::middleware::log_request,
+        ))
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -34,55 +86,567 @@ struct PaginationParams {
     items: Option<usize>,
 }
 
+impl PaginationParams {
+    /// Convert the parameters into a [`Pagination`] object. Absent parameters take their default values, the number of
+    /// items is clamped to `max_items`, and invalid parameters are rejected with `400 Bad Request`.
+    fn to_pagination(&self, max_items: usize) -> Result<Pagination, StatusCode> {
+        let page = self.page.unwrap_or(DEFAULT_PAGE);
+        let items = self.items.unwrap_or(DEFAULT_ITEMS_PER_PAGE).min(max_items);
+        Pagination::try_from_page_and_size(page, items).map_err(|err| {
+            spdlog::debug!(
+                "Invalid pagination parameters: {} (page {}, items {})",
+                err,
+                page,
+                items
+            );
+            StatusCode::BAD_REQUEST
+        })
+    }
+}
+
 const DEFAULT_PAGE: usize = 1;
-const DEFAULT_ITEMS_PER_PAGE: usize = 20;
+pub(super) const DEFAULT_ITEMS_PER_PAGE: usize = 20;
+
+#[derive(Clone, Debug, Deserialize)]
+struct PostsParams {
+    /// Only list posts updated after this timestamp, from the latest updated to the earliest updated.
+    #[serde(default)]
+    since: Option<i64>,
+
+    /// Order of the listed posts. Defaults to [`PostSort::CreatedDesc`].
+    #[serde(default)]
+    sort: Option<PostSort>,
+}
 
 async fn get_posts(
     Extension(ctx): Extension<Arc<ServerContext>>,
     Query(pagination): Query<PaginationParams>,
+    Query(params): Query<PostsParams>,
+) -> Result<Json<PaginatedList<Post>>, StatusCode> {
+    let pagination = pagination.to_pagination(ctx.site.max_items_per_page())?;
+
+    let posts = match (params.since, params.sort) {
+        (Some(since), None | Some(PostSort::UpdatedDesc)) => {
+            ctx.db.get_posts_updated_since(since, &pagination).await
+        }
+        (Some(_), Some(sort)) => {
+            spdlog::debug!(
+                "Posts updated since a timestamp cannot be sorted by {:?}",
+                sort
+            );
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        (None, sort) => {
+            ctx.db
+                .get_posts(false, sort.unwrap_or_default(), &pagination)
+                .await
+        }
+    };
+    posts.map(Json).map_err(|err| {
+        spdlog::error!(
+            "Get posts list from database failed: {} (page {}, items {})",
+            err,
+            pagination.page(),
+            pagination.page_size()
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn get_tag_counts(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+) -> Result<Json<Vec<TaxonomyTerm>>, StatusCode> {
+    let tag_counts = ctx.db.get_tag_counts().await.map_err(|err| {
+        spdlog::error!("Get tag counts from database failed: {}", err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let tags = tag_counts
+        .into_iter()
+        .map(|(name, post_count)| TaxonomyTerm { name, post_count })
+        .collect();
+    Ok(Json(tags))
+}
+
+async fn get_post_archive(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+) -> Result<Json<Vec<ArchiveBucket>>, StatusCode> {
+    ctx.db.get_post_archive().await.map(Json).map_err(|err| {
+        spdlog::error!("Get post archive from database failed: {}", err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn get_posts_by_tag(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    Path((tag,)): Path<(String,)>,
+    Query(pagination): Query<PaginationParams>,
 ) -> Result<Json<PaginatedList<Post>>, StatusCode> {
-    let page = pagination.page.unwrap_or(DEFAULT_PAGE);
-    let items = pagination.items.unwrap_or(DEFAULT_ITEMS_PER_PAGE);
-    let pagination = Pagination::from_page_and_size(page, items);
+    let pagination = pagination.to_pagination(ctx.site.max_items_per_page())?;
 
     ctx.db
-        .get_posts(false, &pagination)
+        .get_posts_by_tag(&tag, &pagination)
         .await
         .map(Json)
         .map_err(|err| {
             spdlog::error!(
-                "Get posts list from database failed: {} (page {}, items {})",
+                "Get posts list by tag from database failed: {} (tag {}, page {}, items {})",
                 err,
-                page,
-                items
+                tag,
+                pagination.page(),
+                pagination.page_size()
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn get_posts_by_category(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    Path((category,)): Path<(String,)>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<PaginatedList<Post>>, StatusCode> {
+    let pagination = pagination.to_pagination(ctx.site.max_items_per_page())?;
+
+    ctx.db
+        .get_posts_by_category(&category, &pagination)
+        .await
+        .map(Json)
+        .map_err(|err| {
+            spdlog::error!(
+                "Get posts list by category from database failed: {} (category {}, page {}, items {})",
+                err,
+                category,
+                pagination.page(),
+                pagination.page_size()
             );
             StatusCode::INTERNAL_SERVER_ERROR
         })
 }
 
+#[derive(Clone, Debug, Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    q: String,
+}
+
+async fn search_posts(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    Query(search): Query<SearchParams>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<PaginatedList<Post>>, StatusCode> {
+    let pagination = pagination.to_pagination(ctx.site.max_items_per_page())?;
+
+    ctx.db
+        .search_posts(&search.q, &pagination)
+        .await
+        .map(Json)
+        .map_err(|err| {
+            spdlog::error!(
+                "Search posts in database failed: {} (query {:?}, page {}, items {})",
+                err,
+                search.q,
+                pagination.page(),
+                pagination.page_size()
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct CreatePostRequest {
+    post: Post,
+
+    #[serde(default)]
+    resources: Vec<Resource>,
+}
+
+/// Maximum size of the body of a request to create a post, in bytes. The body includes the data of the post's resources.
+const MAX_CREATE_POST_BODY_SIZE: usize = 32 * 1024 * 1024;
+
+// `Authorized` comes before `Bytes` so that the request is authorized before its body is received.
+async fn create_post(
+    _: Authorized,
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    body: Bytes,
+) -> Result<Response, StatusCode> {
+    let request: CreatePostRequest = serde_json::from_slice(&body).map_err(|err| {
+        spdlog::warn!("Invalid request to create post: {}", err);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let post = request.post;
+    ctx.db
+        .insert_post(&post, &request.resources)
+        .await
+        .map_err(|err| {
+            let status = database_error_status(&err);
+            if status.is_server_error() {
+                spdlog::error!("Create post in database failed: {}", err);
+            } else {
+                spdlog::warn!("Rejected request to create post: {}", err);
+            }
+            status
+        })?;
+
+    spdlog::info!("Created post through API: {}", post.slug);
+    Ok((StatusCode::CREATED, Json(post)).into_response())
+}
+
+/// Get the status code of the response to a request that fails with the given database error.
+fn database_error_status(err: &DatabaseError<AnyStorageError>) -> StatusCode {
+    match err {
+        DatabaseError::NotFound => StatusCode::NOT_FOUND,
+        // The storage detects duplicate posts created by concurrent requests after the database has checked the slug.
+        DatabaseError::Duplicate { .. }
+        | DatabaseError::Storage(AnyStorageError::Sqlite(SqliteStorageError::Duplicate {
+            ..
+        })) => StatusCode::CONFLICT,
+        DatabaseError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+        DatabaseError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// A post with statistics computed from its content.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostDetail {
+    #[serde(flatten)]
+    post: Post,
+    word_count: usize,
+    reading_time_minutes: usize,
+}
+
+impl PostDetail {
+    fn new(post: Post) -> Self {
+        Self {
+            word_count: post.content.word_count(),
+            reading_time_minutes: post.reading_time_minutes(READING_WORDS_PER_MINUTE),
+            post,
+        }
+    }
+}
+
 async fn get_post(
     Extension(ctx): Extension<Arc<ServerContext>>,
     Path((slug,)): Path<(String,)>,
-) -> Result<Json<Post>, StatusCode> {
-    ctx.db
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let post = ctx
+        .db
         .get_post(&slug)
         .await
         .map_err(|err| {
             spdlog::error!("Get post from database failed: {} (slug {})", err, slug);
             StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let post_json = serde_json::to_vec(&PostDetail::new(post)).unwrap();
+    let etag = compute_etag(Sha256::digest(&post_json));
+    if etag_matches(&headers, &etag) {
+        return Ok(not_modified(etag));
+    }
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(ETAG, etag);
+    let body = WithContentType {
+        content_type: String::from(JSON_CONTENT_TYPE),
+        other: post_json,
+    };
+    Ok((response_headers, body).into_response())
+}
+
+async fn get_post_html(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    Path((slug,)): Path<(String,)>,
+) -> Result<Response, StatusCode> {
+    let post = ctx.db.get_post(&slug).await.map_err(|err| {
+        spdlog::error!("Get post from database failed: {} (slug {})", err, slug);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let post = post.ok_or(StatusCode::NOT_FOUND)?;
+
+    // Key the cache by the canonical slug, so that all spellings of the slug share a single entry.
+    let key = (post.slug.clone(), post.update_timestamp);
+    let html = ctx
+        .post_html_cache
+        .get(key, || async {
+            ublog_doc::render::html::render_html_with_options(
+                &post.content,
+                &ctx.site.html_render_options(),
+            )
         })
-        .and_then(|post| post.ok_or(StatusCode::NOT_FOUND).map(Json))
+        .await
+        .map_err(|err| {
+            spdlog::error!("Render post into HTML failed: {} (slug {})", err, slug);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(WithContentType {
+        content_type: String::from(HTML_CONTENT_TYPE),
+        other: String::clone(&html),
+    }
+    .into_response())
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct ViewPostResponse {
+    views: u64,
+}
+
+/// Count a view of a post.
+///
+/// This is the only place where post views are counted: it increments the total view count of the post and records the
+/// view for the daily stats, so that the two always agree.
+async fn view_post(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    Path((slug,)): Path<(String,)>,
+    headers: HeaderMap,
+) -> Result<Json<ViewPostResponse>, StatusCode> {
+    // Look up the post first so that the view is recorded under the slug of the stored post rather than the requested
+    // one, which may not be normalized.
+    let post = ctx
+        .db
+        .get_post(&slug)
+        .await
+        .map_err(|err| {
+            spdlog::error!("Get post from database failed: {} (slug {})", err, slug);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let views = ctx
+        .db
+        .increment_post_views(&post.slug)
+        .await
+        .map_err(|err| {
+            spdlog::error!(
+                "Increment post views in database failed: {} (slug {})",
+                err,
+                slug
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let referrer = headers.get(REFERER).and_then(|value| value.to_str().ok());
+    ctx.views.record(&post.slug, referrer);
+
+    Ok(Json(ViewPostResponse { views }))
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct PostStatsParams {
+    /// Only count views recorded at or after this timestamp. Defaults to [`DEFAULT_STATS_DAYS`] days ago.
+    #[serde(default)]
+    since: Option<i64>,
+}
+
+/// Number of days counted by the stats of a post if the client does not specify a start time.
+const DEFAULT_STATS_DAYS: i64 = 30;
+
+async fn get_post_stats(
+    _: Authorized,
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    Path((slug,)): Path<(String,)>,
+    Query(params): Query<PostStatsParams>,
+) -> Result<Json<Vec<DailyViewCount>>, StatusCode> {
+    let post = ctx.db.get_post(&slug).await.map_err(|err| {
+        spdlog::error!("Get post from database failed: {} (slug {})", err, slug);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if post.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // Write the buffered views first so that the stats include the latest views.
+    ctx.views.flush(&ctx.db).await;
+
+    let since = params.since.unwrap_or_else(|| {
+        OffsetDateTime::now_utc().unix_timestamp() - DEFAULT_STATS_DAYS * 24 * 60 * 60
+    });
+    ctx.db
+        .get_view_stats(&slug, since)
+        .await
+        .map(Json)
+        .map_err(|err| {
+            spdlog::error!(
+                "Get view stats from database failed: {} (slug {})",
+                err,
+                slug
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Posts adjacent to a post by their creation time.
+#[derive(Clone, Debug, Serialize)]
+struct AdjacentPostsResponse {
+    /// The post created immediately before the post.
+    previous: Option<Post>,
+
+    /// The post created immediately after the post.
+    next: Option<Post>,
+}
+
+async fn get_adjacent_posts(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    Path((slug,)): Path<(String,)>,
+) -> Result<Json<AdjacentPostsResponse>, StatusCode> {
+    let log_error = |err| {
+        spdlog::error!(
+            "Get adjacent posts from database failed: {} (slug {})",
+            err,
+            slug
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    if ctx.db.get_post(&slug).await.map_err(log_error)?.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let (previous, next) = ctx.db.get_adjacent_posts(&slug).await.map_err(log_error)?;
+    Ok(Json(AdjacentPostsResponse { previous, next }))
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RelatedPostsParams {
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+const DEFAULT_RELATED_POSTS: usize = 5;
+
+async fn get_related_posts(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+    Path((slug,)): Path<(String,)>,
+    Query(params): Query<RelatedPostsParams>,
+) -> Result<Json<Vec<Post>>, StatusCode> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_RELATED_POSTS)
+        .min(ctx.site.max_items_per_page());
+    let log_error = |err| {
+        spdlog::error!(
+            "Get related posts from database failed: {} (slug {}, limit {})",
+            err,
+            slug,
+            limit
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    if ctx.db.get_post(&slug).await.map_err(log_error)?.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    ctx.db
+        .get_related_posts(&slug, limit)
+        .await
+        .map(Json)
+        .map_err(log_error)
+}
+
+async fn get_resource_metas(
+    _: Authorized,
+    Extension(ctx): Extension<Arc<ServerContext>>,
+) -> Result<Json<Vec<ResourceMeta>>, StatusCode> {
+    ctx.db.get_resource_metas().await.map(Json).map_err(|err| {
+        spdlog::error!("Get resource metadata from database failed: {}", err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
 }
 
 async fn get_resource(
     Extension(ctx): Extension<Arc<ServerContext>>,
     Path((id,)): Path<(String,)>,
-) -> Result<WithContentType<Vec<u8>>, StatusCode> {
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
     let id = Uuid::try_parse(&id).map_err(|_| {
         spdlog::warn!("Invalid resource ID from client: {}", id);
         StatusCode::BAD_REQUEST
     })?;
 
+    let meta = ctx
+        .db
+        .get_resource_meta(&id)
+        .await
+        .map_err(|err| {
+            spdlog::error!(
+                "Get resource metadata from database failed: {} (id {})",
+                err,
+                id
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let etag = resource_etag(&meta);
+    if etag_matches(&headers, &etag) {
+        return Ok(not_modified(etag));
+    }
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    response_headers.insert(ETAG, etag);
+
+    let range = match parse_range_header(headers.get(RANGE), meta.size) {
+        RangeRequest::Full => None,
+        RangeRequest::Partial(range) => Some(range),
+        RangeRequest::Unsatisfiable => {
+            response_headers.insert(
+                CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{}", meta.size)).unwrap(),
+            );
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, response_headers).into_response());
+        }
+    };
+
+    if let Some(range) = range {
+        response_headers.insert(
+            CONTENT_RANGE,
+            HeaderValue::from_str(&format!(
+                "bytes {}-{}/{}",
+                range.start,
+                range.end - 1,
+                meta.size
+            ))
+            .unwrap(),
+        );
+
+        let len = range.end - range.start;
+        if len > RESOURCE_STREAM_THRESHOLD {
+            let body = stream_resource(ctx, meta, range);
+            return Ok((StatusCode::PARTIAL_CONTENT, response_headers, body).into_response());
+        }
+
+        let data = ctx
+            .db
+            .read_resource_chunk(&id, range.start, len as usize)
+            .await
+            .map_err(|err| {
+                spdlog::error!(
+                    "Read resource chunk from database failed: {} (id {})",
+                    err,
+                    id
+                );
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .ok_or(StatusCode::NOT_FOUND)?;
+        let body = WithContentType {
+            content_type: meta.ty,
+            other: data,
+        };
+        return Ok((StatusCode::PARTIAL_CONTENT, response_headers, body).into_response());
+    }
+
+    if meta.size > RESOURCE_STREAM_THRESHOLD {
+        let size = meta.size;
+        let body = stream_resource(ctx, meta, 0..size);
+        return Ok((response_headers, body).into_response());
+    }
+
     ctx.db
         .get_resource(&id)
         .await
@@ -90,17 +654,236 @@ async fn get_resource(
             spdlog::error!("Get resource from database failed: {} (id {})", err, id);
             StatusCode::INTERNAL_SERVER_ERROR
         })
-        .and_then(|resource| resource.ok_or(StatusCode::NOT_FOUND).map(From::from))
+        .and_then(|resource| resource.ok_or(StatusCode::NOT_FOUND))
+        .map(|resource| (response_headers, WithContentType::from(resource)).into_response())
+}
+
+/// Get the entity tag of the given resource, which is its content hash stored along with the resource.
+fn resource_etag(meta: &ResourceMeta) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", meta.content_hash)).unwrap()
+}
+
+/// Format the given digest into a strong entity tag.
+fn compute_etag<D>(digest: D) -> HeaderValue
+where
+    D: std::fmt::LowerHex,
+{
+    HeaderValue::from_str(&format!("\"{:x}\"", digest)).unwrap()
+}
+
+/// Determine whether the `If-None-Match` header in the given request headers matches the given entity tag.
+///
+/// Entity tags are compared with the weak comparison function, as required for `If-None-Match`.
+fn etag_matches(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let etag = match etag.to_str() {
+        Ok(etag) => etag,
+        Err(_) => return false,
+    };
+
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+fn not_modified(etag: HeaderValue) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, etag);
+    (StatusCode::NOT_MODIFIED, headers).into_response()
+}
+
+/// Create a response that streams the given byte range of the given resource's data chunk by chunk, so that large
+/// resources are never loaded into memory as a whole.
+fn stream_resource(ctx: Arc<ServerContext>, meta: ResourceMeta, range: Range<u64>) -> Response {
+    let id = meta.id;
+    let end = range.end;
+    let chunks = futures::stream::try_unfold(range.start, move |offset| {
+        let ctx = ctx.clone();
+        async move {
+            if offset >= end {
+                return Ok(None);
+            }
+
+            let len = (end - offset).min(RESOURCE_CHUNK_SIZE as u64) as usize;
+            let chunk = ctx
+                .db
+                .read_resource_chunk(&id, offset, len)
+                .await
+                .map_err(|err| {
+                    spdlog::error!(
+                        "Read resource chunk from database failed: {} (id {}, offset {})",
+                        err,
+                        id,
+                        offset
+                    );
+                    std::io::Error::other(err.to_string())
+                })?;
+
+            // The resource may be deleted or truncated while it is being streamed.
+            match chunk {
+                Some(chunk) if chunk.len() == len => Ok(Some((chunk, offset + len as u64))),
+                _ => Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "resource changed while being streamed",
+                )),
+            }
+        }
+    });
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_str(&meta.ty).unwrap());
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(range.end - range.start));
+
+    (headers, StreamBody::new(chunks)).into_response()
+}
+
+/// The byte range requested by a `Range` header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum RangeRequest {
+    /// The whole data is requested.
+    Full,
+
+    /// The given byte range is requested.
+    Partial(Range<u64>),
+
+    /// The requested range lies outside of the data.
+    Unsatisfiable,
+}
+
+/// Parse the given `Range` header against data of the given size.
+///
+/// Only single byte ranges of the forms `bytes=start-end`, `bytes=start-` and `bytes=-suffix` are supported. Malformed
+/// headers and requests for multiple ranges are ignored, in which case the whole data is served.
+fn parse_range_header(header: Option<&HeaderValue>, size: u64) -> RangeRequest {
+    let spec = match header
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().strip_prefix("bytes="))
+    {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return RangeRequest::Full,
+    };
+    let (start, end) = match spec.split_once('-') {
+        Some(bounds) => bounds,
+        None => return RangeRequest::Full,
+    };
+
+    let range = if start.is_empty() {
+        // A suffix range that requests the last `suffix` bytes.
+        match end.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(suffix) => size.saturating_sub(suffix)..size,
+            Err(_) => return RangeRequest::Full,
+        }
+    } else {
+        let start = match start.parse::<u64>() {
+            Ok(start) => start,
+            Err(_) => return RangeRequest::Full,
+        };
+        let end = if end.is_empty() {
+            size
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end.saturating_add(1).min(size),
+                _ => return RangeRequest::Full,
+            }
+        };
+        start..end
+    };
+
+    if range.start >= size {
+        return RangeRequest::Unsatisfiable;
+    }
+    RangeRequest::Partial(range)
 }
 
 async fn get_rss(
     Extension(ctx): Extension<Arc<ServerContext>>,
 ) -> Result<WithContentType<Vec<u8>>, StatusCode> {
-    ctx.rss_cache
-        .get(|| crate::server::feed::compute_rss(ctx.clone()))
+    get_feed(&ctx, Feed::Rss, RSS_CONTENT_TYPE, || async {
+        Ok(crate::server::feed::compute_rss(ctx.clone())
+            .await?
+            .to_string())
+    })
+    .await
+}
+
+async fn get_atom(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+) -> Result<WithContentType<Vec<u8>>, StatusCode> {
+    get_feed(&ctx, Feed::Atom, ATOM_CONTENT_TYPE, || {
+        crate::server::feed::compute_atom(ctx.clone())
+    })
+    .await
+}
+
+async fn get_json_feed(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+) -> Result<WithContentType<Vec<u8>>, StatusCode> {
+    get_feed(&ctx, Feed::Json, JSON_FEED_CONTENT_TYPE, || {
+        crate::server::feed::compute_json_feed(ctx.clone())
+    })
+    .await
+}
+
+/// Get the given feed from the feed cache, computing it with `compute` if it is not cached.
+async fn get_feed<F, R>(
+    ctx: &ServerContext,
+    feed: Feed,
+    content_type: &str,
+    compute: F,
+) -> Result<WithContentType<Vec<u8>>, StatusCode>
+where
+    F: FnOnce() -> R,
+    R: Future<Output = Result<String, Box<dyn Error>>>,
+{
+    ctx.feed_cache
+        .get(feed, compute)
+        .await
+        .map_err(|err| {
+            spdlog::error!("Compute {:?} feed failed: {}", feed, err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+        .map(|feed| WithContentType {
+            content_type: String::from(content_type),
+            other: feed.as_bytes().to_vec(),
+        })
+}
+
+async fn subscribe_post_events(
+    ws: WebSocketUpgrade,
+    Extension(ctx): Extension<Arc<ServerContext>>,
+) -> Response {
+    // Subscribe before the connection is upgraded so that no event written after the handshake is missed.
+    let events = ctx.post_events.subscribe();
+    ws.on_upgrade(move |socket| crate::server::events::push_post_events(socket, events))
+}
+
+async fn get_sitemap(
+    Extension(ctx): Extension<Arc<ServerContext>>,
+) -> Result<WithContentType<Vec<u8>>, StatusCode> {
+    get_feed(&ctx, Feed::Sitemap, XML_CONTENT_TYPE, || {
+        crate::server::sitemap::compute_sitemap(ctx.clone())
+    })
+    .await
+}
+
+async fn get_manifest(Extension(ctx): Extension<Arc<ServerContext>>) -> Response {
+    match ctx
+        .manifest_cache
+        .get((), || {
+            crate::server::manifest::compute_manifest(ctx.clone())
+        })
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
-        .map(|channel| WithContentType::from(&*channel))
+    {
+        Ok(manifest) => Json(&*manifest).into_response(),
+        Err(err) => {
+            spdlog::error!("Compute site manifest failed: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -118,18 +901,6 @@ impl From<Resource> for WithContentType<Vec<u8>> {
     }
 }
 
-impl<'a> From<&'a RssChannel> for WithContentType<Vec<u8>> {
-    fn from(chan: &'a RssChannel) -> Self {
-        let mut chan_xml: Vec<u8> = Vec::new();
-        chan.write_to(&mut chan_xml).unwrap();
-
-        Self {
-            content_type: String::from(RSS_CONTENT_TYPE),
-            other: chan_xml,
-        }
-    }
-}
-
 impl<T> IntoResponse for WithContentType<T>
 where
     T: IntoResponse,
@@ -146,3 +917,1305 @@ where
 }
 
 const RSS_CONTENT_TYPE: &str = "application/rss+xml";
+const ATOM_CONTENT_TYPE: &str = "application/atom+xml";
+const JSON_FEED_CONTENT_TYPE: &str = "application/feed+json";
+const XML_CONTENT_TYPE: &str = "application/xml";
+const JSON_CONTENT_TYPE: &str = "application/json";
+const HTML_CONTENT_TYPE: &str = "text/html; charset=utf-8";
+
+/// Reading speed used to estimate the reading time of posts, in words per minute.
+const READING_WORDS_PER_MINUTE: usize = 200;
+
+/// Resources larger than this size, in bytes, are streamed to clients instead of being loaded into memory at once.
+const RESOURCE_STREAM_THRESHOLD: u64 = 256 * 1024;
+
+/// Size of the chunks in which resources are streamed, in bytes.
+const RESOURCE_CHUNK_SIZE: usize = 64 * 1024;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::Body;
+    use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+    use http::Request;
+    use rss::Channel as RssChannel;
+    use tower::ServiceExt;
+    use ublog_data::storage::remote::{
+        RemoteStorageClient, RemoteStorageOptions, RemoteStorageServer,
+    };
+    use ublog_data::storage::sqlite::SqliteStorage;
+    use ublog_data::storage::Storage;
+    use ublog_doc::{DocumentNode, DocumentNodeTag, DocumentResourceLink};
+
+    use crate::storage::{AnyStorage, RemoteChannel};
+
+    fn create_test_post(slug: &str, category: &str, tags: &[&str]) -> Post {
+        Post {
+            title: format!("Post {}", slug),
+            slug: String::from(slug),
+            author: String::from("tester"),
+            create_timestamp: 0,
+            update_timestamp: 0,
+            category: String::from(category),
+            tags: tags.iter().map(|tag| String::from(*tag)).collect(),
+            is_special: false,
+            views: 0,
+            excerpt: String::new(),
+            content: DocumentNode::new_empty(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_prefix() {
+        let mut site = crate::server::create_test_site_config();
+        site.api_prefix = String::from("/blog-api/");
+        let ctx = crate::server::create_test_context(site);
+
+        let resource = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("image.png"),
+            ty: String::from("image/png"),
+            data: vec![1, 2, 3],
+        };
+        ctx.db.insert_resource(&resource).await.unwrap();
+
+        let image_node = DocumentNode::new(DocumentNodeTag::Image {
+            link: DocumentResourceLink::Embedded {
+                uuid: format!("{}", resource.id.as_hyphenated()),
+            },
+            caption: None,
+        });
+        let rendered = ublog_doc::render::html::render_html_with_options(
+            &image_node,
+            &ctx.site.html_render_options(),
+        )
+        .unwrap();
+        let src = format!("/blog-api/resources/{}", resource.id.as_hyphenated());
+        assert!(rendered.contains(&format!("src=\"{}\"", src)));
+
+        let router = create_router(ctx.clone());
+
+        let response = router
+            .clone()
+            .oneshot(Request::get(&src).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let old_src = format!("/api/resources/{}", resource.id.as_hyphenated());
+        let response = router
+            .oneshot(Request::get(&old_src).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_manifest() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        ctx.db
+            .insert_post(&create_test_post("post1", "rust", &["async"]), &[])
+            .await
+            .unwrap();
+        ctx.db
+            .insert_post(&create_test_post("post2", "life", &[]), &[])
+            .await
+            .unwrap();
+
+        let router = create_router(ctx);
+        let response = router
+            .oneshot(Request::get("/api/manifest").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(manifest["site"]["title"], "Test Blog");
+        assert_eq!(manifest["site"]["apiPrefix"], "/api");
+        assert!(manifest["site"].get("ownerEmail").is_none());
+
+        assert_eq!(
+            manifest["taxonomy"]["categories"],
+            serde_json::json!([
+                { "name": "life", "postCount": 1 },
+                { "name": "rust", "postCount": 1 },
+            ])
+        );
+        assert_eq!(
+            manifest["taxonomy"]["tags"],
+            serde_json::json!([{ "name": "async", "postCount": 1 }])
+        );
+
+        assert_eq!(manifest["posts"]["totalCount"], 2);
+        assert_eq!(manifest["posts"]["objects"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_posts() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        ctx.db
+            .insert_post(&create_test_post("post1", "rust", &["async"]), &[])
+            .await
+            .unwrap();
+        ctx.db
+            .insert_post(&create_test_post("post2", "life", &[]), &[])
+            .await
+            .unwrap();
+
+        let router = create_router(ctx);
+        let response = router
+            .oneshot(
+                Request::get("/api/search?q=async&page=1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let posts: PaginatedList<Post> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(posts.total_count, 1);
+        assert_eq!(posts.objects[0].slug, "post1");
+    }
+
+    #[tokio::test]
+    async fn test_get_posts_by_tag_and_category() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        ctx.db
+            .insert_post(&create_test_post("post1", "rust", &["async"]), &[])
+            .await
+            .unwrap();
+        ctx.db
+            .insert_post(&create_test_post("post2", "life", &["async", "food"]), &[])
+            .await
+            .unwrap();
+
+        let router = create_router(ctx);
+        let get_slugs = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = router
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                let posts: PaginatedList<Post> = serde_json::from_slice(&body).unwrap();
+                let mut slugs: Vec<_> = posts.objects.into_iter().map(|post| post.slug).collect();
+                slugs.sort();
+                slugs
+            }
+        };
+
+        assert_eq!(get_slugs("/api/tags/async/posts").await, ["post1", "post2"]);
+        assert_eq!(get_slugs("/api/tags/food/posts?page=1").await, ["post2"]);
+        assert_eq!(get_slugs("/api/categories/rust/posts").await, ["post1"]);
+        assert!(get_slugs("/api/categories/none/posts").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_posts_updated_since() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        for (slug, update_timestamp) in [("post1", 100), ("post2", 300), ("post3", 200)] {
+            let mut post = create_test_post(slug, "rust", &[]);
+            post.update_timestamp = update_timestamp;
+            ctx.db.insert_post(&post, &[]).await.unwrap();
+        }
+
+        let response = create_router(ctx)
+            .oneshot(
+                Request::get("/api/posts?since=100")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let posts: PaginatedList<Post> = serde_json::from_slice(&body).unwrap();
+        let slugs: Vec<_> = posts.objects.into_iter().map(|post| post.slug).collect();
+        assert_eq!(slugs, ["post2", "post3"]);
+        assert_eq!(posts.total_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_posts_sorted() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        for (slug, title, create_timestamp) in [("post1", "B", 100), ("post2", "A", 200)] {
+            let mut post = create_test_post(slug, "rust", &[]);
+            post.title = String::from(title);
+            post.create_timestamp = create_timestamp;
+            ctx.db.insert_post(&post, &[]).await.unwrap();
+        }
+
+        let router = create_router(ctx);
+        let get_slugs = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let response = router
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                let posts: PaginatedList<Post> = serde_json::from_slice(&body).unwrap();
+                posts
+                    .objects
+                    .into_iter()
+                    .map(|post| post.slug)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(get_slugs("/api/posts").await, ["post2", "post1"]);
+        assert_eq!(
+            get_slugs("/api/posts?sort=createdAsc").await,
+            ["post1", "post2"]
+        );
+        assert_eq!(
+            get_slugs("/api/posts?sort=titleAsc").await,
+            ["post2", "post1"]
+        );
+
+        // Unknown sort orders are rejected while deserializing the query string.
+        let cases = [
+            ("/api/posts?sort=slug", StatusCode::UNPROCESSABLE_ENTITY),
+            ("/api/posts?since=0&sort=titleAsc", StatusCode::BAD_REQUEST),
+        ];
+        for (uri, status) in cases {
+            let response = router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_pagination() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let router = create_router(ctx);
+
+        let uris = [
+            "/api/posts?page=0",
+            "/api/posts?items=0",
+            "/api/tags/async/posts?page=0",
+            "/api/categories/rust/posts?page=0",
+            "/api/search?q=async&page=0",
+            &format!("/api/posts?page={}&items=2", usize::MAX),
+        ];
+        for uri in uris {
+            let response = router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clamp_items_per_page() {
+        let mut site = crate::server::create_test_site_config();
+        site.max_items_per_page = Some(2);
+        let ctx = crate::server::create_test_context(site);
+        for slug in ["post1", "post2", "post3"] {
+            ctx.db
+                .insert_post(&create_test_post(slug, "rust", &[]), &[])
+                .await
+                .unwrap();
+        }
+
+        let response = create_router(ctx)
+            .oneshot(
+                Request::get("/api/posts?items=100000000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let posts: PaginatedList<Post> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(posts.objects.len(), 2);
+        assert_eq!(posts.total_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_get_post_archive() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        // 2022-09-15T00:00:00Z, 2022-10-01T00:00:00Z and 2022-10-15T00:00:00Z.
+        for (slug, create_timestamp) in [
+            ("post1", 1663200000),
+            ("post2", 1664582400),
+            ("post3", 1665792000),
+        ] {
+            let mut post = create_test_post(slug, "rust", &[]);
+            post.create_timestamp = create_timestamp;
+            ctx.db.insert_post(&post, &[]).await.unwrap();
+        }
+
+        let response = create_router(ctx)
+            .oneshot(Request::get("/api/archive").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!([
+                { "year": 2022, "month": 10, "count": 2 },
+                { "year": 2022, "month": 9, "count": 1 },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_tag_counts() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        ctx.db
+            .insert_post(&create_test_post("post1", "rust", &["async", "web"]), &[])
+            .await
+            .unwrap();
+        ctx.db
+            .insert_post(&create_test_post("post2", "rust", &["async"]), &[])
+            .await
+            .unwrap();
+
+        let response = create_router(ctx)
+            .oneshot(Request::get("/api/tags").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!([
+                { "name": "async", "postCount": 2 },
+                { "name": "web", "postCount": 1 },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_post_html() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.heading(1, |b| {
+            b.text("Hello <World>");
+        });
+        let mut post = create_test_post("post", "rust", &[]);
+        post.content = builder.finish();
+        ctx.db.insert_post(&post, &[]).await.unwrap();
+
+        let router = create_router(ctx.clone());
+        let get_html = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                router
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let response = get_html("/api/posts/post/html").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], HTML_CONTENT_TYPE);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains("Hello &lt;World&gt;"));
+
+        // Updating the post invalidates the cached HTML.
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.heading(1, |b| {
+            b.text("Updated");
+        });
+        post.content = builder.finish();
+        post.update_timestamp += 1;
+        ctx.db.update_post(&post, &[]).await.unwrap();
+
+        let response = get_html("/api/posts/post/html").await;
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains("Updated"));
+
+        let response = get_html("/api/posts/missing/html").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_post_html_caches_canonical_slug() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        ctx.db
+            .insert_post(&create_test_post("hello-world", "rust", &[]), &[])
+            .await
+            .unwrap();
+
+        let router = create_router(ctx.clone());
+        for uri in ["/api/posts/hello-world/html", "/api/posts/Hello-World/html"] {
+            let response = router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        assert_eq!(ctx.post_html_cache.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_view_post() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        ctx.db
+            .insert_post(&create_test_post("post", "rust", &[]), &[])
+            .await
+            .unwrap();
+        let router = create_router(ctx);
+
+        for expected_views in 1..=2 {
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::post("/api/posts/post/view")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body, serde_json::json!({ "views": expected_views }));
+        }
+
+        let response = router
+            .oneshot(
+                Request::post("/api/posts/missing/view")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_adjacent_posts() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        for slug in ["post1", "post2", "post3"] {
+            ctx.db
+                .insert_post(&create_test_post(slug, "rust", &[]), &[])
+                .await
+                .unwrap();
+        }
+        let router = create_router(ctx);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/posts/post2/adjacent")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["previous"]["slug"], "post1");
+        assert_eq!(body["next"]["slug"], "post3");
+
+        let response = router
+            .oneshot(
+                Request::get("/api/posts/missing/adjacent")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_related_posts() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let posts = [
+            create_test_post("post", "rust", &["a", "b", "c"]),
+            create_test_post("two-shared", "rust", &["a", "b"]),
+            create_test_post("one-shared", "rust", &["a"]),
+            create_test_post("unrelated", "rust", &["d"]),
+        ];
+        for post in &posts {
+            ctx.db.insert_post(post, &[]).await.unwrap();
+        }
+        let router = create_router(ctx);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/posts/post/related")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let related: Vec<Post> = serde_json::from_slice(&body).unwrap();
+        let related_slugs: Vec<_> = related.iter().map(|post| post.slug.as_str()).collect();
+        assert_eq!(related_slugs, ["two-shared", "one-shared"]);
+
+        let response = router
+            .oneshot(
+                Request::get("/api/posts/missing/related")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_resource_metas() {
+        let mut site = crate::server::create_test_site_config();
+        site.api_token = Some(String::from("secret"));
+        let ctx = crate::server::create_test_context(site);
+        let resource = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("image.png"),
+            ty: String::from("image/png"),
+            data: vec![0; 16],
+        };
+        ctx.db.insert_resource(&resource).await.unwrap();
+
+        let router = create_router(ctx);
+        let response = router
+            .clone()
+            .oneshot(Request::get("/api/resources").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router
+            .oneshot(
+                Request::get("/api/resources")
+                    .header(AUTHORIZATION, "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let metas: Vec<ResourceMeta> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            metas,
+            vec![ResourceMeta {
+                id: resource.id,
+                content_hash: resource.content_hash(),
+                name: resource.name,
+                ty: resource.ty,
+                size: 16,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_resource_streamed() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let small = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("small.txt"),
+            ty: String::from("text/plain"),
+            data: b"hello".to_vec(),
+        };
+        let large = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("large.bin"),
+            ty: String::from("application/octet-stream"),
+            data: (0..RESOURCE_STREAM_THRESHOLD + 1000)
+                .map(|idx| idx as u8)
+                .collect(),
+        };
+        ctx.db.insert_resource(&small).await.unwrap();
+        ctx.db.insert_resource(&large).await.unwrap();
+
+        let router = create_router(ctx);
+        for resource in [&small, &large] {
+            let uri = format!("/api/resources/{}", resource.id);
+            let response = router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["Content-Type"], resource.ty.as_str());
+            assert_eq!(
+                response.headers()["Content-Length"],
+                resource.data.len().to_string().as_str()
+            );
+
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            assert_eq!(body, resource.data);
+        }
+
+        let uri = format!("/api/resources/{}", Uuid::new_v4());
+        let response = router
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_parse_range_header() {
+        let parse =
+            |value: &str| parse_range_header(Some(&HeaderValue::from_str(value).unwrap()), 100);
+
+        assert_eq!(parse_range_header(None, 100), RangeRequest::Full);
+        assert_eq!(parse("bytes=10-19"), RangeRequest::Partial(10..20));
+        assert_eq!(parse("bytes=10-"), RangeRequest::Partial(10..100));
+        assert_eq!(parse("bytes=-30"), RangeRequest::Partial(70..100));
+        assert_eq!(parse("bytes=-300"), RangeRequest::Partial(0..100));
+        assert_eq!(parse("bytes=90-200"), RangeRequest::Partial(90..100));
+        assert_eq!(parse("bytes=100-"), RangeRequest::Unsatisfiable);
+        assert_eq!(parse("bytes=-0"), RangeRequest::Unsatisfiable);
+        assert_eq!(parse("bytes=20-10"), RangeRequest::Full);
+        assert_eq!(parse("bytes=0-1,5-6"), RangeRequest::Full);
+        assert_eq!(parse("items=0-1"), RangeRequest::Full);
+    }
+
+    #[tokio::test]
+    async fn test_get_resource_range() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let resource = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("video.mp4"),
+            ty: String::from("video/mp4"),
+            data: (0..100).collect(),
+        };
+        ctx.db.insert_resource(&resource).await.unwrap();
+
+        let router = create_router(ctx);
+        let uri = format!("/api/resources/{}", resource.id);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get(&uri)
+                    .header(RANGE, "bytes=10-19")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 10-19/100");
+        assert_eq!(response.headers()[CONTENT_TYPE], "video/mp4");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, resource.data[10..20]);
+
+        let response = router
+            .oneshot(
+                Request::get(&uri)
+                    .header(RANGE, "bytes=100-")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes */100");
+    }
+
+    #[tokio::test]
+    async fn test_get_resource_range_streamed() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let size = RESOURCE_STREAM_THRESHOLD * 2;
+        let resource = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("large.bin"),
+            ty: String::from("application/octet-stream"),
+            data: (0..size).map(|idx| idx as u8).collect(),
+        };
+        ctx.db.insert_resource(&resource).await.unwrap();
+
+        let response = create_router(ctx)
+            .oneshot(
+                Request::get(format!("/api/resources/{}", resource.id))
+                    .header(RANGE, "bytes=-300000")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers()[CONTENT_RANGE],
+            format!("bytes {}-{}/{}", size - 300000, size - 1, size).as_str()
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, resource.data[(size - 300000) as usize..]);
+    }
+
+    #[tokio::test]
+    async fn test_get_post_reading_time() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        for _ in 0..50 {
+            builder.paragraph(|b| {
+                b.text("one two three four five six seven eight nine ten");
+            });
+        }
+        let mut post = create_test_post("post", "rust", &[]);
+        post.content = builder.finish();
+        ctx.db.insert_post(&post, &[]).await.unwrap();
+
+        let response = create_router(ctx)
+            .oneshot(Request::get("/api/posts/post").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let detail: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(detail["slug"], "post");
+        assert_eq!(detail["wordCount"], 500);
+        assert_eq!(detail["readingTimeMinutes"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_get() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        ctx.db
+            .insert_post(&create_test_post("post", "rust", &[]), &[])
+            .await
+            .unwrap();
+        let resource = Resource {
+            id: Uuid::new_v4(),
+            name: String::from("image.png"),
+            ty: String::from("image/png"),
+            data: vec![1, 2, 3],
+        };
+        ctx.db.insert_resource(&resource).await.unwrap();
+
+        let router = create_router(ctx);
+        let resource_uri = format!("/api/resources/{}", resource.id);
+        for uri in ["/api/posts/post", resource_uri.as_str()] {
+            let response = router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let etag = response.headers()[ETAG].clone();
+
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::get(uri)
+                        .header(IF_NONE_MATCH, etag.clone())
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(response.headers()[ETAG], etag);
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            assert!(body.is_empty());
+
+            let response = router
+                .clone()
+                .oneshot(
+                    Request::get(uri)
+                        .header(IF_NONE_MATCH, "\"stale\"")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = router
+            .oneshot(Request::get(&resource_uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let expected = format!("\"{:x}\"", Sha256::digest(&resource.data));
+        assert_eq!(response.headers()[ETAG], expected.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_get_atom() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        for (slug, update_timestamp) in [("post1", 1000), ("post2", 3000), ("post3", 2000)] {
+            let mut post = create_test_post(slug, "rust", &[]);
+            post.update_timestamp = update_timestamp;
+            ctx.db.insert_post(&post, &[]).await.unwrap();
+        }
+
+        let response = create_router(ctx)
+            .oneshot(Request::get("/api/atom").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], ATOM_CONTENT_TYPE);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let feed: atom_syndication::Feed = std::str::from_utf8(&body).unwrap().parse().unwrap();
+        assert_eq!(feed.id, "https://example.com");
+        assert_eq!(feed.updated.timestamp(), 3000);
+        assert_eq!(feed.entries.len(), 3);
+
+        let entry = feed
+            .entries
+            .iter()
+            .find(|entry| entry.id == "https://example.com/posts/post1")
+            .unwrap();
+        assert_eq!(entry.updated.timestamp(), 1000);
+        assert!(entry.published.is_some());
+        assert_eq!(entry.authors[0].name, "tester");
+        assert_eq!(entry.categories[0].term, "rust");
+    }
+
+    #[tokio::test]
+    async fn test_get_json_feed() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.text("Hello <feed>");
+        });
+        let mut post = create_test_post("post1", "rust", &["async"]);
+        post.create_timestamp = 1000;
+        post.update_timestamp = 2000;
+        post.content = builder.finish();
+        ctx.db.insert_post(&post, &[]).await.unwrap();
+        ctx.db
+            .insert_post(&create_test_post("post2", "rust", &[]), &[])
+            .await
+            .unwrap();
+
+        let response = create_router(ctx)
+            .oneshot(Request::get("/api/feed.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], JSON_FEED_CONTENT_TYPE);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let feed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(feed["home_page_url"], "https://example.com");
+        assert_eq!(feed["feed_url"], "https://example.com/api/feed.json");
+        assert_eq!(feed["items"].as_array().unwrap().len(), 2);
+
+        let item = &feed["items"][0];
+        assert_eq!(item["id"], "https://example.com/posts/post1");
+        assert_eq!(item["url"], "https://example.com/posts/post1");
+        assert_eq!(item["title"], "Post post1");
+        assert_eq!(item["content_html"], "<p>Hello &lt;feed&gt;</p>");
+        assert_eq!(item["summary"], "Hello <feed>");
+        assert_eq!(item["date_published"], "1970-01-01T00:16:40Z");
+        assert_eq!(item["date_modified"], "1970-01-01T00:33:20Z");
+        assert_eq!(item["tags"], serde_json::json!(["async"]));
+        assert!(feed["items"][1].get("summary").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_rss_content() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let mut post = create_test_post("post", "rust", &[]);
+        let mut builder = ublog_doc::builder::DocumentTreeBuilder::new();
+        builder.paragraph(|b| {
+            b.text("Hello, RSS!");
+        });
+        post.content = builder.finish();
+        ctx.db.insert_post(&post, &[]).await.unwrap();
+
+        let response = create_router(ctx)
+            .oneshot(Request::get("/api/rss").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let channel = RssChannel::read_from(&body[..]).unwrap();
+        let item = &channel.items()[0];
+        assert_eq!(item.description(), Some("<p>Hello, RSS!</p>"));
+        assert_eq!(item.content(), Some("<p>Hello, RSS!</p>"));
+    }
+
+    #[tokio::test]
+    async fn test_rss_refreshed_on_post_change() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let router = create_router(ctx.clone());
+        let get_item_titles = || async {
+            let response = router
+                .clone()
+                .oneshot(Request::get("/api/rss").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            RssChannel::read_from(&body[..])
+                .unwrap()
+                .items()
+                .iter()
+                .map(|item| String::from(item.title().unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(get_item_titles().await.is_empty());
+
+        ctx.db
+            .insert_post(&create_test_post("post1", "rust", &[]), &[])
+            .await
+            .unwrap();
+        assert_eq!(get_item_titles().await, ["Post post1"]);
+
+        let mut post = create_test_post("post1", "rust", &[]);
+        post.title = String::from("Updated");
+        ctx.db.update_post(&post, &[]).await.unwrap();
+        assert_eq!(get_item_titles().await, ["Updated"]);
+
+        ctx.db.delete_post("post1").await.unwrap();
+        assert!(get_item_titles().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_sitemap() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let slugs: Vec<_> = (0..150).map(|idx| format!("post-{}", idx)).collect();
+        for (idx, slug) in slugs.iter().enumerate() {
+            let mut post = create_test_post(slug, "rust", &[]);
+            post.create_timestamp = idx as i64;
+            post.update_timestamp = idx as i64;
+            ctx.db.insert_post(&post, &[]).await.unwrap();
+        }
+
+        let response = create_router(ctx)
+            .oneshot(Request::get("/sitemap.xml").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], XML_CONTENT_TYPE);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let sitemap = std::str::from_utf8(&body).unwrap();
+        for slug in &slugs {
+            let loc = format!("<loc>https://example.com/posts/{}</loc>", slug);
+            assert_eq!(sitemap.matches(&loc).count(), 1, "{}", slug);
+        }
+        assert!(sitemap.contains("<lastmod>1970-01-01T00:00:00Z</lastmod>"));
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let router = create_router(ctx);
+
+        let mut request_ids = Vec::new();
+        for uri in ["/api/posts", "/api/posts/missing"] {
+            let response = router
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let request_id = response.headers()[crate::server::middleware::REQUEST_ID_HEADER]
+                .to_str()
+                .unwrap();
+            request_ids.push(Uuid::try_parse(request_id).unwrap());
+        }
+
+        assert_ne!(request_ids[0], request_ids[1]);
+    }
+
+    #[tokio::test]
+    async fn test_create_post() {
+        let mut site = crate::server::create_test_site_config();
+        site.api_token = Some(String::from("secret"));
+        let ctx = crate::server::create_test_context(site);
+        let router = create_router(ctx.clone());
+
+        let create_post = |token: Option<&'static str>| {
+            let router = router.clone();
+            async move {
+                let body = serde_json::json!({
+                    "post": create_test_post("post", "rust", &["api"]),
+                });
+                let mut request =
+                    Request::post("/api/posts").header(CONTENT_TYPE, "application/json");
+                if let Some(token) = token {
+                    request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let request = request.body(Body::from(body.to_string())).unwrap();
+                router.oneshot(request).await.unwrap().status()
+            }
+        };
+
+        assert_eq!(create_post(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(create_post(Some("wrong")).await, StatusCode::UNAUTHORIZED);
+        assert!(ctx.db.get_post("post").await.unwrap().is_none());
+
+        assert_eq!(create_post(Some("secret")).await, StatusCode::CREATED);
+        let post = ctx.db.get_post("post").await.unwrap().unwrap();
+        assert_eq!(post.tags, vec!["api"]);
+
+        assert_eq!(create_post(Some("secret")).await, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_create_post_body_too_large() {
+        let mut site = crate::server::create_test_site_config();
+        site.api_token = Some(String::from("secret"));
+        let router = create_router(crate::server::create_test_context(site));
+
+        // The body is rejected by its declared length, before any of it is received.
+        let request = Request::post("/api/posts")
+            .header(CONTENT_TYPE, "application/json")
+            .header(CONTENT_LENGTH, MAX_CREATE_POST_BODY_SIZE + 1)
+            .header(AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_create_post_without_api_token() {
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let body = serde_json::json!({
+            "post": create_test_post("post", "rust", &[]),
+        });
+        let response = create_router(ctx)
+            .oneshot(
+                Request::post("/api/posts")
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(AUTHORIZATION, "Bearer ")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+    }
+
+    #[tokio::test]
+    async fn test_serve_from_remote_storage() {
+        let primary = Arc::new(SqliteStorage::new_memory().unwrap());
+        primary
+            .insert_post(&create_test_post("replicated", "rust", &[]), &[])
+            .await
+            .unwrap();
+
+        let client = RemoteStorageClient::with_connector(
+            move || {
+                let primary = primary.clone();
+                async move {
+                    let (client, server) = tokio::io::duplex(64 * 1024);
+                    tokio::spawn(async move {
+                        let _ = RemoteStorageServer::new(&*primary, server).serve().await;
+                    });
+                    Ok(Box::new(client) as Box<dyn RemoteChannel>)
+                }
+            },
+            RemoteStorageOptions::default(),
+        );
+        let ctx = Arc::new(ServerContext::new(
+            crate::server::create_test_site_config(),
+            AnyStorage::Remote(client),
+        ));
+
+        let response = create_router(ctx)
+            .oneshot(
+                Request::get("/api/posts/replicated")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let post: Post = serde_json::from_slice(&body).unwrap();
+        assert_eq!(post.title, "Post replicated");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_post_events_websocket() {
+        use futures::{Stream, StreamExt};
+        use tokio_tungstenite::tungstenite::{Error as ClientError, Message as ClientMessage};
+
+        async fn next_event<S>(client: &mut S) -> serde_json::Value
+        where
+            S: Stream<Item = Result<ClientMessage, ClientError>> + Unpin,
+        {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(5), client.next())
+                .await
+                .expect("no post event received")
+                .unwrap()
+                .unwrap();
+            match message {
+                ClientMessage::Text(text) => serde_json::from_str(&text).unwrap(),
+                message => panic!("unexpected message: {:?}", message),
+            }
+        }
+
+        let ctx = crate::server::create_test_context(crate::server::create_test_site_config());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(create_router(ctx.clone()).into_make_service());
+        tokio::spawn(server);
+
+        let url = format!("ws://{}/api/ws", addr);
+        let (mut client1, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut client2, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+        ctx.db
+            .insert_post(&create_test_post("post1", "category", &[]), &[])
+            .await
+            .unwrap();
+
+        let expected = serde_json::json!({
+            "slug": "post1",
+            "title": "Post post1",
+            "kind": "created",
+        });
+        assert_eq!(next_event(&mut client1).await, expected);
+        assert_eq!(next_event(&mut client2).await, expected);
+
+        // The server keeps broadcasting to the remaining clients after one disconnects.
+        client1.close(None).await.unwrap();
+        ctx.db
+            .insert_post(&create_test_post("post2", "category", &[]), &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            next_event(&mut client2).await,
+            serde_json::json!({
+                "slug": "post2",
+                "title": "Post post2",
+                "kind": "created",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_post_stats() {
+        let mut site = crate::server::create_test_site_config();
+        site.api_token = Some(String::from("secret"));
+        let ctx = crate::server::create_test_context(site);
+        ctx.db
+            .insert_post(&create_test_post("post1", "rust", &[]), &[])
+            .await
+            .unwrap();
+        let router = create_router(ctx);
+
+        // Reading a post does not count as a view.
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/posts/post1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for referrer in [None, Some("https://example.org/"), None] {
+            let mut request = Request::post("/api/posts/post1/view");
+            if let Some(referrer) = referrer {
+                request = request.header(REFERER, referrer);
+            }
+            let response = router
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/posts/post1/stats")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/posts/post1/stats")
+                    .header(AUTHORIZATION, "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let stats: Vec<DailyViewCount> = serde_json::from_slice(&body).unwrap();
+        let today = OffsetDateTime::now_utc().date();
+        assert_eq!(
+            stats,
+            [DailyViewCount {
+                year: today.year(),
+                month: u8::from(today.month()).into(),
+                day: today.day().into(),
+                count: 3,
+            }]
+        );
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/api/posts/post1/stats?since=9999999999")
+                    .header(AUTHORIZATION, "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"[]");
+
+        let response = router
+            .oneshot(
+                Request::get("/api/posts/missing/stats")
+                    .header(AUTHORIZATION, "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
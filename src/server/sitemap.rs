@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use ublog_data::storage::{Pagination, PostSort};
+
+use crate::server::ServerContext;
+
+/// Compute the sitemap of the site, which lists the URLs of all non-special posts.
+pub(super) async fn compute_sitemap(ctx: Arc<ServerContext>) -> Result<String, Box<dyn Error>> {
+    let mut sitemap = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+    );
+
+    for page in 1.. {
+        let pagination = Pagination::from_page_and_size(page, SITEMAP_PAGE_SIZE);
+        let posts = ctx
+            .db
+            .get_posts(false, PostSort::CreatedDesc, &pagination)
+            .await
+            .map_err(|err| {
+                spdlog::error!("Get posts list from database for sitemap failed: {}", err);
+                Box::<dyn Error>::from(err)
+            })?;
+
+        for p in &posts.objects {
+            // W3C datetime is a profile of ISO 8601 that RFC 3339 date times conform to.
+            let last_mod = OffsetDateTime::from_unix_timestamp(p.update_timestamp)
+                .unwrap()
+                .format(&Rfc3339)
+                .unwrap();
+            write!(
+                sitemap,
+                "<url><loc>{}</loc><lastmod>{}</lastmod></url>",
+                escape_xml(&crate::server::feed::create_post_url(&ctx.site, p)),
+                last_mod
+            )
+            .unwrap();
+        }
+
+        if posts.objects.len() < SITEMAP_PAGE_SIZE {
+            break;
+        }
+    }
+
+    sitemap.push_str("</urlset>");
+    Ok(sitemap)
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+const SITEMAP_PAGE_SIZE: usize = 100;
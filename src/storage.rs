@@ -0,0 +1,313 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use ublog_data::models::{
+    ArchiveBucket, Commit, DailyViewCount, Delta, Post, PostView, Resource, ResourceMeta, Taxonomy,
+};
+use ublog_data::storage::remote::{RemoteStorageClient, RemoteStorageError, RemoteStorageOptions};
+use ublog_data::storage::sqlite::{SqliteStorage, SqliteStorageError};
+use ublog_data::storage::{
+    PaginatedList, Pagination, PostSort, Storage, UnsupportedOperationError,
+};
+use uuid::Uuid;
+
+/// A channel to a remote storage server.
+pub(crate) trait RemoteChannel: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+impl<T> RemoteChannel for T where T: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+/// Prefix of storage locations that refer to remote storage servers.
+const REMOTE_LOCATION_PREFIX: &str = "tcp://";
+
+/// A storage that is either a local SQLite database or a storage served by a remote storage server.
+#[derive(Debug)]
+pub(crate) enum AnyStorage {
+    Sqlite(SqliteStorage),
+    Remote(RemoteStorageClient<Box<dyn RemoteChannel>>),
+}
+
+impl AnyStorage {
+    /// Open the storage at the given location.
+    ///
+    /// A location of the form `tcp://<host>:<port>` refers to a remote storage server. Any other location is taken as
+    /// the path to a SQLite database file.
+    pub(crate) fn open(location: &str) -> Result<Self, AnyStorageError> {
+        match location.strip_prefix(REMOTE_LOCATION_PREFIX) {
+            Some(addr) => Ok(Self::connect_tcp(String::from(addr))),
+            None => SqliteStorage::new_file(location)
+                .map(Self::Sqlite)
+                .map_err(AnyStorageError::Sqlite),
+        }
+    }
+
+    /// Create a storage that connects to the remote storage server listening at the given TCP address.
+    ///
+    /// The connection is established when the storage is first accessed, and re-established after it breaks.
+    pub(crate) fn connect_tcp(addr: String) -> Self {
+        let client = RemoteStorageClient::with_connector(
+            move || {
+                let addr = addr.clone();
+                async move {
+                    spdlog::info!("Connecting to remote storage at {}", addr);
+                    let stream = TcpStream::connect(&addr).await?;
+                    stream.set_nodelay(true)?;
+                    Ok(Box::new(stream) as Box<dyn RemoteChannel>)
+                }
+            },
+            RemoteStorageOptions::default(),
+        );
+        Self::Remote(client)
+    }
+}
+
+impl std::fmt::Debug for dyn RemoteChannel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RemoteChannel")
+    }
+}
+
+/// Forward a storage call to the underlying storage and wrap its error.
+macro_rules! dispatch {
+    ($self:expr, $storage:ident => $call:expr) => {
+        match $self {
+            Self::Sqlite($storage) => $call.await.map_err(AnyStorageError::Sqlite),
+            Self::Remote($storage) => $call.await.map_err(AnyStorageError::Remote),
+        }
+    };
+}
+
+#[async_trait]
+impl Storage for AnyStorage {
+    type Error = AnyStorageError;
+
+    async fn insert_post(
+        &self,
+        post: &Post,
+        post_resources: &[Resource],
+    ) -> Result<(), Self::Error> {
+        dispatch!(self, s => s.insert_post(post, post_resources))
+    }
+
+    async fn insert_posts(&self, posts: &[(Post, Vec<Resource>)]) -> Result<(), Self::Error> {
+        dispatch!(self, s => s.insert_posts(posts))
+    }
+
+    async fn update_post(
+        &self,
+        post: &Post,
+        post_resources: &[Resource],
+    ) -> Result<(), Self::Error> {
+        dispatch!(self, s => s.update_post(post, post_resources))
+    }
+
+    async fn delete_post(&self, post_slug: &str) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.delete_post(post_slug))
+    }
+
+    async fn increment_post_views(&self, post_slug: &str) -> Result<Option<u64>, Self::Error> {
+        dispatch!(self, s => s.increment_post_views(post_slug))
+    }
+
+    async fn get_post(&self, post_slug: &str) -> Result<Option<Post>, Self::Error> {
+        dispatch!(self, s => s.get_post(post_slug))
+    }
+
+    async fn get_post_with_resources(
+        &self,
+        post_slug: &str,
+    ) -> Result<Option<(Post, Vec<Resource>)>, Self::Error> {
+        dispatch!(self, s => s.get_post_with_resources(post_slug))
+    }
+
+    async fn get_posts(
+        &self,
+        special: bool,
+        sort: PostSort,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        dispatch!(self, s => s.get_posts(special, sort, pagination))
+    }
+
+    async fn get_posts_by_tag(
+        &self,
+        tag: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        dispatch!(self, s => s.get_posts_by_tag(tag, pagination))
+    }
+
+    async fn get_posts_by_category(
+        &self,
+        category: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        dispatch!(self, s => s.get_posts_by_category(category, pagination))
+    }
+
+    async fn get_posts_updated_since(
+        &self,
+        since: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        dispatch!(self, s => s.get_posts_updated_since(since, pagination))
+    }
+
+    async fn get_adjacent_posts(
+        &self,
+        post_slug: &str,
+    ) -> Result<(Option<Post>, Option<Post>), Self::Error> {
+        dispatch!(self, s => s.get_adjacent_posts(post_slug))
+    }
+
+    async fn get_related_posts(
+        &self,
+        post_slug: &str,
+        limit: usize,
+    ) -> Result<Vec<Post>, Self::Error> {
+        dispatch!(self, s => s.get_related_posts(post_slug, limit))
+    }
+
+    async fn get_taxonomy(&self) -> Result<Taxonomy, Self::Error> {
+        dispatch!(self, s => s.get_taxonomy())
+    }
+
+    async fn get_post_archive(&self) -> Result<Vec<ArchiveBucket>, Self::Error> {
+        dispatch!(self, s => s.get_post_archive())
+    }
+
+    async fn get_tag_counts(&self) -> Result<Vec<(String, usize)>, Self::Error> {
+        dispatch!(self, s => s.get_tag_counts())
+    }
+
+    async fn search_posts(
+        &self,
+        query: &str,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Post>, Self::Error> {
+        dispatch!(self, s => s.search_posts(query, pagination))
+    }
+
+    async fn insert_resource(&self, resource: &Resource) -> Result<(), Self::Error> {
+        dispatch!(self, s => s.insert_resource(resource))
+    }
+
+    async fn update_resource(&self, resource: &Resource) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.update_resource(resource))
+    }
+
+    async fn delete_resource(&self, resource_id: &Uuid) -> Result<bool, Self::Error> {
+        dispatch!(self, s => s.delete_resource(resource_id))
+    }
+
+    async fn get_resource(&self, resource_id: &Uuid) -> Result<Option<Resource>, Self::Error> {
+        dispatch!(self, s => s.get_resource(resource_id))
+    }
+
+    async fn get_resource_meta(
+        &self,
+        resource_id: &Uuid,
+    ) -> Result<Option<ResourceMeta>, Self::Error> {
+        dispatch!(self, s => s.get_resource_meta(resource_id))
+    }
+
+    async fn read_resource_chunk(
+        &self,
+        resource_id: &Uuid,
+        offset: u64,
+        len: usize,
+    ) -> Result<Option<Vec<u8>>, Self::Error> {
+        dispatch!(self, s => s.read_resource_chunk(resource_id, offset, len))
+    }
+
+    async fn find_resource_by_hash(&self, hash: &str) -> Result<Option<ResourceMeta>, Self::Error> {
+        dispatch!(self, s => s.find_resource_by_hash(hash))
+    }
+
+    async fn gc_resources(&self) -> Result<usize, Self::Error> {
+        dispatch!(self, s => s.gc_resources())
+    }
+
+    async fn get_resources_paged(
+        &self,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Resource>, Self::Error> {
+        dispatch!(self, s => s.get_resources_paged(pagination))
+    }
+
+    async fn get_resources(&self) -> Result<Vec<Resource>, Self::Error> {
+        dispatch!(self, s => s.get_resources())
+    }
+
+    async fn get_resource_metas(&self) -> Result<Vec<ResourceMeta>, Self::Error> {
+        dispatch!(self, s => s.get_resource_metas())
+    }
+
+    async fn get_commits_paged(
+        &self,
+        since_timestamp: i64,
+        pagination: &Pagination,
+    ) -> Result<PaginatedList<Commit>, Self::Error> {
+        dispatch!(self, s => s.get_commits_paged(since_timestamp, pagination))
+    }
+
+    async fn get_commits_since(&self, since_timestamp: i64) -> Result<Vec<Commit>, Self::Error> {
+        dispatch!(self, s => s.get_commits_since(since_timestamp))
+    }
+
+    async fn get_latest_commit(&self) -> Result<Option<Commit>, Self::Error> {
+        dispatch!(self, s => s.get_latest_commit())
+    }
+
+    async fn apply_delta(&self, delta: &Delta) -> Result<(), Self::Error> {
+        dispatch!(self, s => s.apply_delta(delta))
+    }
+
+    async fn get_metadata(&self, key: &str) -> Result<Option<String>, Self::Error> {
+        dispatch!(self, s => s.get_metadata(key))
+    }
+
+    async fn set_metadata(&self, key: &str, value: &str) -> Result<(), Self::Error> {
+        dispatch!(self, s => s.set_metadata(key, value))
+    }
+
+    async fn record_views(&self, views: &[PostView]) -> Result<(), Self::Error> {
+        dispatch!(self, s => s.record_views(views))
+    }
+
+    async fn get_view_stats(
+        &self,
+        post_slug: &str,
+        since: i64,
+    ) -> Result<Vec<DailyViewCount>, Self::Error> {
+        dispatch!(self, s => s.get_view_stats(post_slug, since))
+    }
+}
+
+/// Error type of [`AnyStorage`].
+#[derive(Debug)]
+pub(crate) enum AnyStorageError {
+    Sqlite(SqliteStorageError),
+    Remote(RemoteStorageError),
+    Unsupported(UnsupportedOperationError),
+}
+
+impl Display for AnyStorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sqlite(err) => write!(f, "{}", err),
+            Self::Remote(err) => write!(f, "{}", err),
+            Self::Unsupported(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for AnyStorageError {}
+
+impl From<UnsupportedOperationError> for AnyStorageError {
+    fn from(err: UnsupportedOperationError) -> Self {
+        Self::Unsupported(err)
+    }
+}
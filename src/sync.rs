@@ -0,0 +1,150 @@
+use std::error::Error;
+
+use ublog_data::models::Delta;
+use ublog_data::storage::sync::{synchronize_storage, SynchronizeStorageError};
+use ublog_data::storage::Storage;
+
+use crate::storage::AnyStorage;
+use crate::{fallible_step, SyncArgs};
+
+pub(crate) async fn sync(args: &SyncArgs) -> Result<(), Box<dyn Error>> {
+    fallible_step!(
+        "initialize logger",
+        crate::utils::logging::init_basic_logger(args.debug)
+    );
+
+    let source = fallible_step!("open source storage", AnyStorage::open(&args.source));
+    let destination = fallible_step!(
+        "open destination storage",
+        AnyStorage::open(&args.destination)
+    );
+
+    let delta = sync_storages(&source, &destination).await?;
+    let resource_count = delta.added_resources.len()
+        + delta
+            .added_posts
+            .iter()
+            .map(|(_, post_resources)| post_resources.len())
+            .sum::<usize>();
+    spdlog::info!(
+        "{} commits synchronized: {} posts and {} resources transferred, {} posts and {} resources deleted.",
+        delta.commits.len(),
+        delta.added_posts.len(),
+        resource_count,
+        delta.deleted_post_slugs.len(),
+        delta.deleted_resource_ids.len()
+    );
+
+    Ok(())
+}
+
+/// Synchronize data in the source storage to the destination storage. Returns the delta applied to the destination
+/// storage.
+async fn sync_storages<SF, ST>(source: &SF, destination: &ST) -> Result<Delta, Box<dyn Error>>
+where
+    SF: Storage,
+    ST: Storage,
+{
+    match synchronize_storage(source, destination).await {
+        Ok(delta) => Ok(delta),
+        Err(SynchronizeStorageError::DiverseHistory) => Err(Box::<dyn Error>::from(
+            "synchronize storages failed: the destination storage has commits that are not in the source storage; \
+             export the source storage and import the archive into an empty destination instead",
+        )),
+        Err(err) => Err(Box::<dyn Error>::from(format!(
+            "synchronize storages failed: {}",
+            err
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ublog_data::db::Database;
+    use ublog_data::models::{Post, Resource};
+    use ublog_data::storage::sqlite::SqliteStorage;
+    use uuid::Uuid;
+
+    fn create_test_post(slug: &str) -> Post {
+        Post {
+            title: format!("Post {}", slug),
+            slug: String::from(slug),
+            author: String::from("tester"),
+            create_timestamp: 0,
+            update_timestamp: 0,
+            category: String::from("test"),
+            tags: Vec::new(),
+            is_special: false,
+            views: 0,
+            excerpt: String::new(),
+            content: ublog_doc::DocumentNode::new_empty(),
+        }
+    }
+
+    fn create_test_resource(name: &str) -> Resource {
+        Resource {
+            id: Uuid::new_v4(),
+            name: String::from(name),
+            ty: String::from("text/plain"),
+            data: name.as_bytes().to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_sqlite_files() {
+        let dir = std::env::temp_dir().join(format!("ublog-sync-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source_path = dir.join("source.db");
+        let destination_path = dir.join("destination.db");
+
+        let source = Database::new(SqliteStorage::new_file(&source_path).unwrap());
+        source
+            .insert_post(
+                &create_test_post("post1"),
+                &[create_test_resource("post1-image")],
+            )
+            .await
+            .unwrap();
+        source
+            .insert_post(&create_test_post("post2"), &[])
+            .await
+            .unwrap();
+        source
+            .insert_resource(&create_test_resource("standalone"))
+            .await
+            .unwrap();
+
+        let source = AnyStorage::open(source_path.to_str().unwrap()).unwrap();
+        let destination = AnyStorage::open(destination_path.to_str().unwrap()).unwrap();
+
+        let delta = sync_storages(&source, &destination).await.unwrap();
+        assert_eq!(delta.added_posts.len(), 2);
+        assert_eq!(delta.added_resources.len(), 1);
+
+        let synced = destination.get_post_with_resources("post1").await.unwrap();
+        let (post, post_resources) = synced.unwrap();
+        assert_eq!(post.title, "Post post1");
+        assert_eq!(post_resources.len(), 1);
+        assert_eq!(post_resources[0].data, b"post1-image");
+        assert!(destination.get_post("post2").await.unwrap().is_some());
+
+        // Synchronizing again transfers nothing.
+        let delta = sync_storages(&source, &destination).await.unwrap();
+        assert!(delta.added_posts.is_empty());
+        assert!(delta.commits.is_empty());
+
+        destination
+            .insert_post(&create_test_post("post3"), &[])
+            .await
+            .unwrap();
+        let err = sync_storages(&source, &destination).await.unwrap_err();
+
+        drop(source);
+        drop(destination);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(err.to_string().contains("not in the source storage"));
+    }
+}
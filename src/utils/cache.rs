@@ -1,57 +1,219 @@
+use std::collections::HashMap;
 use std::future::Future;
-use std::ops::Deref;
+use std::hash::Hash;
+use std::sync::Arc;
 use std::time::SystemTime;
 
-use tokio::sync::{Mutex, MutexGuard};
+use tokio::sync::Mutex;
 
+/// A cache of values that expire after a fixed time, keyed by `K`.
+///
+/// Concurrent [`get`](Self::get) calls on the same key are single-flight: while a value is being computed, other
+/// callers wait for the computation instead of starting their own.
+///
+/// A cache created by [`with_capacity`](Self::with_capacity) holds at most the given number of keys, evicting expired
+/// values first and then the least recently computed ones. Keys whose values are being computed are never evicted.
 #[derive(Debug)]
-pub(crate) struct Cache<T> {
+pub(crate) struct Cache<K, V> {
     expire_secs: u64,
-    value: Mutex<Option<(SystemTime, T)>>,
+    capacity: Option<usize>,
+    slots: std::sync::Mutex<HashMap<K, Arc<CacheSlot<V>>>>,
 }
 
-impl<T> Cache<T> {
+type CacheSlot<V> = Mutex<Option<(SystemTime, Arc<V>)>>;
+
+impl<K, V> Cache<K, V>
+where
+    K: Clone + Eq + Hash,
+{
     pub(crate) fn new(expire_secs: u64) -> Self {
         Self {
             expire_secs,
-            value: Mutex::new(None),
+            capacity: None,
+            slots: std::sync::Mutex::new(HashMap::new()),
         }
     }
 
-    pub(crate) async fn get<F, R, E>(&self, value_factory: F) -> Result<CachedValue<T>, E>
+    /// Create a cache that holds at most `capacity` keys.
+    pub(crate) fn with_capacity(expire_secs: u64, capacity: usize) -> Self {
+        Self {
+            expire_secs,
+            capacity: Some(capacity),
+            slots: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the cached value of the given key, or compute it with `value_factory` if the value is absent or expired.
+    pub(crate) async fn get<F, R, E>(&self, key: K, value_factory: F) -> Result<Arc<V>, E>
     where
         F: FnOnce() -> R,
-        R: Future<Output = Result<T, E>>,
+        R: Future<Output = Result<V, E>>,
     {
-        let mut lock = self.value.lock().await;
-        if let Some((t, _)) = &*lock {
+        let slot = {
+            let mut slots = self.slots.lock().unwrap();
+            if let Some(capacity) = self.capacity {
+                if !slots.contains_key(&key) && slots.len() >= capacity {
+                    self.evict(&mut slots, capacity);
+                }
+            }
+            slots.entry(key).or_default().clone()
+        };
+
+        let mut lock = slot.lock().await;
+        if let Some((t, value)) = &*lock {
             if t.elapsed().unwrap().as_secs() < self.expire_secs {
-                return Ok(CachedValue { guard: lock });
+                return Ok(value.clone());
             }
         }
 
-        let value = value_factory().await?;
-        *lock = Some((SystemTime::now(), value));
+        let value = Arc::new(value_factory().await?);
+        *lock = Some((SystemTime::now(), value.clone()));
 
-        Ok(CachedValue { guard: lock })
+        Ok(value)
     }
-}
 
-#[derive(Debug)]
-pub(crate) struct CachedValue<'a, T> {
-    guard: MutexGuard<'a, Option<(SystemTime, T)>>,
-}
+    /// Evict values from `slots` until fewer than `capacity` keys are left, or only keys whose values are being
+    /// computed are left.
+    fn evict(&self, slots: &mut HashMap<K, Arc<CacheSlot<V>>>, capacity: usize) {
+        // A slot that cannot be locked is being computed by some caller.
+        slots.retain(|_, slot| match slot.try_lock() {
+            Ok(lock) => match &*lock {
+                Some((t, _)) => t.elapsed().unwrap().as_secs() < self.expire_secs,
+                None => false,
+            },
+            Err(_) => true,
+        });
 
-impl<'a, T> CachedValue<'a, T> {
-    fn cache_pair(&self) -> &(SystemTime, T) {
-        (*self.guard).as_ref().unwrap()
+        while slots.len() >= capacity {
+            let oldest = slots
+                .iter()
+                .filter_map(|(key, slot)| {
+                    let lock = slot.try_lock().ok()?;
+                    lock.as_ref().map(|(t, _)| (*t, key.clone()))
+                })
+                .min_by_key(|(t, _)| *t);
+            match oldest {
+                Some((_, key)) => {
+                    slots.remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Get the number of keys in the cache.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.slots.lock().unwrap().len()
+    }
+
+    /// Drop all cached values.
+    ///
+    /// Values that are being computed when the cache is invalidated are not cached.
+    pub(crate) fn invalidate(&self) {
+        self.slots.lock().unwrap().clear();
     }
 }
 
-impl<'a, T> Deref for CachedValue<'a, T> {
-    type Target = T;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    async fn compute(computations: &AtomicUsize, value: &str) -> Result<String, ()> {
+        computations.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(String::from(value))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_misses_compute_once() {
+        let cache = Arc::new(Cache::new(60));
+        let computations = Arc::new(AtomicUsize::new(0));
+
+        let tasks = (0..2).map(|_| {
+            let cache = cache.clone();
+            let computations = computations.clone();
+            tokio::spawn(async move {
+                cache
+                    .get("rss", || compute(&computations, "value"))
+                    .await
+                    .unwrap()
+            })
+        });
+        for task in tasks.collect::<Vec<_>>() {
+            assert_eq!(*task.await.unwrap(), "value");
+        }
+
+        assert_eq!(computations.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_keys_and_invalidate() {
+        let cache = Cache::new(60);
+        let computations = AtomicUsize::new(0);
+
+        let rss = cache.get("rss", || compute(&computations, "rss")).await;
+        let atom = cache.get("atom", || compute(&computations, "atom")).await;
+        assert_eq!(*rss.unwrap(), "rss");
+        assert_eq!(*atom.unwrap(), "atom");
+        cache
+            .get("rss", || compute(&computations, "rss"))
+            .await
+            .unwrap();
+        assert_eq!(computations.load(Ordering::SeqCst), 2);
+
+        cache.invalidate();
+        cache
+            .get("rss", || compute(&computations, "rss"))
+            .await
+            .unwrap();
+        assert_eq!(computations.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_capacity_evicts_least_recently_computed() {
+        let cache = Cache::with_capacity(60, 2);
+        let computations = AtomicUsize::new(0);
+
+        for key in ["a", "b", "c"] {
+            cache
+                .get(key, || compute(&computations, key))
+                .await
+                .unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!(computations.load(Ordering::SeqCst), 3);
+
+        // "a" is the least recently computed value, so it is the one evicted.
+        cache
+            .get("c", || compute(&computations, "c"))
+            .await
+            .unwrap();
+        assert_eq!(computations.load(Ordering::SeqCst), 3);
+        cache
+            .get("a", || compute(&computations, "a"))
+            .await
+            .unwrap();
+        assert_eq!(computations.load(Ordering::SeqCst), 4);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_value_is_recomputed() {
+        let cache = Cache::new(0);
+        let computations = AtomicUsize::new(0);
 
-    fn deref(&self) -> &Self::Target {
-        &self.cache_pair().1
+        cache
+            .get((), || compute(&computations, "value"))
+            .await
+            .unwrap();
+        cache
+            .get((), || compute(&computations, "value"))
+            .await
+            .unwrap();
+        assert_eq!(computations.load(Ordering::SeqCst), 2);
     }
 }
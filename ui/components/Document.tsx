@@ -51,6 +51,14 @@ export default function Document({ root, headingIdMap, tocCtx }: DocumentProps):
     case "listItem":
       return <li>{getRenderedChildren()}</li>;
 
+    case "todoItem":
+      return (
+        <div className="flex items-baseline gap-2">
+          <input type="checkbox" checked={root.tag.checked} disabled />
+          <div>{getRenderedChildren()}</div>
+        </div>
+      );
+
     case "code":
       return <Code language={root.tag.language} code={root.tag.code} caption={root.tag.caption} />;
 
@@ -72,6 +80,20 @@ export default function Document({ root, headingIdMap, tocCtx }: DocumentProps):
     case "divider":
       return <hr />;
 
+    case "toggle":
+      return (
+        <details className="first:mt-0 last:mb-0 my-4">
+          <summary className="cursor-pointer">{root.tag.summary}</summary>
+          <div className="ml-6">{getRenderedChildren()}</div>
+        </details>
+      );
+
+    case "columns":
+      return <div className="flex flex-col md:flex-row gap-8 first:mt-0 last:mb-0 my-4">{getRenderedChildren()}</div>;
+
+    case "column":
+      return <div className="flex-1 min-w-0">{getRenderedChildren()}</div>;
+
     case "inline":
       return (
         <Inline style={root.tag.style} link={root.tag.link}>
@@ -12,6 +12,7 @@ export interface Post {
   category: string;
   tags: string[];
   isSpecial: boolean;
+  views: number;
   content: DocumentNode;
 }
 
@@ -28,6 +29,7 @@ export type DocumentNodeTag =
   | DocumentNodeQuoteTag
   | DocumentNodeListTag
   | DocumentNodeListItemTag
+  | DocumentNodeTodoItemTag
   | DocumentNodeCodeTag
   | DocumentNodeEquationTag
   | DocumentNodeImageTag
@@ -35,6 +37,9 @@ export type DocumentNodeTag =
   | DocumentNodeTableRowTag
   | DocumentNodeTableCellTag
   | DocumentNodeDividerTag
+  | DocumentNodeToggleTag
+  | DocumentNodeColumnsTag
+  | DocumentNodeColumnTag
   | DocumentNodeInlineTag
   | DocumentNodeInlineTextTag
   | DocumentNodeInlineCodeTag
@@ -51,6 +56,7 @@ export interface DocumentNodeParagraphTag {
 export interface DocumentNodeHeadingTag {
   type: "heading";
   level: number;
+  id: string | null;
 }
 
 export interface DocumentNodeCalloutTag {
@@ -71,6 +77,11 @@ export interface DocumentNodeListItemTag {
   type: "listItem";
 }
 
+export interface DocumentNodeTodoItemTag {
+  type: "todoItem";
+  checked: boolean;
+}
+
 export interface DocumentNodeCodeTag {
   type: "code";
   language: string;
@@ -107,6 +118,19 @@ export interface DocumentNodeDividerTag {
   type: "divider";
 }
 
+export interface DocumentNodeToggleTag {
+  type: "toggle";
+  summary: string;
+}
+
+export interface DocumentNodeColumnsTag {
+  type: "columns";
+}
+
+export interface DocumentNodeColumnTag {
+  type: "column";
+}
+
 export interface DocumentNodeInlineTag {
   type: "inline";
   style: InlineStyle | null;
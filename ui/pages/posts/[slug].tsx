@@ -102,7 +102,7 @@ function getDocumentTocInfo(root: DocumentNode): DocumentTocInfo {
       nextHeadingCount++;
 
       const headingTitle = renderPlaintext(root);
-      const headingId = `${nextHeadingCount}-${encodeURIComponent(headingTitle)}`;
+      const headingId = root.tag.id ?? `${nextHeadingCount}-${encodeURIComponent(headingTitle)}`;
       tocInfo.headingIdMap.set(root, headingId);
       tocInfo.entries.push({
         title: headingTitle,